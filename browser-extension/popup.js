@@ -122,6 +122,13 @@ async function loadEntries() {
 
   try {
     const data = await fetchJson(`/v1/entries?url=${encodeURIComponent(tab.url)}`);
+    if (data.warning) {
+      renderEntries([]);
+      emptyEl.textContent =
+        `Possible phishing site: ${data.warning.host} looks like ${data.warning.similar_to}. Autofill is disabled here.`;
+      setStatus("Lookalike domain detected.", "bad");
+      return;
+    }
     renderEntries(data.entries || []);
     setStatus("", "");
   } catch (err) {
@@ -0,0 +1,18 @@
+//! Feeds arbitrary bytes to the vault header parser, which must reject
+//! malformed input with an error rather than panic or over-allocate.
+//!
+//! Run with `cargo +nightly fuzz run parse_vault_header` from `organizer-core`.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use organizer_core::vault::parse_vault_header;
+
+fuzz_target!(|data: &[u8]| {
+  if let Ok(parsed) = parse_vault_header(data) {
+    // Anything accepted must be decryptable in principle: the nonce and
+    // tag fit, and the recorded KDF parameters are within limits.
+    assert!(parsed.nonce_offset + parsed.header.nonce_len() + 16 <= data.len());
+    assert!(parsed.header.kdf.validate().is_ok());
+  }
+});
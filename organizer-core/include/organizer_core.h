@@ -0,0 +1,46 @@
+/*
+ * C bindings for the organizer-core vault format (build with --features ffi).
+ *
+ * Strings returned by the library are owned by the caller and must be freed
+ * with organizer_string_free(), which zeroizes them first. Handles must be
+ * released with organizer_vault_close(), which zeroizes the decrypted vault.
+ */
+#ifndef ORGANIZER_CORE_H
+#define ORGANIZER_CORE_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef enum OrganizerStatus {
+  ORGANIZER_OK = 0,
+  ORGANIZER_NULL_ARGUMENT = 1,
+  ORGANIZER_INVALID_UTF8 = 2,
+  ORGANIZER_IO = 3,
+  ORGANIZER_AUTH = 4,
+  ORGANIZER_FORMAT = 5,
+  ORGANIZER_NOT_FOUND = 6,
+} OrganizerStatus;
+
+typedef struct OrganizerVault OrganizerVault;
+
+/* Decrypts the vault file at `path`; stores a handle in `*out` on success. */
+OrganizerStatus organizer_vault_open(const char *path, const char *password, OrganizerVault **out);
+
+/* JSON array of {id, title, username, url, type, linked_to}; NULL on error. */
+char *organizer_vault_list(const OrganizerVault *vault);
+
+/* Password of entry `id` (following shared-password links). */
+OrganizerStatus organizer_vault_get_secret(const OrganizerVault *vault, const char *id, char **out);
+
+/* Zeroizes and frees a string returned by the library. NULL is ignored. */
+void organizer_string_free(char *s);
+
+/* Zeroizes the decrypted vault and frees the handle. NULL is ignored. */
+void organizer_vault_close(OrganizerVault *vault);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* ORGANIZER_CORE_H */
@@ -0,0 +1,234 @@
+//! C ABI bindings (feature `ffi`).
+//!
+//! A deliberately small surface for third-party tools: open a vault file,
+//! list entries as JSON, fetch one secret, close. See
+//! `include/organizer_core.h` for the C declarations.
+//!
+//! Every string returned by this module is owned by the caller and must be
+//! released with [`organizer_string_free`], which zeroizes it before freeing.
+//! [`organizer_vault_close`] zeroizes the decrypted vault.
+
+use crate::models::VaultData;
+use crate::vault::{self, VaultError};
+use std::ffi::{c_char, CStr, CString};
+use std::path::Path;
+use std::ptr;
+use zeroize::Zeroize;
+
+/// Status codes returned by the `organizer_*` functions.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OrganizerStatus {
+  Ok = 0,
+  /// A required pointer argument was null.
+  NullArgument = 1,
+  /// A string argument was not valid UTF-8.
+  InvalidUtf8 = 2,
+  /// The vault file could not be read.
+  Io = 3,
+  /// Wrong master password or corrupted vault.
+  Auth = 4,
+  /// The vault file or its payload is malformed.
+  Format = 5,
+  /// No entry with the given id.
+  NotFound = 6,
+}
+
+impl From<VaultError> for OrganizerStatus {
+  fn from(e: VaultError) -> Self {
+    match e {
+      VaultError::Io(_) => OrganizerStatus::Io,
+      VaultError::Crypto(_) | VaultError::Kdf(_) => OrganizerStatus::Auth,
+      VaultError::Format(_) | VaultError::Json(_) | VaultError::Limit(_) => OrganizerStatus::Format,
+    }
+  }
+}
+
+/// Opaque handle to a decrypted vault.
+pub struct OrganizerVault {
+  data: VaultData,
+}
+
+/// Borrows a C string argument as `&str`.
+///
+/// # Safety
+///
+/// `ptr` must be null or point to a NUL-terminated string valid for `'a`.
+unsafe fn str_arg<'a>(ptr: *const c_char) -> Result<&'a str, OrganizerStatus> {
+  if ptr.is_null() {
+    return Err(OrganizerStatus::NullArgument);
+  }
+  CStr::from_ptr(ptr).to_str().map_err(|_| OrganizerStatus::InvalidUtf8)
+}
+
+/// Moves `value` into a caller-owned C string, zeroizing the Rust copy.
+///
+/// Returns null if `value` contains an interior NUL byte.
+fn into_c_string(mut value: String) -> *mut c_char {
+  let result = CString::new(value.as_bytes()).map(CString::into_raw);
+  value.zeroize();
+  result.unwrap_or(ptr::null_mut())
+}
+
+/// Decrypts the vault file at `path` and stores a handle in `*out`.
+///
+/// # Safety
+///
+/// `path` and `password` must be NUL-terminated strings; `out` must be a
+/// valid pointer. On success the handle must be released with
+/// [`organizer_vault_close`].
+#[no_mangle]
+pub unsafe extern "C" fn organizer_vault_open(
+  path: *const c_char,
+  password: *const c_char,
+  out: *mut *mut OrganizerVault,
+) -> OrganizerStatus {
+  if out.is_null() {
+    return OrganizerStatus::NullArgument;
+  }
+  *out = ptr::null_mut();
+
+  let (path, password) = match (str_arg(path), str_arg(password)) {
+    (Ok(path), Ok(password)) => (path, password),
+    (Err(e), _) | (_, Err(e)) => return e,
+  };
+
+  match vault::load_with_password(Path::new(path), password) {
+    Ok((data, _header, mut key)) => {
+      key.zeroize();
+      *out = Box::into_raw(Box::new(OrganizerVault { data }));
+      OrganizerStatus::Ok
+    }
+    Err(e) => e.into(),
+  }
+}
+
+/// Returns a JSON array of entry summaries (no secrets), or null on error.
+///
+/// # Safety
+///
+/// `vault` must be a handle returned by [`organizer_vault_open`].
+#[no_mangle]
+pub unsafe extern "C" fn organizer_vault_list(vault: *const OrganizerVault) -> *mut c_char {
+  let Some(vault) = vault.as_ref() else {
+    return ptr::null_mut();
+  };
+  match serde_json::to_string(&vault.data.summaries()) {
+    Ok(json) => into_c_string(json),
+    Err(_) => ptr::null_mut(),
+  }
+}
+
+/// Copies the password of entry `id` (following shared-password links)
+/// into `*out`.
+///
+/// # Safety
+///
+/// `vault` must be a handle returned by [`organizer_vault_open`], `id` a
+/// NUL-terminated string, and `out` a valid pointer. The returned string
+/// must be released with [`organizer_string_free`].
+#[no_mangle]
+pub unsafe extern "C" fn organizer_vault_get_secret(
+  vault: *const OrganizerVault,
+  id: *const c_char,
+  out: *mut *mut c_char,
+) -> OrganizerStatus {
+  if out.is_null() {
+    return OrganizerStatus::NullArgument;
+  }
+  *out = ptr::null_mut();
+
+  let Some(vault) = vault.as_ref() else {
+    return OrganizerStatus::NullArgument;
+  };
+  let id = match str_arg(id) {
+    Ok(id) => id,
+    Err(e) => return e,
+  };
+  let Some(entry) = vault.data.resolve_password_source(id) else {
+    return OrganizerStatus::NotFound;
+  };
+
+  let secret = into_c_string(entry.password.clone());
+  if secret.is_null() {
+    return OrganizerStatus::Format;
+  }
+  *out = secret;
+  OrganizerStatus::Ok
+}
+
+/// Zeroizes and frees a string returned by this library. Null is ignored.
+///
+/// # Safety
+///
+/// `s` must be null or a string returned by this library that has not
+/// been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn organizer_string_free(s: *mut c_char) {
+  if s.is_null() {
+    return;
+  }
+  let mut bytes = CString::from_raw(s).into_bytes_with_nul();
+  bytes.zeroize();
+}
+
+/// Zeroizes the decrypted vault and frees the handle. Null is ignored.
+///
+/// # Safety
+///
+/// `vault` must be null or a handle returned by [`organizer_vault_open`]
+/// that has not been closed yet.
+#[no_mangle]
+pub unsafe extern "C" fn organizer_vault_close(vault: *mut OrganizerVault) {
+  if !vault.is_null() {
+    // Entries zeroize themselves on drop.
+    drop(Box::from_raw(vault));
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::models::Entry;
+
+  #[test]
+  fn open_list_get_secret_close() {
+    let path = std::env::temp_dir().join(format!("the-organizer-test-ffi-{}.dat", std::process::id()));
+    let header = vault::VaultHeader::default();
+    let key = header.derive_key("ffi").expect("kdf");
+    let mut data = VaultData::default();
+    data
+      .entries
+      .push(Entry::new("Mail".to_string(), "me".to_string(), "s3cret".to_string(), String::new(), String::new()));
+    let id = CString::new(data.entries[0].id.clone()).unwrap();
+    vault::save_with_key(&path, &data, &header, &key).expect("save");
+
+    let c_path = CString::new(path.to_str().unwrap()).unwrap();
+    let wrong = CString::new("nope").unwrap();
+    let right = CString::new("ffi").unwrap();
+    let mut handle = ptr::null_mut();
+    unsafe {
+      assert_eq!(organizer_vault_open(c_path.as_ptr(), wrong.as_ptr(), &mut handle), OrganizerStatus::Auth);
+      assert!(handle.is_null());
+      assert_eq!(organizer_vault_open(c_path.as_ptr(), right.as_ptr(), &mut handle), OrganizerStatus::Ok);
+
+      let list = organizer_vault_list(handle);
+      let json = CStr::from_ptr(list).to_str().unwrap().to_string();
+      assert!(json.contains("\"title\":\"Mail\"") && !json.contains("s3cret"));
+      organizer_string_free(list);
+
+      let mut secret = ptr::null_mut();
+      assert_eq!(organizer_vault_get_secret(handle, id.as_ptr(), &mut secret), OrganizerStatus::Ok);
+      assert_eq!(CStr::from_ptr(secret).to_str().unwrap(), "s3cret");
+      organizer_string_free(secret);
+
+      let missing = CString::new("missing").unwrap();
+      assert_eq!(
+        organizer_vault_get_secret(handle, missing.as_ptr(), &mut secret),
+        OrganizerStatus::NotFound
+      );
+      organizer_vault_close(handle);
+    }
+    let _ = std::fs::remove_file(&path);
+  }
+}
@@ -0,0 +1,273 @@
+//! Encrypted write-ahead journal for single-file vaults.
+//!
+//! Rewriting `vault.dat` after every change leaves a window in which a change
+//! exists only in memory. Before the rewrite, the changed entries are
+//! appended to `vault.dat.wal` and synced; after the rewrite the journal is
+//! removed. A journal found at unlock therefore holds changes that may not
+//! have reached the vault file, and [`crate::vault::recover_journal`] replays
+//! them. A lock that cannot wait for a failed write also leaves its unsaved
+//! changes here, as a whole-vault record if they are not per entry.
+//!
+//! Each record is `[u32 LE length][nonce][ciphertext+tag]`, sealed with the
+//! vault's cipher and key over a JSON list of [`JournalOp`]s, with
+//! `TORG-WAL/<vault id>` as associated data. A torn final record (power loss
+//! during an append) is ignored.
+//!
+//! Record stores (SQLite) write each change in a transaction and need no
+//! journal.
+
+use crate::models::{Entry, VaultData};
+use crate::store::VaultStore;
+use crate::vault::{CipherSuite, VaultError};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use zeroize::Zeroize;
+
+/// Appended to the vault file name to get the journal path.
+pub const JOURNAL_SUFFIX: &str = ".wal";
+
+/// One logged change.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum JournalOp {
+  /// The entry as it was after the change (added or edited).
+  Upsert { entry: Box<Entry> },
+  Delete { id: String },
+  /// The whole vault, for changes that are not per entry (metadata, bulk
+  /// edits).
+  Vault { data: Box<VaultData> },
+}
+
+impl JournalOp {
+  /// Ops describing the current state of the entries with `ids`; ids no
+  /// longer in `data` were deleted.
+  pub fn for_entries(data: &VaultData, ids: &[&str]) -> Vec<JournalOp> {
+    ids
+      .iter()
+      .map(|id| match data.find_entry(id) {
+        Some(entry) => JournalOp::Upsert {
+          entry: Box::new(entry.clone()),
+        },
+        None => JournalOp::Delete { id: id.to_string() },
+      })
+      .collect()
+  }
+
+  /// Applies the op to `data`. Replaying an op that is already reflected
+  /// in `data` changes nothing.
+  pub fn apply(self, data: &mut VaultData) {
+    match self {
+      JournalOp::Upsert { entry } => match data.entries.iter_mut().find(|e| e.id == entry.id) {
+        Some(existing) => *existing = *entry,
+        None => data.entries.push(*entry),
+      },
+      JournalOp::Delete { id } => data.entries.retain(|e| e.id != id),
+      JournalOp::Vault { data: vault } => *data = *vault,
+    }
+  }
+}
+
+/// What a journal holds, read without the key.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct JournalSummary {
+  /// Complete records, each one save's changes.
+  pub records: usize,
+  /// A partial record follows them (cut off mid-append; skipped on replay).
+  pub torn: bool,
+}
+
+/// The journal file next to a file-backed vault.
+#[derive(Clone, Debug)]
+pub struct Journal {
+  path: PathBuf,
+}
+
+impl Journal {
+  pub fn new(path: impl Into<PathBuf>) -> Self {
+    Self { path: path.into() }
+  }
+
+  /// Journal for `store`, if it is a single-file vault.
+  pub fn for_store(store: &dyn VaultStore) -> Option<Journal> {
+    if store.records().is_some() {
+      return None;
+    }
+    let vault_path = store.path()?;
+    let mut name = vault_path.file_name()?.to_os_string();
+    name.push(JOURNAL_SUFFIX);
+    Some(Journal::new(vault_path.with_file_name(name)))
+  }
+
+  pub fn path(&self) -> &Path {
+    &self.path
+  }
+
+  pub fn exists(&self) -> bool {
+    self.path.exists()
+  }
+
+  /// Counts the records by their length prefixes, without decrypting them.
+  /// A missing journal reads as empty.
+  pub fn summary(&self) -> io::Result<JournalSummary> {
+    let bytes = match fs::read(&self.path) {
+      Ok(bytes) => bytes,
+      Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(JournalSummary::default()),
+      Err(e) => return Err(e),
+    };
+    let mut summary = JournalSummary::default();
+    let mut rest = bytes.as_slice();
+    while rest.len() >= 4 {
+      let len = u32::from_le_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+      if rest.len() - 4 < len {
+        break;
+      }
+      rest = &rest[4 + len..];
+      summary.records += 1;
+    }
+    summary.torn = !rest.is_empty();
+    Ok(summary)
+  }
+
+  /// Seals `ops` as one record and appends it, syncing before returning.
+  pub fn append(&self, ops: &[JournalOp], vault_id: &str, cipher: CipherSuite, key: &[u8; 32]) -> Result<(), VaultError> {
+    let nonce = cipher.random_nonce();
+    let mut plaintext = serde_json::to_vec(ops).map_err(|e| VaultError::Json(e.to_string()))?;
+    let sealed = cipher.encrypt(key, &nonce, &plaintext, &journal_aad(vault_id));
+    plaintext.zeroize();
+    let sealed = sealed?;
+
+    let len = u32::try_from(nonce.len() + sealed.len())
+      .map_err(|_| VaultError::Format("journal record too large".to_string()))?;
+    let mut record = Vec::with_capacity(4 + len as usize);
+    record.extend_from_slice(&len.to_le_bytes());
+    record.extend_from_slice(&nonce);
+    record.extend_from_slice(&sealed);
+
+    let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+    file.write_all(&record)?;
+    file.sync_all()?;
+    Ok(())
+  }
+
+  /// Reads every complete record in order. A missing journal reads as empty.
+  ///
+  /// Fails if a complete record does not authenticate (wrong key, another
+  /// vault's journal, or tampering).
+  pub fn read(&self, vault_id: &str, cipher: CipherSuite, key: &[u8; 32]) -> Result<Vec<JournalOp>, VaultError> {
+    let bytes = match fs::read(&self.path) {
+      Ok(bytes) => bytes,
+      Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+      Err(e) => return Err(e.into()),
+    };
+
+    let aad = journal_aad(vault_id);
+    let mut ops = Vec::new();
+    let mut rest = bytes.as_slice();
+    while rest.len() >= 4 {
+      let len = u32::from_le_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+      let Some(record) = rest.get(4..4 + len) else {
+        break; // torn append
+      };
+      rest = &rest[4 + len..];
+      if record.len() < cipher.nonce_len() {
+        return Err(VaultError::Format("journal record too small".to_string()));
+      }
+      let (nonce, sealed) = record.split_at(cipher.nonce_len());
+      let mut plaintext = cipher.decrypt(key, nonce, sealed, &aad)?;
+      let parsed = serde_json::from_slice::<Vec<JournalOp>>(&plaintext).map_err(|e| VaultError::Json(e.to_string()));
+      plaintext.zeroize();
+      ops.extend(parsed?);
+    }
+    Ok(ops)
+  }
+
+  /// Removes the journal once its changes are in the vault file.
+  pub fn clear(&self) -> io::Result<()> {
+    match fs::remove_file(&self.path) {
+      Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+      _ => Ok(()),
+    }
+  }
+}
+
+fn journal_aad(vault_id: &str) -> Vec<u8> {
+  format!("TORG-WAL/{vault_id}").into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::store::FileStore;
+  use crate::vault::{self, VaultHeader};
+
+  #[test]
+  fn interrupted_save_is_replayed_and_torn_tail_ignored() {
+    let path = std::env::temp_dir().join(format!("the-organizer-test-journal-{}.dat", std::process::id()));
+    let store = FileStore::new(&path);
+    let journal = Journal::for_store(&store).expect("file store has a journal");
+    let _ = journal.clear();
+    let header = VaultHeader::default();
+    let key = [3u8; 32];
+
+    let on_disk = VaultData::default();
+    vault::save_to_store(&store, &on_disk, &header, &key).expect("save");
+
+    // The change reaches the journal, then the process dies before the rewrite.
+    let mut data = on_disk.clone();
+    let entry = Entry::new("t".into(), "u".into(), "p".into(), String::new(), String::new());
+    let id = entry.id.clone();
+    data.entries.push(entry);
+    let ops = JournalOp::for_entries(&data, &[&id]);
+    journal.append(&ops, &data.meta.id, header.cipher, &key).expect("append");
+    let mut file = OpenOptions::new().append(true).open(journal.path()).unwrap();
+    file.write_all(&[200, 0, 0, 0, 1, 2, 3]).unwrap();
+    drop(file);
+
+    assert!(journal.read("another vault", header.cipher, &key).is_err());
+    assert_eq!(journal.summary().unwrap(), JournalSummary { records: 1, torn: true });
+
+    let mut recovered = on_disk;
+    assert_eq!(vault::recover_journal(&store, &mut recovered, &header, &key).expect("recover"), 1);
+    assert_eq!(recovered.find_entry(&id).map(|e| e.password.as_str()), Some("p"));
+    assert!(!journal.exists());
+
+    let _ = std::fs::remove_file(&path);
+  }
+
+  #[test]
+  fn whole_vault_records_replace_the_loaded_vault() {
+    let path = std::env::temp_dir().join(format!("the-organizer-test-journal-vault-{}.dat", std::process::id()));
+    let store = FileStore::new(&path);
+    let journal = Journal::for_store(&store).expect("file store has a journal");
+    let _ = journal.clear();
+    let header = VaultHeader::default();
+    let key = [4u8; 32];
+
+    let mut data = VaultData::default();
+    data.entries.push(Entry::new("t".into(), "u".into(), "p".into(), String::new(), String::new()));
+    vault::save_to_store(&store, &data, &header, &key).expect("save");
+
+    // A metadata change and a removal that only a whole-vault record covers.
+    let mut changed = data.clone();
+    changed.entries.clear();
+    changed.meta.canary_entry = Some("decoy".into());
+    let later = Entry::new("later".into(), "u".into(), "q".into(), String::new(), String::new());
+    let later_id = later.id.clone();
+    let ops = [
+      JournalOp::Vault {
+        data: Box::new(changed.clone()),
+      },
+      JournalOp::Upsert { entry: Box::new(later) },
+    ];
+    journal.append(&ops, &data.meta.id, header.cipher, &key).expect("append");
+
+    assert_eq!(vault::recover_journal(&store, &mut data, &header, &key).expect("recover"), 2);
+    assert_eq!(data.meta.canary_entry.as_deref(), Some("decoy"));
+    assert_eq!(data.entries.len(), 1);
+    assert!(data.find_entry(&later_id).is_some());
+
+    let _ = std::fs::remove_file(&path);
+  }
+}
@@ -0,0 +1,27 @@
+//! Core vault format, cryptography, and data model for The Organizer.
+//!
+//! This crate has no Tauri or GUI dependencies so the desktop app, the CLI,
+//! and other tools (native-messaging host, fuzz targets) all read and write
+//! vaults with exactly the same code:
+//!
+//! - [`ffi`] - C ABI bindings (feature `ffi`)
+//! - [`journal`] - Encrypted write-ahead journal for single-file vaults
+//! - [`limits`] - Size and sanity limits for untrusted vault and import files
+//! - [`models`] - Entries, item types, and the decrypted vault payload
+//! - [`store`] - Storage backends for the encrypted vault (file, in-memory)
+//! - [`sqlite_store`] - Per-entry SQLite vault storage (feature `sqlite`)
+//! - [`vault`] - Encryption, decryption, and key derivation
+//! - [`wasm`] - Read-only WebAssembly bindings (feature `wasm`)
+
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod journal;
+pub mod limits;
+pub mod models;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_store;
+pub mod store;
+mod stream;
+pub mod vault;
+#[cfg(feature = "wasm")]
+pub mod wasm;
@@ -0,0 +1,176 @@
+//! Sanity limits for vault and import files.
+//!
+//! Vault files may come from sync folders, backups, or other machines, so
+//! their size and contents are untrusted until authenticated, and even an
+//! authenticated payload may have been written by a buggy or hostile tool.
+//! [`Limits`] bounds how much is read and accepted; the defaults are far
+//! above any real vault.
+
+use crate::models::{Entry, VaultData};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io;
+
+/// Largest vault or import file read by default (256 MiB).
+pub const DEFAULT_MAX_FILE_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Most entries accepted in one vault by default.
+pub const DEFAULT_MAX_ENTRIES: usize = 100_000;
+
+/// Longest single field accepted by default (1 MiB), e.g. notes or a key.
+pub const DEFAULT_MAX_FIELD_BYTES: usize = 1024 * 1024;
+
+/// Configurable bounds applied when loading vaults and importing files.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Limits {
+  pub max_file_bytes: u64,
+  pub max_entries: usize,
+  /// Applies to each text field; type-specific fields are counted together
+  /// by their serialized size.
+  pub max_field_bytes: usize,
+}
+
+impl Default for Limits {
+  fn default() -> Self {
+    Self {
+      max_file_bytes: DEFAULT_MAX_FILE_BYTES,
+      max_entries: DEFAULT_MAX_ENTRIES,
+      max_field_bytes: DEFAULT_MAX_FIELD_BYTES,
+    }
+  }
+}
+
+/// Which limit was exceeded, with the offending value.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "limit", rename_all = "snake_case")]
+pub enum LimitExceeded {
+  FileSize { size: u64, max: u64 },
+  EntryCount { count: usize, max: usize },
+  FieldLength {
+    entry_id: String,
+    field: &'static str,
+    len: usize,
+    max: usize,
+  },
+}
+
+impl fmt::Display for LimitExceeded {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      LimitExceeded::FileSize { size, max } => write!(f, "file is {size} bytes (limit {max})"),
+      LimitExceeded::EntryCount { count, max } => write!(f, "{count} entries (limit {max})"),
+      LimitExceeded::FieldLength {
+        entry_id,
+        field,
+        len,
+        max,
+      } => write!(f, "entry {entry_id}: {field} is {len} bytes (limit {max})"),
+    }
+  }
+}
+
+impl Limits {
+  pub fn check_file_size(&self, size: u64) -> Result<(), LimitExceeded> {
+    if size > self.max_file_bytes {
+      return Err(LimitExceeded::FileSize {
+        size,
+        max: self.max_file_bytes,
+      });
+    }
+    Ok(())
+  }
+
+  pub fn check_entry_count(&self, count: usize) -> Result<(), LimitExceeded> {
+    if count > self.max_entries {
+      return Err(LimitExceeded::EntryCount {
+        count,
+        max: self.max_entries,
+      });
+    }
+    Ok(())
+  }
+
+  /// Checks every field of one entry.
+  pub fn check_entry(&self, entry: &Entry) -> Result<(), LimitExceeded> {
+    let fields = [
+      ("title", entry.title.len()),
+      ("username", entry.username.len()),
+      ("password", entry.password.len()),
+      ("url", entry.url.len()),
+      ("notes", entry.notes.len()),
+      ("totp_secret", entry.totp_secret.as_ref().map_or(0, String::len)),
+      ("tags", entry.tags.iter().map(String::len).sum()),
+      (entry.kind.type_name(), serialized_len(&entry.kind)),
+    ];
+    for (field, len) in fields {
+      if len > self.max_field_bytes {
+        return Err(LimitExceeded::FieldLength {
+          entry_id: entry.id.clone(),
+          field,
+          len,
+          max: self.max_field_bytes,
+        });
+      }
+    }
+    Ok(())
+  }
+
+  /// Checks the entry count and every entry of a decrypted vault.
+  pub fn check_data(&self, data: &VaultData) -> Result<(), LimitExceeded> {
+    self.check_entry_count(data.entries.len())?;
+    data.entries.iter().try_for_each(|entry| self.check_entry(entry))
+  }
+}
+
+/// Serialized JSON size, counted without buffering (the value may hold
+/// secrets that would otherwise be copied to an unzeroized buffer).
+fn serialized_len<T: Serialize>(value: &T) -> usize {
+  struct Counter(usize);
+  impl io::Write for Counter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+      self.0 += buf.len();
+      Ok(buf.len())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+      Ok(())
+    }
+  }
+  let mut counter = Counter(0);
+  // Serializing plain data into a writer that never fails cannot fail.
+  let _ = serde_json::to_writer(&mut counter, value);
+  counter.0
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn reports_the_field_that_is_too_long() {
+    let limits = Limits {
+      max_field_bytes: 32,
+      ..Limits::default()
+    };
+    let mut entry = Entry::new("t".into(), "u".into(), "p".into(), String::new(), String::new());
+    assert!(limits.check_entry(&entry).is_ok());
+
+    entry.notes = "x".repeat(33);
+    match limits.check_entry(&entry) {
+      Err(LimitExceeded::FieldLength { field, len, .. }) => assert_eq!((field, len), ("notes", 33)),
+      other => panic!("unexpected {other:?}"),
+    }
+  }
+
+  #[test]
+  fn entry_count_and_file_size() {
+    let limits = Limits {
+      max_file_bytes: 10,
+      max_entries: 1,
+      ..Limits::default()
+    };
+    assert!(limits.check_file_size(10).is_ok());
+    assert!(limits.check_file_size(11).is_err());
+    assert!(limits.check_entry_count(2).is_err());
+  }
+}
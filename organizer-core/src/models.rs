@@ -0,0 +1,751 @@
+//! Vault data model shared by the app and other tools.
+//!
+//! - [`Entry`] - A vault item with secure memory handling
+//! - [`EntryKind`] - Item type and its type-specific fields
+//! - [`VaultData`] - Decrypted vault payload (metadata + entries)
+//!
+//! All sensitive data implements [`Zeroize`] to securely clear memory on drop.
+
+use crate::vault::{CipherSuite, KdfParams};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// Filename for the encrypted vault file.
+pub const VAULT_FILENAME: &str = "vault.dat";
+
+/// Filename for the SQLite-backed vault (feature `sqlite`); preferred over
+/// [`VAULT_FILENAME`] when present.
+pub const SQLITE_VAULT_FILENAME: &str = "vault.db";
+
+/// Current vault file format version (v2: cipher suite and KDF parameters in the header).
+pub const VAULT_FORMAT_VERSION: u8 = 0x02;
+
+/// Length of the salt used for key derivation (32 bytes).
+pub const SALT_LEN: usize = 32;
+
+/// Length of the XChaCha20-Poly1305 nonce (24 bytes).
+pub const NONCE_LEN: usize = 24;
+
+/// Personal details used for filling address and contact forms.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Zeroize)]
+#[serde(default)]
+pub struct IdentityFields {
+  pub full_name: String,
+  pub given_name: String,
+  pub family_name: String,
+  pub email: String,
+  pub phone: String,
+  pub address_line1: String,
+  pub address_line2: String,
+  pub city: String,
+  pub region: String,
+  pub postal_code: String,
+  pub country: String,
+}
+
+impl IdentityFields {
+  /// Field names accepted by [`IdentityFields::get`].
+  pub const FIELD_NAMES: &'static [&'static str] = &[
+    "full_name",
+    "given_name",
+    "family_name",
+    "email",
+    "phone",
+    "address_line1",
+    "address_line2",
+    "city",
+    "region",
+    "postal_code",
+    "country",
+  ];
+
+  /// Looks up a field by its serialized name.
+  pub fn get(&self, name: &str) -> Option<&str> {
+    let value = match name {
+      "full_name" => &self.full_name,
+      "given_name" => &self.given_name,
+      "family_name" => &self.family_name,
+      "email" => &self.email,
+      "phone" => &self.phone,
+      "address_line1" => &self.address_line1,
+      "address_line2" => &self.address_line2,
+      "city" => &self.city,
+      "region" => &self.region,
+      "postal_code" => &self.postal_code,
+      "country" => &self.country,
+      _ => return None,
+    };
+    Some(value.as_str())
+  }
+}
+
+/// Payment card details.
+///
+/// `number` and `cvv` are secrets: they are never sent to the frontend and
+/// can only be copied via `copy_field`. The security code is optional so
+/// users can choose not to store it at all.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Zeroize)]
+#[serde(default)]
+pub struct CardFields {
+  pub cardholder_name: String,
+  /// Card number, digits only.
+  pub number: String,
+  /// Expiry as `MM/YY`.
+  pub expiry: String,
+  pub cvv: Option<String>,
+}
+
+impl CardFields {
+  /// Returns the card network inferred from the number's prefix.
+  pub fn brand(&self) -> &'static str {
+    let n = self.number.as_str();
+    let prefix = |len: usize| n.get(..len).and_then(|p| p.parse::<u32>().ok()).unwrap_or(0);
+    if n.starts_with('4') {
+      "visa"
+    } else if (51..=55).contains(&prefix(2)) || (2221..=2720).contains(&prefix(4)) {
+      "mastercard"
+    } else if n.starts_with("34") || n.starts_with("37") {
+      "amex"
+    } else if n.starts_with("6011") || n.starts_with("65") || (644..=649).contains(&prefix(3)) {
+      "discover"
+    } else if n.starts_with("35") {
+      "jcb"
+    } else if n.starts_with("36") || n.starts_with("38") || (300..=305).contains(&prefix(3)) {
+      "diners"
+    } else {
+      "unknown"
+    }
+  }
+
+  /// Returns the last four digits of the card number.
+  pub fn last4(&self) -> String {
+    let digits: Vec<char> = self.number.chars().collect();
+    digits[digits.len().saturating_sub(4)..].iter().collect()
+  }
+}
+
+/// An SSH keypair.
+///
+/// `private_key` (OpenSSH PEM) is secret and only leaves the backend through
+/// `copy_field`; the remaining fields are public.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Zeroize)]
+#[serde(default)]
+pub struct SshKeyFields {
+  /// OpenSSH algorithm name, e.g. `ssh-ed25519`.
+  pub algorithm: String,
+  pub private_key: String,
+  /// Public key in `authorized_keys` format.
+  pub public_key: String,
+  /// SHA-256 fingerprint (`SHA256:...`).
+  pub fingerprint: String,
+  pub comment: String,
+}
+
+/// An exported PGP/GPG keypair.
+///
+/// The ASCII-armored `private_key` and its `passphrase` are secret and only
+/// leave the backend through `copy_field`. Armored blocks are stored verbatim
+/// so they survive round-trips that would mangle them in free-form notes.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Zeroize)]
+#[serde(default)]
+pub struct PgpKeyFields {
+  /// Primary user id, e.g. `Alice <alice@example.com>`.
+  pub user_id: String,
+  /// Key fingerprint as shown by `gpg --fingerprint`.
+  pub fingerprint: String,
+  /// Armored public key block (optional).
+  pub public_key: String,
+  /// Armored secret key block.
+  pub private_key: String,
+  /// Passphrase protecting the secret key, if any.
+  pub passphrase: Option<String>,
+}
+
+/// An API key or access token.
+///
+/// `token` is secret and only leaves the backend through `copy_field`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApiKeyFields {
+  /// Service the token belongs to, e.g. `GitHub` or `Stripe`.
+  pub service: String,
+  pub token: String,
+  /// Granted scopes or permissions.
+  pub scopes: Vec<String>,
+  /// When the token stops working, if it expires.
+  pub expires_at: Option<DateTime<Utc>>,
+  /// Deployment environment, e.g. `production` or `staging`.
+  pub environment: String,
+  /// Environment variable name used by `.env` export and `run-with-env`;
+  /// derived from the entry title when empty.
+  pub env_var: String,
+}
+
+impl Zeroize for ApiKeyFields {
+  fn zeroize(&mut self) {
+    self.service.zeroize();
+    self.token.zeroize();
+    self.scopes.zeroize();
+    self.environment.zeroize();
+    self.env_var.zeroize();
+  }
+}
+
+/// The type of an entry together with its type-specific fields.
+///
+/// Stored internally tagged (`"type": "identity", ...`); entries written
+/// before item types existed deserialize as [`EntryKind::Login`].
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EntryKind {
+  /// Website or app login (username + password).
+  #[default]
+  Login,
+  /// Name, address, and contact details for form filling.
+  Identity(Box<IdentityFields>),
+  /// Payment card with secret number and security code.
+  Card(Box<CardFields>),
+  /// SSH keypair with a secret private key.
+  SshKey(Box<SshKeyFields>),
+  /// Exported PGP secret key with its passphrase.
+  PgpKey(Box<PgpKeyFields>),
+  /// API key or token with expiry and scope metadata.
+  ApiKey(Box<ApiKeyFields>),
+}
+
+impl EntryKind {
+  /// The serialized `type` tag, e.g. `"api_key"`.
+  pub fn type_name(&self) -> &'static str {
+    match self {
+      EntryKind::Login => "login",
+      EntryKind::Identity(_) => "identity",
+      EntryKind::Card(_) => "card",
+      EntryKind::SshKey(_) => "ssh_key",
+      EntryKind::PgpKey(_) => "pgp_key",
+      EntryKind::ApiKey(_) => "api_key",
+    }
+  }
+}
+
+impl Zeroize for EntryKind {
+  fn zeroize(&mut self) {
+    match self {
+      EntryKind::Login => {}
+      EntryKind::Identity(fields) => fields.zeroize(),
+      EntryKind::Card(fields) => fields.zeroize(),
+      EntryKind::SshKey(fields) => fields.zeroize(),
+      EntryKind::PgpKey(fields) => fields.zeroize(),
+      EntryKind::ApiKey(fields) => fields.zeroize(),
+    }
+  }
+}
+
+/// A password entry stored in the vault.
+///
+/// Each entry contains credentials for a single account or service.
+/// Sensitive fields (especially `password`) are securely zeroized when
+/// the entry is dropped, preventing them from lingering in memory.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Entry {
+  /// Unique identifier (UUID v4).
+  pub id: String,
+  /// Display name for the entry.
+  pub title: String,
+  /// Username or email for the account.
+  pub username: String,
+  /// The secret password (zeroized on drop).
+  pub password: String,
+  /// URL of the service or website.
+  pub url: String,
+  /// Additional notes about the entry.
+  pub notes: String,
+  /// Timestamp when the entry was created.
+  pub created_at: DateTime<Utc>,
+  /// Timestamp of the last modification.
+  pub updated_at: DateTime<Utc>,
+  /// Incremented on every change and never lowered, so a writer can tell
+  /// whether the entry changed since it was read. 0 for entries written
+  /// before revisions existed.
+  #[serde(default)]
+  pub revision: u64,
+  /// Timestamp of the last secret use (copy or extension fill), if any.
+  #[serde(default)]
+  pub last_used_at: Option<DateTime<Utc>>,
+  /// Base32 TOTP shared secret, if two-factor codes are configured (zeroized on drop).
+  #[serde(default)]
+  pub totp_secret: Option<String>,
+  /// Id of another entry whose password this entry shares (e.g. one SSO
+  /// account used by several tools). When set, `password` is unused.
+  #[serde(default)]
+  pub linked_to: Option<String>,
+  /// Item type and type-specific fields.
+  #[serde(default)]
+  pub kind: EntryKind,
+  /// Free-form labels, e.g. a project name.
+  #[serde(default)]
+  pub tags: Vec<String>,
+  /// Folder the entry is filed in; `None` is the top level.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub folder: Option<String>,
+  /// Which fields changed at each edit, oldest first.
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub history: Vec<EntryChange>,
+  /// Left out of entry lists and extension matches; found only by an exact
+  /// title search after re-entering the master password.
+  #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+  pub hidden: bool,
+}
+
+impl Entry {
+  /// Creates a new entry with a generated UUID and current timestamp.
+  pub fn new(title: String, username: String, password: String, url: String, notes: String) -> Self {
+    let now = Utc::now();
+    Self {
+      id: Uuid::new_v4().to_string(),
+      title,
+      username,
+      password,
+      url,
+      notes,
+      created_at: now,
+      updated_at: now,
+      revision: 0,
+      last_used_at: None,
+      totp_secret: None,
+      linked_to: None,
+      kind: EntryKind::Login,
+      tags: Vec::new(),
+      folder: None,
+      history: Vec::new(),
+      hidden: false,
+    }
+  }
+
+  /// Records a change now: updates `updated_at` and the revision.
+  pub fn touch(&mut self) {
+    self.touch_at(Utc::now());
+  }
+
+  /// Records a change made at `at`.
+  pub fn touch_at(&mut self, at: DateTime<Utc>) {
+    self.updated_at = at;
+    self.revision = self.revision.saturating_add(1);
+  }
+
+  /// Returns true if a non-empty TOTP secret is configured.
+  pub fn has_totp(&self) -> bool {
+    self.totp_secret.as_deref().is_some_and(|s| !s.trim().is_empty())
+  }
+
+  /// Returns true if the entry carries `tag` (case-insensitive).
+  pub fn has_tag(&self, tag: &str) -> bool {
+    self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag.trim()))
+  }
+
+  /// Records that the secret was just used.
+  pub fn mark_used(&mut self) {
+    self.last_used_at = Some(Utc::now());
+  }
+}
+
+impl Zeroize for Entry {
+  fn zeroize(&mut self) {
+    self.id.zeroize();
+    self.title.zeroize();
+    self.username.zeroize();
+    self.password.zeroize();
+    self.url.zeroize();
+    self.notes.zeroize();
+    self.totp_secret.zeroize();
+    self.linked_to.zeroize();
+    self.kind.zeroize();
+    self.tags.zeroize();
+    self.history.zeroize();
+  }
+}
+
+impl Drop for Entry {
+  fn drop(&mut self) {
+    self.zeroize();
+  }
+}
+
+/// One changed field of an entry edit.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Zeroize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FieldChange {
+  /// A non-secret field, with its values before and after.
+  Text { field: String, old: String, new: String },
+  /// A secret field. Only whether it held a value is recorded.
+  Secret { field: String, was_set: bool, is_set: bool },
+}
+
+/// The fields changed by one edit of an entry.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntryChange {
+  /// Revision the edit produced.
+  pub revision: u64,
+  pub at: DateTime<Utc>,
+  /// Label of the device the edit was made on, if known.
+  #[serde(default)]
+  pub device: Option<String>,
+  pub fields: Vec<FieldChange>,
+}
+
+impl Zeroize for EntryChange {
+  fn zeroize(&mut self) {
+    self.device.zeroize();
+    self.fields.zeroize();
+  }
+}
+
+/// Aggregate password health at one point in time, for the security trend.
+/// Counts only, no entry ids.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SecuritySnapshot {
+  pub taken_at: DateTime<Utc>,
+  pub entries: usize,
+  pub weak: usize,
+  pub reused: usize,
+}
+
+/// Issues found by the last scheduled security audit, so the next one can
+/// tell new issues from known ones. Issues are keys like `weak/<entry id>`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditCheckpoint {
+  pub ran_at: DateTime<Utc>,
+  pub issues: Vec<String>,
+}
+
+/// Criteria of a saved search. Empty criteria match every entry.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchFilter {
+  /// Free text, matched like the quick-access search.
+  pub query: String,
+  /// Entries must carry all of these tags.
+  pub tags: Vec<String>,
+  /// Entry type name, e.g. `login` or `api_key`.
+  #[serde(rename = "type")]
+  pub kind: Option<String>,
+  pub folder: Option<String>,
+}
+
+/// A named filter ("smart folder") kept in the vault.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedSearch {
+  pub id: String,
+  pub name: String,
+  pub filter: SearchFilter,
+}
+
+/// Relay this vault syncs through.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncRelay {
+  pub url: String,
+  /// Hex-encoded 32-byte secret naming the vault's blob on the relay. It
+  /// grants access to the ciphertext only, never to the vault key.
+  pub secret: String,
+}
+
+/// A device enrolled to sync this vault.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EnrolledDevice {
+  pub id: String,
+  pub label: String,
+  pub enrolled_at: DateTime<Utc>,
+}
+
+/// Key of another vault file this vault writes (a folder export or a
+/// shared vault), derived once from that file's own password so later
+/// writes need no password. Kept only inside the encrypted vault.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportKey {
+  pub cipher: CipherSuite,
+  pub kdf: KdfParams,
+  /// Hex-encoded KDF salt.
+  pub salt: String,
+  /// Hex-encoded 32-byte derived key.
+  pub key: String,
+}
+
+/// A password from the generator, kept in case it was used before its entry
+/// was saved.
+#[derive(Clone, Debug, Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+pub struct GeneratedPassword {
+  pub password: String,
+  /// What it was generated for, e.g. the title of the entry being edited.
+  #[serde(default)]
+  pub label: Option<String>,
+  #[zeroize(skip)]
+  pub generated_at: DateTime<Utc>,
+}
+
+/// A folder exported to its own password-protected vault file.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FolderExport {
+  /// Also the id of the exported vault, so every re-export is the same vault.
+  pub id: String,
+  pub folder: String,
+  pub path: String,
+  /// Days between automatic re-exports; `None` re-exports only on request.
+  #[serde(default)]
+  pub every_days: Option<u32>,
+  pub key: ExportKey,
+  /// Time of the latest export attempt.
+  #[serde(default)]
+  pub last_run: Option<DateTime<Utc>>,
+  /// Set when the latest export attempt failed.
+  #[serde(default)]
+  pub last_error: Option<String>,
+}
+
+/// A shared vault file whose entries are mirrored in a local folder.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SharedVault {
+  /// The shared file's vault id, also its key in `VaultMeta::last_merged`.
+  pub id: String,
+  pub folder: String,
+  pub path: String,
+  pub key: ExportKey,
+  /// Time of the latest sync attempt.
+  #[serde(default)]
+  pub last_attempt: Option<DateTime<Utc>>,
+  /// Set when the latest sync attempt failed.
+  #[serde(default)]
+  pub last_error: Option<String>,
+}
+
+/// Vault-level metadata stored inside the encrypted payload.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct VaultMeta {
+  /// Stable identifier for this vault (UUID v4), assigned at creation.
+  pub id: String,
+  /// Timestamp when the vault was created.
+  pub created_at: DateTime<Utc>,
+  /// Periodic security snapshots, oldest first.
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub security_history: Vec<SecuritySnapshot>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub last_security_audit: Option<AuditCheckpoint>,
+  /// User-chosen entry order per folder, as entry ids. The top level is
+  /// keyed by `""`.
+  #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+  pub manual_order: BTreeMap<String, Vec<String>>,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub saved_searches: Vec<SavedSearch>,
+  /// When another copy of the vault was last merged in, by peer id.
+  #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+  pub last_merged: BTreeMap<String, DateTime<Utc>>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub sync: Option<SyncRelay>,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub devices: Vec<EnrolledDevice>,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub folder_exports: Vec<FolderExport>,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub shared_vaults: Vec<SharedVault>,
+  /// Recently generated passwords, newest first, when the user keeps the
+  /// generator history across locks.
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub generated_passwords: Vec<GeneratedPassword>,
+  /// Id of a decoy entry whose secret the owner never reads; any read of it
+  /// raises an alarm.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub canary_entry: Option<String>,
+}
+
+impl VaultMeta {
+  /// Creates metadata for a brand-new vault.
+  pub fn new() -> Self {
+    Self {
+      id: Uuid::new_v4().to_string(),
+      created_at: Utc::now(),
+      security_history: Vec::new(),
+      last_security_audit: None,
+      manual_order: BTreeMap::new(),
+      saved_searches: Vec::new(),
+      last_merged: BTreeMap::new(),
+      sync: None,
+      devices: Vec::new(),
+      folder_exports: Vec::new(),
+      shared_vaults: Vec::new(),
+      generated_passwords: Vec::new(),
+      canary_entry: None,
+    }
+  }
+}
+
+impl Default for VaultMeta {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+/// Entry fields that are safe to hand to external tools (no secrets).
+#[derive(Clone, Debug, Serialize)]
+pub struct EntrySummary<'a> {
+  pub id: &'a str,
+  pub title: &'a str,
+  pub username: &'a str,
+  pub url: &'a str,
+  #[serde(rename = "type")]
+  pub kind: &'static str,
+  pub linked_to: Option<&'a str>,
+}
+
+/// A deleted entry, kept until it is restored or purged.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TrashedEntry {
+  pub entry: Entry,
+  pub deleted_at: DateTime<Utc>,
+}
+
+/// Record of a deleted entry, so a merge can tell an entry deleted on one
+/// side from one the other side never had.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tombstone {
+  pub id: String,
+  pub deleted_at: DateTime<Utc>,
+}
+
+/// Decrypted vault contents held in memory while unlocked.
+///
+/// This is the JSON document that gets encrypted into `vault.dat`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct VaultData {
+  /// Vault identity and creation details.
+  pub meta: VaultMeta,
+  /// All password entries.
+  pub entries: Vec<Entry>,
+  /// Deleted entries, oldest deletion first.
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub trash: Vec<TrashedEntry>,
+  /// Deleted entry ids, kept after the trash is emptied so a merge does
+  /// not bring deleted entries back. Pruned after a retention period.
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub tombstones: Vec<Tombstone>,
+  /// Set when the payload was read from the pre-metadata format (a bare
+  /// entry array). Callers should persist the vault to upgrade it.
+  #[serde(skip)]
+  pub legacy_payload: bool,
+}
+
+impl VaultData {
+  /// Finds an entry by id.
+  pub fn find_entry(&self, id: &str) -> Option<&Entry> {
+    self.entries.iter().find(|e| e.id == id)
+  }
+
+  /// Returns the entry holding the password for `id`, following a link.
+  ///
+  /// Links are a single level deep (enforced when they are created), so a
+  /// linked entry always resolves to an unlinked one.
+  pub fn resolve_password_source(&self, id: &str) -> Option<&Entry> {
+    let entry = self.find_entry(id)?;
+    match entry.linked_to.as_deref() {
+      Some(target) => self.find_entry(target),
+      None => Some(entry),
+    }
+  }
+
+  /// Non-secret summaries of all entries, for listing in external tools.
+  pub fn summaries(&self) -> Vec<EntrySummary<'_>> {
+    self
+      .entries
+      .iter()
+      .map(|e| EntrySummary {
+        id: &e.id,
+        title: &e.title,
+        username: &e.username,
+        url: &e.url,
+        kind: e.kind.type_name(),
+        linked_to: e.linked_to.as_deref(),
+      })
+      .collect()
+  }
+
+  /// Ids of entries that link to `id`.
+  pub fn entries_linking_to(&self, id: &str) -> Vec<&str> {
+    self
+      .entries
+      .iter()
+      .filter(|e| e.linked_to.as_deref() == Some(id))
+      .map(|e| e.id.as_str())
+      .collect()
+  }
+
+  /// Moves entry `id` to the trash. Returns false if there is no such entry.
+  pub fn trash_entry(&mut self, id: &str, now: DateTime<Utc>) -> bool {
+    let Some(index) = self.entries.iter().position(|e| e.id == id) else {
+      return false;
+    };
+    let entry = self.entries.remove(index);
+    self.trash.push(TrashedEntry { entry, deleted_at: now });
+    self.record_deletion(id, now);
+    true
+  }
+
+  /// Moves entry `id` from the trash back to the entries. The entry counts
+  /// as edited at `now`, so merges keep it over the earlier deletion.
+  pub fn restore_entry(&mut self, id: &str, now: DateTime<Utc>) -> Option<&Entry> {
+    let index = self.trash.iter().position(|t| t.entry.id == id)?;
+    let mut trashed = self.trash.remove(index);
+    trashed.entry.touch_at(now);
+    self.tombstones.retain(|t| t.id != id);
+    self.entries.push(trashed.entry);
+    self.entries.last()
+  }
+
+  /// Records that entry `id` was deleted at `at`, keeping the latest time.
+  pub fn record_deletion(&mut self, id: &str, at: DateTime<Utc>) {
+    match self.tombstones.iter_mut().find(|t| t.id == id) {
+      Some(tombstone) => tombstone.deleted_at = tombstone.deleted_at.max(at),
+      None => self.tombstones.push(Tombstone {
+        id: id.to_string(),
+        deleted_at: at,
+      }),
+    }
+  }
+
+  /// When entry `id` was deleted, if a tombstone or the trash says so.
+  pub fn deleted_at(&self, id: &str) -> Option<DateTime<Utc>> {
+    let tombstone = self.tombstones.iter().find(|t| t.id == id).map(|t| t.deleted_at);
+    let trashed = self.trash.iter().find(|t| t.entry.id == id).map(|t| t.deleted_at);
+    tombstone.max(trashed)
+  }
+
+  /// Drops tombstones recorded before `cutoff`. Returns how many were
+  /// dropped.
+  pub fn purge_tombstones(&mut self, cutoff: DateTime<Utc>) -> usize {
+    let before = self.tombstones.len();
+    self.tombstones.retain(|t| t.deleted_at >= cutoff);
+    before - self.tombstones.len()
+  }
+
+  /// Drops entries deleted before `cutoff`. Returns how many were dropped.
+  pub fn purge_trash(&mut self, cutoff: DateTime<Utc>) -> usize {
+    let before = self.trash.len();
+    self.trash.retain(|t| t.deleted_at >= cutoff);
+    before - self.trash.len()
+  }
+
+  /// Wraps entries from a pre-metadata payload, backfilling the creation
+  /// date from the oldest entry.
+  pub fn from_legacy(entries: Vec<Entry>) -> Self {
+    let mut meta = VaultMeta::new();
+    if let Some(oldest) = entries.iter().map(|e| e.created_at).min() {
+      meta.created_at = oldest;
+    }
+    Self {
+      meta,
+      entries,
+      trash: Vec::new(),
+      tombstones: Vec::new(),
+      legacy_payload: true,
+    }
+  }
+}
@@ -0,0 +1,186 @@
+//! SQLite storage backend (feature `sqlite`).
+//!
+//! Each entry is sealed separately and stored as its own row, so changing
+//! one entry rewrites one row instead of the whole vault, and single entries
+//! can be loaded by id. Rows hold only ciphertext keyed by entry id; titles,
+//! URLs, and timestamps never appear in plaintext.
+//!
+//! ```text
+//! header(id = 0, data)        sealed vault with metadata, no entries
+//! entries(id PRIMARY KEY, data) [24B nonce][ciphertext+tag] per entry
+//! ```
+
+use crate::store::{RecordStore, VaultStore};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+const SCHEMA: &str = "
+  CREATE TABLE IF NOT EXISTS header (
+    id INTEGER PRIMARY KEY CHECK (id = 0),
+    data BLOB NOT NULL
+  );
+  CREATE TABLE IF NOT EXISTS entries (
+    id TEXT PRIMARY KEY NOT NULL,
+    data BLOB NOT NULL
+  );
+";
+
+fn sql_err(e: rusqlite::Error) -> io::Error {
+  io::Error::other(format!("sqlite: {e}"))
+}
+
+/// Vault stored as per-entry rows in a SQLite database.
+#[derive(Debug)]
+pub struct SqliteStore {
+  path: PathBuf,
+  conn: Mutex<Connection>,
+}
+
+impl SqliteStore {
+  /// Opens (or creates) the database at `path` and ensures the schema exists.
+  pub fn open(path: impl Into<PathBuf>) -> io::Result<Self> {
+    let path = path.into();
+    if let Some(parent) = path.parent() {
+      std::fs::create_dir_all(parent)?;
+    }
+    let conn = Connection::open(&path).map_err(sql_err)?;
+    conn.execute_batch(SCHEMA).map_err(sql_err)?;
+    Ok(Self {
+      path,
+      conn: Mutex::new(conn),
+    })
+  }
+
+  fn conn(&self) -> io::Result<MutexGuard<'_, Connection>> {
+    self.conn.lock().map_err(|_| io::Error::other("sqlite connection mutex poisoned"))
+  }
+}
+
+impl VaultStore for SqliteStore {
+  fn exists(&self) -> bool {
+    matches!(self.header(), Ok(Some(_)))
+  }
+
+  fn read(&self) -> io::Result<Vec<u8>> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "sqlite store is record-based"))
+  }
+
+  fn write(&self, _bytes: &[u8]) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "sqlite store is record-based"))
+  }
+
+  fn location(&self) -> String {
+    self.path.display().to_string()
+  }
+
+  fn path(&self) -> Option<&Path> {
+    Some(&self.path)
+  }
+
+  fn records(&self) -> Option<&dyn RecordStore> {
+    Some(self)
+  }
+}
+
+impl RecordStore for SqliteStore {
+  fn header(&self) -> io::Result<Option<Vec<u8>>> {
+    self
+      .conn()?
+      .query_row("SELECT data FROM header WHERE id = 0", [], |row| row.get(0))
+      .optional()
+      .map_err(sql_err)
+  }
+
+  fn record(&self, id: &str) -> io::Result<Option<Vec<u8>>> {
+    self
+      .conn()?
+      .query_row("SELECT data FROM entries WHERE id = ?1", params![id], |row| row.get(0))
+      .optional()
+      .map_err(sql_err)
+  }
+
+  fn all_records(&self) -> io::Result<Vec<(String, Vec<u8>)>> {
+    let conn = self.conn()?;
+    let mut stmt = conn.prepare("SELECT id, data FROM entries ORDER BY rowid").map_err(sql_err)?;
+    let rows = stmt
+      .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+      .map_err(sql_err)?;
+    rows.collect::<Result<Vec<_>, _>>().map_err(sql_err)
+  }
+
+  fn record_ids(&self) -> io::Result<Vec<String>> {
+    let conn = self.conn()?;
+    let mut stmt = conn.prepare("SELECT id FROM entries ORDER BY rowid").map_err(sql_err)?;
+    let rows = stmt.query_map([], |row| row.get(0)).map_err(sql_err)?;
+    rows.collect::<Result<Vec<_>, _>>().map_err(sql_err)
+  }
+
+  fn apply(&self, header: Option<&[u8]>, upserts: &[(String, Vec<u8>)], deletes: &[String]) -> io::Result<()> {
+    let mut conn = self.conn()?;
+    let tx = conn.transaction().map_err(sql_err)?;
+    if let Some(header) = header {
+      tx.execute(
+        "INSERT INTO header (id, data) VALUES (0, ?1) ON CONFLICT(id) DO UPDATE SET data = excluded.data",
+        params![header],
+      )
+      .map_err(sql_err)?;
+    }
+    for (id, data) in upserts {
+      tx.execute(
+        "INSERT INTO entries (id, data) VALUES (?1, ?2) ON CONFLICT(id) DO UPDATE SET data = excluded.data",
+        params![id, data],
+      )
+      .map_err(sql_err)?;
+    }
+    for id in deletes {
+      tx.execute("DELETE FROM entries WHERE id = ?1", params![id]).map_err(sql_err)?;
+    }
+    tx.commit().map_err(sql_err)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::models::{Entry, VaultData};
+  use crate::vault;
+
+  #[test]
+  fn stores_entries_as_rows_and_writes_incrementally() {
+    let path = std::env::temp_dir().join(format!("the-organizer-test-sqlite-{}.db", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let store = SqliteStore::open(&path).expect("open");
+    assert!(!store.exists());
+
+    let header = vault::VaultHeader::default();
+    let key = header.derive_key("sqlite").expect("kdf");
+    let mut data = VaultData::default();
+    for title in ["a", "b"] {
+      data
+        .entries
+        .push(Entry::new(title.to_string(), String::new(), "pw".to_string(), String::new(), String::new()));
+    }
+    vault::save_to_store(&store, &data, &header, &key).expect("save");
+    assert_eq!(store.record_ids().expect("ids").len(), 2);
+
+    let removed = data.entries.remove(0).id.clone();
+    data.entries[0].title = "b2".to_string();
+    let kept = data.entries[0].id.clone();
+    vault::save_entries(&store, &data, &header, &key, &[&removed, &kept]).expect("save entries");
+
+    let (loaded, loaded_header, _) = vault::load_from_store(&store, "sqlite").expect("load");
+    assert_eq!(loaded_header, header);
+    assert_eq!(loaded.meta.id, data.meta.id);
+    assert_eq!(loaded.entries.len(), 1);
+    assert_eq!(loaded.entries[0].title, "b2");
+
+    let single = vault::load_entry(&store, &header, &data.meta.id, &kept, &key).expect("load entry");
+    assert_eq!(single.map(|e| e.password.clone()), Some("pw".to_string()));
+    assert!(vault::load_from_store(&store, "wrong").is_err());
+
+    drop(store);
+    let _ = std::fs::remove_file(&path);
+  }
+}
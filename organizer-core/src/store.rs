@@ -0,0 +1,173 @@
+//! Storage backends for the encrypted vault blob.
+//!
+//! [`VaultStore`] only moves opaque, already-encrypted bytes; all
+//! cryptography stays in [`crate::vault`]. [`FileStore`] is what the app
+//! uses; [`MemoryStore`] lets the command layer run in tests or embedders
+//! without touching the filesystem. Other backends (sync, SQLite) implement
+//! the same trait.
+//!
+//! Backends that can store entries individually also expose a
+//! [`RecordStore`]; the vault is then sealed per entry so single-entry
+//! changes are written incrementally and entries can be loaded on demand.
+
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Persistence for the encrypted vault file.
+pub trait VaultStore: Send + Sync + fmt::Debug {
+  /// Returns true if a vault has been written to this store.
+  fn exists(&self) -> bool;
+
+  /// Reads the full encrypted vault. Fails with `NotFound` if none exists.
+  fn read(&self) -> io::Result<Vec<u8>>;
+
+  /// Replaces the stored vault with `bytes`.
+  fn write(&self, bytes: &[u8]) -> io::Result<()>;
+
+  /// Size of the stored vault in bytes, if known without reading it, so
+  /// oversized files can be rejected before they are loaded into memory.
+  fn size(&self) -> io::Result<Option<u64>> {
+    Ok(None)
+  }
+
+  /// Human-readable location, e.g. for the emergency kit.
+  fn location(&self) -> String;
+
+  /// Filesystem path of the vault, if the store is file-backed.
+  ///
+  /// Sidecar files such as the audit log are placed next to it.
+  fn path(&self) -> Option<&Path> {
+    None
+  }
+
+  /// Record-level access for backends that store entries individually.
+  ///
+  /// When this returns `Some`, [`crate::vault`] uses it instead of
+  /// [`VaultStore::read`] and [`VaultStore::write`].
+  fn records(&self) -> Option<&dyn RecordStore> {
+    None
+  }
+}
+
+/// Per-entry storage of individually sealed vault records.
+///
+/// The header is a sealed vault holding the salt and metadata but no
+/// entries; each record is one entry sealed under the same key.
+pub trait RecordStore {
+  /// Reads the sealed header, if a vault has been written.
+  fn header(&self) -> io::Result<Option<Vec<u8>>>;
+
+  /// Reads a single sealed record by entry id.
+  fn record(&self, id: &str) -> io::Result<Option<Vec<u8>>>;
+
+  /// Reads all sealed records as `(entry id, bytes)` in insertion order.
+  fn all_records(&self) -> io::Result<Vec<(String, Vec<u8>)>>;
+
+  /// Lists the ids of all stored records.
+  fn record_ids(&self) -> io::Result<Vec<String>>;
+
+  /// Atomically replaces the header (if given), upserts and deletes records.
+  fn apply(&self, header: Option<&[u8]>, upserts: &[(String, Vec<u8>)], deletes: &[String]) -> io::Result<()>;
+}
+
+/// Vault stored as a single file on disk.
+#[derive(Clone, Debug)]
+pub struct FileStore {
+  path: PathBuf,
+}
+
+impl FileStore {
+  pub fn new(path: impl Into<PathBuf>) -> Self {
+    Self { path: path.into() }
+  }
+}
+
+impl VaultStore for FileStore {
+  fn exists(&self) -> bool {
+    self.path.exists()
+  }
+
+  fn read(&self) -> io::Result<Vec<u8>> {
+    fs::read(&self.path)
+  }
+
+  fn size(&self) -> io::Result<Option<u64>> {
+    Ok(Some(fs::metadata(&self.path)?.len()))
+  }
+
+  /// Writes a temporary file next to the vault and renames it into place,
+  /// so a crash mid-write leaves either the old or the new vault.
+  fn write(&self, bytes: &[u8]) -> io::Result<()> {
+    if let Some(parent) = self.path.parent() {
+      fs::create_dir_all(parent)?;
+    }
+    let mut tmp_name = self.path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp = self.path.with_file_name(tmp_name);
+
+    let mut file = fs::File::create(&tmp)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    drop(file);
+    fs::rename(&tmp, &self.path)
+  }
+
+  fn location(&self) -> String {
+    self.path.display().to_string()
+  }
+
+  fn path(&self) -> Option<&Path> {
+    Some(&self.path)
+  }
+}
+
+/// Vault kept in process memory; nothing is persisted.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+  bytes: Mutex<Option<Vec<u8>>>,
+}
+
+impl MemoryStore {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Creates a store pre-populated with an encrypted vault.
+  pub fn with_bytes(bytes: Vec<u8>) -> Self {
+    Self {
+      bytes: Mutex::new(Some(bytes)),
+    }
+  }
+}
+
+impl VaultStore for MemoryStore {
+  fn exists(&self) -> bool {
+    self.bytes.lock().map(|b| b.is_some()).unwrap_or(false)
+  }
+
+  fn read(&self) -> io::Result<Vec<u8>> {
+    let guard = self
+      .bytes
+      .lock()
+      .map_err(|_| io::Error::other("memory store mutex poisoned"))?;
+    guard
+      .clone()
+      .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no vault in memory store"))
+  }
+
+  fn write(&self, bytes: &[u8]) -> io::Result<()> {
+    let mut guard = self
+      .bytes
+      .lock()
+      .map_err(|_| io::Error::other("memory store mutex poisoned"))?;
+    *guard = Some(bytes.to_vec());
+    Ok(())
+  }
+
+  fn location(&self) -> String {
+    "(in memory)".to_string()
+  }
+}
@@ -0,0 +1,296 @@
+//! Chunked (STREAM) encryption of vault payloads.
+//!
+//! The payload is split into fixed-size chunks, each sealed with the
+//! vault's cipher under a nonce made of a random prefix, a big-endian chunk
+//! counter, and a last-chunk flag (the STREAM construction, "BE32" variant).
+//! Chunks cannot be reordered, dropped, or truncated without failing
+//! authentication.
+//!
+//! Serialization writes straight into the encryptor and deserialization
+//! reads straight from the decryptor, so the full plaintext is never held
+//! in memory next to the full ciphertext.
+
+use crate::vault::{CipherSuite, VaultError};
+use aes_gcm_siv::Aes256GcmSiv;
+use chacha20poly1305::aead::stream::{DecryptorBE32, EncryptorBE32};
+use chacha20poly1305::aead::{KeyInit, Payload};
+use chacha20poly1305::XChaCha20Poly1305;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::io::{self, Read, Write};
+use zeroize::Zeroizing;
+
+/// Bytes of the cipher nonce taken by the chunk counter and last-chunk flag.
+pub(crate) const NONCE_OVERHEAD: usize = 5;
+
+const TAG_LEN: usize = 16;
+
+// The AES key schedule is much larger than the ChaCha key, hence the box.
+enum Encryptor {
+  XChaCha(EncryptorBE32<XChaCha20Poly1305>),
+  Aes(Box<EncryptorBE32<Aes256GcmSiv>>),
+}
+
+enum Decryptor {
+  XChaCha(DecryptorBE32<XChaCha20Poly1305>),
+  Aes(Box<DecryptorBE32<Aes256GcmSiv>>),
+}
+
+fn crypto_error(e: chacha20poly1305::aead::Error) -> io::Error {
+  io::Error::new(io::ErrorKind::InvalidData, format!("chunk authentication failed: {e}"))
+}
+
+impl Encryptor {
+  fn new(cipher: CipherSuite, key: &[u8; 32], nonce_prefix: &[u8]) -> Self {
+    match cipher {
+      CipherSuite::XChaCha20Poly1305 => Encryptor::XChaCha(EncryptorBE32::from_aead(
+        XChaCha20Poly1305::new(key.into()),
+        nonce_prefix.into(),
+      )),
+      CipherSuite::Aes256GcmSiv => {
+        Encryptor::Aes(Box::new(EncryptorBE32::from_aead(Aes256GcmSiv::new(key.into()), nonce_prefix.into())))
+      }
+    }
+  }
+
+  fn next(&mut self, msg: &[u8], aad: &[u8]) -> io::Result<Vec<u8>> {
+    let payload = Payload { msg, aad };
+    match self {
+      Encryptor::XChaCha(e) => e.encrypt_next(payload),
+      Encryptor::Aes(e) => e.encrypt_next(payload),
+    }
+    .map_err(crypto_error)
+  }
+
+  fn last(self, msg: &[u8], aad: &[u8]) -> io::Result<Vec<u8>> {
+    let payload = Payload { msg, aad };
+    match self {
+      Encryptor::XChaCha(e) => e.encrypt_last(payload),
+      Encryptor::Aes(e) => (*e).encrypt_last(payload),
+    }
+    .map_err(crypto_error)
+  }
+}
+
+impl Decryptor {
+  fn new(cipher: CipherSuite, key: &[u8; 32], nonce_prefix: &[u8]) -> Self {
+    match cipher {
+      CipherSuite::XChaCha20Poly1305 => Decryptor::XChaCha(DecryptorBE32::from_aead(
+        XChaCha20Poly1305::new(key.into()),
+        nonce_prefix.into(),
+      )),
+      CipherSuite::Aes256GcmSiv => {
+        Decryptor::Aes(Box::new(DecryptorBE32::from_aead(Aes256GcmSiv::new(key.into()), nonce_prefix.into())))
+      }
+    }
+  }
+
+  fn next(&mut self, msg: &[u8], aad: &[u8]) -> io::Result<Vec<u8>> {
+    let payload = Payload { msg, aad };
+    match self {
+      Decryptor::XChaCha(d) => d.decrypt_next(payload),
+      Decryptor::Aes(d) => d.decrypt_next(payload),
+    }
+    .map_err(crypto_error)
+  }
+
+  fn last(self, msg: &[u8], aad: &[u8]) -> io::Result<Vec<u8>> {
+    let payload = Payload { msg, aad };
+    match self {
+      Decryptor::XChaCha(d) => d.decrypt_last(payload),
+      Decryptor::Aes(d) => (*d).decrypt_last(payload),
+    }
+    .map_err(crypto_error)
+  }
+}
+
+/// Buffers one chunk of plaintext at a time and appends sealed chunks to
+/// `out`. A full chunk is only sealed once more data arrives, so the final
+/// chunk (sealed by [`ChunkWriter::finish`]) is never empty unless the
+/// whole payload is.
+struct ChunkWriter<'a> {
+  encryptor: Option<Encryptor>,
+  aad: &'a [u8],
+  chunk_size: usize,
+  buf: Zeroizing<Vec<u8>>,
+  out: Vec<u8>,
+}
+
+impl Write for ChunkWriter<'_> {
+  fn write(&mut self, mut data: &[u8]) -> io::Result<usize> {
+    let written = data.len();
+    while !data.is_empty() {
+      if self.buf.len() == self.chunk_size {
+        let encryptor = self.encryptor.as_mut().ok_or_else(|| io::Error::other("stream finished"))?;
+        let sealed = encryptor.next(&self.buf, self.aad)?;
+        self.out.extend_from_slice(&sealed);
+        self.buf.clear();
+      }
+      let take = (self.chunk_size - self.buf.len()).min(data.len());
+      self.buf.extend_from_slice(&data[..take]);
+      data = &data[take..];
+    }
+    Ok(written)
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    Ok(())
+  }
+}
+
+impl ChunkWriter<'_> {
+  fn finish(mut self) -> io::Result<Vec<u8>> {
+    let encryptor = self.encryptor.take().ok_or_else(|| io::Error::other("stream finished"))?;
+    let sealed = encryptor.last(&self.buf, self.aad)?;
+    self.out.extend_from_slice(&sealed);
+    Ok(self.out)
+  }
+}
+
+/// Opens sealed chunks from `body` on demand.
+struct ChunkReader<'a> {
+  decryptor: Option<Decryptor>,
+  aad: &'a [u8],
+  sealed_chunk_len: usize,
+  body: &'a [u8],
+  plain: Zeroizing<Vec<u8>>,
+  plain_pos: usize,
+  /// Set when a chunk fails authentication (wrong key or tampering), so the
+  /// caller can tell that apart from malformed JSON.
+  auth_failed: bool,
+}
+
+impl ChunkReader<'_> {
+  fn finished(&self) -> bool {
+    self.decryptor.is_none() && self.plain_pos == self.plain.len()
+  }
+
+  fn open_next(&mut self) -> io::Result<()> {
+    let Some(mut decryptor) = self.decryptor.take() else {
+      return Ok(());
+    };
+    // Every chunk but the last is exactly one full sealed chunk.
+    let result = if self.body.len() > self.sealed_chunk_len {
+      let (chunk, rest) = self.body.split_at(self.sealed_chunk_len);
+      self.body = rest;
+      let opened = decryptor.next(chunk, self.aad);
+      self.decryptor = Some(decryptor);
+      opened
+    } else {
+      let chunk = std::mem::take(&mut self.body);
+      decryptor.last(chunk, self.aad)
+    };
+    match result {
+      Ok(plain) => {
+        self.plain = Zeroizing::new(plain);
+        self.plain_pos = 0;
+        Ok(())
+      }
+      Err(e) => {
+        self.auth_failed = true;
+        self.decryptor = None;
+        Err(e)
+      }
+    }
+  }
+}
+
+impl Read for ChunkReader<'_> {
+  fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+    while self.plain_pos == self.plain.len() {
+      if self.decryptor.is_none() {
+        return Ok(0);
+      }
+      self.open_next()?;
+    }
+    let n = (self.plain.len() - self.plain_pos).min(out.len());
+    out[..n].copy_from_slice(&self.plain[self.plain_pos..self.plain_pos + n]);
+    self.plain_pos += n;
+    Ok(n)
+  }
+}
+
+/// Serializes `value` as JSON and seals it in chunks of `chunk_size`
+/// plaintext bytes, each authenticated with `aad`.
+pub(crate) fn seal_json<T: Serialize>(
+  value: &T,
+  cipher: CipherSuite,
+  key: &[u8; 32],
+  nonce_prefix: &[u8],
+  aad: &[u8],
+  chunk_size: usize,
+) -> Result<Vec<u8>, VaultError> {
+  let mut writer = ChunkWriter {
+    encryptor: Some(Encryptor::new(cipher, key, nonce_prefix)),
+    aad,
+    chunk_size,
+    buf: Zeroizing::new(Vec::with_capacity(chunk_size)),
+    out: Vec::new(),
+  };
+  serde_json::to_writer(&mut writer, value).map_err(|e| VaultError::Json(e.to_string()))?;
+  writer.finish().map_err(|e| VaultError::Crypto(e.to_string()))
+}
+
+/// Opens chunks sealed by [`seal_json`] and deserializes the JSON inside.
+pub(crate) fn open_json<T: DeserializeOwned>(
+  body: &[u8],
+  cipher: CipherSuite,
+  key: &[u8; 32],
+  nonce_prefix: &[u8],
+  aad: &[u8],
+  chunk_size: usize,
+) -> Result<T, VaultError> {
+  let mut reader = ChunkReader {
+    decryptor: Some(Decryptor::new(cipher, key, nonce_prefix)),
+    aad,
+    sealed_chunk_len: chunk_size + TAG_LEN,
+    body,
+    plain: Zeroizing::new(Vec::new()),
+    plain_pos: 0,
+    auth_failed: false,
+  };
+  // `from_reader` reads to the end to reject trailing data, so every chunk
+  // including the last is authenticated before a value is returned.
+  let parsed = serde_json::from_reader(&mut reader);
+  if reader.auth_failed {
+    return Err(VaultError::Crypto("payload authentication failed".to_string()));
+  }
+  let value = parsed.map_err(|e| VaultError::Json(e.to_string()))?;
+  if !reader.finished() {
+    return Err(VaultError::Format("payload has unread chunks".to_string()));
+  }
+  Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn chunks_roundtrip_and_detect_truncation() {
+    let key = [7u8; 32];
+    let prefix = [1u8; 24 - NONCE_OVERHEAD];
+    let value: Vec<String> = (0..200).map(|i| format!("value-{i}")).collect();
+
+    let sealed = seal_json(&value, CipherSuite::XChaCha20Poly1305, &key, &prefix, b"hdr", 64).expect("seal");
+    assert!(sealed.len() > 3 * (64 + TAG_LEN));
+    let opened: Vec<String> =
+      open_json(&sealed, CipherSuite::XChaCha20Poly1305, &key, &prefix, b"hdr", 64).expect("open");
+    assert_eq!(opened, value);
+
+    // Dropping the final chunk makes the previous one look last.
+    let last_len = match sealed.len() % (64 + TAG_LEN) {
+      0 => 64 + TAG_LEN,
+      partial => partial,
+    };
+    let truncated = &sealed[..sealed.len() - last_len];
+    assert!(matches!(
+      open_json::<Vec<String>>(truncated, CipherSuite::XChaCha20Poly1305, &key, &prefix, b"hdr", 64),
+      Err(VaultError::Crypto(_))
+    ));
+    assert!(matches!(
+      open_json::<Vec<String>>(&sealed, CipherSuite::XChaCha20Poly1305, &key, &prefix, b"other", 64),
+      Err(VaultError::Crypto(_))
+    ));
+  }
+}
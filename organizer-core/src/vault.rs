@@ -0,0 +1,1341 @@
+//! Vault encryption and decryption module.
+//!
+//! This module handles all cryptographic operations for the password vault:
+//! - Key derivation using Argon2id (memory-hard KDF), or scrypt for
+//!   low-memory devices
+//! - Encryption/decryption using XChaCha20-Poly1305 (default) or AES-256-GCM-SIV
+//! - Vault file format management with versioning support
+//!
+//! # Security
+//!
+//! - **KDF**: Argon2id with 64 MiB memory, 3 iterations, parallelism=1
+//! - **Cipher**: chosen at vault creation and recorded in the header
+//! - **Nonce**: randomly generated per save operation (24 bytes XChaCha, 12 bytes AES)
+//! - **Salt**: 32 bytes, randomly generated per vault and on password change
+//! - **Header**: authenticated as associated data, so it cannot be altered
+//! - **Envelope**: optionally, the payload key is a random data key wrapped
+//!   in the header under the password-derived key, so it can be rotated
+//!   without a password change (see [`VaultHeader::wrap_key`])
+//! - **Memory Safety**: Sensitive data (keys, plaintext) zeroized after use
+
+use crate::journal::{Journal, JournalOp};
+use crate::limits::{LimitExceeded, Limits};
+use crate::models::{Entry, VaultData, NONCE_LEN, SALT_LEN, VAULT_FORMAT_VERSION};
+use crate::store::{FileStore, RecordStore, VaultStore};
+use crate::stream;
+use aes_gcm_siv::Aes256GcmSiv;
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use chrono::{DateTime, Utc};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::Path;
+use zeroize::Zeroize;
+
+const VAULT_MAGIC: &[u8; 4] = b"TORG";
+
+/// Format byte of v1 files (XChaCha20-Poly1305 and Argon2id, nothing recorded).
+const FORMAT_V1: u8 = 0x01;
+
+/// Authentication tag length shared by both cipher suites.
+const AEAD_TAG_LEN: usize = 16;
+
+/// Upper bound on the v2 header parameter block.
+const MAX_HEADER_PARAMS_LEN: usize = 64 * 1024;
+
+/// Associated data of a wrapped data key.
+const WRAPPED_KEY_AAD: &[u8] = b"TORG-KEY";
+
+/// Longest master password hint accepted, in characters.
+pub const MAX_HINT_CHARS: usize = 200;
+
+/// Argon2id memory cost in KiB (64 MiB).
+pub const ARGON2_MEMORY_KIB: u32 = 64 * 1024;
+
+/// Argon2id iteration count.
+pub const ARGON2_ITERATIONS: u32 = 3;
+
+/// Argon2id degree of parallelism.
+pub const ARGON2_PARALLELISM: u32 = 1;
+
+/// Plaintext bytes per chunk for new vaults (64 KiB).
+pub const STREAM_CHUNK_SIZE: u32 = 64 * 1024;
+
+/// Chunk sizes accepted from a vault header, bounding the per-chunk buffer.
+const MIN_STREAM_CHUNK_SIZE: u32 = 1024;
+const MAX_STREAM_CHUNK_SIZE: u32 = 16 * 1024 * 1024;
+
+/// Largest Argon2 memory cost accepted from a vault header (1 GiB), so a
+/// crafted file cannot make unlocking exhaust memory.
+const MAX_ARGON2_MEMORY_KIB: u32 = 1024 * 1024;
+
+/// Largest Argon2 iteration count accepted from a vault header.
+const MAX_ARGON2_ITERATIONS: u32 = 64;
+
+/// KDF name recorded for Argon2id vaults.
+pub const KDF_ARGON2ID: &str = "argon2id";
+
+/// KDF name recorded for scrypt vaults.
+pub const KDF_SCRYPT: &str = "scrypt";
+
+/// scrypt cost for new scrypt vaults: N = 2^15 with r = 8 (32 MiB).
+pub const SCRYPT_LOG_N: u8 = 15;
+
+/// scrypt block size. Fixed, so N can be recovered from `memory_kib`.
+const SCRYPT_BLOCK_SIZE: u32 = 8;
+
+/// Largest scrypt cost accepted from a vault header (N = 2^20, 1 GiB).
+const MAX_SCRYPT_LOG_N: u8 = 20;
+
+/// Largest scrypt parallelism accepted from a vault header.
+const MAX_SCRYPT_PARALLELISM: u32 = 16;
+
+/// Key derivation parameters used for a vault.
+///
+/// These are not secret; they are needed (together with the salt stored in
+/// the file) to re-derive the key from the master password.
+///
+/// For scrypt, `memory_kib` is the memory cost `128 * r * N / 1024` with
+/// r = 8 (so it equals N), `iterations` is always 1, and `parallelism` is p.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KdfParams {
+  pub algorithm: String,
+  pub memory_kib: u32,
+  pub iterations: u32,
+  pub parallelism: u32,
+}
+
+impl KdfParams {
+  /// Returns the parameters used for new vaults.
+  pub fn current() -> Self {
+    Self {
+      algorithm: KDF_ARGON2ID.to_string(),
+      memory_kib: ARGON2_MEMORY_KIB,
+      iterations: ARGON2_ITERATIONS,
+      parallelism: ARGON2_PARALLELISM,
+    }
+  }
+
+  /// scrypt parameters for new vaults on devices where Argon2id at 64 MiB
+  /// is too slow.
+  pub fn scrypt() -> Self {
+    Self {
+      algorithm: KDF_SCRYPT.to_string(),
+      memory_kib: 1 << SCRYPT_LOG_N,
+      iterations: 1,
+      parallelism: 1,
+    }
+  }
+
+  /// Parameters for new vaults using the named algorithm, if supported.
+  pub fn for_algorithm(algorithm: &str) -> Option<Self> {
+    match algorithm {
+      KDF_ARGON2ID => Some(Self::current()),
+      KDF_SCRYPT => Some(Self::scrypt()),
+      _ => None,
+    }
+  }
+
+  /// Checks that the algorithm is supported and the parameters are within
+  /// the limits accepted from a vault header, without deriving a key.
+  pub fn validate(&self) -> Result<(), VaultError> {
+    match self.algorithm.as_str() {
+      KDF_ARGON2ID => argon2_params(self).map(|_| ()),
+      KDF_SCRYPT => scrypt_params(self).map(|_| ()),
+      other => Err(VaultError::Kdf(format!("unsupported kdf: {other}"))),
+    }
+  }
+
+  /// The fixed parameters every pre-v2 vault was written with.
+  fn legacy() -> Self {
+    Self {
+      algorithm: KDF_ARGON2ID.to_string(),
+      memory_kib: 64 * 1024,
+      iterations: 3,
+      parallelism: 1,
+    }
+  }
+}
+
+/// AEAD cipher protecting the vault payload, chosen at vault creation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CipherSuite {
+  #[default]
+  #[serde(rename = "xchacha20-poly1305")]
+  XChaCha20Poly1305,
+  /// For environments that require AES; nonce-misuse resistant.
+  #[serde(rename = "aes-256-gcm-siv")]
+  Aes256GcmSiv,
+}
+
+impl CipherSuite {
+  /// Identifier byte stored in the v2 header.
+  pub fn id(self) -> u8 {
+    match self {
+      CipherSuite::XChaCha20Poly1305 => 0x01,
+      CipherSuite::Aes256GcmSiv => 0x02,
+    }
+  }
+
+  pub fn from_id(id: u8) -> Option<Self> {
+    match id {
+      0x01 => Some(CipherSuite::XChaCha20Poly1305),
+      0x02 => Some(CipherSuite::Aes256GcmSiv),
+      _ => None,
+    }
+  }
+
+  /// Human-readable name, e.g. for the emergency kit.
+  pub fn name(self) -> &'static str {
+    match self {
+      CipherSuite::XChaCha20Poly1305 => "XChaCha20-Poly1305",
+      CipherSuite::Aes256GcmSiv => "AES-256-GCM-SIV",
+    }
+  }
+
+  pub fn nonce_len(self) -> usize {
+    match self {
+      CipherSuite::XChaCha20Poly1305 => NONCE_LEN,
+      CipherSuite::Aes256GcmSiv => 12,
+    }
+  }
+
+  pub(crate) fn random_nonce(self) -> Vec<u8> {
+    let mut nonce = vec![0u8; self.nonce_len()];
+    OsRng.fill_bytes(&mut nonce);
+    nonce
+  }
+
+  pub(crate) fn encrypt(self, key: &[u8; 32], nonce: &[u8], msg: &[u8], aad: &[u8]) -> Result<Vec<u8>, VaultError> {
+    let payload = Payload { msg, aad };
+    let result = match self {
+      CipherSuite::XChaCha20Poly1305 => {
+        XChaCha20Poly1305::new(key.into()).encrypt(XNonce::from_slice(nonce), payload)
+      }
+      CipherSuite::Aes256GcmSiv => {
+        Aes256GcmSiv::new(key.into()).encrypt(aes_gcm_siv::Nonce::from_slice(nonce), payload)
+      }
+    };
+    result.map_err(|e| VaultError::Crypto(e.to_string()))
+  }
+
+  pub(crate) fn decrypt(self, key: &[u8; 32], nonce: &[u8], msg: &[u8], aad: &[u8]) -> Result<Vec<u8>, VaultError> {
+    let payload = Payload { msg, aad };
+    let result = match self {
+      CipherSuite::XChaCha20Poly1305 => {
+        XChaCha20Poly1305::new(key.into()).decrypt(XNonce::from_slice(nonce), payload)
+      }
+      CipherSuite::Aes256GcmSiv => {
+        Aes256GcmSiv::new(key.into()).decrypt(aes_gcm_siv::Nonce::from_slice(nonce), payload)
+      }
+    };
+    result.map_err(|e| VaultError::Crypto(e.to_string()))
+  }
+}
+
+/// Chunking of a payload sealed with [`crate::stream`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StreamParams {
+  /// Plaintext bytes per chunk; each sealed chunk adds a 16-byte tag.
+  pub chunk_size: u32,
+}
+
+impl StreamParams {
+  fn validate(&self) -> Result<(), VaultError> {
+    if !(MIN_STREAM_CHUNK_SIZE..=MAX_STREAM_CHUNK_SIZE).contains(&self.chunk_size) {
+      return Err(VaultError::Format(format!("invalid chunk size {}", self.chunk_size)));
+    }
+    Ok(())
+  }
+}
+
+impl Default for StreamParams {
+  fn default() -> Self {
+    Self {
+      chunk_size: STREAM_CHUNK_SIZE,
+    }
+  }
+}
+
+/// Non-secret parameters needed to re-derive the key and decrypt a vault.
+///
+/// Written in the clear at the start of the file and authenticated as
+/// associated data of the payload.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VaultHeader {
+  pub cipher: CipherSuite,
+  pub kdf: KdfParams,
+  pub salt: [u8; SALT_LEN],
+  /// Chunked payload encryption; `None` for payloads sealed in one piece
+  /// (all pre-v2 files and v2 files written before chunking existed).
+  pub stream: Option<StreamParams>,
+  /// User-written master password hint. Stored in the clear so it can be
+  /// shown on the unlock screen; authenticated like the rest of the header.
+  pub hint: Option<String>,
+  /// When the master password was set. `None` for vaults written before
+  /// this was recorded.
+  pub password_changed_at: Option<DateTime<Utc>>,
+  /// Data key sealed under the password-derived key. `None` means the
+  /// payload is encrypted with the password-derived key directly.
+  pub wrapped_key: Option<WrappedKey>,
+  /// The app install that wrote the vault. [`seal`] refreshes `at`.
+  pub written_by: Option<WriterStamp>,
+}
+
+/// Identifies the device that last wrote a vault, for sync and merge
+/// messages ("modified by LAPTOP-WORK 2 hours ago").
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WriterStamp {
+  /// Random id of the app install.
+  pub device_id: String,
+  /// Human-readable name, usually the hostname.
+  pub label: String,
+  pub at: DateTime<Utc>,
+}
+
+/// A 32-byte data key sealed with the header's cipher.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WrappedKey {
+  pub nonce: Vec<u8>,
+  /// The key and its authentication tag.
+  pub ciphertext: Vec<u8>,
+}
+
+impl VaultHeader {
+  /// Header for a new vault: random salt, the current KDF parameters, and a
+  /// chunked payload.
+  pub fn new(cipher: CipherSuite) -> Self {
+    Self::with_kdf(cipher, KdfParams::current())
+  }
+
+  /// Header for a new vault with explicit KDF parameters.
+  pub fn with_kdf(cipher: CipherSuite, kdf: KdfParams) -> Self {
+    Self {
+      cipher,
+      kdf,
+      salt: generate_salt(),
+      stream: Some(StreamParams::default()),
+      hint: None,
+      password_changed_at: Some(Utc::now()),
+      wrapped_key: None,
+      written_by: None,
+    }
+  }
+
+  /// Length of the nonce stored before the payload. Chunked payloads store
+  /// only the prefix; the rest of each chunk's nonce is its position.
+  pub fn nonce_len(&self) -> usize {
+    match self.stream {
+      Some(_) => self.cipher.nonce_len() - stream::NONCE_OVERHEAD,
+      None => self.cipher.nonce_len(),
+    }
+  }
+
+  /// Same cipher and KDF algorithm with a fresh salt and that algorithm's
+  /// current parameters, for re-keying on a master password change. The
+  /// hint belonged to the old password and is dropped; the password change
+  /// time is now. The writer is kept.
+  pub fn rekeyed(&self) -> Self {
+    let kdf = KdfParams::for_algorithm(&self.kdf.algorithm).unwrap_or_else(KdfParams::current);
+    Self {
+      written_by: self.written_by.clone(),
+      ..Self::with_kdf(self.cipher, kdf)
+    }
+  }
+
+  /// Derives the vault key from the master password with this header's
+  /// salt and KDF parameters, unwrapping the data key if there is one.
+  pub fn derive_key(&self, master_password: &str) -> Result<[u8; 32], VaultError> {
+    let mut kek = self.derive_kek(master_password)?;
+    let result = self.unwrap_key(&kek);
+    kek.zeroize();
+    result
+  }
+
+  /// Derives the password-derived key with this header's salt and KDF
+  /// parameters, without unwrapping. Lets a caller run the KDF once for
+  /// both [`Self::unwrap_key`] and [`Self::wrap_key_with`].
+  pub fn derive_kek(&self, master_password: &str) -> Result<[u8; 32], VaultError> {
+    derive_key_with(master_password, &self.salt, &self.kdf)
+  }
+
+  /// The vault key for a password-derived `kek`: the unwrapped data key,
+  /// or `kek` itself if there is none.
+  pub fn unwrap_key(&self, kek: &[u8; 32]) -> Result<[u8; 32], VaultError> {
+    let Some(wrapped) = &self.wrapped_key else {
+      return Ok(*kek);
+    };
+    let mut plaintext = self
+      .cipher
+      .decrypt(kek, &wrapped.nonce, &wrapped.ciphertext, WRAPPED_KEY_AAD)?;
+    let key: Result<[u8; 32], _> = plaintext.as_slice().try_into();
+    plaintext.zeroize();
+    key.map_err(|_| VaultError::Format("wrapped key has the wrong length".to_string()))
+  }
+
+  /// Generates a fresh random data key, wraps it under the key derived from
+  /// `master_password`, and returns it. The payload must then be sealed
+  /// with the returned key; salt and KDF parameters are unchanged.
+  pub fn wrap_key(&mut self, master_password: &str) -> Result<[u8; 32], VaultError> {
+    let mut kek = self.derive_kek(master_password)?;
+    let result = self.wrap_key_with(&kek);
+    kek.zeroize();
+    result
+  }
+
+  /// Like [`Self::wrap_key`], with a key from [`Self::derive_kek`].
+  pub fn wrap_key_with(&mut self, kek: &[u8; 32]) -> Result<[u8; 32], VaultError> {
+    let mut key = [0u8; 32];
+    OsRng.fill_bytes(&mut key);
+    let nonce = self.cipher.random_nonce();
+    match self.cipher.encrypt(kek, &nonce, &key, WRAPPED_KEY_AAD) {
+      Ok(ciphertext) => {
+        self.wrapped_key = Some(WrappedKey { nonce, ciphertext });
+        Ok(key)
+      }
+      Err(e) => {
+        key.zeroize();
+        Err(e)
+      }
+    }
+  }
+
+  /// Header implied by pre-v2 files.
+  fn legacy(salt: [u8; SALT_LEN]) -> Self {
+    Self {
+      cipher: CipherSuite::XChaCha20Poly1305,
+      kdf: KdfParams::legacy(),
+      salt,
+      stream: None,
+      hint: None,
+      password_changed_at: None,
+      wrapped_key: None,
+      written_by: None,
+    }
+  }
+}
+
+impl Default for VaultHeader {
+  fn default() -> Self {
+    Self::new(CipherSuite::default())
+  }
+}
+
+/// Variable-length part of the v2 header, stored as JSON.
+#[derive(Serialize, Deserialize)]
+struct HeaderParams {
+  kdf: KdfParams,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  stream: Option<StreamParams>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  hint: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  password_changed_at: Option<DateTime<Utc>>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  wrapped_key: Option<WrappedKey>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  written_by: Option<WriterStamp>,
+}
+
+/// Errors that can occur during vault operations.
+#[derive(Debug)]
+pub enum VaultError {
+  /// File I/O error
+  Io(String),
+  /// Invalid vault file format
+  Format(String),
+  /// Encryption/decryption error (wrong password or corrupted data)
+  Crypto(String),
+  /// JSON serialization/deserialization error
+  Json(String),
+  /// Key derivation function error
+  Kdf(String),
+  /// The file or its contents exceed the configured [`Limits`]
+  Limit(LimitExceeded),
+}
+
+/// Result of loading a vault: contents, header, and derived key.
+pub type VaultLoadResult = (VaultData, VaultHeader, [u8; 32]);
+
+/// Encrypted payload layouts, newest first.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredPayload {
+  Current(Box<VaultData>),
+  /// Pre-metadata payload: a bare array of entries.
+  Legacy(Vec<Entry>),
+}
+
+impl From<LimitExceeded> for VaultError {
+  fn from(e: LimitExceeded) -> Self {
+    VaultError::Limit(e)
+  }
+}
+
+impl From<io::Error> for VaultError {
+  fn from(e: io::Error) -> Self {
+    VaultError::Io(e.to_string())
+  }
+}
+
+/// Generates a cryptographically secure random salt for key derivation.
+///
+/// # Returns
+///
+/// A 32-byte array filled with random data from the OS's CSPRNG.
+///
+/// # Security
+///
+/// Uses `OsRng` which provides cryptographically secure randomness.
+/// The salt should be unique per vault and stored alongside the ciphertext.
+pub fn generate_salt() -> [u8; SALT_LEN] {
+  let mut salt = [0u8; SALT_LEN];
+  OsRng.fill_bytes(&mut salt);
+  salt
+}
+
+/// Derives a 256-bit encryption key from the master password using Argon2id
+/// with the current parameters.
+///
+/// # Arguments
+///
+/// - `master_password`: The user-provided master password
+/// - `salt`: A 32-byte salt unique to this vault
+///
+/// # Returns
+///
+/// A 32-byte key suitable for either cipher suite.
+///
+/// # Security
+///
+/// Uses Argon2id with memory-hard parameters to resist brute force attacks.
+pub fn derive_key(master_password: &str, salt: &[u8; SALT_LEN]) -> Result<[u8; 32], VaultError> {
+  derive_key_with(master_password, salt, &KdfParams::current())
+}
+
+/// Derives a key with explicit KDF parameters, e.g. those read from a header.
+pub fn derive_key_with(master_password: &str, salt: &[u8; SALT_LEN], kdf: &KdfParams) -> Result<[u8; 32], VaultError> {
+  match kdf.algorithm.as_str() {
+    KDF_ARGON2ID => derive_argon2id(master_password, salt, kdf),
+    KDF_SCRYPT => derive_scrypt(master_password, salt, kdf),
+    other => Err(VaultError::Kdf(format!("unsupported kdf: {other}"))),
+  }
+}
+
+fn derive_argon2id(master_password: &str, salt: &[u8; SALT_LEN], kdf: &KdfParams) -> Result<[u8; 32], VaultError> {
+  let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, argon2_params(kdf)?);
+
+  let mut key = [0u8; 32];
+  argon2
+    .hash_password_into(master_password.as_bytes(), salt, &mut key)
+    .map_err(|e| VaultError::Kdf(format!("argon2: {e}")))?;
+
+  Ok(key)
+}
+
+fn derive_scrypt(master_password: &str, salt: &[u8; SALT_LEN], kdf: &KdfParams) -> Result<[u8; 32], VaultError> {
+  let params = scrypt_params(kdf)?;
+
+  let mut key = [0u8; 32];
+  scrypt::scrypt(master_password.as_bytes(), salt, &params, &mut key)
+    .map_err(|e| VaultError::Kdf(format!("scrypt: {e}")))?;
+
+  Ok(key)
+}
+
+fn argon2_params(kdf: &KdfParams) -> Result<Params, VaultError> {
+  if kdf.memory_kib > MAX_ARGON2_MEMORY_KIB || kdf.iterations > MAX_ARGON2_ITERATIONS {
+    return Err(VaultError::Kdf("kdf parameters exceed limits".to_string()));
+  }
+  Params::new(kdf.memory_kib, kdf.iterations, kdf.parallelism, Some(32))
+    .map_err(|e| VaultError::Kdf(format!("argon2 params: {e}")))
+}
+
+fn scrypt_params(kdf: &KdfParams) -> Result<scrypt::Params, VaultError> {
+  let n = kdf.memory_kib;
+  if !n.is_power_of_two() || n < 2 || kdf.iterations != 1 {
+    return Err(VaultError::Kdf("invalid scrypt parameters".to_string()));
+  }
+  let log_n = n.trailing_zeros() as u8;
+  if log_n > MAX_SCRYPT_LOG_N || kdf.parallelism > MAX_SCRYPT_PARALLELISM {
+    return Err(VaultError::Kdf("kdf parameters exceed limits".to_string()));
+  }
+  scrypt::Params::new(log_n, SCRYPT_BLOCK_SIZE, kdf.parallelism, 32)
+    .map_err(|e| VaultError::Kdf(format!("scrypt params: {e}")))
+}
+
+/// Saves the vault to a file with the current format version.
+pub fn save_with_key(
+  path: &Path,
+  data: &VaultData,
+  header: &VaultHeader,
+  key_bytes: &[u8; 32],
+) -> Result<(), VaultError> {
+  save_to_store(&FileStore::new(path), data, header, key_bytes)
+}
+
+/// Encrypts the vault and writes it to `store`.
+pub fn save_to_store(
+  store: &dyn VaultStore,
+  data: &VaultData,
+  header: &VaultHeader,
+  key_bytes: &[u8; 32],
+) -> Result<(), VaultError> {
+  if let Some(records) = store.records() {
+    return save_records(records, data, header, key_bytes, None);
+  }
+  let out = seal(data, header, key_bytes)?;
+  store.write(&out)?;
+  // Everything journaled is now in the file, possibly under a new key.
+  if let Some(journal) = Journal::for_store(store) {
+    journal.clear()?;
+  }
+  Ok(())
+}
+
+/// Persists changes to the entries with the given ids.
+///
+/// Record stores only rewrite those entries (ids no longer in `data` are
+/// deleted); blob stores journal the changes (see [`crate::journal`]) and
+/// fall back to a full [`save_to_store`].
+pub fn save_entries(
+  store: &dyn VaultStore,
+  data: &VaultData,
+  header: &VaultHeader,
+  key_bytes: &[u8; 32],
+  ids: &[&str],
+) -> Result<(), VaultError> {
+  if let Some(records) = store.records() {
+    return save_records(records, data, header, key_bytes, Some(ids));
+  }
+  if let Some(journal) = Journal::for_store(store) {
+    journal.append(&JournalOp::for_entries(data, ids), &data.meta.id, header.cipher, key_bytes)?;
+  }
+  save_to_store(store, data, header, key_bytes)
+}
+
+/// Replays changes left in the journal of `store` by an interrupted save
+/// into freshly loaded `data`, then rewrites the vault and removes the
+/// journal. Returns the number of replayed changes.
+///
+/// The journal is left in place if it cannot be read, so nothing is lost.
+pub fn recover_journal(
+  store: &dyn VaultStore,
+  data: &mut VaultData,
+  header: &VaultHeader,
+  key_bytes: &[u8; 32],
+) -> Result<usize, VaultError> {
+  let Some(journal) = Journal::for_store(store).filter(Journal::exists) else {
+    return Ok(0);
+  };
+  let ops = journal.read(&data.meta.id, header.cipher, key_bytes)?;
+  let replayed = ops.len();
+  for op in ops {
+    op.apply(data);
+  }
+  save_to_store(store, data, header, key_bytes)?;
+  Ok(replayed)
+}
+
+/// Writes the header and the `changed` entries (all entries when `None`).
+fn save_records(
+  records: &dyn RecordStore,
+  data: &VaultData,
+  header: &VaultHeader,
+  key_bytes: &[u8; 32],
+  changed: Option<&[&str]>,
+) -> Result<(), VaultError> {
+  let sealed_header = match changed {
+    Some(_) => None,
+    None => {
+      let header_data = VaultData {
+        meta: data.meta.clone(),
+        entries: Vec::new(),
+        trash: data.trash.clone(),
+        tombstones: data.tombstones.clone(),
+        legacy_payload: false,
+      };
+      Some(seal(&header_data, header, key_bytes)?)
+    }
+  };
+
+  let ids: Vec<&str> = match changed {
+    Some(ids) => ids.to_vec(),
+    None => data.entries.iter().map(|e| e.id.as_str()).collect(),
+  };
+
+  let mut upserts = Vec::with_capacity(ids.len());
+  let mut deletes = Vec::new();
+  for id in ids {
+    match data.find_entry(id) {
+      Some(entry) => upserts.push((id.to_string(), seal_record(entry, &data.meta.id, header.cipher, key_bytes)?)),
+      None => deletes.push(id.to_string()),
+    }
+  }
+  if changed.is_none() {
+    for id in records.record_ids()? {
+      if data.find_entry(&id).is_none() {
+        deletes.push(id);
+      }
+    }
+  }
+
+  records.apply(sealed_header.as_deref(), &upserts, &deletes)?;
+  Ok(())
+}
+
+/// Associated data binding a record to its vault and entry id, so sealed
+/// rows cannot be swapped between entries or vaults.
+fn record_aad(vault_id: &str, entry_id: &str) -> Vec<u8> {
+  format!("{vault_id}/{entry_id}").into_bytes()
+}
+
+/// Seals one entry as `[nonce][ciphertext+tag]`.
+fn seal_record(entry: &Entry, vault_id: &str, cipher: CipherSuite, key_bytes: &[u8; 32]) -> Result<Vec<u8>, VaultError> {
+  let nonce = cipher.random_nonce();
+  let mut plaintext = serde_json::to_vec(entry).map_err(|e| VaultError::Json(e.to_string()))?;
+  let result = cipher.encrypt(key_bytes, &nonce, &plaintext, &record_aad(vault_id, &entry.id));
+  plaintext.zeroize();
+
+  let mut out = nonce;
+  out.extend_from_slice(&result?);
+  Ok(out)
+}
+
+/// Opens a record sealed by [`seal_record`].
+fn open_record(
+  bytes: &[u8],
+  vault_id: &str,
+  entry_id: &str,
+  cipher: CipherSuite,
+  key_bytes: &[u8; 32],
+) -> Result<Entry, VaultError> {
+  if bytes.len() < cipher.nonce_len() + AEAD_TAG_LEN {
+    return Err(VaultError::Format("record too small".to_string()));
+  }
+  let (nonce, ciphertext) = bytes.split_at(cipher.nonce_len());
+  let mut plaintext = cipher.decrypt(key_bytes, nonce, ciphertext, &record_aad(vault_id, entry_id))?;
+  let parsed = serde_json::from_slice(&plaintext).map_err(|e| VaultError::Json(e.to_string()));
+  plaintext.zeroize();
+  parsed
+}
+
+/// Loads a single entry from a record store without decrypting the rest.
+///
+/// Returns `Ok(None)` if the entry does not exist. Fails with a format
+/// error for blob stores, which can only be loaded as a whole.
+pub fn load_entry(
+  store: &dyn VaultStore,
+  header: &VaultHeader,
+  vault_id: &str,
+  entry_id: &str,
+  key_bytes: &[u8; 32],
+) -> Result<Option<Entry>, VaultError> {
+  let records = store
+    .records()
+    .ok_or_else(|| VaultError::Format("store does not support per-entry loads".to_string()))?;
+  match records.record(entry_id)? {
+    Some(bytes) => open_record(&bytes, vault_id, entry_id, header.cipher, key_bytes).map(Some),
+    None => Ok(None),
+  }
+}
+
+/// Encrypts the vault with the current format version.
+///
+/// Format (v2):
+/// `[4B magic][1B version][1B cipher][4B LE n][n B params JSON][32B salt][nonce][ciphertext+tag]`
+///
+/// Everything before the nonce is the header and is passed to the cipher as
+/// associated data.
+pub fn seal(data: &VaultData, header: &VaultHeader, key_bytes: &[u8; 32]) -> Result<Vec<u8>, VaultError> {
+  let params = serde_json::to_vec(&HeaderParams {
+    kdf: header.kdf.clone(),
+    stream: header.stream,
+    hint: header.hint.clone(),
+    password_changed_at: header.password_changed_at,
+    wrapped_key: header.wrapped_key.clone(),
+    written_by: header.written_by.clone().map(|stamp| WriterStamp { at: Utc::now(), ..stamp }),
+  })
+  .map_err(|e| VaultError::Json(e.to_string()))?;
+
+  let mut out = Vec::with_capacity(4 + 1 + 1 + 4 + params.len() + SALT_LEN);
+  out.extend_from_slice(VAULT_MAGIC);
+  out.push(VAULT_FORMAT_VERSION);
+  out.push(header.cipher.id());
+  out.extend_from_slice(&(params.len() as u32).to_le_bytes());
+  out.extend_from_slice(&params);
+  out.extend_from_slice(&header.salt);
+
+  let mut nonce = vec![0u8; header.nonce_len()];
+  OsRng.fill_bytes(&mut nonce);
+  let ciphertext = match header.stream {
+    Some(params) => stream::seal_json(data, header.cipher, key_bytes, &nonce, &out, params.chunk_size as usize)?,
+    None => {
+      let mut plaintext = serde_json::to_vec(data).map_err(|e| VaultError::Json(e.to_string()))?;
+      let result = header.cipher.encrypt(key_bytes, &nonce, &plaintext, &out);
+      plaintext.zeroize();
+      result?
+    }
+  };
+
+  out.extend_from_slice(&nonce);
+  out.extend_from_slice(&ciphertext);
+  Ok(out)
+}
+
+/// Layout of a vault file, as detected by [`parse_vault_header`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VaultFormat {
+  /// `[magic][0x02][cipher][params]...`, see [`seal`].
+  V2,
+  /// `[4B magic][0x01][32B salt][24B nonce][ciphertext+tag]`
+  V1,
+  /// `[0x01][32B salt][24B nonce][ciphertext+tag]`. Cannot be told apart from
+  /// a v0 file whose salt starts with `0x01` without decrypting;
+  /// [`open`] falls back to v0 if decryption fails.
+  LegacyVersioned,
+  /// `[32B salt][24B nonce][ciphertext+tag]`
+  LegacyV0,
+}
+
+/// Structure of a vault file, validated without key derivation or decryption.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParsedVault {
+  pub format: VaultFormat,
+  pub header: VaultHeader,
+  /// Where the nonce starts. For v2 everything before it is the header,
+  /// authenticated as associated data.
+  pub nonce_offset: usize,
+}
+
+/// Validates the structure of a vault file without deriving a key or
+/// decrypting anything.
+///
+/// This is the only code that interprets untrusted bytes before
+/// authentication, so it must never panic or allocate based on lengths read
+/// from the input; it is the target of the `parse_vault_header` fuzzer. The
+/// recorded KDF parameters are checked against the same limits used when
+/// unlocking, so a file that parses cannot make unlocking exhaust memory.
+pub fn parse_vault_header(bytes: &[u8]) -> Result<ParsedVault, VaultError> {
+  if bytes.len() >= 5 && bytes[..4] == VAULT_MAGIC[..] {
+    return match bytes[4] {
+      VAULT_FORMAT_VERSION => parse_v2_header(bytes),
+      FORMAT_V1 => parse_legacy(bytes, 5, VaultFormat::V1),
+      other => Err(VaultError::Format(format!("unsupported vault version {other}"))),
+    };
+  }
+  if bytes.first() == Some(&FORMAT_V1) && bytes.len() >= 1 + SALT_LEN + NONCE_LEN + AEAD_TAG_LEN {
+    return parse_legacy(bytes, 1, VaultFormat::LegacyVersioned);
+  }
+  parse_legacy(bytes, 0, VaultFormat::LegacyV0)
+}
+
+/// Parses a pre-v2 layout whose salt starts at `offset`.
+fn parse_legacy(bytes: &[u8], offset: usize, format: VaultFormat) -> Result<ParsedVault, VaultError> {
+  let nonce_offset = offset + SALT_LEN;
+  if bytes.len() < nonce_offset + NONCE_LEN + AEAD_TAG_LEN {
+    return Err(VaultError::Format("vault file too small".to_string()));
+  }
+  let mut salt = [0u8; SALT_LEN];
+  salt.copy_from_slice(&bytes[offset..nonce_offset]);
+  Ok(ParsedVault {
+    format,
+    header: VaultHeader::legacy(salt),
+    nonce_offset,
+  })
+}
+
+/// Parses a v2 header.
+fn parse_v2_header(bytes: &[u8]) -> Result<ParsedVault, VaultError> {
+  let too_small = || VaultError::Format("vault file too small".to_string());
+
+  let cipher_id = *bytes.get(5).ok_or_else(too_small)?;
+  let cipher = CipherSuite::from_id(cipher_id)
+    .ok_or_else(|| VaultError::Format(format!("unknown cipher suite {cipher_id:#04x}")))?;
+
+  let len_bytes: [u8; 4] = bytes.get(6..10).ok_or_else(too_small)?.try_into().map_err(|_| too_small())?;
+  let params_len = u32::from_le_bytes(len_bytes) as usize;
+  if params_len > MAX_HEADER_PARAMS_LEN {
+    return Err(VaultError::Format("vault header too large".to_string()));
+  }
+  let params_end = 10 + params_len;
+  let params: HeaderParams = serde_json::from_slice(bytes.get(10..params_end).ok_or_else(too_small)?)
+    .map_err(|e| VaultError::Format(format!("vault header: {e}")))?;
+  params.kdf.validate()?;
+  if let Some(stream) = &params.stream {
+    stream.validate()?;
+  }
+  if let Some(wrapped) = &params.wrapped_key {
+    if wrapped.nonce.len() != cipher.nonce_len() || wrapped.ciphertext.len() != 32 + AEAD_TAG_LEN {
+      return Err(VaultError::Format("invalid wrapped key".to_string()));
+    }
+  }
+
+  let salt_end = params_end + SALT_LEN;
+  let mut salt = [0u8; SALT_LEN];
+  salt.copy_from_slice(bytes.get(params_end..salt_end).ok_or_else(too_small)?);
+
+  let header = VaultHeader {
+    cipher,
+    kdf: params.kdf,
+    salt,
+    stream: params.stream,
+    hint: params.hint,
+    password_changed_at: params.password_changed_at,
+    wrapped_key: params.wrapped_key,
+    written_by: params.written_by,
+  };
+  if bytes.len() < salt_end + header.nonce_len() + AEAD_TAG_LEN {
+    return Err(too_small());
+  }
+  Ok(ParsedVault {
+    format: VaultFormat::V2,
+    header,
+    nonce_offset: salt_end,
+  })
+}
+
+/// Parses a decrypted payload, accepting current and pre-metadata layouts.
+fn decode_payload(mut plaintext: Vec<u8>) -> Result<VaultData, VaultError> {
+  let parsed: Result<StoredPayload, _> = serde_json::from_slice(&plaintext);
+  plaintext.zeroize();
+  match parsed.map_err(|e| VaultError::Json(e.to_string()))? {
+    StoredPayload::Current(data) => Ok(*data),
+    StoredPayload::Legacy(entries) => Ok(VaultData::from_legacy(entries)),
+  }
+}
+
+/// Derives the key for a parsed vault and decrypts its payload.
+fn open_parsed(bytes: &[u8], parsed: ParsedVault, master_password: &str) -> Result<VaultLoadResult, VaultError> {
+  let mut key = parsed.header.derive_key(master_password)?;
+  match decrypt_parsed(bytes, &parsed, &key) {
+    // The caller keeps the key while unlocked.
+    Ok(data) => Ok((data, parsed.header, key)),
+    Err(e) => {
+      key.zeroize();
+      Err(e)
+    }
+  }
+}
+
+/// Decrypts the payload of a parsed vault with an already derived key.
+fn decrypt_parsed(bytes: &[u8], parsed: &ParsedVault, key: &[u8; 32]) -> Result<VaultData, VaultError> {
+  let header = &parsed.header;
+  let nonce_offset = parsed.nonce_offset;
+  let ciphertext_start = nonce_offset + header.nonce_len();
+  // Only v2 authenticates its header; older formats had no associated data.
+  let aad: &[u8] = match parsed.format {
+    VaultFormat::V2 => &bytes[..nonce_offset],
+    _ => &[],
+  };
+
+  if let Some(params) = header.stream {
+    // Chunked payloads postdate the bare-array layout, so they are always
+    // decoded straight into the current structure.
+    let nonce = &bytes[nonce_offset..ciphertext_start];
+    let chunk_size = params.chunk_size as usize;
+    return stream::open_json(&bytes[ciphertext_start..], header.cipher, key, nonce, aad, chunk_size);
+  }
+  let plaintext = header
+    .cipher
+    .decrypt(key, &bytes[nonce_offset..ciphertext_start], &bytes[ciphertext_start..], aad)?;
+  decode_payload(plaintext)
+}
+
+/// Decrypts a vault with the key of an unlocked session, e.g. a copy of the
+/// same vault written by another device. Fails with [`VaultError::Crypto`]
+/// if the copy was re-keyed since (new password, salt, or data key).
+pub fn open_with_key(bytes: &[u8], key: &[u8; 32], limits: &Limits) -> Result<(VaultData, VaultHeader), VaultError> {
+  limits.check_file_size(bytes.len() as u64)?;
+  let parsed = parse_vault_header(bytes)?;
+  let data = decrypt_parsed(bytes, &parsed, key)?;
+  limits.check_data(&data)?;
+  Ok((data, parsed.header))
+}
+
+/// Loads the vault from a file; see [`open`] for the supported formats.
+pub fn load_with_password(
+  path: &Path,
+  master_password: &str,
+) -> Result<VaultLoadResult, VaultError> {
+  load_from_store(&FileStore::new(path), master_password)
+}
+
+/// Reads and decrypts the vault held by `store` within the default [`Limits`].
+pub fn load_from_store(store: &dyn VaultStore, master_password: &str) -> Result<VaultLoadResult, VaultError> {
+  load_from_store_with_limits(store, master_password, &Limits::default())
+}
+
+/// Reads and decrypts the vault held by `store`, rejecting files, entry
+/// counts, and fields beyond `limits`.
+pub fn load_from_store_with_limits(
+  store: &dyn VaultStore,
+  master_password: &str,
+  limits: &Limits,
+) -> Result<VaultLoadResult, VaultError> {
+  if store.records().is_some() {
+    let ((mut data, vault_header, mut key), sealed) = open_header(store, master_password, limits)?;
+    match open_records(&sealed, &data.meta.id, vault_header.cipher, &key) {
+      Ok(entries) => data.entries = entries,
+      Err(e) => {
+        key.zeroize();
+        return Err(e);
+      }
+    }
+    return within_limits((data, vault_header, key), limits);
+  }
+
+  if let Some(size) = store.size()? {
+    limits.check_file_size(size)?;
+  }
+  let bytes = store.read()?;
+  open_with_limits(&bytes, master_password, limits)
+}
+
+/// Entry records of a record store vault, still sealed, as `(entry id,
+/// bytes)` in insertion order.
+pub type SealedRecords = Vec<(String, Vec<u8>)>;
+
+/// Opens only the header of a record store vault: verifies the master
+/// password and returns the metadata without entries, plus the sealed
+/// entry records for [`open_records`]. Lets a caller unlock before every
+/// entry is decrypted. Fails with a format error for blob stores.
+pub fn open_header(
+  store: &dyn VaultStore,
+  master_password: &str,
+  limits: &Limits,
+) -> Result<(VaultLoadResult, SealedRecords), VaultError> {
+  let records = store
+    .records()
+    .ok_or_else(|| VaultError::Format("store does not support per-entry loads".to_string()))?;
+  let header = records
+    .header()?
+    .ok_or_else(|| VaultError::Io("vault does not exist".to_string()))?;
+  let sealed = records.all_records()?;
+  limits.check_entry_count(sealed.len())?;
+  Ok((open_with_limits(&header, master_password, limits)?, sealed))
+}
+
+/// Decrypts records returned by [`open_header`], keeping their order.
+pub fn open_records(
+  records: &[(String, Vec<u8>)],
+  vault_id: &str,
+  cipher: CipherSuite,
+  key_bytes: &[u8; 32],
+) -> Result<Vec<Entry>, VaultError> {
+  records
+    .iter()
+    .map(|(id, bytes)| open_record(bytes, vault_id, id, cipher, key_bytes))
+    .collect()
+}
+
+/// Reads the header of the vault held by `store` without deriving a key,
+/// e.g. to show the password hint on the unlock screen.
+pub fn read_header(store: &dyn VaultStore) -> Result<VaultHeader, VaultError> {
+  let bytes = match store.records() {
+    Some(records) => records
+      .header()?
+      .ok_or_else(|| VaultError::Io("vault does not exist".to_string()))?,
+    None => store.read()?,
+  };
+  Ok(parse_vault_header(&bytes)?.header)
+}
+
+/// Checks decrypted contents against `limits`, zeroizing the key on failure.
+fn within_limits(loaded: VaultLoadResult, limits: &Limits) -> Result<VaultLoadResult, VaultError> {
+  let (data, header, mut key) = loaded;
+  match limits.check_data(&data) {
+    Ok(()) => Ok((data, header, key)),
+    Err(e) => {
+      key.zeroize();
+      Err(e.into())
+    }
+  }
+}
+
+/// Decrypts a vault in any supported format within the default [`Limits`];
+/// see [`VaultFormat`].
+pub fn open(bytes: &[u8], master_password: &str) -> Result<VaultLoadResult, VaultError> {
+  open_with_limits(bytes, master_password, &Limits::default())
+}
+
+/// Decrypts a vault, rejecting it if it exceeds `limits`.
+pub fn open_with_limits(bytes: &[u8], master_password: &str, limits: &Limits) -> Result<VaultLoadResult, VaultError> {
+  limits.check_file_size(bytes.len() as u64)?;
+  let parsed = parse_vault_header(bytes)?;
+  let format = parsed.format;
+
+  #[cfg(debug_assertions)]
+  eprintln!("Loaded vault format: {:?}", format);
+
+  let loaded = match open_parsed(bytes, parsed, master_password) {
+    // A v0 file whose salt happens to start with the version byte looks
+    // like a versioned file; retry as v0 and report the original error if
+    // that fails too.
+    Err(e_v1 @ VaultError::Crypto(_)) if format == VaultFormat::LegacyVersioned => {
+      let v0 = parse_legacy(bytes, 0, VaultFormat::LegacyV0)?;
+      open_parsed(bytes, v0, master_password).map_err(|_| e_v1)?
+    }
+    result => result?,
+  };
+  within_limits(loaded, limits)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::models::{Entry, VaultData, VaultMeta};
+
+  fn temp_file_path(name: &str) -> std::path::PathBuf {
+    let mut p = std::env::temp_dir();
+    p.push(format!("the-organizer-test-{}-{}.dat", name, std::process::id()));
+    p
+  }
+
+  #[test]
+  fn roundtrip_encrypt_decrypt() {
+    let path = temp_file_path("roundtrip");
+    let _ = std::fs::remove_file(&path);
+
+    let header = VaultHeader::default();
+    let password = "correct horse battery staple";
+    let key = header.derive_key(password).expect("kdf");
+
+    let now = Utc::now();
+    let meta = VaultMeta::new();
+    let entries = vec![Entry {
+      id: "id1".to_string(),
+      title: "Example".to_string(),
+      username: "alice".to_string(),
+      password: "secret".to_string(),
+      url: "https://example.com".to_string(),
+      notes: "n".to_string(),
+      created_at: now,
+      updated_at: now,
+      revision: 0,
+      last_used_at: None,
+      totp_secret: None,
+      linked_to: None,
+      kind: Default::default(),
+      tags: Vec::new(),
+      folder: None,
+      history: Vec::new(),
+      hidden: false,
+    }];
+    let data = VaultData {
+      meta: meta.clone(),
+      entries,
+      trash: Vec::new(),
+      tombstones: Vec::new(),
+      legacy_payload: false,
+    };
+
+    save_with_key(&path, &data, &header, &key).expect("save");
+
+    let loaded = load_with_password(&path, password).expect("load");
+    assert_eq!(loaded.0.entries.len(), 1);
+    assert_eq!(loaded.1, header);
+    assert_eq!(loaded.0.meta.id, meta.id);
+    assert!(!loaded.0.legacy_payload);
+    assert_eq!(loaded.0.entries[0].title, "Example");
+    assert_eq!(loaded.0.entries[0].username, "alice");
+    assert_eq!(loaded.0.entries[0].password, "secret");
+
+    let _ = std::fs::remove_file(&path);
+  }
+
+  #[test]
+  fn wrong_password_fails() {
+    let path = temp_file_path("wrongpw");
+    let _ = std::fs::remove_file(&path);
+
+    let header = VaultHeader::default();
+    let password = "pw1";
+    let key = header.derive_key(password).expect("kdf");
+
+    save_with_key(&path, &VaultData::default(), &header, &key).expect("save");
+
+    let res = load_with_password(&path, "pw2");
+    assert!(res.is_err());
+
+    let _ = std::fs::remove_file(&path);
+  }
+
+  #[test]
+  fn legacy_v0_compatibility_ignores_version_byte_collision() {
+    use std::fs;
+    use chacha20poly1305::aead::Aead;
+    use chacha20poly1305::XChaCha20Poly1305;
+
+    let path = temp_file_path("legacy-v0");
+    let _ = std::fs::remove_file(&path);
+
+    let password = "v0-compat";
+    let mut salt = [0u8; SALT_LEN];
+    salt[0] = FORMAT_V1;
+
+    let key = derive_key(password, &salt).expect("kdf");
+    let entries: Vec<Entry> = Vec::new();
+
+    let nonce = [0u8; NONCE_LEN];
+    let cipher = XChaCha20Poly1305::new((&key).into());
+    let plaintext = serde_json::to_vec(&entries).expect("json");
+    let ciphertext = cipher
+      .encrypt(XNonce::from_slice(&nonce), plaintext.as_ref())
+      .expect("encrypt");
+
+    let mut out = Vec::with_capacity(SALT_LEN + NONCE_LEN + ciphertext.len());
+    out.extend_from_slice(&salt);
+    out.extend_from_slice(&nonce);
+    out.extend_from_slice(&ciphertext);
+    fs::write(&path, out).expect("write");
+
+    let loaded = load_with_password(&path, password).expect("load");
+    assert_eq!(loaded.0.entries.len(), 0);
+    assert!(loaded.0.legacy_payload);
+    assert_eq!(loaded.1.salt, salt);
+    assert_eq!(loaded.1.cipher, CipherSuite::XChaCha20Poly1305);
+
+    let _ = std::fs::remove_file(&path);
+  }
+
+  #[test]
+  fn memory_store_roundtrip_without_filesystem() {
+    use crate::store::MemoryStore;
+
+    let store = MemoryStore::new();
+    assert!(!store.exists());
+
+    let header = VaultHeader::default();
+    let key = header.derive_key("in-memory").expect("kdf");
+    save_to_store(&store, &VaultData::default(), &header, &key).expect("save");
+    assert!(store.exists());
+
+    let loaded = load_from_store(&store, "in-memory").expect("load");
+    assert_eq!(loaded.1, header);
+    assert!(load_from_store(&store, "wrong").is_err());
+  }
+
+  #[test]
+  fn aes_gcm_siv_vault_roundtrips_and_header_is_authenticated() {
+    let header = VaultHeader::new(CipherSuite::Aes256GcmSiv);
+    let key = header.derive_key("aes").expect("kdf");
+    let mut sealed = seal(&VaultData::default(), &header, &key).expect("seal");
+    assert_eq!(sealed[4], VAULT_FORMAT_VERSION);
+    assert_eq!(sealed[5], CipherSuite::Aes256GcmSiv.id());
+
+    let (_, loaded, _) = open(&sealed, "aes").expect("open");
+    assert_eq!(loaded, header);
+
+    // Flipping a header byte (here: inside the salt) must fail authentication
+    // even though the key is derived from the tampered salt.
+    let params_len = u32::from_le_bytes(sealed[6..10].try_into().unwrap()) as usize;
+    sealed[10 + params_len] ^= 1;
+    assert!(open(&sealed, "aes").is_err());
+  }
+
+  #[test]
+  fn scrypt_vault_records_kdf_and_keeps_it_on_rekey() {
+    let header = VaultHeader::with_kdf(CipherSuite::default(), KdfParams::scrypt());
+    let key = header.derive_key("low-memory").expect("kdf");
+    let sealed = seal(&VaultData::default(), &header, &key).expect("seal");
+
+    let (_, loaded, loaded_key) = open(&sealed, "low-memory").expect("open");
+    assert_eq!(loaded.kdf.algorithm, KDF_SCRYPT);
+    assert_eq!(loaded_key, key);
+    assert!(open(&sealed, "wrong").is_err());
+    assert_eq!(header.rekeyed().kdf, KdfParams::scrypt());
+
+    let mut oversized = KdfParams::scrypt();
+    oversized.memory_kib = 1 << 24;
+    assert!(derive_key_with("x", &header.salt, &oversized).is_err());
+  }
+
+  #[test]
+  fn wrapped_data_key_rotates_without_a_password_change() {
+    let mut header = VaultHeader::with_kdf(CipherSuite::default(), KdfParams::scrypt());
+    let first = header.wrap_key("rotate").expect("wrap");
+    assert_eq!(header.derive_key("rotate").expect("unwrap"), first);
+
+    // One derivation serves both the check and the new wrap.
+    let salt = header.salt;
+    let kek = header.derive_kek("rotate").expect("kdf");
+    assert_eq!(header.unwrap_key(&kek).expect("unwrap"), first);
+    let second = header.wrap_key_with(&kek).expect("rewrap");
+    assert_ne!(second, first);
+    assert_eq!(header.salt, salt);
+    assert!(header.unwrap_key(&[0u8; 32]).is_err());
+
+    let sealed = seal(&VaultData::default(), &header, &second).expect("seal");
+    let (_, loaded, key) = open(&sealed, "rotate").expect("open");
+    assert_eq!(loaded, header);
+    assert_eq!(key, second);
+    assert!(open(&sealed, "wrong").is_err());
+  }
+
+  #[test]
+  fn parse_vault_header_rejects_malformed_input_without_kdf() {
+    let header = VaultHeader {
+      hint: Some("first pet".to_string()),
+      ..VaultHeader::default()
+    };
+    let key = header.derive_key("parse").expect("kdf");
+    let sealed = seal(&VaultData::default(), &header, &key).expect("seal");
+
+    let parsed = parse_vault_header(&sealed).expect("parse");
+    assert_eq!(parsed.format, VaultFormat::V2);
+    assert_eq!(parsed.header, header);
+
+    // Every cut before the end of the first tag is an error, never a panic.
+    let min_len = parsed.nonce_offset + header.nonce_len() + AEAD_TAG_LEN;
+    for len in 0..min_len {
+      assert!(parse_vault_header(&sealed[..len]).is_err(), "accepted {len} bytes");
+    }
+
+    // Hostile KDF parameters are rejected before any key derivation.
+    let mut hostile = header.clone();
+    hostile.kdf.memory_kib = u32::MAX;
+    let bytes = seal(&VaultData::default(), &hostile, &key).expect("seal");
+    assert!(matches!(parse_vault_header(&bytes), Err(VaultError::Kdf(_))));
+
+    // A declared parameter block longer than the file.
+    let mut oversized = sealed.clone();
+    oversized[6..10].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert!(parse_vault_header(&oversized).is_err());
+  }
+
+  #[test]
+  fn limits_reject_oversized_files_and_entry_counts() {
+    use crate::limits::Limits;
+    use crate::store::MemoryStore;
+
+    let header = VaultHeader::default();
+    let key = header.derive_key("limits").expect("kdf");
+    let mut data = VaultData::default();
+    data.entries.push(Entry::new("a".into(), String::new(), String::new(), String::new(), String::new()));
+    data.entries.push(Entry::new("b".into(), String::new(), String::new(), String::new(), String::new()));
+    let store = MemoryStore::new();
+    save_to_store(&store, &data, &header, &key).expect("save");
+
+    let too_few = Limits {
+      max_entries: 1,
+      ..Limits::default()
+    };
+    assert!(matches!(
+      load_from_store_with_limits(&store, "limits", &too_few),
+      Err(VaultError::Limit(LimitExceeded::EntryCount { count: 2, max: 1 }))
+    ));
+
+    let tiny = Limits {
+      max_file_bytes: 16,
+      ..Limits::default()
+    };
+    assert!(matches!(
+      load_from_store_with_limits(&store, "limits", &tiny),
+      Err(VaultError::Limit(LimitExceeded::FileSize { max: 16, .. }))
+    ));
+  }
+}
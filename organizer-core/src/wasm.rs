@@ -0,0 +1,59 @@
+//! WebAssembly bindings (feature `wasm`).
+//!
+//! Exposes read-only access to an exported vault so a web viewer or the
+//! browser extension can decrypt it with the same code as the app. On
+//! `wasm32` the RNG used by [`crate::vault`] is backed by the browser's
+//! `crypto.getRandomValues`; no native code is involved.
+//!
+//! ```js
+//! const vault = PortableVault.open(bytes, password);
+//! const entries = JSON.parse(vault.entries());
+//! const secret = vault.password(entries[0].id);
+//! vault.free(); // zeroizes the decrypted contents
+//! ```
+
+use crate::models::VaultData;
+use crate::vault::{self, VaultError};
+use wasm_bindgen::prelude::*;
+use zeroize::Zeroize;
+
+fn js_error(e: VaultError) -> JsError {
+  match e {
+    VaultError::Crypto(_) => JsError::new("invalid password or corrupted vault"),
+    other => JsError::new(&format!("{other:?}")),
+  }
+}
+
+/// A decrypted vault held in WASM memory.
+///
+/// Entries are zeroized when the object is freed.
+#[wasm_bindgen]
+pub struct PortableVault {
+  data: VaultData,
+}
+
+#[wasm_bindgen]
+impl PortableVault {
+  /// Decrypts vault file bytes with the master password.
+  pub fn open(bytes: &[u8], password: &str) -> Result<PortableVault, JsError> {
+    let (data, _header, mut key) = vault::open(bytes, password).map_err(js_error)?;
+    key.zeroize();
+    Ok(Self { data })
+  }
+
+  /// The vault's stable id.
+  #[wasm_bindgen(js_name = vaultId)]
+  pub fn vault_id(&self) -> String {
+    self.data.meta.id.clone()
+  }
+
+  /// JSON array of entry summaries (no secrets).
+  pub fn entries(&self) -> Result<String, JsError> {
+    serde_json::to_string(&self.data.summaries()).map_err(|e| JsError::new(&e.to_string()))
+  }
+
+  /// Returns the password for `id`, following a shared-password link.
+  pub fn password(&self, id: &str) -> Option<String> {
+    self.data.resolve_password_source(id).map(|e| e.password.clone())
+  }
+}
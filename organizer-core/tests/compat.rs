@@ -0,0 +1,154 @@
+//! Compatibility suite: committed vault files in every format the loader has
+//! ever had to read, plus known-answer KDF vectors.
+//!
+//! The fixtures in `tests/fixtures` were written once and must never be
+//! regenerated. If a format or KDF change breaks one of these tests, it has
+//! broken existing users' vaults.
+//!
+//! Every fixture holds the same single entry (pre-metadata fixtures store it
+//! as a bare entry array), except `v2-streamed.vault`, which adds a second
+//! entry long enough to span several chunks.
+
+use organizer_core::models::{VaultData, SALT_LEN, VAULT_FORMAT_VERSION};
+use organizer_core::vault::{self, CipherSuite, KdfParams, VaultHeader, KDF_ARGON2ID, KDF_SCRYPT};
+
+const V1_VAULT_ID: &str = "5b0e4a52-0000-4000-8000-00000000c0de";
+
+fn fixture(name: &str) -> Vec<u8> {
+  let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name);
+  std::fs::read(&path).unwrap_or_else(|e| panic!("read {}: {e}", path.display()))
+}
+
+fn hex(bytes: &[u8]) -> String {
+  bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Opens a fixture, checks the shared entry, and returns the result.
+fn open_fixture(name: &str, password: &str) -> (VaultData, VaultHeader, [u8; 32]) {
+  let bytes = fixture(name);
+  assert!(vault::open(&bytes, "not the password").is_err(), "{name}: wrong password accepted");
+
+  let (data, header, key) = vault::open(&bytes, password).unwrap_or_else(|e| panic!("{name}: {e:?}"));
+  assert_eq!(data.entries.len(), 1, "{name}");
+  let entry = &data.entries[0];
+  assert_eq!(entry.id, "7f3c1c1e-0000-4000-8000-000000000001");
+  assert_eq!(entry.title, "Example");
+  assert_eq!(entry.username, "alice");
+  assert_eq!(entry.password, "hunter2");
+  assert_eq!(entry.url, "https://example.com");
+  assert_eq!(entry.notes, "fixture");
+  assert!(entry.tags.is_empty());
+  (data, header, key)
+}
+
+/// Old files must come back as the legacy header (XChaCha20, Argon2id with
+/// the original parameters) and be rewritten as the current format.
+fn assert_upgrades(data: &VaultData, header: &VaultHeader, key: &[u8; 32], password: &str) {
+  assert_eq!(header.cipher, CipherSuite::XChaCha20Poly1305);
+  assert_eq!(header.kdf.algorithm, KDF_ARGON2ID);
+  assert_eq!((header.kdf.memory_kib, header.kdf.iterations, header.kdf.parallelism), (64 * 1024, 3, 1));
+
+  let resealed = vault::seal(data, header, key).expect("seal");
+  assert_eq!(&resealed[..5], b"TORG\x02");
+  assert_eq!(resealed[4], VAULT_FORMAT_VERSION);
+  let (reopened, reheader, _) = vault::open(&resealed, password).expect("reopen");
+  assert_eq!(&reheader, header);
+  assert_eq!(reopened.entries[0].password, "hunter2");
+  assert!(!reopened.legacy_payload);
+}
+
+#[test]
+fn legacy_v0_without_header() {
+  let (data, header, key) = open_fixture("v0.vault", "compat-v0");
+  assert!(data.legacy_payload);
+  assert_upgrades(&data, &header, &key, "compat-v0");
+}
+
+#[test]
+fn legacy_v0_whose_salt_starts_with_the_version_byte() {
+  let (data, header, key) = open_fixture("v0-salt-collision.vault", "compat-v0");
+  assert_eq!(header.salt[0], 0x01);
+  assert!(data.legacy_payload);
+  assert_upgrades(&data, &header, &key, "compat-v0");
+}
+
+#[test]
+fn legacy_versioned_without_magic() {
+  let (data, header, key) = open_fixture("versioned.vault", "compat-versioned");
+  assert!(data.legacy_payload);
+  assert_upgrades(&data, &header, &key, "compat-versioned");
+}
+
+#[test]
+fn v1_magic_header() {
+  let (data, header, key) = open_fixture("v1-magic.vault", "compat-v1");
+  assert!(!data.legacy_payload);
+  assert_eq!(data.meta.id, V1_VAULT_ID);
+  assert_upgrades(&data, &header, &key, "compat-v1");
+}
+
+#[test]
+fn v2_cipher_suites_and_kdfs() {
+  let cases = [
+    ("v2-xchacha-argon2id.vault", CipherSuite::XChaCha20Poly1305, KdfParams::current()),
+    ("v2-aes-gcm-siv.vault", CipherSuite::Aes256GcmSiv, KdfParams::current()),
+    ("v2-scrypt.vault", CipherSuite::XChaCha20Poly1305, KdfParams::scrypt()),
+  ];
+  for (name, cipher, kdf) in cases {
+    let (data, header, _) = open_fixture(name, "compat-v2");
+    assert!(!data.legacy_payload);
+    assert_eq!(data.meta.id, V1_VAULT_ID);
+    assert_eq!(header.cipher, cipher, "{name}");
+    assert_eq!(header.kdf, kdf, "{name}");
+    // Written before chunked payloads existed.
+    assert_eq!(header.stream, None, "{name}");
+  }
+}
+
+#[test]
+fn v2_streamed_payload_across_several_chunks() {
+  let bytes = fixture("v2-streamed.vault");
+  let (data, header, _) = vault::open(&bytes, "compat-v2").expect("open");
+  assert_eq!(header.stream.map(|s| s.chunk_size), Some(1024));
+  assert_eq!(data.entries.len(), 2);
+  assert_eq!(data.entries[0].password, "hunter2");
+  assert_eq!(data.entries[1].notes, "0123456789".repeat(400));
+  assert!(vault::open(&bytes, "not the password").is_err());
+
+  // Dropping the last chunk must not yield a shorter valid payload.
+  assert!(vault::open(&bytes[..bytes.len() - 100], "compat-v2").is_err());
+}
+
+#[test]
+fn v2_header_bytes_are_authenticated() {
+  let mut bytes = fixture("v2-xchacha-argon2id.vault");
+  let params_len = u32::from_le_bytes(bytes[6..10].try_into().unwrap()) as usize;
+  // Last salt byte: the key changes too, but decryption must fail either way.
+  bytes[10 + params_len + SALT_LEN - 1] ^= 0x80;
+  assert!(vault::open(&bytes, "compat-v2").is_err());
+}
+
+// Known answers, cross-checked against independent implementations
+// (Python `cryptography` Argon2id and `hashlib.scrypt`).
+
+const KAT_PASSWORD: &str = "password";
+
+fn kat_salt() -> [u8; SALT_LEN] {
+  std::array::from_fn(|i| i as u8)
+}
+
+#[test]
+fn argon2id_known_answer() {
+  let key = vault::derive_key_with(KAT_PASSWORD, &kat_salt(), &KdfParams::current()).expect("kdf");
+  assert_eq!(hex(&key), "8af46d62904bdbb312b5afff66d4262db02e8a9863c34b940c8b0ad9b0031198");
+  // `derive_key` is the same derivation with the current parameters.
+  assert_eq!(vault::derive_key(KAT_PASSWORD, &kat_salt()).expect("kdf"), key);
+}
+
+#[test]
+fn scrypt_known_answer() {
+  let kdf = KdfParams::scrypt();
+  assert_eq!(kdf.algorithm, KDF_SCRYPT);
+  let key = vault::derive_key_with(KAT_PASSWORD, &kat_salt(), &kdf).expect("kdf");
+  assert_eq!(hex(&key), "94c51f70b5bd7b4605580849c7b3a9f86fd1ab99160eb8c459de7b15a9886ee4");
+}
@@ -0,0 +1,165 @@
+//! Activity tracking for auto-lock.
+//!
+//! Every command and extension request records activity, so the hot path is
+//! a pair of atomic stores rather than a mutex. Activity is tagged by
+//! [`ActivitySource`]: [`AutoLockPolicy`] gives each source its own idle
+//! timeout, and [`Activity::snapshot`] shows which source keeps resetting
+//! the timer.
+
+use crate::models::INACTIVITY_TIMEOUT_SECS;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Where activity came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivitySource {
+  /// The desktop UI (commands and the frontend's periodic heartbeat).
+  Ui,
+  /// The browser extension bridge.
+  Extension,
+  /// Command-line tools talking to the running app.
+  Cli,
+}
+
+impl ActivitySource {
+  pub const ALL: [ActivitySource; 3] = [ActivitySource::Ui, ActivitySource::Extension, ActivitySource::Cli];
+
+  fn index(self) -> usize {
+    self as usize
+  }
+}
+
+/// Idle timeout per source. The vault locks once every source has been idle
+/// for longer than its timeout.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoLockPolicy {
+  pub ui_secs: u64,
+  pub extension_secs: u64,
+  pub cli_secs: u64,
+}
+
+impl Default for AutoLockPolicy {
+  fn default() -> Self {
+    Self {
+      ui_secs: INACTIVITY_TIMEOUT_SECS,
+      extension_secs: INACTIVITY_TIMEOUT_SECS,
+      cli_secs: INACTIVITY_TIMEOUT_SECS,
+    }
+  }
+}
+
+impl AutoLockPolicy {
+  pub fn timeout(&self, source: ActivitySource) -> Duration {
+    Duration::from_secs(match source {
+      ActivitySource::Ui => self.ui_secs,
+      ActivitySource::Extension => self.extension_secs,
+      ActivitySource::Cli => self.cli_secs,
+    })
+  }
+}
+
+/// Activity of one source, for diagnostics.
+#[derive(Clone, Debug, Serialize)]
+pub struct SourceActivity {
+  pub source: ActivitySource,
+  /// Events recorded since the app started.
+  pub count: u64,
+  /// Seconds since the last event (or since the last lock).
+  pub idle_secs: u64,
+}
+
+/// Last-activity timestamps and counters, one slot per source.
+///
+/// Timestamps are milliseconds since `epoch`, so they fit an `AtomicU64`
+/// and stay monotonic.
+#[derive(Debug)]
+pub struct Activity {
+  epoch: Instant,
+  last_ms: [AtomicU64; ActivitySource::ALL.len()],
+  counts: [AtomicU64; ActivitySource::ALL.len()],
+}
+
+impl Default for Activity {
+  fn default() -> Self {
+    Self {
+      epoch: Instant::now(),
+      last_ms: Default::default(),
+      counts: Default::default(),
+    }
+  }
+}
+
+impl Activity {
+  fn now_ms(&self) -> u64 {
+    self.epoch.elapsed().as_millis() as u64
+  }
+
+  /// Records activity from `source`, resetting its idle timer.
+  pub fn record(&self, source: ActivitySource) {
+    self.last_ms[source.index()].store(self.now_ms(), Ordering::Relaxed);
+    self.counts[source.index()].fetch_add(1, Ordering::Relaxed);
+  }
+
+  /// Restarts every idle timer without counting an event, e.g. on lock.
+  pub fn reset(&self) {
+    let now = self.now_ms();
+    for last in &self.last_ms {
+      last.store(now, Ordering::Relaxed);
+    }
+  }
+
+  /// Time since `source` was last active.
+  pub fn idle(&self, source: ActivitySource) -> Duration {
+    let last = self.last_ms[source.index()].load(Ordering::Relaxed);
+    Duration::from_millis(self.now_ms().saturating_sub(last))
+  }
+
+  /// True once every source has been idle past its timeout.
+  pub fn is_idle(&self, policy: &AutoLockPolicy) -> bool {
+    ActivitySource::ALL
+      .iter()
+      .all(|&source| self.idle(source) > policy.timeout(source))
+  }
+
+  pub fn snapshot(&self) -> Vec<SourceActivity> {
+    ActivitySource::ALL
+      .iter()
+      .map(|&source| SourceActivity {
+        source,
+        count: self.counts[source.index()].load(Ordering::Relaxed),
+        idle_secs: self.idle(source).as_secs(),
+      })
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn each_source_has_its_own_timeout() {
+    let activity = Activity::default();
+    let policy = AutoLockPolicy {
+      ui_secs: 0,
+      extension_secs: 3600,
+      cli_secs: 0,
+    };
+    std::thread::sleep(Duration::from_millis(5));
+    // The extension's long timeout keeps the vault open.
+    assert!(!activity.is_idle(&policy));
+    assert!(activity.is_idle(&AutoLockPolicy {
+      extension_secs: 0,
+      ..policy
+    }));
+
+    activity.record(ActivitySource::Extension);
+    activity.record(ActivitySource::Extension);
+    let snapshot = activity.snapshot();
+    assert_eq!(snapshot[ActivitySource::Extension.index()].count, 2);
+    assert_eq!(snapshot[ActivitySource::Ui.index()].count, 0);
+  }
+}
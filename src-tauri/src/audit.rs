@@ -0,0 +1,468 @@
+//! Append-only audit log of notable vault activity.
+//!
+//! Events are written as JSON lines to `audit.log` next to the vault file.
+//! The log lives outside the encrypted vault so that events can be recorded
+//! while the vault is locked; for the same reason it never contains secrets
+//! or entry titles, only entry ids and short non-sensitive details.
+
+use crate::models::AppState;
+use crate::settings::RetentionPolicy;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Filename for the audit log (stored next to `vault.dat`).
+pub const AUDIT_LOG_FILENAME: &str = "audit.log";
+
+/// What happened.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditKind {
+  /// A secret field was copied to the clipboard from the desktop app.
+  SecretCopied,
+  /// A secret field was shown in the desktop app.
+  SecretRevealed,
+  /// The browser extension retrieved a secret or identity data.
+  ExtensionSecretRead,
+  /// A CLI mode printed a secret derived from an entry; `detail` holds the
+  /// mode.
+  CliSecretRead,
+  /// A process that is not a known browser asked the extension bridge for
+  /// a secret; `client` holds its executable name.
+  ExtensionClientRejected,
+  /// Tagged API keys were written to a `.env` file.
+  EnvExported,
+  /// Entry fields were put into the environment of a process started from
+  /// the app; `detail` holds the variable names and the program.
+  SecretsInjected,
+  /// The vault was unlocked.
+  Unlocked,
+  /// An unlock attempt failed.
+  UnlockFailed,
+  /// Too many failed unlocks; `detail` holds the lockout in seconds.
+  /// Recorded when the lockout policy asks for it.
+  UnlockLockedOut,
+  /// An entry was created.
+  EntryAdded,
+  /// An entry was edited.
+  EntryUpdated,
+  /// An entry was moved to the trash.
+  EntryDeleted,
+  /// An entry was restored from the trash.
+  EntryRestored,
+  /// The vault was exported to an encrypted file.
+  VaultExported,
+  /// The vault was replaced by an imported file.
+  VaultImported,
+  /// Another copy of the vault was merged in; `detail` holds the conflict count.
+  VaultMerged,
+  /// Another device was enrolled to sync the vault; `detail` holds its id.
+  DeviceEnrolled,
+  /// The vault was replaced by a backup; `detail` holds the backup's path.
+  BackupRestored,
+  /// Logins were imported from another store; `detail` holds the source and
+  /// count.
+  EntriesImported,
+  /// A hidden entry was returned by an exact search after re-authentication.
+  HiddenEntryShown,
+  /// The canary entry's secret was read; `detail` holds how.
+  CanaryTripped,
+}
+
+impl AuditKind {
+  /// The activity feed category this kind belongs to.
+  pub fn category(self) -> FeedCategory {
+    match self {
+      AuditKind::Unlocked | AuditKind::UnlockFailed | AuditKind::UnlockLockedOut => FeedCategory::Unlocks,
+      AuditKind::EnvExported | AuditKind::VaultExported
+      | AuditKind::VaultImported
+      | AuditKind::VaultMerged
+      | AuditKind::DeviceEnrolled
+      | AuditKind::BackupRestored => {
+        FeedCategory::Exports
+      }
+      AuditKind::EntryAdded
+      | AuditKind::EntryUpdated
+      | AuditKind::EntryDeleted
+      | AuditKind::EntryRestored
+      | AuditKind::EntriesImported => {
+        FeedCategory::Edits
+      }
+      AuditKind::ExtensionSecretRead | AuditKind::ExtensionClientRejected => FeedCategory::Extension,
+      AuditKind::SecretCopied
+      | AuditKind::SecretRevealed
+      | AuditKind::SecretsInjected
+      | AuditKind::CliSecretRead
+      | AuditKind::HiddenEntryShown
+      | AuditKind::CanaryTripped => FeedCategory::Secrets,
+    }
+  }
+}
+
+/// Groups of audit kinds, for filtering the activity feed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeedCategory {
+  Unlocks,
+  Exports,
+  Edits,
+  Extension,
+  Secrets,
+}
+
+/// A single audit log record.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AuditEvent {
+  pub at: DateTime<Utc>,
+  pub kind: AuditKind,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub entry_id: Option<String>,
+  /// Short non-secret context, e.g. the copied field name.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub detail: Option<String>,
+  /// Executable name of the extension bridge client, when identified.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub client: Option<String>,
+}
+
+impl AuditEvent {
+  pub fn new(kind: AuditKind) -> Self {
+    Self {
+      at: Utc::now(),
+      kind,
+      entry_id: None,
+      detail: None,
+      client: None,
+    }
+  }
+
+  pub fn entry(mut self, id: &str) -> Self {
+    self.entry_id = Some(id.to_string());
+    self
+  }
+
+  pub fn detail(mut self, detail: impl Into<String>) -> Self {
+    self.detail = Some(detail.into());
+    self
+  }
+
+  pub fn client(mut self, client: Option<&str>) -> Self {
+    self.client = client.map(String::from);
+    self
+  }
+}
+
+/// Resolves the audit log path next to the vault file.
+///
+/// Returns `None` until a command has resolved the vault store, or when the
+/// store is not file-backed.
+pub fn log_path(state: &AppState) -> Option<PathBuf> {
+  let store = state.current_store()?;
+  Some(store.path()?.with_file_name(AUDIT_LOG_FILENAME))
+}
+
+/// Appends an event to the audit log. Failures are reported but never block
+/// the operation being audited.
+pub fn record(state: &AppState, event: AuditEvent) {
+  let Some(path) = log_path(state) else {
+    eprintln!("audit: vault path not resolved; dropping {:?} event", event.kind);
+    return;
+  };
+  if let Err(err) = append(&path, &event) {
+    eprintln!("audit: {err}");
+  }
+  let limit = state.settings.lock().map(|s| s.retention).unwrap_or_default().audit_log_limit();
+  if let Err(err) = trim(&path, limit) {
+    eprintln!("audit: {err}");
+  }
+}
+
+/// Appends an event to the audit log next to the vault file at `vault`, for
+/// CLI modes, which run without app state. The log is kept within the
+/// default size limit; the app trims it to the configured one.
+pub fn record_beside(vault: &Path, event: AuditEvent) {
+  let path = vault.with_file_name(AUDIT_LOG_FILENAME);
+  let result = append(&path, &event).and_then(|()| trim(&path, RetentionPolicy::default().audit_log_limit()));
+  if let Err(err) = result {
+    eprintln!("audit: {err}");
+  }
+}
+
+/// Appends one JSON line to the log at `path`.
+pub fn append(path: &Path, event: &AuditEvent) -> Result<(), String> {
+  let mut line = serde_json::to_string(event).map_err(|e| format!("serialize audit event failed: {e}"))?;
+  line.push('\n');
+  let mut file = OpenOptions::new()
+    .create(true)
+    .append(true)
+    .open(path)
+    .map_err(|e| format!("open audit log failed: {e}"))?;
+  file
+    .write_all(line.as_bytes())
+    .map_err(|e| format!("write audit log failed: {e}"))
+}
+
+/// Drops the oldest events once the log at `path` exceeds `max_bytes`,
+/// keeping the newest ones within three quarters of the limit so the log is
+/// not rewritten on every append. Returns how many events were dropped.
+pub fn trim(path: &Path, max_bytes: u64) -> Result<usize, String> {
+  let size = match fs::metadata(path) {
+    Ok(meta) => meta.len(),
+    Err(_) => return Ok(0),
+  };
+  if size <= max_bytes {
+    return Ok(0);
+  }
+  let raw = fs::read_to_string(path).map_err(|e| format!("read audit log failed: {e}"))?;
+  let lines: Vec<&str> = raw.lines().filter(|line| !line.trim().is_empty()).collect();
+  let budget = max_bytes / 4 * 3;
+  let mut kept_bytes = 0u64;
+  let mut keep = 0;
+  for line in lines.iter().rev() {
+    kept_bytes += line.len() as u64 + 1;
+    if kept_bytes > budget {
+      break;
+    }
+    keep += 1;
+  }
+  let mut kept = lines[lines.len() - keep..].join("\n");
+  if keep > 0 {
+    kept.push('\n');
+  }
+  crate::backup::write_atomically(path, kept.as_bytes()).map_err(|e| format!("trim audit log failed: {e}"))?;
+  Ok(lines.len() - keep)
+}
+
+/// Reads all events from the log at `path`, oldest first.
+///
+/// A missing file is an empty log; unparseable lines are skipped.
+pub fn read_all(path: &Path) -> Result<Vec<AuditEvent>, String> {
+  if !path.exists() {
+    return Ok(Vec::new());
+  }
+  let raw = fs::read_to_string(path).map_err(|e| format!("read audit log failed: {e}"))?;
+  Ok(
+    raw
+      .lines()
+      .filter(|line| !line.trim().is_empty())
+      .filter_map(|line| serde_json::from_str(line).ok())
+      .collect(),
+  )
+}
+
+/// Failed unlock attempts between the previous successful unlock and the
+/// current one.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct UnlockHistory {
+  pub failed_attempts: usize,
+  pub last_failed_at: Option<DateTime<Utc>>,
+  /// The unlock before the current one, if any.
+  pub previous_unlock_at: Option<DateTime<Utc>>,
+}
+
+/// Summarizes unlock events from a log read by [`read_all`], as seen right
+/// after an unlock: failures since the unlock before the latest one.
+pub fn unlock_history(events: &[AuditEvent]) -> UnlockHistory {
+  let mut unlocks = events
+    .iter()
+    .enumerate()
+    .rev()
+    .filter(|(_, e)| e.kind == AuditKind::Unlocked)
+    .map(|(i, e)| (i, e.at));
+  let current = unlocks.next().map_or(events.len(), |(i, _)| i);
+  let previous = unlocks.next();
+  let start = previous.map_or(0, |(i, _)| i + 1);
+
+  let failed: Vec<&AuditEvent> = events[start..current]
+    .iter()
+    .filter(|e| e.kind == AuditKind::UnlockFailed)
+    .collect();
+  UnlockHistory {
+    failed_attempts: failed.len(),
+    last_failed_at: failed.last().map(|e| e.at),
+    previous_unlock_at: previous.map(|(_, at)| at),
+  }
+}
+
+/// One access to an entry's secrets.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct EntryAccess {
+  pub at: DateTime<Utc>,
+  pub kind: AuditKind,
+  pub detail: Option<String>,
+  pub client: Option<String>,
+}
+
+/// Secret accesses (copies, reveals, extension and CLI reads) of one entry.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct EntryActivity {
+  pub copies: usize,
+  pub reveals: usize,
+  pub extension_reads: usize,
+  pub cli_reads: usize,
+  pub last_access_at: Option<DateTime<Utc>>,
+  /// Most recent accesses first, at most the requested number.
+  pub recent: Vec<EntryAccess>,
+}
+
+/// Counts the secret accesses of `entry_id` in a log read by [`read_all`].
+pub fn entry_activity(events: &[AuditEvent], entry_id: &str, recent_limit: usize) -> EntryActivity {
+  let mut activity = EntryActivity::default();
+  for event in events.iter().rev() {
+    if event.entry_id.as_deref() != Some(entry_id) {
+      continue;
+    }
+    match event.kind {
+      AuditKind::SecretCopied => activity.copies += 1,
+      AuditKind::SecretRevealed => activity.reveals += 1,
+      AuditKind::ExtensionSecretRead => activity.extension_reads += 1,
+      AuditKind::CliSecretRead => activity.cli_reads += 1,
+      _ => continue,
+    }
+    activity.last_access_at = activity.last_access_at.or(Some(event.at));
+    if activity.recent.len() < recent_limit {
+      activity.recent.push(EntryAccess {
+        at: event.at,
+        kind: event.kind,
+        detail: event.detail.clone(),
+        client: event.client.clone(),
+      });
+    }
+  }
+  activity
+}
+
+/// Narrows the activity feed. Unset fields match everything.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct FeedFilter {
+  /// Only events in these categories.
+  pub categories: Option<Vec<FeedCategory>>,
+  pub entry_id: Option<String>,
+  /// Only events at or after this time.
+  pub since: Option<DateTime<Utc>>,
+}
+
+impl FeedFilter {
+  fn matches(&self, event: &AuditEvent) -> bool {
+    if let Some(categories) = &self.categories {
+      if !categories.contains(&event.kind.category()) {
+        return false;
+      }
+    }
+    if let Some(id) = &self.entry_id {
+      if event.entry_id.as_ref() != Some(id) {
+        return false;
+      }
+    }
+    self.since.is_none_or(|since| event.at >= since)
+  }
+}
+
+/// Events from a log read by [`read_all`] that match `filter`, newest first,
+/// at most `limit` of them.
+pub fn activity_feed(events: &[AuditEvent], limit: usize, filter: &FeedFilter) -> Vec<AuditEvent> {
+  events
+    .iter()
+    .rev()
+    .filter(|event| filter.matches(event))
+    .take(limit)
+    .cloned()
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn counts_failures_since_the_previous_unlock() {
+    let kinds = [
+      AuditKind::UnlockFailed,
+      AuditKind::Unlocked,
+      AuditKind::SecretCopied,
+      AuditKind::UnlockFailed,
+      AuditKind::UnlockFailed,
+      AuditKind::UnlockFailed,
+      AuditKind::Unlocked,
+    ];
+    let events: Vec<AuditEvent> = kinds.into_iter().map(AuditEvent::new).collect();
+    let history = unlock_history(&events);
+    assert_eq!(history.failed_attempts, 3);
+    assert_eq!(history.last_failed_at, Some(events[5].at));
+    assert_eq!(history.previous_unlock_at, Some(events[1].at));
+
+    // First unlock ever: everything before it counts.
+    assert_eq!(unlock_history(&events[..2]).failed_attempts, 1);
+  }
+
+  #[test]
+  fn trim_keeps_the_newest_events_within_the_limit() {
+    let path = std::env::temp_dir().join(format!("the-organizer-audit-{}.log", std::process::id()));
+    let _ = fs::remove_file(&path);
+    for n in 0..100 {
+      append(&path, &AuditEvent::new(AuditKind::SecretCopied).detail(format!("field-{n}"))).unwrap();
+    }
+    let size = fs::metadata(&path).unwrap().len();
+    assert_eq!(trim(&path, size).unwrap(), 0);
+
+    let dropped = trim(&path, size / 2).unwrap();
+    let events = read_all(&path).unwrap();
+    assert_eq!(dropped + events.len(), 100);
+    assert!(fs::metadata(&path).unwrap().len() <= size / 2 / 4 * 3);
+    assert_eq!(events.last().unwrap().detail.as_deref(), Some("field-99"));
+    let _ = fs::remove_file(&path);
+  }
+
+  #[test]
+  fn entry_activity_counts_each_kind_of_access() {
+    let events = vec![
+      AuditEvent::new(AuditKind::SecretCopied).entry("bank").detail("password"),
+      AuditEvent::new(AuditKind::SecretCopied).entry("mail").detail("password"),
+      AuditEvent::new(AuditKind::SecretRevealed).entry("bank").detail("password"),
+      AuditEvent::new(AuditKind::ExtensionSecretRead).entry("bank").detail("password"),
+      AuditEvent::new(AuditKind::CliSecretRead).entry("bank").detail("curl-header"),
+      AuditEvent::new(AuditKind::EnvExported).entry("bank"),
+    ];
+    let activity = entry_activity(&events, "bank", 2);
+    assert_eq!(
+      (activity.copies, activity.reveals, activity.extension_reads, activity.cli_reads),
+      (1, 1, 1, 1)
+    );
+    assert_eq!(activity.last_access_at, Some(events[4].at));
+    assert_eq!(activity.recent.len(), 2);
+    assert_eq!(activity.recent[0].kind, AuditKind::CliSecretRead);
+  }
+
+  #[test]
+  fn activity_feed_filters_by_category_and_entry() {
+    let events = vec![
+      AuditEvent::new(AuditKind::Unlocked),
+      AuditEvent::new(AuditKind::EntryAdded).entry("bank"),
+      AuditEvent::new(AuditKind::ExtensionSecretRead).entry("bank"),
+      AuditEvent::new(AuditKind::EntryUpdated).entry("mail"),
+      AuditEvent::new(AuditKind::VaultExported),
+    ];
+    let kinds = |feed: Vec<AuditEvent>| feed.into_iter().map(|e| e.kind).collect::<Vec<_>>();
+
+    assert_eq!(
+      kinds(activity_feed(&events, 2, &FeedFilter::default())),
+      [AuditKind::VaultExported, AuditKind::EntryUpdated]
+    );
+    let edits = FeedFilter {
+      categories: Some(vec![FeedCategory::Edits]),
+      ..FeedFilter::default()
+    };
+    assert_eq!(kinds(activity_feed(&events, 10, &edits)), [AuditKind::EntryUpdated, AuditKind::EntryAdded]);
+    let bank = FeedFilter {
+      entry_id: Some("bank".into()),
+      ..FeedFilter::default()
+    };
+    assert_eq!(
+      kinds(activity_feed(&events, 10, &bank)),
+      [AuditKind::ExtensionSecretRead, AuditKind::EntryAdded]
+    );
+  }
+}
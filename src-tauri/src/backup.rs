@@ -0,0 +1,314 @@
+//! Scheduled backups of the encrypted vault.
+//!
+//! A backup is a copy of the sealed vault file, so it needs no password and
+//! is protected exactly like the vault. Backups go to a user-chosen
+//! directory (e.g. a NAS mount) as `vault-backup-<UTC time>.dat`, written to
+//! a temporary name first so a half-written copy never looks complete. The
+//! inactivity monitor starts a due backup on a background thread, locked or
+//! not; the outcome is kept in `backup-status.json` and reported through
+//! notifications.
+//!
+//! Before a backup replaces the vault, [`preview`] checks its header and,
+//! given the master password, decrypts it and counts its entries, so the
+//! user sees what they are restoring first.
+
+use crate::commands::resolve_store;
+use crate::limits::Limits;
+use crate::models::AppState;
+use crate::notifications::{self, Notice, NoticeKind};
+use crate::vault::{self, VaultError, VaultLoadResult, WriterStamp};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::thread;
+use tauri::{AppHandle, Manager};
+
+/// File in the app data directory holding [`BackupStatus`].
+pub const BACKUP_STATUS_FILENAME: &str = "backup-status.json";
+
+/// Prefix of backup file names.
+pub const BACKUP_PREFIX: &str = "vault-backup-";
+
+/// Wait before retrying a failed backup.
+const RETRY_AFTER_MINUTES: i64 = 60;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupFrequency {
+  #[default]
+  Daily,
+  Weekly,
+}
+
+/// Where and how often backups are written.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupSchedule {
+  pub enabled: bool,
+  pub frequency: BackupFrequency,
+  pub directory: Option<PathBuf>,
+}
+
+impl BackupSchedule {
+  /// Checks the schedule itself; whether the directory is reachable is only
+  /// checked when it is chosen, since a NAS may be offline at startup.
+  pub fn validate(&self) -> Result<(), String> {
+    if self.enabled && self.directory.is_none() {
+      return Err("choose a backup directory".to_string());
+    }
+    Ok(())
+  }
+
+  pub fn interval(&self) -> Duration {
+    match self.frequency {
+      BackupFrequency::Daily => Duration::days(1),
+      BackupFrequency::Weekly => Duration::weeks(1),
+    }
+  }
+}
+
+/// Outcome of the latest backups.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupStatus {
+  pub last_run: Option<DateTime<Utc>>,
+  pub last_success: Option<DateTime<Utc>>,
+  /// Set when the latest run failed.
+  pub last_error: Option<String>,
+  pub last_file: Option<PathBuf>,
+  #[serde(skip)]
+  pub running: bool,
+}
+
+impl BackupStatus {
+  /// When the next backup is due under `schedule`: right away if none ran
+  /// yet, an hour after a failure, otherwise one interval after the last.
+  pub fn next_run(&self, schedule: &BackupSchedule, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    if !schedule.enabled {
+      return None;
+    }
+    let Some(last_run) = self.last_run else {
+      return Some(now);
+    };
+    Some(match self.last_error {
+      Some(_) => last_run + Duration::minutes(RETRY_AFTER_MINUTES),
+      None => last_run + schedule.interval(),
+    })
+  }
+}
+
+fn status_path(app: &AppHandle) -> Result<PathBuf, String> {
+  let dir = app
+    .path()
+    .app_data_dir()
+    .map_err(|e| format!("app_data_dir failed: {e}"))?;
+  fs::create_dir_all(&dir).map_err(|e| format!("create_dir_all failed: {e}"))?;
+  Ok(dir.join(BACKUP_STATUS_FILENAME))
+}
+
+pub fn load_status(app: &AppHandle) -> Result<BackupStatus, String> {
+  let path = status_path(app)?;
+  if !path.exists() {
+    return Ok(BackupStatus::default());
+  }
+  let raw = fs::read_to_string(&path).map_err(|e| format!("read backup status failed: {e}"))?;
+  serde_json::from_str(&raw).map_err(|e| format!("parse backup status failed: {e}"))
+}
+
+fn save_status(app: &AppHandle, status: &BackupStatus) -> Result<(), String> {
+  let path = status_path(app)?;
+  let serialized = serde_json::to_string_pretty(status).map_err(|e| format!("serialize backup status failed: {e}"))?;
+  fs::write(&path, serialized).map_err(|e| format!("write backup status failed: {e}"))
+}
+
+/// The sealed vault as stored. Record-based (SQLite) vaults are sealed from
+/// memory, so they are only backed up while unlocked.
+pub(crate) fn sealed_vault(app: &AppHandle, state: &AppState) -> Result<Vec<u8>, String> {
+  let store = resolve_store(app, state)?;
+  if store.records().is_none() {
+    if !store.exists() {
+      return Err("there is no vault to back up".to_string());
+    }
+    return store.read().map_err(|e| format!("read vault failed: {e}"));
+  }
+  let session = state.session.lock().map_err(|_| "session mutex poisoned".to_string())?;
+  let vault = state.vault.lock().map_err(|_| "vault mutex poisoned".to_string())?;
+  match (session.as_ref(), vault.as_ref()) {
+    (Some(session), Some(data)) => {
+      vault::seal(data, &session.header, session.key_bytes()).map_err(|e| format!("seal: {:?}", e))
+    }
+    _ => Err("SQLite vaults are only backed up while unlocked".to_string()),
+  }
+}
+
+/// Writes `bytes` as a new backup in `dir` and returns its path.
+pub fn write_backup(dir: &Path, bytes: &[u8], now: DateTime<Utc>) -> Result<PathBuf, String> {
+  if !dir.is_dir() {
+    return Err(format!("backup directory not found: {}", dir.display()));
+  }
+  let path = dir.join(format!("{BACKUP_PREFIX}{}.dat", now.format("%Y%m%d-%H%M%S")));
+  write_atomically(&path, bytes).map_err(|e| format!("write backup failed: {e}"))?;
+  Ok(path)
+}
+
+/// Writes `bytes` to `path` through a `.part` file renamed into place, so
+/// a half-written file never replaces or looks like a complete one.
+pub(crate) fn write_atomically(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+  let mut partial = path.as_os_str().to_owned();
+  partial.push(".part");
+  let partial = PathBuf::from(partial);
+  let write = || -> std::io::Result<()> {
+    let mut file = fs::File::create(&partial)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    fs::rename(&partial, path)
+  };
+  write().inspect_err(|_| {
+    let _ = fs::remove_file(&partial);
+  })
+}
+
+/// What a backup holds, shown before it is restored.
+#[derive(Clone, Debug, Serialize)]
+pub struct BackupPreview {
+  pub size: u64,
+  pub cipher: String,
+  pub kdf: String,
+  /// Device and time of the write that produced the backup, if recorded.
+  pub written_by: Option<WriterStamp>,
+  /// The fields below need the master password.
+  pub entry_count: Option<usize>,
+  pub trash_count: Option<usize>,
+  /// Newest entry edit or deletion.
+  pub last_modified: Option<DateTime<Utc>>,
+  pub vault_id: Option<String>,
+}
+
+/// Verifies a backup: its header always, and with `master_password` its
+/// contents (decryption and the vault limits). Nothing is written.
+pub fn preview(bytes: &[u8], master_password: Option<&str>) -> Result<(BackupPreview, Option<VaultLoadResult>), String> {
+  let rejected = |e: VaultError| match e {
+    VaultError::Limit(limit) => format!("backup rejected: {limit}"),
+    VaultError::Crypto(_) => "the backup could not be decrypted with this password".to_string(),
+    other => format!("invalid backup: {:?}", other),
+  };
+  let limits = Limits::default();
+  limits.check_file_size(bytes.len() as u64).map_err(|e| rejected(e.into()))?;
+  let header = vault::parse_vault_header(bytes).map_err(rejected)?.header;
+  let mut preview = BackupPreview {
+    size: bytes.len() as u64,
+    cipher: header.cipher.name().to_string(),
+    kdf: header.kdf.algorithm.clone(),
+    written_by: header.written_by.clone(),
+    entry_count: None,
+    trash_count: None,
+    last_modified: None,
+    vault_id: None,
+  };
+  let Some(master_password) = master_password else {
+    return Ok((preview, None));
+  };
+  let opened = vault::open_with_limits(bytes, master_password, &limits).map_err(rejected)?;
+  let data = &opened.0;
+  preview.entry_count = Some(data.entries.len());
+  preview.trash_count = Some(data.trash.len());
+  preview.last_modified = data
+    .entries
+    .iter()
+    .map(|e| e.updated_at)
+    .chain(data.trash.iter().map(|t| t.deleted_at))
+    .max();
+  preview.vault_id = Some(data.meta.id.clone());
+  Ok((preview, Some(opened)))
+}
+
+/// Runs one backup now and records and announces the outcome.
+pub fn run(app: &AppHandle, state: &AppState, schedule: &BackupSchedule) -> Result<PathBuf, String> {
+  let now = Utc::now();
+  let result = schedule
+    .directory
+    .as_deref()
+    .ok_or_else(|| "choose a backup directory".to_string())
+    .and_then(|dir| sealed_vault(app, state).and_then(|bytes| write_backup(dir, &bytes, now)));
+
+  let status = match state.backup.lock() {
+    Ok(mut status) => {
+      status.running = false;
+      status.last_run = Some(now);
+      match &result {
+        Ok(path) => {
+          status.last_success = Some(now);
+          status.last_error = None;
+          status.last_file = Some(path.clone());
+        }
+        Err(err) => status.last_error = Some(err.clone()),
+      }
+      status.clone()
+    }
+    Err(_) => return Err("backup status mutex poisoned".to_string()),
+  };
+  if let Err(err) = save_status(app, &status) {
+    eprintln!("{err}");
+  }
+
+  let notice = match &result {
+    Ok(_) => Notice::new(NoticeKind::BackupResult, "The vault was backed up."),
+    Err(err) => Notice::new(NoticeKind::BackupFailure, format!("The vault could not be backed up: {err}")),
+  };
+  notifications::notify(app, state, notice);
+  result
+}
+
+/// Starts a due backup on a background thread. Called by the inactivity
+/// monitor on every tick.
+pub fn maybe_run(state: &AppState, app: &AppHandle) {
+  let Ok(schedule) = state.settings.lock().map(|s| s.backup.clone()) else {
+    return;
+  };
+  {
+    let Ok(mut status) = state.backup.lock() else {
+      return;
+    };
+    let due = status.next_run(&schedule, Utc::now()).is_some_and(|at| at <= Utc::now());
+    if status.running || !due {
+      return;
+    }
+    status.running = true;
+  }
+  let (state, app) = (state.clone(), app.clone());
+  thread::spawn(move || {
+    if let Err(err) = run(&app, &state, &schedule) {
+      eprintln!("scheduled backup: {err}");
+    }
+  });
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn next_run_follows_frequency_and_retries_failures_sooner() {
+    let now: DateTime<Utc> = "2024-11-02T10:00:00Z".parse().unwrap();
+    let mut schedule = BackupSchedule {
+      enabled: true,
+      frequency: BackupFrequency::Weekly,
+      directory: Some(PathBuf::from("/mnt/nas")),
+    };
+    let mut status = BackupStatus::default();
+    assert_eq!(status.next_run(&schedule, now), Some(now));
+
+    status.last_run = Some(now);
+    assert_eq!(status.next_run(&schedule, now), Some(now + Duration::weeks(1)));
+    status.last_error = Some("backup directory not found".into());
+    assert_eq!(status.next_run(&schedule, now), Some(now + Duration::hours(1)));
+
+    schedule.enabled = false;
+    assert_eq!(status.next_run(&schedule, now), None);
+    schedule.enabled = true;
+    schedule.directory = None;
+    assert!(schedule.validate().is_err());
+  }
+}
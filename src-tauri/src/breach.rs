@@ -0,0 +1,191 @@
+//! Breached-password checks against an offline Have I Been Pwned dataset.
+//!
+//! For air-gapped machines: the user downloads the "SHA-1, ordered by hash"
+//! Pwned Passwords file (lines of `HASH:COUNT`, uppercase hex, sorted) and
+//! points the settings at it. Lookups binary-search the file on disk, so the
+//! multi-gigabyte dataset is never loaded into memory, and no password or
+//! hash leaves the machine.
+
+use crate::models::Entry;
+use serde::Serialize;
+use sha1::{Digest, Sha1};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// Below this many bytes the search scans lines instead of bisecting.
+const SCAN_WINDOW: u64 = 4096;
+
+/// Length of a SHA-1 hash in hex.
+const HASH_HEX_LEN: usize = 40;
+
+/// An entry whose password appears in the dataset.
+#[derive(Clone, Debug, Serialize)]
+pub struct BreachedEntry {
+  pub id: String,
+  pub title: String,
+  pub url: String,
+  /// How often the password appears in known breaches.
+  pub occurrences: u64,
+}
+
+/// An opened dataset file.
+pub struct HibpDataset {
+  path: PathBuf,
+  reader: BufReader<File>,
+  len: u64,
+}
+
+impl HibpDataset {
+  /// Opens `path`, checking that it looks like the SHA-1 ordered-by-hash
+  /// file (the NTLM variant has 32-character hashes).
+  pub fn open(path: &Path) -> Result<Self, String> {
+    let file = File::open(path).map_err(|e| format!("open breach dataset failed: {e}"))?;
+    let len = file.metadata().map_err(|e| format!("read breach dataset failed: {e}"))?.len();
+    let mut dataset = Self {
+      path: path.to_path_buf(),
+      reader: BufReader::new(file),
+      len,
+    };
+    let first = dataset.line_at(0).map_err(|e| format!("read breach dataset failed: {e}"))?;
+    if parse_line(&first).is_none() {
+      return Err(format!(
+        "{} is not a SHA-1 Pwned Passwords file (expected HASH:COUNT lines)",
+        dataset.path.display()
+      ));
+    }
+    Ok(dataset)
+  }
+
+  /// Breach count for an uppercase hex SHA-1 hash, or `None` if absent.
+  pub fn lookup(&mut self, hash: &str) -> io::Result<Option<u64>> {
+    // Invariant: `lo` is a line start, and the line for `hash`, if
+    // present, starts in `lo..hi`.
+    let (mut lo, mut hi) = (0, self.len);
+    while hi - lo > SCAN_WINDOW {
+      let mid = lo + (hi - lo) / 2;
+      let start = self.next_line_start(mid)?;
+      if start >= hi {
+        // No line starts after `mid`; the target starts at or before it.
+        hi = mid + 1;
+        continue;
+      }
+      let line = self.line_at(start)?;
+      match parse_line(&line) {
+        Some((line_hash, count)) if line_hash == hash => return Ok(Some(count)),
+        Some((line_hash, _)) if line_hash < hash => lo = start + line.len() as u64,
+        _ => hi = start,
+      }
+    }
+
+    self.reader.seek(SeekFrom::Start(lo))?;
+    let mut position = lo;
+    let mut line = String::new();
+    while position < hi {
+      line.clear();
+      let read = self.reader.read_line(&mut line)?;
+      if read == 0 {
+        break;
+      }
+      position += read as u64;
+      match parse_line(&line) {
+        Some((line_hash, count)) if line_hash == hash => return Ok(Some(count)),
+        Some((line_hash, _)) if line_hash > hash => break,
+        _ => {}
+      }
+    }
+    Ok(None)
+  }
+
+  /// Offset of the first line starting after `offset`.
+  fn next_line_start(&mut self, offset: u64) -> io::Result<u64> {
+    self.reader.seek(SeekFrom::Start(offset))?;
+    let mut skipped = Vec::new();
+    let read = self.reader.read_until(b'\n', &mut skipped)?;
+    Ok(offset + read as u64)
+  }
+
+  /// The line starting at `offset`, including its line ending.
+  fn line_at(&mut self, offset: u64) -> io::Result<String> {
+    self.reader.seek(SeekFrom::Start(offset))?;
+    let mut line = String::new();
+    self.reader.read_line(&mut line)?;
+    Ok(line)
+  }
+}
+
+/// Splits `HASH:COUNT` (with optional `\r\n`) into its parts.
+fn parse_line(line: &str) -> Option<(&str, u64)> {
+  let (hash, count) = line.trim_end().split_once(':')?;
+  if hash.len() != HASH_HEX_LEN || !hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+    return None;
+  }
+  Some((hash, count.trim().parse().ok()?))
+}
+
+/// Uppercase hex SHA-1 of `password`, as used by the dataset.
+pub fn password_hash(password: &str) -> String {
+  Sha1::digest(password.as_bytes())
+    .iter()
+    .map(|byte| format!("{byte:02X}"))
+    .collect()
+}
+
+/// Looks up every entry's password. Each distinct password is looked up
+/// once; entries without a password of their own (e.g. linked entries) are
+/// skipped. Most-breached first.
+pub fn find_breached(entries: &[Entry], dataset: &mut HibpDataset) -> Result<Vec<BreachedEntry>, String> {
+  let mut counts: HashMap<String, Option<u64>> = HashMap::new();
+  let mut breached = Vec::new();
+  for entry in entries.iter().filter(|e| !e.password.is_empty()) {
+    let hash = password_hash(&entry.password);
+    let occurrences = match counts.get(&hash) {
+      Some(count) => *count,
+      None => {
+        let count = dataset
+          .lookup(&hash)
+          .map_err(|e| format!("read breach dataset failed: {e}"))?;
+        counts.insert(hash, count);
+        count
+      }
+    };
+    if let Some(occurrences) = occurrences {
+      breached.push(BreachedEntry {
+        id: entry.id.clone(),
+        title: entry.title.clone(),
+        url: entry.url.clone(),
+        occurrences,
+      });
+    }
+  }
+  breached.sort_by_key(|e| Reverse(e.occurrences));
+  Ok(breached)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::io::Write;
+
+  #[test]
+  fn binary_search_finds_present_hashes_only() {
+    // Enough filler lines that lookups bisect before scanning.
+    let mut hashes: Vec<String> = (0..2000u32).map(|i| password_hash(&format!("filler-{i}"))).collect();
+    hashes.push(password_hash("password"));
+    hashes.sort();
+    let contents: String = hashes.iter().enumerate().map(|(i, h)| format!("{h}:{}\r\n", i + 1)).collect();
+
+    let path = std::env::temp_dir().join(format!("the-organizer-hibp-{}.txt", std::process::id()));
+    File::create(&path).unwrap().write_all(contents.as_bytes()).unwrap();
+    let mut dataset = HibpDataset::open(&path).unwrap();
+
+    let index = hashes.iter().position(|h| *h == password_hash("password")).unwrap();
+    assert_eq!(dataset.lookup(&password_hash("password")).unwrap(), Some(index as u64 + 1));
+    assert_eq!(dataset.lookup(&hashes[0]).unwrap(), Some(1));
+    assert_eq!(dataset.lookup(hashes.last().unwrap()).unwrap(), Some(hashes.len() as u64));
+    assert_eq!(dataset.lookup(&password_hash("not in the file")).unwrap(), None);
+    let _ = std::fs::remove_file(&path);
+  }
+}
@@ -0,0 +1,176 @@
+//! Time-boxed extension sessions that must be re-approved from the desktop.
+//!
+//! With `ExtensionConfig::session_hours` set, the pairing token alone no
+//! longer reaches entries or secrets. A client asks for a session with it
+//! (`POST /v1/session`), the request is shown in the desktop app, and once
+//! approved the client collects a session token valid for that many hours
+//! (`GET /v1/session?request=<id>`). Expired session tokens are kept, so the
+//! bridge can tell the client to reauthorize instead of reporting a bad
+//! token, until the origin gets a new session.
+//!
+//! Sessions are held in memory only; restarting the app ends them all.
+//!
+//! Lock order: extension config → bridge sessions.
+
+use crate::peer::PeerProcess;
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Most session requests waiting for approval; older ones are dropped.
+const MAX_PENDING: usize = 20;
+
+/// A client waiting for its session to be approved.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct SessionRequest {
+  pub id: String,
+  pub origin: String,
+  /// The process that asked, when it could be identified.
+  pub process: Option<PeerProcess>,
+  pub requested_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Session {
+  pub token: String,
+  pub origin: String,
+  pub expires_at: DateTime<Utc>,
+}
+
+/// State of a session token presented to the bridge.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SessionCheck {
+  Valid,
+  Expired,
+  Unknown,
+}
+
+/// Answer to a client polling for its session.
+#[derive(Clone, Debug)]
+pub enum Collect {
+  Pending,
+  Ready(Session),
+  /// Denied, already collected, or never requested.
+  Unknown,
+}
+
+#[derive(Debug, Default)]
+pub struct BridgeSessions {
+  pending: Vec<SessionRequest>,
+  /// Approved sessions not yet collected, by request id.
+  approved: HashMap<String, Session>,
+  sessions: Vec<Session>,
+}
+
+impl BridgeSessions {
+  /// Queues a session request from `origin`, or returns the one already
+  /// waiting for it. The flag is true for a new request.
+  pub fn request(&mut self, origin: &str, process: Option<PeerProcess>, now: DateTime<Utc>) -> (SessionRequest, bool) {
+    if let Some(existing) = self.pending.iter().find(|r| r.origin == origin) {
+      return (existing.clone(), false);
+    }
+    if self.pending.len() >= MAX_PENDING {
+      self.pending.remove(0);
+    }
+    let request = SessionRequest {
+      id: Uuid::new_v4().to_string(),
+      origin: origin.to_string(),
+      process,
+      requested_at: now,
+    };
+    self.pending.push(request.clone());
+    (request, true)
+  }
+
+  pub fn pending(&self) -> &[SessionRequest] {
+    &self.pending
+  }
+
+  /// Approves request `id` for `hours`, replacing earlier sessions of the
+  /// same origin.
+  pub fn approve(&mut self, id: &str, hours: u32, now: DateTime<Utc>) -> Result<(), String> {
+    let at = self
+      .pending
+      .iter()
+      .position(|r| r.id == id)
+      .ok_or_else(|| "session request not found".to_string())?;
+    let request = self.pending.remove(at);
+    self.sessions.retain(|s| s.origin != request.origin);
+    self.approved.retain(|_, s| s.origin != request.origin);
+    self.approved.insert(
+      request.id,
+      Session {
+        token: Uuid::new_v4().to_string(),
+        origin: request.origin,
+        expires_at: now + Duration::hours(i64::from(hours)),
+      },
+    );
+    Ok(())
+  }
+
+  pub fn deny(&mut self, id: &str) {
+    self.pending.retain(|r| r.id != id);
+  }
+
+  /// Hands out the session approved for request `id`, once.
+  pub fn collect(&mut self, id: &str) -> Collect {
+    if let Some(session) = self.approved.remove(id) {
+      self.sessions.push(session.clone());
+      return Collect::Ready(session);
+    }
+    if self.pending.iter().any(|r| r.id == id) {
+      Collect::Pending
+    } else {
+      Collect::Unknown
+    }
+  }
+
+  pub fn check(&self, token: &str, now: DateTime<Utc>) -> SessionCheck {
+    match self.sessions.iter().find(|s| s.token == token) {
+      Some(session) if now < session.expires_at => SessionCheck::Valid,
+      Some(_) => SessionCheck::Expired,
+      None => SessionCheck::Unknown,
+    }
+  }
+
+  /// Ends every session and drops waiting requests.
+  pub fn clear(&mut self) {
+    *self = Self::default();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn approved_sessions_are_collected_once_and_expire() {
+    let now = Utc::now();
+    let mut sessions = BridgeSessions::default();
+    let (request, new) = sessions.request("chrome-extension://abc", None, now);
+    assert!(new);
+    assert!(!sessions.request("chrome-extension://abc", None, now).1);
+    assert!(matches!(sessions.collect(&request.id), Collect::Pending));
+
+    sessions.approve(&request.id, 8, now).unwrap();
+    assert!(sessions.pending().is_empty());
+    let Collect::Ready(session) = sessions.collect(&request.id) else {
+      panic!("session not ready");
+    };
+    assert!(matches!(sessions.collect(&request.id), Collect::Unknown));
+    assert_eq!(sessions.check(&session.token, now + Duration::hours(7)), SessionCheck::Valid);
+    assert_eq!(sessions.check(&session.token, now + Duration::hours(8)), SessionCheck::Expired);
+    assert_eq!(sessions.check("other", now), SessionCheck::Unknown);
+
+    // A new session for the origin replaces the expired one.
+    let (again, _) = sessions.request("chrome-extension://abc", None, now);
+    sessions.approve(&again.id, 8, now).unwrap();
+    assert_eq!(sessions.check(&session.token, now), SessionCheck::Unknown);
+
+    let (denied, _) = sessions.request("chrome-extension://xyz", None, now);
+    sessions.deny(&denied.id);
+    assert!(matches!(sessions.collect(&denied.id), Collect::Unknown));
+    assert!(sessions.approve(&denied.id, 8, now).is_err());
+  }
+}
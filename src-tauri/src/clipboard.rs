@@ -0,0 +1,125 @@
+//! Clipboard access with automatic clearing.
+//!
+//! Every secret placed on the clipboard is wiped by a background thread after
+//! a timeout. If the app crashes before that thread runs, the value remains on
+//! the clipboard; this is a known limitation of cross-platform clipboard
+//! management. On a normal exit, [`clear_pending`] clears right away instead
+//! of leaving a secret behind when those threads are cut short.
+//!
+//! Secrets are set with each platform's "sensitive content" hint, which
+//! well-behaved clipboard history managers honour: `x-kde-passwordManagerHint`
+//! on Linux, the history/cloud/monitoring exclusion formats on Windows, and
+//! the concealed type on macOS. On Linux, text selected with the mouse (e.g.
+//! a revealed password) also lands in the primary selection, so every clear
+//! clears that too.
+
+use arboard::Clipboard;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+use zeroize::Zeroize;
+
+/// Default time a copied password stays on the clipboard (15 seconds).
+pub const DEFAULT_CLEAR_SECS: u64 = 15;
+
+/// Time a copied card number or expiry stays on the clipboard (10 seconds).
+pub const CARD_CLEAR_SECS: u64 = 10;
+
+/// Time a copied card security code stays on the clipboard (5 seconds).
+pub const CVV_CLEAR_SECS: u64 = 5;
+
+/// Clears scheduled by [`copy_with_clear`] that have not run yet.
+static PENDING_CLEARS: AtomicUsize = AtomicUsize::new(0);
+
+/// Places `secret` on the clipboard and clears it after `clear_after_secs`.
+///
+/// The caller's copy of the secret is zeroized before returning.
+pub fn copy_with_clear(secret: String, clear_after_secs: u64) -> Result<(), String> {
+  set_zeroizing(secret)?;
+
+  PENDING_CLEARS.fetch_add(1, Ordering::SeqCst);
+  thread::spawn(move || {
+    thread::sleep(Duration::from_secs(clear_after_secs));
+    // Already cleared by `clear_pending` if the count was reset.
+    let pending = PENDING_CLEARS.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1));
+    if pending.is_ok() {
+      clear();
+    }
+  });
+
+  Ok(())
+}
+
+/// Runs every scheduled clear now, e.g. before the app exits. Returns
+/// whether there was one to run.
+pub fn clear_pending() -> bool {
+  if PENDING_CLEARS.swap(0, Ordering::SeqCst) == 0 {
+    return false;
+  }
+  clear();
+  true
+}
+
+/// Like [`copy_with_clear`], but waits for the clear on the calling thread.
+/// For the CLI, which would otherwise exit before the clear, and on Linux
+/// take the clipboard contents with it.
+pub fn copy_and_wait(secret: String, clear_after_secs: u64) -> Result<(), String> {
+  let clipboard = set_zeroizing(secret)?;
+  thread::sleep(Duration::from_secs(clear_after_secs));
+  clear();
+  drop(clipboard);
+  Ok(())
+}
+
+/// Sets `secret` on a new clipboard handle and zeroizes it.
+fn set_zeroizing(mut secret: String) -> Result<Clipboard, String> {
+  let result = Clipboard::new()
+    .map_err(|e| format!("clipboard init failed: {e}"))
+    .and_then(|mut clipboard| {
+      set_secret(&mut clipboard, &secret).map_err(|e| format!("clipboard set failed: {e}"))?;
+      Ok(clipboard)
+    });
+  secret.zeroize();
+  result
+}
+
+/// Sets `secret` with the platform's hints to keep it out of clipboard
+/// histories.
+fn set_secret(clipboard: &mut Clipboard, secret: &str) -> Result<(), arboard::Error> {
+  let set = clipboard.set();
+  #[cfg(all(unix, not(any(target_os = "macos", target_os = "android", target_os = "emscripten"))))]
+  let set = {
+    use arboard::SetExtLinux;
+    set.exclude_from_history()
+  };
+  #[cfg(windows)]
+  let set = {
+    use arboard::SetExtWindows;
+    set.exclude_from_history().exclude_from_cloud().exclude_from_monitoring()
+  };
+  #[cfg(target_os = "macos")]
+  let set = {
+    use arboard::SetExtApple;
+    set.exclude_from_history()
+  };
+  set.text(secret)
+}
+
+/// Clears the clipboard and the primary selection immediately (best-effort).
+pub fn clear() {
+  if let Ok(mut cb) = Clipboard::new() {
+    let _ = cb.set_text("".to_string());
+  }
+  clear_primary_selection();
+}
+
+/// Clears the X11/Wayland primary selection (best-effort; no-op on other
+/// platforms). Called when a revealed secret is hidden, since selecting it
+/// copied it there.
+pub fn clear_primary_selection() {
+  #[cfg(all(unix, not(any(target_os = "macos", target_os = "android", target_os = "emscripten"))))]
+  if let Ok(mut cb) = Clipboard::new() {
+    use arboard::{ClearExtLinux, LinuxClipboardKind};
+    let _ = cb.clear_with().clipboard(LinuxClipboardKind::Primary);
+  }
+}
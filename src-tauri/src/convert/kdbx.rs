@@ -0,0 +1,743 @@
+//! KeePass KDBX 4 databases.
+//!
+//! The format of KeePass 2.35+ and KeePassXC 2.5+:
+//!
+//! - A clear header names the cipher (AES-256-CBC or ChaCha20), the KDF
+//!   (Argon2d, Argon2id, or AES-KDF) and their parameters. It is checked with
+//!   SHA-256 and HMAC-SHA256 before anything is decrypted.
+//! - The payload is a sequence of HMAC-SHA256 authenticated blocks holding
+//!   the encrypted, optionally gzipped, inner database.
+//! - The inner database is a small binary header (with the key of the inner
+//!   ChaCha20 stream that masks protected values) followed by XML.
+//!
+//! The key is `SHA-256(SHA-256(password))` through the KDF; key files and
+//! other key providers are not supported. Older KDBX 3.1 files are rejected
+//! with a message asking for the file to be saved as KDBX 4.
+//!
+//! Entries map to KeePass entries with their title, user name, password,
+//! URL, notes, tags, and times; the TOTP secret is written as KeePassXC's
+//! `otp` field. A folder becomes a group under the root group. On reading,
+//! other fields are appended to the notes so nothing is lost, and the
+//! recycle bin is left out.
+
+use crate::models::Entry;
+use crate::vault::{ARGON2_ITERATIONS, ARGON2_MEMORY_KIB, ARGON2_PARALLELISM};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use cbc::cipher::block_padding::Pkcs7;
+use cbc::cipher::{BlockDecryptMut, BlockEncrypt, BlockEncryptMut, KeyInit, KeyIvInit};
+use chacha20::cipher::StreamCipher;
+use chacha20::ChaCha20;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::{Digest, Sha256, Sha512};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use uuid::Uuid;
+use zeroize::Zeroizing;
+
+const SIGNATURE_1: u32 = 0x9AA2_D903;
+const SIGNATURE_2: u32 = 0xB54B_FB67;
+/// Major version in the high 16 bits, minor in the low.
+const VERSION_4: u32 = 0x0004_0000;
+
+const CIPHER_AES256: [u8; 16] = [
+  0x31, 0xC1, 0xF2, 0xE6, 0xBF, 0x71, 0x43, 0x50, 0xBE, 0x58, 0x05, 0x21, 0x6A, 0xFC, 0x5A, 0xFF,
+];
+const CIPHER_CHACHA20: [u8; 16] = [
+  0xD6, 0x03, 0x8A, 0x2B, 0x8B, 0x6F, 0x4C, 0xB5, 0xA5, 0x24, 0x33, 0x9A, 0x31, 0xDB, 0xB5, 0x9A,
+];
+const KDF_AES: [u8; 16] = [
+  0xC9, 0xD9, 0xF3, 0x9A, 0x62, 0x8A, 0x44, 0x60, 0xBF, 0x74, 0x0D, 0x08, 0xC1, 0x8A, 0x4F, 0xEA,
+];
+const KDF_ARGON2D: [u8; 16] = [
+  0xEF, 0x63, 0x6D, 0xDF, 0x8C, 0x29, 0x44, 0x4B, 0x91, 0xF7, 0xA9, 0xA4, 0x03, 0xE3, 0x0A, 0x0C,
+];
+const KDF_ARGON2ID: [u8; 16] = [
+  0x9E, 0x29, 0x8B, 0x19, 0x56, 0xDB, 0x47, 0x73, 0xB2, 0x3D, 0xFC, 0x3E, 0xC6, 0xF0, 0xA1, 0xE6,
+];
+
+// Outer header fields.
+const FIELD_END: u8 = 0;
+const FIELD_CIPHER: u8 = 2;
+const FIELD_COMPRESSION: u8 = 3;
+const FIELD_MASTER_SEED: u8 = 4;
+const FIELD_IV: u8 = 7;
+const FIELD_KDF: u8 = 11;
+
+// Inner header fields.
+const INNER_END: u8 = 0;
+const INNER_STREAM_ID: u8 = 1;
+const INNER_STREAM_KEY: u8 = 2;
+const INNER_STREAM_CHACHA20: u32 = 3;
+
+// Variant dictionary value types.
+const VARIANT_END: u8 = 0x00;
+const VARIANT_U32: u8 = 0x04;
+const VARIANT_U64: u8 = 0x05;
+const VARIANT_BYTES: u8 = 0x42;
+
+/// Payload block size when writing.
+const BLOCK_SIZE: usize = 1024 * 1024;
+/// Refuses KDF parameters that would take unreasonable memory or time.
+const MAX_ARGON2_MEMORY: u64 = 1024 * 1024 * 1024;
+const MAX_KDF_ITERATIONS: u64 = 1_000;
+const MAX_AES_KDF_ROUNDS: u64 = 100_000_000;
+/// Refuses inner databases that decompress beyond this size.
+const MAX_XML_BYTES: u64 = 256 * 1024 * 1024;
+/// Seconds from 0001-01-01 (KDBX 4 time origin) to the Unix epoch.
+const EPOCH_OFFSET: i64 = 62_135_596_800;
+
+const WRONG_KEY: &str = "the KeePass password is incorrect or the file is damaged";
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Little-endian reader over a byte slice.
+struct Cursor<'a>(&'a [u8]);
+
+impl<'a> Cursor<'a> {
+  fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+    if self.0.len() < n {
+      return Err("the KeePass file is truncated".to_string());
+    }
+    let (head, rest) = self.0.split_at(n);
+    self.0 = rest;
+    Ok(head)
+  }
+
+  fn u8(&mut self) -> Result<u8, String> {
+    Ok(self.take(1)?[0])
+  }
+
+  fn u32(&mut self) -> Result<u32, String> {
+    Ok(u32::from_le_bytes(self.take(4)?.try_into().expect("4 bytes")))
+  }
+
+  fn u16(&mut self) -> Result<u16, String> {
+    Ok(u16::from_le_bytes(self.take(2)?.try_into().expect("2 bytes")))
+  }
+
+  fn len(&mut self) -> Result<usize, String> {
+    usize::try_from(self.u32()? as i32).map_err(|_| "the KeePass file is damaged".to_string())
+  }
+}
+
+/// `SHA-256(SHA-256(password))`, the composite key of a password-only database.
+fn composite_key(password: &str) -> Zeroizing<Vec<u8>> {
+  let inner = Zeroizing::new(Sha256::digest(password.as_bytes()).to_vec());
+  Zeroizing::new(Sha256::digest(inner.as_slice()).to_vec())
+}
+
+/// KDF parameters from the header's variant dictionary.
+#[derive(Default)]
+struct KdfParams {
+  uuid: Vec<u8>,
+  salt: Vec<u8>,
+  memory: u64,
+  iterations: u64,
+  parallelism: u32,
+  version: u32,
+  rounds: u64,
+}
+
+fn parse_kdf(raw: &[u8]) -> Result<KdfParams, String> {
+  let mut cursor = Cursor(raw);
+  if cursor.u16()? & 0xFF00 != 0x0100 {
+    return Err("unsupported KeePass KDF parameters".to_string());
+  }
+  let mut params = KdfParams::default();
+  loop {
+    let kind = cursor.u8()?;
+    if kind == VARIANT_END {
+      break;
+    }
+    let name_len = cursor.len()?;
+    let name = cursor.take(name_len)?;
+    let value_len = cursor.len()?;
+    let value = cursor.take(value_len)?;
+    let number = || -> u64 {
+      let mut bytes = [0u8; 8];
+      let n = value.len().min(8);
+      bytes[..n].copy_from_slice(&value[..n]);
+      u64::from_le_bytes(bytes)
+    };
+    match name {
+      b"$UUID" => params.uuid = value.to_vec(),
+      b"S" => params.salt = value.to_vec(),
+      b"M" => params.memory = number(),
+      b"I" => params.iterations = number(),
+      b"P" => params.parallelism = number() as u32,
+      b"V" => params.version = number() as u32,
+      b"R" => params.rounds = number(),
+      _ => {}
+    }
+  }
+  Ok(params)
+}
+
+fn write_kdf(salt: &[u8], memory_kib: u32, iterations: u32) -> Vec<u8> {
+  let mut out = 0x0100u16.to_le_bytes().to_vec();
+  let mut field = |kind: u8, name: &str, value: &[u8]| {
+    out.push(kind);
+    out.extend_from_slice(&(name.len() as u32).to_le_bytes());
+    out.extend_from_slice(name.as_bytes());
+    out.extend_from_slice(&(value.len() as u32).to_le_bytes());
+    out.extend_from_slice(value);
+  };
+  field(VARIANT_BYTES, "$UUID", &KDF_ARGON2ID);
+  field(VARIANT_BYTES, "S", salt);
+  field(VARIANT_U64, "M", &(u64::from(memory_kib) * 1024).to_le_bytes());
+  field(VARIANT_U64, "I", &u64::from(iterations).to_le_bytes());
+  field(VARIANT_U32, "P", &ARGON2_PARALLELISM.to_le_bytes());
+  field(VARIANT_U32, "V", &0x13u32.to_le_bytes());
+  out.push(VARIANT_END);
+  out
+}
+
+fn transform_key(composite: &[u8], kdf: &KdfParams) -> Result<Zeroizing<Vec<u8>>, String> {
+  let mut out = Zeroizing::new(vec![0u8; 32]);
+  if kdf.uuid == KDF_ARGON2D || kdf.uuid == KDF_ARGON2ID {
+    if kdf.memory > MAX_ARGON2_MEMORY || kdf.iterations > MAX_KDF_ITERATIONS {
+      return Err("the KeePass KDF parameters are too costly to open".to_string());
+    }
+    let algorithm = if kdf.uuid == KDF_ARGON2D {
+      argon2::Algorithm::Argon2d
+    } else {
+      argon2::Algorithm::Argon2id
+    };
+    let version = if kdf.version == 0x10 {
+      argon2::Version::V0x10
+    } else {
+      argon2::Version::V0x13
+    };
+    let params = argon2::Params::new(
+      (kdf.memory / 1024) as u32,
+      kdf.iterations as u32,
+      kdf.parallelism,
+      Some(32),
+    )
+    .map_err(|e| format!("KeePass KDF parameters: {e}"))?;
+    argon2::Argon2::new(algorithm, version, params)
+      .hash_password_into(composite, &kdf.salt, &mut out)
+      .map_err(|e| format!("KeePass KDF: {e}"))?;
+  } else if kdf.uuid == KDF_AES {
+    if kdf.rounds > MAX_AES_KDF_ROUNDS {
+      return Err("the KeePass KDF parameters are too costly to open".to_string());
+    }
+    let cipher = aes::Aes256::new_from_slice(&kdf.salt).map_err(|_| "invalid KeePass KDF seed".to_string())?;
+    let mut blocks = Zeroizing::new(composite.to_vec());
+    for _ in 0..kdf.rounds {
+      for block in blocks.chunks_exact_mut(16) {
+        cipher.encrypt_block(block.into());
+      }
+    }
+    out.copy_from_slice(&Sha256::digest(blocks.as_slice()));
+  } else {
+    return Err("unsupported KeePass KDF".to_string());
+  }
+  Ok(out)
+}
+
+/// Per-block HMAC key; `u64::MAX` is the header's.
+fn block_key(hmac_key: &[u8], index: u64) -> Zeroizing<Vec<u8>> {
+  let mut hasher = Sha512::new();
+  hasher.update(index.to_le_bytes());
+  hasher.update(hmac_key);
+  Zeroizing::new(hasher.finalize().to_vec())
+}
+
+fn block_mac(hmac_key: &[u8], index: u64, data: &[u8]) -> HmacSha256 {
+  let key = block_key(hmac_key, index);
+  let mut mac = <HmacSha256 as Mac>::new_from_slice(&key).expect("HMAC takes any key length");
+  mac.update(&index.to_le_bytes());
+  mac.update(&(data.len() as u32).to_le_bytes());
+  mac.update(data);
+  mac
+}
+
+/// Keys derived from the transformed key and the header's master seed.
+struct Keys {
+  cipher: Zeroizing<Vec<u8>>,
+  hmac: Zeroizing<Vec<u8>>,
+}
+
+fn keys(master_seed: &[u8], transformed: &[u8]) -> Keys {
+  let mut hasher = Sha256::new();
+  hasher.update(master_seed);
+  hasher.update(transformed);
+  let cipher = Zeroizing::new(hasher.finalize().to_vec());
+  let mut hasher = Sha512::new();
+  hasher.update(master_seed);
+  hasher.update(transformed);
+  hasher.update([1u8]);
+  let hmac = Zeroizing::new(hasher.finalize().to_vec());
+  Keys { cipher, hmac }
+}
+
+/// The inner stream that masks protected values, in document order.
+fn inner_stream(key: &[u8]) -> ChaCha20 {
+  let hash = Zeroizing::new(Sha512::digest(key).to_vec());
+  ChaCha20::new(hash[..32].into(), hash[32..44].into())
+}
+
+/// Reads the entries of a KDBX 4 database.
+pub fn read(bytes: &[u8], password: &str) -> Result<Vec<Entry>, String> {
+  let mut cursor = Cursor(bytes);
+  if cursor.u32()? != SIGNATURE_1 || cursor.u32()? != SIGNATURE_2 {
+    return Err("not a KeePass database".to_string());
+  }
+  let version = cursor.u32()?;
+  if version >> 16 != 4 {
+    return Err("only KDBX 4 databases can be read; save the file in KDBX 4 format in KeePass or KeePassXC first".to_string());
+  }
+
+  let (mut cipher_id, mut compressed, mut seed, mut iv, mut kdf) = (Vec::new(), false, Vec::new(), Vec::new(), None);
+  loop {
+    let id = cursor.u8()?;
+    let len = cursor.len()?;
+    let value = cursor.take(len)?;
+    match id {
+      FIELD_END => break,
+      FIELD_CIPHER => cipher_id = value.to_vec(),
+      FIELD_COMPRESSION => compressed = value.first().copied().unwrap_or(0) != 0,
+      FIELD_MASTER_SEED => seed = value.to_vec(),
+      FIELD_IV => iv = value.to_vec(),
+      FIELD_KDF => kdf = Some(parse_kdf(value)?),
+      _ => {}
+    }
+  }
+  let header = &bytes[..bytes.len() - cursor.0.len()];
+  let kdf = kdf.ok_or("the KeePass file has no KDF parameters")?;
+  if seed.len() != 32 {
+    return Err("the KeePass file is damaged".to_string());
+  }
+
+  if cursor.take(32)? != Sha256::digest(header).as_slice() {
+    return Err("the KeePass header is damaged".to_string());
+  }
+  let transformed = transform_key(&composite_key(password), &kdf)?;
+  let keys = keys(&seed, &transformed);
+  let header_mac = cursor.take(32)?;
+  let mut mac = <HmacSha256 as Mac>::new_from_slice(&block_key(&keys.hmac, u64::MAX)).expect("any key length");
+  mac.update(header);
+  mac.verify_slice(header_mac).map_err(|_| WRONG_KEY.to_string())?;
+
+  let mut encrypted = Vec::new();
+  for index in 0.. {
+    let mac = cursor.take(32)?;
+    let len = cursor.len()?;
+    let data = cursor.take(len)?;
+    block_mac(&keys.hmac, index, data)
+      .verify_slice(mac)
+      .map_err(|_| "the KeePass file is damaged".to_string())?;
+    if data.is_empty() {
+      break;
+    }
+    encrypted.extend_from_slice(data);
+  }
+
+  let payload = if cipher_id == CIPHER_AES256 {
+    let decryptor = cbc::Decryptor::<aes::Aes256>::new_from_slices(&keys.cipher, &iv)
+      .map_err(|_| "the KeePass file is damaged".to_string())?;
+    Zeroizing::new(
+      decryptor
+        .decrypt_padded_vec_mut::<Pkcs7>(&encrypted)
+        .map_err(|_| WRONG_KEY.to_string())?,
+    )
+  } else if cipher_id == CIPHER_CHACHA20 {
+    let mut cipher =
+      ChaCha20::new_from_slices(&keys.cipher, &iv).map_err(|_| "the KeePass file is damaged".to_string())?;
+    cipher.apply_keystream(&mut encrypted);
+    Zeroizing::new(encrypted)
+  } else {
+    return Err("unsupported KeePass cipher".to_string());
+  };
+
+  let inner = if compressed {
+    let mut out = Zeroizing::new(Vec::new());
+    flate2::read::GzDecoder::new(payload.as_slice())
+      .take(MAX_XML_BYTES + 1)
+      .read_to_end(&mut out)
+      .map_err(|_| "the KeePass file is damaged".to_string())?;
+    if out.len() as u64 > MAX_XML_BYTES {
+      return Err("the KeePass database is too large".to_string());
+    }
+    out
+  } else {
+    payload
+  };
+
+  let mut cursor = Cursor(&inner);
+  let mut stream = None;
+  loop {
+    let id = cursor.u8()?;
+    let len = cursor.len()?;
+    let value = cursor.take(len)?;
+    match id {
+      INNER_END => break,
+      INNER_STREAM_ID if value != INNER_STREAM_CHACHA20.to_le_bytes() => {
+        return Err("unsupported KeePass inner stream".to_string())
+      }
+      INNER_STREAM_KEY => stream = Some(inner_stream(value)),
+      _ => {}
+    }
+  }
+  let mut stream = stream.ok_or("the KeePass file is damaged")?;
+  let xml = std::str::from_utf8(cursor.0).map_err(|_| "the KeePass database is not valid UTF-8".to_string())?;
+  parse_xml(xml, &mut stream)
+}
+
+/// Text of the first child element named `name`.
+fn child_text<'a>(node: roxmltree::Node<'a, '_>, name: &str) -> Option<&'a str> {
+  node.children().find(|n| n.has_tag_name(name)).and_then(|n| n.text())
+}
+
+fn parse_time(raw: &str) -> Option<DateTime<Utc>> {
+  let raw = raw.trim();
+  if let Ok(bytes) = BASE64.decode(raw) {
+    let seconds = i64::from_le_bytes(bytes.try_into().ok()?);
+    return DateTime::from_timestamp(seconds - EPOCH_OFFSET, 0);
+  }
+  DateTime::parse_from_rfc3339(raw).ok().map(|t| t.with_timezone(&Utc))
+}
+
+fn parse_xml(xml: &str, stream: &mut ChaCha20) -> Result<Vec<Entry>, String> {
+  let doc = roxmltree::Document::parse(xml).map_err(|e| format!("parse KeePass database: {e}"))?;
+
+  // Protected values are masked by one stream in document order, so they
+  // are all unmasked up front, including those in history and metadata.
+  let mut protected = HashMap::new();
+  for node in doc.descendants().filter(|n| n.has_tag_name("Value")) {
+    if node.attribute("Protected") != Some("True") {
+      continue;
+    }
+    let mut bytes = Zeroizing::new(
+      BASE64
+        .decode(node.text().unwrap_or_default())
+        .map_err(|_| "the KeePass database is damaged".to_string())?,
+    );
+    stream.apply_keystream(&mut bytes);
+    let text = String::from_utf8(bytes.to_vec()).map_err(|_| "the KeePass database is damaged".to_string())?;
+    protected.insert(node.id(), Zeroizing::new(text));
+  }
+
+  let root = doc.root_element();
+  let recycle_bin = root
+    .children()
+    .find(|n| n.has_tag_name("Meta"))
+    .and_then(|meta| child_text(meta, "RecycleBinUUID"))
+    .map(str::to_string);
+  let top = root
+    .children()
+    .find(|n| n.has_tag_name("Root"))
+    .and_then(|r| r.children().find(|n| n.has_tag_name("Group")))
+    .ok_or("the KeePass database has no root group")?;
+
+  let mut entries = Vec::new();
+  // Entries directly in a group; those under `History` are old versions.
+  for node in top
+    .descendants()
+    .filter(|n| n.has_tag_name("Entry") && n.parent().is_some_and(|p| p.has_tag_name("Group")))
+  {
+    let groups: Vec<_> = node
+      .ancestors()
+      .filter(|n| n.has_tag_name("Group"))
+      .take_while(|n| *n != top)
+      .collect();
+    let in_recycle_bin = groups
+      .iter()
+      .any(|g| recycle_bin.is_some() && child_text(*g, "UUID").map(str::to_string) == recycle_bin);
+    if in_recycle_bin {
+      continue;
+    }
+    // Nested groups become a `parent/child` folder name.
+    let folder = groups
+      .iter()
+      .rev()
+      .map(|g| child_text(*g, "Name").unwrap_or_default().trim())
+      .collect::<Vec<_>>()
+      .join("/");
+    entries.push(parse_entry(node, Some(folder), &protected));
+  }
+  Ok(entries)
+}
+
+fn parse_entry(
+  node: roxmltree::Node,
+  folder: Option<String>,
+  protected: &HashMap<roxmltree::NodeId, Zeroizing<String>>,
+) -> Entry {
+  let mut fields: Vec<(String, Zeroizing<String>)> = Vec::new();
+  for string in node.children().filter(|n| n.has_tag_name("String")) {
+    let key = child_text(string, "Key").unwrap_or_default().to_string();
+    let value = string
+      .children()
+      .find(|n| n.has_tag_name("Value"))
+      .map(|v| match protected.get(&v.id()) {
+        Some(text) => text.clone(),
+        None => Zeroizing::new(v.text().unwrap_or_default().to_string()),
+      })
+      .unwrap_or_default();
+    fields.push((key, value));
+  }
+  let mut take = |key: &str| -> String {
+    fields
+      .iter()
+      .position(|(k, _)| k == key)
+      .map(|i| fields.remove(i).1.to_string())
+      .unwrap_or_default()
+  };
+
+  let mut entry = Entry::new(take("Title"), take("UserName"), take("Password"), take("URL"), take("Notes"));
+  let totp = Some(take("otp"))
+    .filter(|t| !t.is_empty())
+    .unwrap_or_else(|| take("TimeOtp-Secret-Base32"));
+  entry.totp_secret = Some(totp).filter(|t| !t.trim().is_empty());
+  for (key, value) in fields.iter().filter(|(_, v)| !v.trim().is_empty()) {
+    if !entry.notes.is_empty() {
+      entry.notes.push('\n');
+    }
+    entry.notes.push_str(&format!("{key}: {}", value.as_str()));
+  }
+  entry.tags = child_text(node, "Tags")
+    .unwrap_or_default()
+    .split([';', ','])
+    .map(|t| t.trim().to_string())
+    .filter(|t| !t.is_empty())
+    .collect();
+  entry.folder = folder.filter(|f| !f.trim().is_empty());
+  if let Some(times) = node.children().find(|n| n.has_tag_name("Times")) {
+    if let Some(created) = child_text(times, "CreationTime").and_then(parse_time) {
+      entry.created_at = created;
+    }
+    if let Some(modified) = child_text(times, "LastModificationTime").and_then(parse_time) {
+      entry.updated_at = modified;
+    }
+  }
+  entry
+}
+
+fn escape(text: &str) -> String {
+  let mut out = String::with_capacity(text.len());
+  for c in text.chars() {
+    match c {
+      '&' => out.push_str("&amp;"),
+      '<' => out.push_str("&lt;"),
+      '>' => out.push_str("&gt;"),
+      '"' => out.push_str("&quot;"),
+      // Control characters other than tab and newlines are not valid XML 1.0.
+      c if c.is_control() && !matches!(c, '\t' | '\n' | '\r') => {}
+      c => out.push(c),
+    }
+  }
+  out
+}
+
+fn uuid_text(id: &str) -> String {
+  let uuid = Uuid::parse_str(id).unwrap_or_else(|_| Uuid::new_v4());
+  BASE64.encode(uuid.as_bytes())
+}
+
+fn time_text(time: DateTime<Utc>) -> String {
+  BASE64.encode((time.timestamp() + EPOCH_OFFSET).to_le_bytes())
+}
+
+/// The TOTP secret as an `otpauth://` URI, the form KeePassXC stores.
+fn otp_uri(entry: &Entry, secret: &str) -> String {
+  if secret.starts_with("otpauth://") {
+    return secret.to_string();
+  }
+  let label: String = url::form_urlencoded::byte_serialize(entry.title.as_bytes()).collect();
+  let secret: String = secret.chars().filter(|c| !c.is_whitespace()).collect();
+  format!("otpauth://totp/{label}?secret={secret}")
+}
+
+fn write_string(xml: &mut String, key: &str, value: &str, stream: Option<&mut ChaCha20>) {
+  xml.push_str("<String><Key>");
+  xml.push_str(key);
+  match stream {
+    Some(stream) => {
+      let mut bytes = Zeroizing::new(value.as_bytes().to_vec());
+      stream.apply_keystream(&mut bytes);
+      xml.push_str("</Key><Value Protected=\"True\">");
+      xml.push_str(&BASE64.encode(bytes.as_slice()));
+    }
+    None => {
+      xml.push_str("</Key><Value>");
+      xml.push_str(&escape(value));
+    }
+  }
+  xml.push_str("</Value></String>");
+}
+
+fn write_entry(xml: &mut String, entry: &Entry, stream: &mut ChaCha20) {
+  xml.push_str("<Entry><UUID>");
+  xml.push_str(&uuid_text(&entry.id));
+  xml.push_str("</UUID><Tags>");
+  xml.push_str(&escape(&entry.tags.join(";")));
+  xml.push_str("</Tags><Times><CreationTime>");
+  xml.push_str(&time_text(entry.created_at));
+  xml.push_str("</CreationTime><LastModificationTime>");
+  xml.push_str(&time_text(entry.updated_at));
+  xml.push_str("</LastModificationTime></Times>");
+  write_string(xml, "Title", &entry.title, None);
+  write_string(xml, "UserName", &entry.username, None);
+  write_string(xml, "Password", &entry.password, Some(stream));
+  write_string(xml, "URL", &entry.url, None);
+  write_string(xml, "Notes", &entry.notes, None);
+  if let Some(secret) = entry.totp_secret.as_deref().filter(|s| !s.trim().is_empty()) {
+    let uri = Zeroizing::new(otp_uri(entry, secret));
+    write_string(xml, "otp", &uri, Some(stream));
+  }
+  xml.push_str("</Entry>");
+}
+
+fn write_group(xml: &mut String, name: &str, entries: &[&Entry], stream: &mut ChaCha20) {
+  xml.push_str("<Group><UUID>");
+  xml.push_str(&BASE64.encode(Uuid::new_v4().as_bytes()));
+  xml.push_str("</UUID><Name>");
+  xml.push_str(&escape(name));
+  xml.push_str("</Name>");
+  for entry in entries {
+    write_entry(xml, entry, stream);
+  }
+}
+
+fn build_xml(name: &str, entries: &[Entry], stream: &mut ChaCha20) -> Zeroizing<String> {
+  let mut xml = Zeroizing::new(String::new());
+  xml.push_str("<?xml version=\"1.0\" encoding=\"utf-8\" standalone=\"yes\"?>\n<KeePassFile><Meta>");
+  xml.push_str("<Generator>The Organizer</Generator><DatabaseName>");
+  xml.push_str(&escape(name));
+  xml.push_str("</DatabaseName><MemoryProtection><ProtectTitle>False</ProtectTitle>");
+  xml.push_str("<ProtectUserName>False</ProtectUserName><ProtectPassword>True</ProtectPassword>");
+  xml.push_str("<ProtectURL>False</ProtectURL><ProtectNotes>False</ProtectNotes></MemoryProtection>");
+  xml.push_str("</Meta><Root>");
+
+  let top: Vec<&Entry> = entries.iter().filter(|e| e.folder.is_none()).collect();
+  write_group(&mut xml, name, &top, stream);
+  let mut folders: Vec<&str> = entries.iter().filter_map(|e| e.folder.as_deref()).collect();
+  folders.sort_unstable();
+  folders.dedup();
+  for folder in folders {
+    let filed: Vec<&Entry> = entries.iter().filter(|e| e.folder.as_deref() == Some(folder)).collect();
+    write_group(&mut xml, folder, &filed, stream);
+    xml.push_str("</Group>");
+  }
+  xml.push_str("</Group></Root></KeePassFile>");
+  xml
+}
+
+/// Writes `entries` as a KDBX 4 database (AES-256, Argon2id with the vault's
+/// own cost, gzip) named `name`, locked with `password`.
+pub fn write(entries: &[Entry], name: &str, password: &str) -> Result<Vec<u8>, String> {
+  write_with_kdf(entries, name, password, ARGON2_MEMORY_KIB, ARGON2_ITERATIONS)
+}
+
+fn write_with_kdf(
+  entries: &[Entry],
+  name: &str,
+  password: &str,
+  memory_kib: u32,
+  iterations: u32,
+) -> Result<Vec<u8>, String> {
+  let mut rng = rand::rngs::OsRng;
+  let mut seed = [0u8; 32];
+  let mut salt = [0u8; 32];
+  let mut iv = [0u8; 16];
+  let mut stream_key = Zeroizing::new([0u8; 64]);
+  rng.fill_bytes(&mut seed);
+  rng.fill_bytes(&mut salt);
+  rng.fill_bytes(&mut iv);
+  rng.fill_bytes(stream_key.as_mut_slice());
+
+  let mut header = Vec::new();
+  header.extend_from_slice(&SIGNATURE_1.to_le_bytes());
+  header.extend_from_slice(&SIGNATURE_2.to_le_bytes());
+  header.extend_from_slice(&VERSION_4.to_le_bytes());
+  let mut field = |id: u8, value: &[u8]| {
+    header.push(id);
+    header.extend_from_slice(&(value.len() as u32).to_le_bytes());
+    header.extend_from_slice(value);
+  };
+  field(FIELD_CIPHER, &CIPHER_AES256);
+  field(FIELD_COMPRESSION, &1u32.to_le_bytes());
+  field(FIELD_MASTER_SEED, &seed);
+  field(FIELD_IV, &iv);
+  let kdf_params = write_kdf(&salt, memory_kib, iterations);
+  field(FIELD_KDF, &kdf_params);
+  field(FIELD_END, b"\r\n\r\n");
+
+  let kdf = parse_kdf(&kdf_params)?;
+  let transformed = transform_key(&composite_key(password), &kdf)?;
+  let keys = keys(&seed, &transformed);
+
+  let mut inner = Zeroizing::new(Vec::new());
+  let mut inner_field = |id: u8, value: &[u8]| {
+    inner.push(id);
+    inner.extend_from_slice(&(value.len() as u32).to_le_bytes());
+    inner.extend_from_slice(value);
+  };
+  inner_field(INNER_STREAM_ID, &INNER_STREAM_CHACHA20.to_le_bytes());
+  inner_field(INNER_STREAM_KEY, stream_key.as_slice());
+  inner_field(INNER_END, &[]);
+  let xml = build_xml(name, entries, &mut inner_stream(stream_key.as_slice()));
+  inner.extend_from_slice(xml.as_bytes());
+
+  let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+  gzip.write_all(&inner).map_err(|e| format!("compress: {e}"))?;
+  let compressed = Zeroizing::new(gzip.finish().map_err(|e| format!("compress: {e}"))?);
+  let encryptor =
+    cbc::Encryptor::<aes::Aes256>::new_from_slices(&keys.cipher, &iv).expect("32-byte key and 16-byte IV");
+  let encrypted = encryptor.encrypt_padded_vec_mut::<Pkcs7>(&compressed);
+
+  let mut out = header.clone();
+  out.extend_from_slice(&Sha256::digest(&header));
+  let mut mac = <HmacSha256 as Mac>::new_from_slice(&block_key(&keys.hmac, u64::MAX)).expect("any key length");
+  mac.update(&header);
+  out.extend_from_slice(&mac.finalize().into_bytes());
+  let blocks = encrypted.chunks(BLOCK_SIZE).chain(std::iter::once(&[][..]));
+  for (index, block) in blocks.enumerate() {
+    out.extend_from_slice(&block_mac(&keys.hmac, index as u64, block).finalize().into_bytes());
+    out.extend_from_slice(&(block.len() as u32).to_le_bytes());
+    out.extend_from_slice(block);
+  }
+  Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn round_trips_entries_through_a_database() {
+    let mut mail = Entry::new(
+      "Mail <work>".into(),
+      "me@example.com".into(),
+      "hunter2 & more".into(),
+      "https://mail.example.com".into(),
+      "note".into(),
+    );
+    mail.tags = vec!["work".into()];
+    mail.totp_secret = Some("JBSWY3DPEHPK3PXP".into());
+    let mut shop = Entry::new("Shop".into(), "me".into(), "s3cret".into(), String::new(), String::new());
+    shop.folder = Some("Personal".into());
+
+    let bytes = write_with_kdf(&[mail.clone(), shop.clone()], "Test", "correct horse", 64, 1).unwrap();
+    assert!(read(&bytes, "wrong horse").is_err());
+    let entries = read(&bytes, "correct horse").unwrap();
+    assert_eq!(entries.len(), 2);
+    let read_mail = entries.iter().find(|e| e.title == mail.title).unwrap();
+    assert_eq!(read_mail.password, mail.password);
+    assert_eq!(read_mail.tags, mail.tags);
+    assert_eq!(read_mail.folder, None);
+    assert_eq!(read_mail.created_at.timestamp(), mail.created_at.timestamp());
+    assert_eq!(
+      read_mail.totp_secret.as_deref(),
+      Some("otpauth://totp/Mail+%3Cwork%3E?secret=JBSWY3DPEHPK3PXP")
+    );
+    let read_shop = entries.iter().find(|e| e.title == "Shop").unwrap();
+    assert_eq!(read_shop.password, "s3cret");
+    assert_eq!(read_shop.folder.as_deref(), Some("Personal"));
+  }
+}
@@ -0,0 +1,304 @@
+//! Converting entries between file formats without the live vault.
+//!
+//! [`convert`] reads one file into memory, optionally keeps only the entries
+//! with a tag, and writes them to a new file in another format. The app's
+//! own vault and session are never involved, so a subset can be handed to
+//! someone else, e.g. as a KeePass database, without importing anything.
+//!
+//! - [`Format::Vault`]: the portable vault file written by `export_vault`.
+//! - [`Format::Csv`]: plain text with a header row (see [`CSV_COLUMNS`]).
+//!   Columns are matched by name, ignoring case, so KeePass and KeePassXC
+//!   CSV exports (`Title`, `Username`, `Group`, ...) read as well.
+//! - [`Format::Kdbx`]: KeePass KDBX 4; see [`kdbx`].
+//!
+//! CSV and KDBX hold logins only; other entry types are left out and
+//! counted in the [`ConvertReport`]. Linked passwords are copied into each
+//! entry, since the link target may not be part of the output.
+
+pub mod kdbx;
+
+use crate::limits::Limits;
+use crate::models::{Entry, EntryKind, VaultData, VaultMeta};
+use crate::vault::{self, CipherSuite, VaultHeader};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use zeroize::{Zeroize, Zeroizing};
+
+/// A file format entries can be converted from and to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Format {
+  Vault,
+  Csv,
+  Kdbx,
+}
+
+/// Columns written to CSV files, in order.
+pub const CSV_COLUMNS: [&str; 8] = ["title", "username", "password", "url", "notes", "totp", "tags", "folder"];
+
+impl Format {
+  pub fn name(self) -> &'static str {
+    match self {
+      Format::Vault => "vault",
+      Format::Csv => "csv",
+      Format::Kdbx => "kdbx",
+    }
+  }
+
+  pub fn parse(name: &str) -> Option<Self> {
+    match name.trim().to_ascii_lowercase().as_str() {
+      "vault" => Some(Format::Vault),
+      "csv" => Some(Format::Csv),
+      "kdbx" | "keepass" => Some(Format::Kdbx),
+      _ => None,
+    }
+  }
+
+  /// Guesses the format from the file extension.
+  pub fn from_path(path: &Path) -> Option<Self> {
+    match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+      "vault" | "dat" => Some(Format::Vault),
+      "csv" => Some(Format::Csv),
+      "kdbx" => Some(Format::Kdbx),
+      _ => None,
+    }
+  }
+
+  /// The explicit format if given, otherwise the one `path` suggests.
+  pub fn resolve(explicit: Option<Format>, path: &Path) -> Result<Self, String> {
+    explicit
+      .or_else(|| Format::from_path(path))
+      .ok_or_else(|| format!("cannot tell the format of {}; name it explicitly", path.display()))
+  }
+
+  /// Whether files in this format are locked with a password.
+  pub fn encrypted(self) -> bool {
+    self != Format::Csv
+  }
+}
+
+/// One conversion; passwords are needed for encrypted formats only.
+pub struct Conversion<'a> {
+  pub input: &'a Path,
+  pub input_format: Format,
+  pub input_password: Option<&'a str>,
+  pub output: &'a Path,
+  pub output_format: Format,
+  pub output_password: Option<&'a str>,
+  /// Keeps only entries with this tag.
+  pub tag: Option<&'a str>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ConvertReport {
+  /// Entries written to the output.
+  pub converted: usize,
+  /// Entries of types the output format cannot hold (cards, SSH keys, ...).
+  pub left_out: usize,
+}
+
+fn password<'a>(password: Option<&'a str>, format: Format, path: &Path) -> Result<&'a str, String> {
+  password
+    .filter(|p| !p.is_empty())
+    .ok_or_else(|| format!("a password is required for the {} file {}", format.name(), path.display()))
+}
+
+/// Converts `job.input` into a new file at `job.output`. Existing files are
+/// never overwritten.
+pub fn convert(job: &Conversion) -> Result<ConvertReport, String> {
+  if job.output.exists() {
+    return Err(format!("{} already exists", job.output.display()));
+  }
+  let mut entries = read(job.input, job.input_format, job.input_password)?;
+  if let Some(tag) = job.tag.map(str::trim).filter(|t| !t.is_empty()) {
+    entries.retain(|e| e.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)));
+  }
+  let total = entries.len();
+  if job.output_format != Format::Vault {
+    entries.retain(|e| matches!(e.kind, EntryKind::Login));
+  }
+  let bytes = write(&entries, job.output, job.output_format, job.output_password)?;
+
+  let mut file = OpenOptions::new()
+    .write(true)
+    .create_new(true)
+    .open(job.output)
+    .map_err(|e| format!("create {}: {e}", job.output.display()))?;
+  if let Err(e) = file.write_all(&bytes).and_then(|()| file.sync_all()) {
+    drop(file);
+    let _ = fs::remove_file(job.output);
+    return Err(format!("write {}: {e}", job.output.display()));
+  }
+  Ok(ConvertReport {
+    converted: entries.len(),
+    left_out: total - entries.len(),
+  })
+}
+
+/// Reads every entry of a file, with linked passwords copied in.
+pub fn read(path: &Path, format: Format, password_for: Option<&str>) -> Result<Vec<Entry>, String> {
+  let size = fs::metadata(path).map_err(|e| format!("read {}: {e}", path.display()))?.len();
+  Limits::default()
+    .check_file_size(size)
+    .map_err(|limit| format!("{} rejected: {limit}", path.display()))?;
+  let bytes = Zeroizing::new(fs::read(path).map_err(|e| format!("read {}: {e}", path.display()))?);
+  match format {
+    Format::Vault => {
+      let master = password(password_for, format, path)?;
+      let (mut data, _, mut key) = vault::open_with_limits(&bytes, master, &Limits::default()).map_err(|e| match e {
+        vault::VaultError::Limit(limit) => format!("{} rejected: {limit}", path.display()),
+        _ => format!("could not open {}: wrong password or damaged file", path.display()),
+      })?;
+      key.zeroize();
+      resolve_links(&mut data);
+      Ok(std::mem::take(&mut data.entries))
+    }
+    Format::Csv => {
+      let text = std::str::from_utf8(&bytes).map_err(|_| format!("{} is not UTF-8 text", path.display()))?;
+      read_csv(text)
+    }
+    Format::Kdbx => kdbx::read(&bytes, password(password_for, format, path)?),
+  }
+}
+
+/// Replaces each link with a copy of the target's password.
+fn resolve_links(data: &mut VaultData) {
+  for index in 0..data.entries.len() {
+    let Some(target) = data.entries[index].linked_to.clone() else {
+      continue;
+    };
+    let password = data.find_entry(&target).map(|t| t.password.clone()).unwrap_or_default();
+    let entry = &mut data.entries[index];
+    entry.password = password;
+    entry.linked_to = None;
+  }
+}
+
+fn write(entries: &[Entry], path: &Path, format: Format, password_for: Option<&str>) -> Result<Zeroizing<Vec<u8>>, String> {
+  match format {
+    Format::Vault => {
+      let header = VaultHeader::new(CipherSuite::default());
+      let mut key = header
+        .derive_key(password(password_for, format, path)?)
+        .map_err(|e| format!("kdf: {:?}", e))?;
+      let data = VaultData {
+        meta: VaultMeta::new(),
+        entries: entries.to_vec(),
+        trash: Vec::new(),
+        tombstones: Vec::new(),
+        legacy_payload: false,
+      };
+      let sealed = vault::seal(&data, &header, &key).map_err(|e| format!("seal: {:?}", e));
+      key.zeroize();
+      Ok(Zeroizing::new(sealed?))
+    }
+    Format::Csv => write_csv(entries),
+    Format::Kdbx => {
+      let name = path.file_stem().and_then(|s| s.to_str()).unwrap_or("The Organizer");
+      Ok(Zeroizing::new(kdbx::write(entries, name, password(password_for, format, path)?)?))
+    }
+  }
+}
+
+fn write_csv(entries: &[Entry]) -> Result<Zeroizing<Vec<u8>>, String> {
+  let mut writer = csv::Writer::from_writer(Vec::new());
+  let failed = |e: csv::Error| format!("write CSV: {e}");
+  writer.write_record(CSV_COLUMNS).map_err(failed)?;
+  for entry in entries {
+    let tags = entry.tags.join(";");
+    writer
+      .write_record([
+        entry.title.as_str(),
+        &entry.username,
+        &entry.password,
+        &entry.url,
+        &entry.notes,
+        entry.totp_secret.as_deref().unwrap_or_default(),
+        &tags,
+        entry.folder.as_deref().unwrap_or_default(),
+      ])
+      .map_err(failed)?;
+  }
+  let bytes = writer.into_inner().map_err(|e| format!("write CSV: {e}"))?;
+  Ok(Zeroizing::new(bytes))
+}
+
+fn read_csv(text: &str) -> Result<Vec<Entry>, String> {
+  let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(text.as_bytes());
+  let headers = reader.headers().map_err(|e| format!("read CSV: {e}"))?.clone();
+  let column = |names: &[&str]| {
+    headers
+      .iter()
+      .position(|h| names.iter().any(|name| h.trim().eq_ignore_ascii_case(name)))
+  };
+  let Some(password_column) = column(&["password"]) else {
+    return Err("the CSV file has no password column".to_string());
+  };
+  let title = column(&["title", "name"]);
+  let username = column(&["username", "user name", "login"]);
+  let url = column(&["url", "website"]);
+  let notes = column(&["notes", "note"]);
+  let totp = column(&["totp", "otp"]);
+  let tags = column(&["tags"]);
+  let folder = column(&["folder", "group"]);
+
+  let mut entries = Vec::new();
+  for (row, record) in reader.records().enumerate() {
+    let record = record.map_err(|e| format!("read CSV row {}: {e}", row + 2))?;
+    let field = |index: Option<usize>| index.and_then(|i| record.get(i)).unwrap_or_default().to_string();
+    let mut entry = Entry::new(
+      field(title),
+      field(username),
+      field(Some(password_column)),
+      field(url),
+      field(notes),
+    );
+    entry.totp_secret = Some(field(totp)).filter(|t| !t.trim().is_empty());
+    entry.tags = field(tags)
+      .split(';')
+      .map(|t| t.trim().to_string())
+      .filter(|t| !t.is_empty())
+      .collect();
+    entry.folder = Some(field(folder)).filter(|f| !f.trim().is_empty());
+    entries.push(entry);
+  }
+  Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn csv_round_trips_and_reads_keepass_headers() {
+    let mut entry = Entry::new(
+      "Mail, work".into(),
+      "me@example.com".into(),
+      "hunter2\"".into(),
+      "https://mail.example.com".into(),
+      "line one\nline two".into(),
+    );
+    entry.tags = vec!["work".into(), "mail".into()];
+    entry.folder = Some("Shared".into());
+    entry.totp_secret = Some("JBSWY3DPEHPK3PXP".into());
+
+    let bytes = write_csv(std::slice::from_ref(&entry)).unwrap();
+    let read = read_csv(std::str::from_utf8(&bytes).unwrap()).unwrap();
+    assert_eq!(read.len(), 1);
+    assert_eq!(read[0].title, entry.title);
+    assert_eq!(read[0].password, entry.password);
+    assert_eq!(read[0].notes, entry.notes);
+    assert_eq!(read[0].tags, entry.tags);
+    assert_eq!(read[0].folder, entry.folder);
+    assert_eq!(read[0].totp_secret, entry.totp_secret);
+
+    let keepassxc = "\"Group\",\"Title\",\"Username\",\"Password\",\"URL\",\"Notes\",\"TOTP\"\n\
+                     \"Root\",\"Shop\",\"me\",\"s3cret\",\"https://shop.example.com\",\"\",\"\"\n";
+    let read = read_csv(keepassxc).unwrap();
+    assert_eq!(read[0].username, "me");
+    assert_eq!(read[0].folder.as_deref(), Some("Root"));
+    assert_eq!(read[0].totp_secret, None);
+  }
+}
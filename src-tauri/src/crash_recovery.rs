@@ -0,0 +1,192 @@
+//! Detection of a previous run that did not shut down cleanly.
+//!
+//! Each run writes a marker file at startup, and [`crate::shutdown`] removes
+//! it on exit. When the marker is still there at the next launch, the
+//! previous run crashed, was killed, or lost power. The vault is then
+//! inspected without the key, and the result is kept as a
+//! [`RecoveryReport`] until the user dismisses it. While the report says the
+//! vault could not be read, `unlock_vault` refuses to open it.
+//!
+//! Saves to a vault file are atomic (a temporary file renamed into place)
+//! and journaled first, so the checks look for what an interrupted save
+//! leaves behind: the temporary file and changes still in the journal.
+
+use crate::diagnostics::{DiagnosticCheck, Severity};
+use crate::journal::Journal;
+use crate::models::AppState;
+use crate::store::VaultStore;
+use crate::vault;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::fs;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+
+const MARKER_FILENAME: &str = "running.marker";
+
+/// Returned by `unlock_vault` while a report says the vault is damaged.
+pub const VAULT_DAMAGED: &str = "the vault could not be read after the last run ended unexpectedly";
+
+/// What was found after an unclean shutdown.
+#[derive(Clone, Debug, Serialize)]
+pub struct RecoveryReport {
+  pub detected_at: DateTime<Utc>,
+  /// When the interrupted run started, if its marker could be read.
+  pub previous_start: Option<DateTime<Utc>>,
+  pub checks: Vec<DiagnosticCheck>,
+  /// The vault header could not be read; unlocking is refused until the
+  /// report is dismissed.
+  pub vault_damaged: bool,
+  /// The latest scheduled backup, to restore from if the vault is damaged.
+  pub last_backup: Option<PathBuf>,
+}
+
+fn marker_path(app: &AppHandle) -> Result<PathBuf, String> {
+  let dir = app
+    .path()
+    .app_data_dir()
+    .map_err(|e| format!("app_data_dir failed: {e}"))?;
+  fs::create_dir_all(&dir).map_err(|e| format!("create_dir_all failed: {e}"))?;
+  Ok(dir.join(MARKER_FILENAME))
+}
+
+/// Checks for the previous run's marker, writes this run's, and inspects the
+/// vault if the previous run did not shut down cleanly.
+pub fn start(app: &AppHandle, state: &AppState) -> Result<(), String> {
+  let path = marker_path(app)?;
+  let previous = fs::read_to_string(&path).ok();
+  fs::write(&path, Utc::now().to_rfc3339()).map_err(|e| format!("write run marker failed: {e}"))?;
+  let Some(previous) = previous else {
+    return Ok(());
+  };
+
+  let previous_start = DateTime::parse_from_rfc3339(previous.trim())
+    .ok()
+    .map(|at| at.with_timezone(&Utc));
+  let store = crate::commands::resolve_store(app, state)?;
+  let last_backup = state.backup.lock().ok().and_then(|status| status.last_file.clone());
+  let report = inspect(store.as_ref(), previous_start, last_backup, Utc::now());
+  eprintln!(
+    "previous run did not shut down cleanly; vault {}",
+    if report.vault_damaged { "unreadable" } else { "readable" }
+  );
+  if let Ok(mut slot) = state.recovery_report.lock() {
+    *slot = Some(report);
+  }
+  Ok(())
+}
+
+/// Removes this run's marker. Called last on a clean exit.
+pub fn finish(app: &AppHandle) {
+  let removed = marker_path(app).and_then(|path| fs::remove_file(path).map_err(|e| e.to_string()));
+  if let Err(err) = removed {
+    eprintln!("run marker not removed: {err}");
+  }
+}
+
+/// Inspects the vault in `store` without the key.
+pub fn inspect(
+  store: &dyn VaultStore,
+  previous_start: Option<DateTime<Utc>>,
+  last_backup: Option<PathBuf>,
+  now: DateTime<Utc>,
+) -> RecoveryReport {
+  let mut checks = Vec::new();
+  let mut vault_damaged = false;
+
+  if !store.exists() {
+    checks.push(check("vault", Severity::Ok, "No vault has been created yet.".to_string()));
+  } else {
+    match vault::read_header(store) {
+      Ok(_) => checks.push(check("vault", Severity::Ok, "The vault file is intact.".to_string())),
+      Err(e) => {
+        vault_damaged = true;
+        let restore = match &last_backup {
+          Some(path) => format!(" The latest backup is {}.", path.display()),
+          None => String::new(),
+        };
+        checks.push(check(
+          "vault",
+          Severity::Warning,
+          format!("The vault could not be read ({e:?}). Restore it from a backup.{restore}"),
+        ));
+      }
+    }
+  }
+
+  if let Some(path) = store.path() {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    let tmp = path.with_file_name(name);
+    if tmp.exists() {
+      checks.push(check(
+        "interrupted_write",
+        Severity::Warning,
+        format!(
+          "A save was interrupted before it replaced the vault file. {} holds the incomplete write and is overwritten by the next save.",
+          tmp.display()
+        ),
+      ));
+    }
+  }
+
+  if let Some(journal) = Journal::for_store(store).filter(Journal::exists) {
+    let (severity, message) = match journal.summary() {
+      Ok(summary) => {
+        let mut message = match summary.records {
+          0 => "No complete change was left in the journal.".to_string(),
+          n => format!("{n} journaled change(s) from the interrupted run are replayed at the next unlock."),
+        };
+        if summary.torn {
+          message.push_str(" The last change was cut off mid-write and is skipped.");
+        }
+        (Severity::Warning, message)
+      }
+      Err(e) => (Severity::Warning, format!("The journal could not be read: {e}.")),
+    };
+    checks.push(check("journal", severity, message));
+  }
+
+  RecoveryReport {
+    detected_at: now,
+    previous_start,
+    checks,
+    vault_damaged,
+    last_backup,
+  }
+}
+
+fn check(id: &'static str, severity: Severity, message: String) -> DiagnosticCheck {
+  DiagnosticCheck { id, severity, message }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::store::FileStore;
+
+  #[test]
+  fn damaged_vault_and_leftovers_are_reported() {
+    let dir = std::env::temp_dir().join(format!("the-organizer-recovery-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("vault.dat");
+    let store = FileStore::new(&path);
+
+    let report = inspect(&store, None, None, Utc::now());
+    assert!(!report.vault_damaged);
+    assert_eq!(report.checks.len(), 1);
+
+    fs::write(&path, b"half a vault").unwrap();
+    fs::write(dir.join("vault.dat.tmp"), b"partial").unwrap();
+    fs::write(dir.join("vault.dat.wal"), [9, 0, 0, 0, 1]).unwrap();
+    let report = inspect(&store, None, Some(dir.join("backup.dat")), Utc::now());
+    assert!(report.vault_damaged);
+    let ids: Vec<_> = report.checks.iter().map(|c| c.id).collect();
+    assert_eq!(ids, ["vault", "interrupted_write", "journal"]);
+    assert!(report.checks[0].message.contains("backup.dat"));
+    assert!(report.checks[2].message.starts_with("No complete change"));
+    assert!(report.checks[2].message.contains("cut off"));
+
+    let _ = fs::remove_dir_all(&dir);
+  }
+}
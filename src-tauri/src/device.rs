@@ -0,0 +1,98 @@
+//! Identity of this app install, recorded in the vault header.
+//!
+//! Each install gets a random id on first start, kept in `device.json` in
+//! the app data directory, plus a label (the hostname) so other devices can
+//! say who last wrote a synced vault. Neither is secret: the header is
+//! stored in the clear (but authenticated).
+
+use crate::vault::WriterStamp;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use tauri::{AppHandle, Manager};
+use uuid::Uuid;
+
+/// File in the app data directory holding the device identity.
+pub const DEVICE_FILENAME: &str = "device.json";
+
+/// Longest label kept, in characters.
+const MAX_LABEL_CHARS: usize = 64;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceIdentity {
+  pub id: String,
+  pub label: String,
+}
+
+impl DeviceIdentity {
+  /// A fresh identity labelled with `label`, or "Unknown device".
+  pub fn new(label: Option<String>) -> Self {
+    let label = label
+      .map(|l| l.trim().chars().take(MAX_LABEL_CHARS).collect::<String>())
+      .filter(|l| !l.is_empty())
+      .unwrap_or_else(|| "Unknown device".to_string());
+    Self {
+      id: Uuid::new_v4().to_string(),
+      label,
+    }
+  }
+
+  /// Stamp for a header written now.
+  pub fn stamp(&self) -> WriterStamp {
+    WriterStamp {
+      device_id: self.id.clone(),
+      label: self.label.clone(),
+      at: Utc::now(),
+    }
+  }
+}
+
+fn device_path(app: &AppHandle) -> Result<PathBuf, String> {
+  let dir = app
+    .path()
+    .app_data_dir()
+    .map_err(|e| format!("app_data_dir failed: {e}"))?;
+  fs::create_dir_all(&dir).map_err(|e| format!("create_dir_all failed: {e}"))?;
+  Ok(dir.join(DEVICE_FILENAME))
+}
+
+/// Reads this install's identity, creating it on first start.
+pub fn load_or_create(app: &AppHandle) -> Result<DeviceIdentity, String> {
+  let path = device_path(app)?;
+  if path.exists() {
+    let raw = fs::read_to_string(&path).map_err(|e| format!("read device identity failed: {e}"))?;
+    return serde_json::from_str(&raw).map_err(|e| format!("parse device identity failed: {e}"));
+  }
+  let identity = DeviceIdentity::new(hostname());
+  let serialized =
+    serde_json::to_string_pretty(&identity).map_err(|e| format!("serialize device identity failed: {e}"))?;
+  fs::write(&path, serialized).map_err(|e| format!("write device identity failed: {e}"))?;
+  Ok(identity)
+}
+
+/// Best-effort hostname.
+fn hostname() -> Option<String> {
+  if let Ok(name) = std::env::var("COMPUTERNAME") {
+    return Some(name);
+  }
+  if let Ok(name) = fs::read_to_string("/etc/hostname") {
+    return Some(name);
+  }
+  let output = Command::new("hostname").output().ok()?;
+  Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn labels_are_trimmed_and_never_empty() {
+    assert_eq!(DeviceIdentity::new(Some("  LAPTOP-WORK\n".into())).label, "LAPTOP-WORK");
+    assert_eq!(DeviceIdentity::new(Some(" \n".into())).label, "Unknown device");
+    assert_eq!(DeviceIdentity::new(Some("x".repeat(100))).label.len(), MAX_LABEL_CHARS);
+    assert_ne!(DeviceIdentity::new(None).id, DeviceIdentity::new(None).id);
+  }
+}
@@ -0,0 +1,274 @@
+//! Developer secret helpers: `.env` rendering and environment injection.
+//!
+//! API-key entries carrying a project tag can be exported as a `.env` file or
+//! injected straight into a child process's environment (CLI
+//! `run-with-env`). Rendered output is held in [`Zeroizing`] buffers.
+//!
+//! Environment templates name any entry field instead: a variable whose
+//! value contains `{{entry:field}}` placeholders, with the entry given by id
+//! or title, is expanded when a process is started (CLI `run`, command
+//! `run_with_secrets`), so the secrets never appear in shell history or
+//! dotfiles.
+
+use crate::models::{Entry, EntryKind};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use zeroize::Zeroizing;
+
+/// A single environment variable resolved from an API-key entry.
+pub struct EnvVar {
+  pub name: String,
+  pub value: Zeroizing<String>,
+}
+
+/// Derives an environment variable name from free text, e.g.
+/// `"Stripe live key"` → `STRIPE_LIVE_KEY`.
+pub fn env_var_name(raw: &str) -> String {
+  let mut name = String::with_capacity(raw.len());
+  for c in raw.trim().chars() {
+    if c.is_ascii_alphanumeric() {
+      name.push(c.to_ascii_uppercase());
+    } else if !name.ends_with('_') {
+      name.push('_');
+    }
+  }
+  let name = name.trim_matches('_').to_string();
+  if name.starts_with(|c: char| c.is_ascii_digit()) {
+    format!("_{name}")
+  } else {
+    name
+  }
+}
+
+/// Collects the API-key entries tagged `tag` as environment variables.
+///
+/// The variable name comes from the key's `env_var` field, falling back to
+/// the entry title. Fails on duplicate names rather than silently dropping one.
+pub fn collect_env(entries: &[Entry], tag: &str) -> Result<Vec<EnvVar>, String> {
+  let mut vars: Vec<EnvVar> = Vec::new();
+  for entry in entries.iter().filter(|e| e.has_tag(tag)) {
+    let EntryKind::ApiKey(key) = &entry.kind else {
+      continue;
+    };
+    let name = if key.env_var.trim().is_empty() {
+      env_var_name(&entry.title)
+    } else {
+      env_var_name(&key.env_var)
+    };
+    if name.is_empty() {
+      return Err(format!("entry '{}' has no usable variable name", entry.title));
+    }
+    if vars.iter().any(|v| v.name == name) {
+      return Err(format!("duplicate variable name {name} for tag '{tag}'"));
+    }
+    vars.push(EnvVar {
+      name,
+      value: Zeroizing::new(key.token.clone()),
+    });
+  }
+  Ok(vars)
+}
+
+/// Entry with id `query`, or else the only one titled `query` (ignoring case
+/// and surrounding spaces).
+pub fn find_entry<'a>(entries: &'a [Entry], query: &str) -> Result<&'a Entry, String> {
+  if let Some(entry) = entries.iter().find(|e| e.id == query) {
+    return Ok(entry);
+  }
+  let wanted = query.trim().to_lowercase();
+  let mut titled = entries.iter().filter(|e| e.title.trim().to_lowercase() == wanted);
+  match (titled.next(), titled.count()) {
+    (Some(entry), 0) => Ok(entry),
+    (Some(_), more) => Err(format!("{} entries are titled '{query}'; use the entry id", more + 1)),
+    (None, _) => Err(format!("no entry with id or title '{query}'")),
+  }
+}
+
+/// True if `value` contains a `{{` placeholder opening.
+pub fn has_placeholder(value: &str) -> bool {
+  value.contains("{{")
+}
+
+/// Parses an environment template: `NAME=value` lines, with blank lines and
+/// `#` comments skipped, an optional `export ` prefix, and values optionally
+/// wrapped in single or double quotes (taken literally).
+pub fn parse_env_template(text: &str) -> Result<Vec<(String, String)>, String> {
+  let mut vars: Vec<(String, String)> = Vec::new();
+  for (index, line) in text.lines().enumerate() {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+      continue;
+    }
+    let line = line.strip_prefix("export ").unwrap_or(line);
+    let (name, value) = line
+      .split_once('=')
+      .ok_or_else(|| format!("line {}: expected NAME=value", index + 1))?;
+    let name = name.trim();
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+      return Err(format!("line {}: invalid variable name '{name}'", index + 1));
+    }
+    let value = value.trim();
+    let value = ['"', '\'']
+      .iter()
+      .find_map(|q| value.strip_prefix(*q).and_then(|v| v.strip_suffix(*q)))
+      .unwrap_or(value);
+    vars.retain(|(existing, _)| existing != name);
+    vars.push((name.to_string(), value.to_string()));
+  }
+  Ok(vars)
+}
+
+/// Replaces every `{{entry:field}}` in `template` with `lookup(entry, field)`.
+/// The entry reference may itself contain colons; the field is the part
+/// after the last one.
+pub fn expand_placeholders(
+  template: &str,
+  mut lookup: impl FnMut(&str, &str) -> Result<Zeroizing<String>, String>,
+) -> Result<Zeroizing<String>, String> {
+  let mut out = Zeroizing::new(String::with_capacity(template.len()));
+  let mut rest = template;
+  while let Some(start) = rest.find("{{") {
+    out.push_str(&rest[..start]);
+    let after = &rest[start + 2..];
+    let end = after.find("}}").ok_or_else(|| "unterminated '{{' placeholder".to_string())?;
+    let placeholder = &after[..end];
+    let (entry, field) = placeholder
+      .rsplit_once(':')
+      .map(|(entry, field)| (entry.trim(), field.trim()))
+      .filter(|(entry, field)| !entry.is_empty() && !field.is_empty())
+      .ok_or_else(|| format!("placeholder '{{{{{placeholder}}}}}' must be {{{{entry:field}}}}"))?;
+    out.push_str(&lookup(entry, field)?);
+    rest = &after[end + 2..];
+  }
+  out.push_str(rest);
+  Ok(out)
+}
+
+/// Expands the placeholders in each template variable, failing on the first
+/// one that does not resolve.
+pub fn resolve_env_template(
+  vars: &[(String, String)],
+  mut lookup: impl FnMut(&str, &str) -> Result<Zeroizing<String>, String>,
+) -> Result<Vec<EnvVar>, String> {
+  vars
+    .iter()
+    .map(|(name, template)| {
+      let value = expand_placeholders(template, &mut lookup).map_err(|e| format!("{name}: {e}"))?;
+      Ok(EnvVar {
+        name: name.clone(),
+        value,
+      })
+    })
+    .collect()
+}
+
+/// Renders variables in `.env` syntax with double-quoted, escaped values.
+pub fn render_dotenv(vars: &[EnvVar]) -> Zeroizing<String> {
+  let mut out = Zeroizing::new(String::new());
+  for var in vars {
+    out.push_str(&var.name);
+    out.push_str("=\"");
+    for c in var.value.chars() {
+      match c {
+        '\\' => out.push_str("\\\\"),
+        '"' => out.push_str("\\\""),
+        '$' => out.push_str("\\$"),
+        '\n' => out.push_str("\\n"),
+        other => out.push(other),
+      }
+    }
+    out.push_str("\"\n");
+  }
+  out
+}
+
+/// Writes `contents` to `path`, readable only by the owner on unix.
+///
+/// An existing file is truncated; its permissions are tightened as well.
+pub fn write_private_file(path: &Path, contents: &[u8]) -> io::Result<()> {
+  if let Some(parent) = path.parent() {
+    fs::create_dir_all(parent)?;
+  }
+  let mut options = OpenOptions::new();
+  options.write(true).create(true).truncate(true);
+  #[cfg(unix)]
+  {
+    use std::os::unix::fs::OpenOptionsExt;
+    options.mode(0o600);
+  }
+  let mut file = options.open(path)?;
+  #[cfg(unix)]
+  {
+    use std::os::unix::fs::PermissionsExt;
+    file.set_permissions(fs::Permissions::from_mode(0o600))?;
+  }
+  file.write_all(contents)?;
+  file.sync_all()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::models::ApiKeyFields;
+
+  fn api_key(title: &str, env_var: &str, token: &str, tags: &[&str]) -> Entry {
+    let mut e = Entry::new(title.to_string(), String::new(), String::new(), String::new(), String::new());
+    e.tags = tags.iter().map(|t| t.to_string()).collect();
+    e.kind = EntryKind::ApiKey(Box::new(ApiKeyFields {
+      token: token.to_string(),
+      env_var: env_var.to_string(),
+      ..Default::default()
+    }));
+    e
+  }
+
+  #[test]
+  fn derives_variable_names() {
+    assert_eq!(env_var_name("Stripe live key"), "STRIPE_LIVE_KEY");
+    assert_eq!(env_var_name("  github-token! "), "GITHUB_TOKEN");
+    assert_eq!(env_var_name("1password"), "_1PASSWORD");
+  }
+
+  #[test]
+  fn renders_tagged_keys_as_dotenv() {
+    let entries = vec![
+      api_key("Stripe", "STRIPE_KEY", "sk_\"live\"$x", &["shop"]),
+      api_key("GitHub token", "", "ghp_1", &["shop"]),
+      api_key("Other", "", "nope", &["blog"]),
+    ];
+    let vars = collect_env(&entries, "shop").expect("collect");
+    let rendered = render_dotenv(&vars);
+    assert_eq!(
+      rendered.as_str(),
+      "STRIPE_KEY=\"sk_\\\"live\\\"\\$x\"\nGITHUB_TOKEN=\"ghp_1\"\n"
+    );
+  }
+
+  #[test]
+  fn rejects_duplicate_names() {
+    let entries = vec![api_key("A", "TOKEN", "1", &["p"]), api_key("B", "token", "2", &["p"])];
+    assert!(collect_env(&entries, "p").is_err());
+  }
+
+  #[test]
+  fn expands_env_templates() {
+    let template = "# shop\nexport DB_URL=\"postgres://{{Prod DB:username}}:{{ Prod DB : password }}@db\"\n\nPLAIN=1\n";
+    let vars = parse_env_template(template).unwrap();
+    assert_eq!(vars[1], ("PLAIN".to_string(), "1".to_string()));
+
+    let lookup = |entry: &str, field: &str| match (entry, field) {
+      ("Prod DB", "username") => Ok(Zeroizing::new("app".to_string())),
+      ("Prod DB", "password") => Ok(Zeroizing::new("s3cret".to_string())),
+      _ => Err(format!("no {entry}:{field}")),
+    };
+    let resolved = resolve_env_template(&vars, lookup).unwrap();
+    assert_eq!(resolved[0].name, "DB_URL");
+    assert_eq!(resolved[0].value.as_str(), "postgres://app:s3cret@db");
+
+    assert!(expand_placeholders("{{Prod DB}}", lookup).is_err());
+    assert!(expand_placeholders("{{Prod DB:password", lookup).is_err());
+    assert!(expand_placeholders("{{Other:token}}", lookup).is_err());
+    assert!(parse_env_template("BAD NAME=1").is_err());
+  }
+}
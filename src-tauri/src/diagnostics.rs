@@ -0,0 +1,312 @@
+//! Environment checks surfaced by `run_diagnostics`.
+//!
+//! Checks only look at the local machine and never touch the vault, so they
+//! run while it is locked. Timings of the latest vault load and save are
+//! kept in [`StorageTimings`] (durations and counts only) so slow storage
+//! or an unusually large vault shows up here too, and restarts of the
+//! inactivity monitor are reported from [`MonitorHealth`].
+
+use crate::monitor::MonitorHealth;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::Duration;
+
+/// Emitted (once per run of the app) with the detected manager names when a
+/// secret is first copied while a clipboard history manager is running.
+pub const CLIPBOARD_HISTORY_EVENT: &str = "clipboard://history-detected";
+
+/// Unlocks (including decrypting entries) slower than this are reported.
+pub const SLOW_LOAD: Duration = Duration::from_secs(3);
+
+/// Saves slower than this are reported.
+pub const SLOW_SAVE: Duration = Duration::from_secs(1);
+
+/// Clipboard history managers, as `(process name, display name)`. Process
+/// names are compared case-insensitively, without a `.exe` suffix.
+const CLIPBOARD_MANAGERS: &[(&str, &str)] = &[
+  ("ditto", "Ditto"),
+  ("clipx", "ClipX"),
+  ("clipclip", "ClipClip"),
+  ("copyq", "CopyQ"),
+  ("klipper", "Klipper"),
+  ("parcellite", "Parcellite"),
+  ("clipit", "ClipIt"),
+  ("diodon", "Diodon"),
+  ("gpaste-daemon", "GPaste"),
+  ("cliphist", "cliphist"),
+  ("clipman", "Clipman"),
+  ("xfce4-clipman", "Clipman"),
+  ("maccy", "Maccy"),
+  ("paste", "Paste"),
+  ("clipy", "Clipy"),
+  ("flycut", "Flycut"),
+];
+
+/// GNOME Shell extensions that keep a clipboard history, by directory
+/// prefix. They run inside `gnome-shell`, so they are found by install
+/// directory rather than by process.
+const GNOME_CLIPBOARD_EXTENSIONS: &[(&str, &str)] = &[
+  ("clipboard-indicator@", "GNOME Clipboard Indicator"),
+  ("clipboard-history@", "GNOME Clipboard History"),
+  ("pano@", "Pano"),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+  Ok,
+  Warning,
+}
+
+/// Result of one check.
+#[derive(Clone, Debug, Serialize)]
+pub struct DiagnosticCheck {
+  pub id: &'static str,
+  pub severity: Severity,
+  pub message: String,
+}
+
+/// The latest vault unlock.
+#[derive(Clone, Debug, Serialize)]
+pub struct LoadTiming {
+  pub at: DateTime<Utc>,
+  /// Key derivation and header decryption, until `unlock_vault` returned
+  /// (including the entries unless they were streamed).
+  pub unlock_ms: u64,
+  /// Decrypting the entries of a SQLite vault, which are sealed one by
+  /// one; 0 for vault files, where they are part of the payload.
+  pub entries_ms: u64,
+  pub entries: usize,
+  /// Entries were decrypted in the background after the unlock returned.
+  pub streamed: bool,
+}
+
+impl LoadTiming {
+  fn total(&self) -> Duration {
+    let unlock = Duration::from_millis(self.unlock_ms);
+    if self.streamed {
+      unlock + Duration::from_millis(self.entries_ms)
+    } else {
+      unlock
+    }
+  }
+}
+
+/// The latest vault write.
+#[derive(Clone, Debug, Serialize)]
+pub struct SaveTiming {
+  pub at: DateTime<Utc>,
+  pub ms: u64,
+  /// The whole vault was rewritten rather than individual entries.
+  pub full: bool,
+}
+
+/// Timings of the latest vault load and save in this run of the app.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct StorageTimings {
+  pub last_load: Option<LoadTiming>,
+  pub last_save: Option<SaveTiming>,
+}
+
+/// Runs every check.
+pub fn run(timings: &StorageTimings, monitor: &MonitorHealth) -> Vec<DiagnosticCheck> {
+  let mut checks = vec![clipboard_history_check(&detect_clipboard_managers())];
+  checks.extend(storage_check(timings));
+  checks.push(monitor_check(monitor));
+  checks
+}
+
+/// Warns once the inactivity monitor has had to be restarted: auto-lock
+/// was not enforced for a while before the restart.
+fn monitor_check(health: &MonitorHealth) -> DiagnosticCheck {
+  let (severity, message) = match (&health.last_restart, &health.last_failure) {
+    (Some(at), Some(failure)) => (
+      Severity::Warning,
+      format!(
+        "The auto-lock monitor was restarted {} time(s); last at {} ({failure}).",
+        health.restarts,
+        at.format("%Y-%m-%d %H:%M:%S UTC")
+      ),
+    ),
+    _ => (Severity::Ok, "The auto-lock monitor is running.".to_string()),
+  };
+  DiagnosticCheck {
+    id: "inactivity_monitor",
+    severity,
+    message,
+  }
+}
+
+/// Reports the latest load and save times; `None` before the first unlock.
+fn storage_check(timings: &StorageTimings) -> Option<DiagnosticCheck> {
+  let load = timings.last_load.as_ref()?;
+  let mut message = format!(
+    "Last unlock took {} ms for {} entries",
+    load.total().as_millis(),
+    load.entries
+  );
+  if load.streamed {
+    message.push_str(&format!(" ({} ms until the vault opened)", load.unlock_ms));
+  }
+  message.push('.');
+  let mut slow = load.total() > SLOW_LOAD;
+  if let Some(save) = &timings.last_save {
+    message.push_str(&format!(" Last save took {} ms.", save.ms));
+    slow |= Duration::from_millis(save.ms) > SLOW_SAVE;
+  }
+  if slow {
+    message.push_str(" Slow storage (a network or synced folder) or a very large vault can cause this.");
+  }
+  Some(DiagnosticCheck {
+    id: "storage_timings",
+    severity: if slow { Severity::Warning } else { Severity::Ok },
+    message,
+  })
+}
+
+fn clipboard_history_check(managers: &[&'static str]) -> DiagnosticCheck {
+  let (severity, message) = if managers.is_empty() {
+    (Severity::Ok, "No clipboard history manager detected.".to_string())
+  } else {
+    (
+      Severity::Warning,
+      format!(
+        "Clipboard history manager running: {}. Copied secrets may be kept in its history; \
+         the automatic clipboard clear cannot remove them.",
+        managers.join(", ")
+      ),
+    )
+  };
+  DiagnosticCheck {
+    id: "clipboard_history",
+    severity,
+    message,
+  }
+}
+
+/// Display names of the clipboard managers among `processes`.
+pub fn find_clipboard_managers<'a>(processes: impl IntoIterator<Item = &'a str>) -> Vec<&'static str> {
+  let mut found: Vec<&'static str> = Vec::new();
+  for process in processes {
+    let process = process.trim();
+    // `ps` may print a full path; tasklist prints `Ditto.exe`.
+    let name = process.rsplit(['/', '\\']).next().unwrap_or(process).to_lowercase();
+    let name = name.strip_suffix(".exe").unwrap_or(&name);
+    let matched = CLIPBOARD_MANAGERS.iter().find(|(process, _)| *process == name);
+    if let Some(&(_, display)) = matched {
+      if !found.contains(&display) {
+        found.push(display);
+      }
+    }
+  }
+  found
+}
+
+/// Clipboard managers running now or installed as GNOME Shell extensions.
+/// Detection is best-effort: if the process list cannot be read, only the
+/// extension check applies.
+pub fn detect_clipboard_managers() -> Vec<&'static str> {
+  let processes = process_names().unwrap_or_default();
+  let mut found = find_clipboard_managers(processes.lines());
+  for display in gnome_clipboard_extensions() {
+    if !found.contains(&display) {
+      found.push(display);
+    }
+  }
+  found
+}
+
+/// Names of running processes, one per line.
+fn process_names() -> Option<String> {
+  let output = if cfg!(windows) {
+    Command::new("tasklist").args(["/fo", "csv", "/nh"]).output().ok()?
+  } else {
+    Command::new("ps").args(["-A", "-o", "comm="]).output().ok()?
+  };
+  let text = String::from_utf8_lossy(&output.stdout);
+  if !cfg!(windows) {
+    return Some(text.into_owned());
+  }
+  // `"Ditto.exe","1234",...`: keep the first column.
+  Some(
+    text
+      .lines()
+      .filter_map(|line| line.split(',').next())
+      .map(|name| name.trim_matches('"'))
+      .collect::<Vec<_>>()
+      .join("\n"),
+  )
+}
+
+fn gnome_clipboard_extensions() -> Vec<&'static str> {
+  let dirs: Vec<PathBuf> = [
+    dirs::data_dir().map(|dir| dir.join("gnome-shell/extensions")),
+    Some(PathBuf::from("/usr/share/gnome-shell/extensions")),
+  ]
+  .into_iter()
+  .flatten()
+  .collect();
+
+  let mut found = Vec::new();
+  for dir in dirs {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+      continue;
+    };
+    for entry in entries.flatten() {
+      let name = entry.file_name().to_string_lossy().to_lowercase();
+      for &(prefix, display) in GNOME_CLIPBOARD_EXTENSIONS {
+        if name.starts_with(prefix) && !found.contains(&display) {
+          found.push(display);
+        }
+      }
+    }
+  }
+  found
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn matches_process_names_from_ps_and_tasklist() {
+    let processes = ["systemd", "/usr/bin/copyq", "Ditto.exe", "copyq", "bash"];
+    assert_eq!(find_clipboard_managers(processes), ["CopyQ", "Ditto"]);
+    assert!(find_clipboard_managers(["copyqueue", "paster"]).is_empty());
+
+    let check = clipboard_history_check(&["CopyQ"]);
+    assert_eq!(check.severity, Severity::Warning);
+    assert!(check.message.contains("CopyQ"));
+  }
+
+  #[test]
+  fn storage_check_warns_about_slow_loads_including_streamed_entries() {
+    assert!(storage_check(&StorageTimings::default()).is_none());
+
+    let mut timings = StorageTimings {
+      last_load: Some(LoadTiming {
+        at: Utc::now(),
+        unlock_ms: 900,
+        entries_ms: 2500,
+        entries: 20_000,
+        streamed: true,
+      }),
+      last_save: None,
+    };
+    let check = storage_check(&timings).unwrap();
+    assert_eq!(check.severity, Severity::Warning);
+    assert!(check.message.starts_with("Last unlock took 3400 ms for 20000 entries (900 ms"));
+
+    if let Some(load) = timings.last_load.as_mut() {
+      load.streamed = false;
+    }
+    timings.last_save = Some(SaveTiming {
+      at: Utc::now(),
+      ms: 40,
+      full: false,
+    });
+    assert_eq!(storage_check(&timings).unwrap().severity, Severity::Ok);
+  }
+}
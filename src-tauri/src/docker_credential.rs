@@ -0,0 +1,194 @@
+//! The docker credential helper protocol (`get`, `store`, `erase`, `list`)
+//! over vault entries.
+//!
+//! Docker runs `docker-credential-<name> <operation>` and writes a registry's
+//! server URL to its stdin, or for `store` a JSON object with the URL,
+//! username, and secret. Registries are matched to login entries by the host
+//! and port of their URL, preferring entries tagged [`DOCKER_TAG`], so an
+//! existing login for a registry works without setup. `store`, `erase`, and
+//! `list` only touch tagged entries, so docker never overwrites or trashes a
+//! login the user keeps for the registry's website.
+//!
+//! Like [`crate::git_credential`], this module only applies requests to
+//! decrypted [`VaultData`]; the [`crate::cli`] mode reads and saves the vault.
+
+use crate::history;
+use crate::models::{Entry, EntryKind, VaultData};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use url::Url;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// Tag given to entries added by `store` and reported by `list`.
+pub const DOCKER_TAG: &str = "docker";
+
+/// Docker tells a missing credential apart from a failure by this exact text.
+pub const NOT_FOUND: &str = "credentials not found in native keychain";
+
+/// A registry credential in docker's JSON shape.
+#[derive(Debug, Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+pub struct Credentials {
+  #[serde(rename = "ServerURL")]
+  pub server_url: String,
+  #[serde(rename = "Username")]
+  pub username: String,
+  #[serde(rename = "Secret")]
+  pub secret: String,
+}
+
+/// Lower-cased host and non-default port of a registry URL; a URL without a
+/// scheme is read as https. Docker Hub's `https://index.docker.io/v1/` gives
+/// `index.docker.io`.
+pub fn registry(raw: &str) -> Option<String> {
+  let trimmed = raw.trim();
+  let url = if trimmed.contains("://") {
+    Url::parse(trimmed)
+  } else {
+    Url::parse(&format!("https://{trimmed}"))
+  }
+  .ok()?;
+  let host = url.host_str()?.to_lowercase();
+  Some(match url.port() {
+    Some(port) => format!("{host}:{port}"),
+    None => host,
+  })
+}
+
+/// The login entry for `server_url`: the first one tagged [`DOCKER_TAG`],
+/// else the first untagged one.
+fn find<'a>(entries: &'a [Entry], server_url: &str) -> Option<&'a Entry> {
+  let wanted = registry(server_url)?;
+  let mut candidates = entries
+    .iter()
+    .filter(|e| matches!(e.kind, EntryKind::Login))
+    .filter(|e| registry(&e.url).as_deref() == Some(wanted.as_str()));
+  let first = candidates.next()?;
+  if is_tagged(first) {
+    return Some(first);
+  }
+  candidates.find(|e| is_tagged(e)).or(Some(first))
+}
+
+fn is_tagged(entry: &Entry) -> bool {
+  entry.tags.iter().any(|t| t == DOCKER_TAG)
+}
+
+/// The tagged entry for `server_url`, which `store` and `erase` act on.
+fn find_tagged<'a>(entries: &'a [Entry], server_url: &str) -> Option<&'a Entry> {
+  find(entries, server_url).filter(|e| is_tagged(e))
+}
+
+/// Answers `get`, following password links.
+pub fn get(data: &VaultData, server_url: &str) -> Option<Credentials> {
+  let entry = find(&data.entries, server_url)?;
+  let source = data.resolve_password_source(&entry.id)?;
+  Some(Credentials {
+    server_url: server_url.to_string(),
+    username: entry.username.clone(),
+    secret: source.password.clone(),
+  })
+}
+
+/// Applies `store`: updates the registry's tagged entry or adds one.
+/// Returns the id of the changed entry, or `None` when it already held these
+/// credentials. Entries sharing another entry's password are left alone.
+pub fn store(data: &mut VaultData, credentials: &Credentials, history_limit: usize) -> Option<String> {
+  let Some(id) = find_tagged(&data.entries, &credentials.server_url).map(|e| e.id.clone()) else {
+    let host = registry(&credentials.server_url)?;
+    let url = if credentials.server_url.contains("://") {
+      credentials.server_url.clone()
+    } else {
+      format!("https://{}", credentials.server_url)
+    };
+    let mut entry = Entry::new(
+      host,
+      credentials.username.clone(),
+      credentials.secret.clone(),
+      url,
+      String::new(),
+    );
+    entry.tags.push(DOCKER_TAG.to_string());
+    let id = entry.id.clone();
+    data.entries.push(entry);
+    return Some(id);
+  };
+
+  let entry = data.entries.iter_mut().find(|e| e.id == id)?;
+  if entry.linked_to.is_some() || (entry.username == credentials.username && entry.password == credentials.secret) {
+    return None;
+  }
+  let before = entry.clone();
+  entry.username = credentials.username.clone();
+  entry.password = credentials.secret.clone();
+  entry.touch();
+  history::record(entry, &before, None, history_limit);
+  Some(id)
+}
+
+/// Applies `erase`: moves the registry's tagged entry to the trash, unless
+/// other entries link to it. Returns false when nothing was erased.
+pub fn erase(data: &mut VaultData, server_url: &str, now: DateTime<Utc>) -> bool {
+  let Some(id) = find_tagged(&data.entries, server_url).map(|e| e.id.clone()) else {
+    return false;
+  };
+  data.entries_linking_to(&id).is_empty() && data.trash_entry(&id, now)
+}
+
+/// Answers `list`: server URL to username for entries tagged [`DOCKER_TAG`].
+pub fn list(entries: &[Entry]) -> BTreeMap<String, String> {
+  entries
+    .iter()
+    .filter(|e| matches!(e.kind, EntryKind::Login) && is_tagged(e))
+    .filter(|e| registry(&e.url).is_some())
+    .map(|e| (e.url.clone(), e.username.clone()))
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn maps_registries_to_entries() {
+    assert_eq!(registry("https://index.docker.io/v1/").as_deref(), Some("index.docker.io"));
+    assert_eq!(registry("localhost:5000").as_deref(), Some("localhost:5000"));
+    assert_eq!(registry("https://ghcr.io:443").as_deref(), Some("ghcr.io"));
+
+    let mut data = VaultData::default();
+    data
+      .entries
+      .push(Entry::new("GitHub".into(), "me".into(), "web".into(), "https://ghcr.io".into(), String::new()));
+    let login = |secret: &str| Credentials {
+      server_url: "ghcr.io".to_string(),
+      username: "me".to_string(),
+      secret: secret.to_string(),
+    };
+
+    // An untagged login answers until docker stores its own credentials,
+    // which go to a new entry and are updated there.
+    assert_eq!(get(&data, "https://ghcr.io").unwrap().secret, "web");
+    assert!(!erase(&mut data, "ghcr.io", Utc::now()));
+    let id = store(&mut data, &login("pat"), 10).unwrap();
+    assert_eq!(data.entries.len(), 2);
+    assert_eq!(data.entries[0].password, "web");
+    assert_eq!(get(&data, "ghcr.io").unwrap().secret, "pat");
+    assert_eq!(store(&mut data, &login("pat"), 10), None);
+    assert_eq!(store(&mut data, &login("rotated"), 10), Some(id.clone()));
+    assert_eq!(data.find_entry(&id).unwrap().history.len(), 1);
+    assert_eq!(list(&data.entries).get("https://ghcr.io").map(String::as_str), Some("me"));
+
+    let local = Credentials {
+      server_url: "localhost:5000".to_string(),
+      username: "ci".to_string(),
+      secret: "tok".to_string(),
+    };
+    store(&mut data, &local, 10).unwrap();
+    assert_eq!(list(&data.entries).get("https://localhost:5000").map(String::as_str), Some("ci"));
+    assert!(get(&data, "localhost:5001").is_none());
+
+    assert!(erase(&mut data, "localhost:5000", Utc::now()));
+    assert!(!erase(&mut data, "localhost:5000", Utc::now()));
+    assert_eq!(list(&data.entries).len(), 1);
+  }
+}
@@ -1,22 +1,149 @@
 //! Local HTTP bridge for the browser extension integration.
 //!
-//! The server is bound to 127.0.0.1 and guarded by a shared token. It exposes
-//! endpoints for matching entries by URL and retrieving secrets for autofill.
+//! The server is bound to 127.0.0.1, or with [`BridgeTransport::Socket`] to
+//! a Unix domain socket in a directory only the current user can open (on
+//! Windows, a named pipe only the current user can open, see
+//! [`crate::pipe`]), and guarded by a shared token. It exposes endpoints
+//! for matching entries by URL and retrieving secrets for autofill.
+//!
+//! Besides the main token, which allows everything, the app can issue tokens
+//! with a narrower [`TokenScope`] for automation tools: a status-only or
+//! read-entries token is refused (403) by the secret and identity endpoints.
+//! The bridge can also require time-boxed sessions approved from the desktop
+//! app before entries or secrets are served (see [`crate::bridge_session`]).
+//!
+//! Endpoints that return secrets or identity data additionally require the
+//! client's `Origin` to be approved from the desktop app. Unknown origins get
+//! a 403 and are queued for approval (see [`ORIGIN_APPROVAL_EVENT`]). Where
+//! connecting processes can be identified ([`peer::SUPPORTED`]), the client
+//! must also be a known browser or explicitly allowed; one that cannot be
+//! identified is refused. On Linux, connections to the Unix socket go
+//! through a [`Relay`] that reads their peer credentials, and on Windows
+//! every pipe connection does; the loopback server behind it refuses every
+//! connection the relay did not open.
+//!
+//! Sites on the autofill deny-list in settings get no matches or secrets,
+//! whatever the token.
+//!
+//! The bridge can turn itself off after going unused for some days or while
+//! the vault stays locked (see [`enforce_auto_disable`]); it then stays off
+//! until re-enabled from the desktop app.
+//!
+//! Requests are handled one at a time, so clients get bounded URLs and
+//! bodies and a socket timeout ([`CLIENT_TIMEOUT`]): a local process that
+//! stalls mid-request is disconnected instead of holding up the bridge.
+//! On exit, [`stop_extension_server`] waits for the request in progress and
+//! removes the socket file.
 
-use crate::models::{AppState, Entry, ExtensionConfig};
-use serde::Serialize;
+use crate::activity::ActivitySource;
+use crate::audit::{self, AuditEvent, AuditKind};
+use crate::bridge_session::{Collect, SessionCheck};
+use crate::commands;
+use crate::host_index::HostIndex;
+use crate::import;
+use crate::models::{AppState, AutoDisableReason, BridgeTransport, Entry, EntryKind, ExtensionConfig, IdentityFields, PendingOrigin, TokenScope};
+use crate::notifications::{self, Notice, NoticeKind};
+use crate::peer::{self, PeerProcess};
+use crate::phishing;
+use crate::totp;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use serde_json::json;
+use socket2::{Domain, SockAddr, Socket, Type};
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
-use std::thread;
-use tauri::{AppHandle, Manager};
+use std::io;
+use std::net::{SocketAddr, TcpListener};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
 use tiny_http::{Header, Method, Request, Response, Server, StatusCode};
 use url::{form_urlencoded, Url};
 use zeroize::Zeroize;
 
 const EXTENSION_CONFIG_FILENAME: &str = "extension.json";
 
+/// Returned when [`BridgeTransport::Socket`] is chosen on Windows.
+pub const SOCKET_UNSUPPORTED: &str = "the socket transport is not supported on this platform";
+
+/// Directory (inside the app data dir) holding the bridge socket.
+const SOCKET_DIRNAME: &str = "bridge";
+const SOCKET_FILENAME: &str = "bridge.sock";
+
+/// Event emitted (with a [`PendingOrigin`] payload) when an unapproved client
+/// asks for protected data.
+pub const ORIGIN_APPROVAL_EVENT: &str = "extension://approval-requested";
+
+/// Event emitted (with an [`AutoDisableReason`] payload) when the bridge
+/// turns itself off.
+pub const AUTO_DISABLED_EVENT: &str = "extension://auto-disabled";
+
+/// Event emitted (with an [`AutofillEvent`] payload) whenever the extension
+/// receives a password or identity data.
+pub const AUTOFILL_EVENT: &str = "extension://autofilled";
+
+/// Event emitted (with a [`crate::bridge_session::SessionRequest`] payload)
+/// when a client asks for a session that needs approval.
+pub const SESSION_REQUESTED_EVENT: &str = "extension://session-requested";
+
+/// Longest session that can be set, in hours.
+pub const MAX_SESSION_HOURS: u32 = 7 * 24;
+
+/// Longest auto-disable periods that can be set.
+pub const MAX_IDLE_DAYS: u32 = 365;
+pub const MAX_LOCKED_MINUTES: u32 = 7 * 24 * 60;
+
+/// Most extra tokens with a limited scope.
+pub const MAX_SCOPED_TOKENS: usize = 20;
+
+/// How often a use of the bridge is written to `last_used_at`.
+const USE_RECORD_INTERVAL: chrono::Duration = chrono::Duration::hours(1);
+
+/// Bridge protocol version reported by `/v1/capabilities`, bumped when an
+/// existing endpoint changes incompatibly. New endpoints and features are
+/// announced through [`Capabilities`] instead.
+const PROTOCOL_VERSION: u32 = 1;
+
+/// Endpoints served by this bridge; requests are routed by [`route`].
+const ENDPOINTS: &[&str] = &[
+  "/v1/capabilities",
+  "/v1/status",
+  "/v1/entries",
+  "/v1/exists",
+  "/v1/secret",
+  IDENTITY_ENDPOINT,
+  "/v1/session",
+  TOTP_ENDPOINT,
+];
+
+const IDENTITY_ENDPOINT: &str = "/v1/identity";
+
+/// Stores a TOTP secret in entry `{id}` ([`totp_entry_id`]).
+const TOTP_ENDPOINT: &str = "/v1/entries/{id}/totp";
+
+/// Most origins waiting for approval; the oldest is dropped for a new one.
+const MAX_PENDING_ORIGINS: usize = 20;
+
+/// How long an origin waits for approval before it is dropped; the client
+/// is queued again on its next request.
+const PENDING_ORIGIN_TTL: chrono::Duration = chrono::Duration::hours(1);
+
+/// Origin recorded for clients that send no `Origin` header (non-browser processes).
+const NO_ORIGIN: &str = "null";
+
+/// Longest request URL accepted (the page URL arrives in the query string).
+const MAX_URL_BYTES: usize = 8 * 1024;
+
+/// Largest request body accepted.
+const MAX_BODY_BYTES: usize = 64 * 1024;
+
+/// How long a client may stall while sending a request or receiving a
+/// response before it is disconnected.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
 #[derive(Serialize)]
 struct ExtensionEntry {
   id: String,
@@ -25,17 +152,58 @@ struct ExtensionEntry {
   url: String,
 }
 
-impl From<&Entry> for ExtensionEntry {
-  fn from(entry: &Entry) -> Self {
+impl ExtensionEntry {
+  /// The entry as listed to the extension; presentation mode masks the
+  /// username.
+  fn new(entry: &Entry, presenting: bool) -> Self {
     Self {
       id: entry.id.clone(),
       title: entry.title.clone(),
-      username: entry.username.clone(),
+      username: if presenting { commands::mask(&entry.username) } else { entry.username.clone() },
       url: entry.url.clone(),
     }
   }
 }
 
+/// Response of `/v1/capabilities`, so an extension can check what this app
+/// supports instead of probing endpoints.
+#[derive(Serialize)]
+struct Capabilities {
+  version: u32,
+  app_version: &'static str,
+  endpoints: &'static [&'static str],
+  features: Features,
+}
+
+#[derive(Serialize)]
+struct Features {
+  /// Storing a site's TOTP secret via `/v1/entries/{id}/totp`.
+  totp: bool,
+  /// Saving logins captured by the extension.
+  save: bool,
+  /// Identity fields via `/v1/identity`.
+  identities: bool,
+  /// Entries and secrets need a desktop-approved session (`/v1/session`).
+  sessions: bool,
+}
+
+impl Capabilities {
+  fn current(sessions: bool) -> Self {
+    Self {
+      version: PROTOCOL_VERSION,
+      app_version: env!("CARGO_PKG_VERSION"),
+      endpoints: ENDPOINTS,
+      features: Features {
+        totp: ENDPOINTS.contains(&TOTP_ENDPOINT),
+        // No endpoint takes logins from the extension yet.
+        save: false,
+        identities: ENDPOINTS.contains(&IDENTITY_ENDPOINT),
+        sessions,
+      },
+    }
+  }
+}
+
 fn extension_config_path(app: &AppHandle) -> Result<PathBuf, String> {
   let dir = app
     .path()
@@ -71,31 +239,359 @@ pub fn save_config(app: &AppHandle, config: &ExtensionConfig) -> Result<(), Stri
   Ok(())
 }
 
-pub fn start_extension_server(_app: &AppHandle, state: AppState) {
-  let port = match state.extension_config.lock() {
-    Ok(cfg) => cfg.port,
+/// Path of the bridge socket for [`BridgeTransport::Socket`].
+#[cfg(not(windows))]
+pub fn socket_path(app: &AppHandle) -> Result<PathBuf, String> {
+  let dir = app
+    .path()
+    .app_data_dir()
+    .map_err(|e| format!("app_data_dir failed: {e}"))?;
+  Ok(dir.join(SOCKET_DIRNAME).join(SOCKET_FILENAME))
+}
+
+/// Name of the bridge pipe for [`BridgeTransport::Socket`].
+#[cfg(windows)]
+pub fn socket_path(_app: &AppHandle) -> Result<PathBuf, String> {
+  crate::pipe::bridge_name()
+    .map(PathBuf::from)
+    .map_err(|e| format!("bridge pipe name failed: {e}"))
+}
+
+/// Where clients reach the bridge with `config`, as shown in the app.
+pub fn endpoint(app: &AppHandle, config: &ExtensionConfig) -> Result<String, String> {
+  match config.transport {
+    BridgeTransport::Tcp => Ok(format!("http://127.0.0.1:{}", config.port)),
+    BridgeTransport::Socket => {
+      let scheme = if cfg!(windows) { "pipe" } else { "unix" };
+      Ok(format!("{scheme}:{}", socket_path(app)?.display()))
+    }
+  }
+}
+
+/// The running bridge, kept in [`AppState::bridge_server`] so it can be
+/// stopped on exit.
+pub struct BridgeServer {
+  server: Arc<Server>,
+  thread: JoinHandle<()>,
+  /// Socket file to remove once stopped, or on Windows the pipe, for
+  /// [`BridgeTransport::Socket`].
+  socket: Option<PathBuf>,
+  relay: Option<Relay>,
+}
+
+/// Processes behind connections to the bridge socket, by the loopback port
+/// their requests are relayed from; `None` if the peer credentials could
+/// not be read.
+type Relayed = Arc<Mutex<HashMap<u16, Option<PeerProcess>>>>;
+
+/// Accepts connections to the bridge socket in front of the HTTP server,
+/// which cannot report who is at the other end of a Unix socket. Each
+/// connection is identified by its peer credentials and copied to the
+/// server over loopback TCP from a port recorded in `clients`, where
+/// [`verify_client`] looks it up. Any local process can reach that server,
+/// so requests from other ports are refused ([`bypasses_relay`]). Used on
+/// Linux, and on Windows for the named pipe, which the HTTP server cannot
+/// listen on; processes are not identified there.
+struct Relay {
+  clients: Relayed,
+  stopping: Arc<AtomicBool>,
+  thread: JoinHandle<()>,
+}
+
+pub fn start_extension_server(app: &AppHandle, state: AppState) {
+  let config = match state.extension_config.lock() {
+    Ok(cfg) => cfg.clone(),
     Err(_) => {
       eprintln!("extension server: extension config mutex poisoned");
       return;
     }
   };
-  let address = format!("127.0.0.1:{port}");
-  let server = match Server::http(&address) {
-    Ok(server) => server,
+  // A socket the user chose over TCP never falls back to a TCP port.
+  let server = match config.transport {
+    BridgeTransport::Tcp => {
+      bind_tcp(SocketAddr::from(([127, 0, 0, 1], config.port)), CLIENT_TIMEOUT).map(|server| (server, None))
+    }
+    BridgeTransport::Socket => socket_path(app).and_then(|path| bind_socket(&path, CLIENT_TIMEOUT)),
+  };
+  let (server, relay) = match server {
+    Ok(bound) => bound,
     Err(e) => {
-      eprintln!("extension server: failed to bind {address}: {e}");
+      let endpoint = endpoint(app, &config).unwrap_or_default();
+      eprintln!("extension server: failed to bind {endpoint}: {e}");
       return;
     }
   };
 
+  let socket = match config.transport {
+    BridgeTransport::Tcp => None,
+    BridgeTransport::Socket => socket_path(app).ok(),
+  };
+
+  let server = Arc::new(server);
+  let serving = Arc::clone(&server);
+  let relayed = relay.as_ref().map(|relay| Arc::clone(&relay.clients));
+  let app = app.clone();
+  let bridge = Arc::clone(&state.bridge_server);
+  let thread = thread::spawn(move || {
+    for request in serving.incoming_requests() {
+      handle_request(&app, &state, relayed.as_ref(), request);
+    }
+  });
+  let Ok(mut slot) = bridge.lock() else {
+    return;
+  };
+  *slot = Some(BridgeServer { server, thread, socket, relay });
+}
+
+/// Stops accepting requests, waits for the one being handled, and removes
+/// the socket file. Does nothing if the server is not running.
+pub fn stop_extension_server(state: &AppState) {
+  let running = match state.bridge_server.lock() {
+    Ok(mut slot) => slot.take(),
+    Err(_) => None,
+  };
+  let Some(running) = running else {
+    return;
+  };
+  if let (Some(relay), Some(path)) = (running.relay, running.socket.as_deref()) {
+    relay.stopping.store(true, Ordering::SeqCst);
+    // Wakes the relay from accept so it sees the flag.
+    #[cfg(unix)]
+    drop(std::os::unix::net::UnixStream::connect(path));
+    #[cfg(windows)]
+    drop(fs::OpenOptions::new().read(true).write(true).open(path));
+    if relay.thread.join().is_err() {
+      eprintln!("extension server: socket relay panicked");
+    }
+  }
+  running.server.unblock();
+  if running.thread.join().is_err() {
+    eprintln!("extension server: request handler panicked");
+  }
+  // A pipe goes away with its last handle.
+  #[cfg(unix)]
+  if let Some(path) = running.socket {
+    if let Err(e) = fs::remove_file(&path) {
+      if e.kind() != io::ErrorKind::NotFound {
+        eprintln!("extension server: remove socket failed: {e}");
+      }
+    }
+  }
+}
+
+/// Creates a listening socket on `address`. Accepted connections inherit
+/// its read and write `timeout`.
+fn listen(domain: Domain, address: &SockAddr, timeout: Duration) -> io::Result<Socket> {
+  let socket = Socket::new(domain, Type::STREAM, None)?;
+  socket.set_read_timeout(Some(timeout))?;
+  socket.set_write_timeout(Some(timeout))?;
+  // Matches std's `TcpListener::bind`, so a restart can rebind right away.
+  #[cfg(unix)]
+  if domain != Domain::UNIX {
+    socket.set_reuse_address(true)?;
+  }
+  socket.bind(address)?;
+  socket.listen(128)?;
+  Ok(socket)
+}
+
+fn bind_tcp(address: SocketAddr, timeout: Duration) -> Result<Server, String> {
+  let socket = listen(Domain::IPV4, &address.into(), timeout).map_err(|e| e.to_string())?;
+  Server::from_listener(TcpListener::from(socket), None).map_err(|e| e.to_string())
+}
+
+/// Binds the bridge socket at `path`. Its directory is made private to the
+/// current user, which is what restricts access to the bridge.
+#[cfg(unix)]
+fn bind_socket(path: &Path, timeout: Duration) -> Result<(Server, Option<Relay>), String> {
+  use std::os::fd::OwnedFd;
+  use std::os::unix::fs::PermissionsExt;
+  use std::os::unix::net::{UnixListener, UnixStream};
+
+  let dir = path.parent().ok_or_else(|| "socket path has no directory".to_string())?;
+  fs::create_dir_all(dir).map_err(|e| format!("create_dir_all failed: {e}"))?;
+  fs::set_permissions(dir, fs::Permissions::from_mode(0o700)).map_err(|e| format!("set permissions failed: {e}"))?;
+  if UnixStream::connect(path).is_ok() {
+    return Err("another instance is serving the bridge".to_string());
+  }
+  // A socket file left by a previous run would make the bind fail.
+  match fs::remove_file(path) {
+    Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(format!("remove stale socket failed: {e}")),
+    _ => {}
+  }
+
+  let address = SockAddr::unix(path).map_err(|e| e.to_string())?;
+  let socket = listen(Domain::UNIX, &address, timeout).map_err(|e| e.to_string())?;
+  fs::set_permissions(path, fs::Permissions::from_mode(0o600)).map_err(|e| format!("set permissions failed: {e}"))?;
+  serve_socket(UnixListener::from(OwnedFd::from(socket)), timeout)
+}
+
+/// Creates the bridge pipe at `path`, which only the current user can open,
+/// and relays it to a loopback server.
+#[cfg(windows)]
+fn bind_socket(path: &Path, timeout: Duration) -> Result<(Server, Option<Relay>), String> {
+  let name = path.to_str().ok_or_else(|| "pipe name is not valid unicode".to_string())?;
+  // Fails if another process holds the name, whoever it belongs to.
+  let mut listener = crate::pipe::PipeListener::bind(name).map_err(|e| format!("create pipe failed: {e}"))?;
+  serve_relay(move || listener.accept().map(|client| (client, None)), timeout)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn bind_socket(_path: &Path, _timeout: Duration) -> Result<(Server, Option<Relay>), String> {
+  Err(SOCKET_UNSUPPORTED.to_string())
+}
+
+/// Serves the bridge on `listener` through a [`Relay`] to a loopback server.
+#[cfg(target_os = "linux")]
+fn serve_socket(
+  listener: std::os::unix::net::UnixListener,
+  timeout: Duration,
+) -> Result<(Server, Option<Relay>), String> {
+  serve_relay(
+    move || {
+      let (client, _) = listener.accept()?;
+      let process = peer::peer_cred(&client);
+      Ok((client, process))
+    },
+    timeout,
+  )
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn serve_socket(
+  listener: std::os::unix::net::UnixListener,
+  _timeout: Duration,
+) -> Result<(Server, Option<Relay>), String> {
+  let server = Server::from_listener(listener, None).map_err(|e| e.to_string())?;
+  Ok((server, None))
+}
+
+/// A client connection the [`Relay`] copies to the loopback server.
+#[cfg(any(target_os = "linux", windows))]
+trait RelayStream: io::Read + io::Write + Send + Sized + 'static {
+  fn try_clone(&self) -> io::Result<Self>;
+  /// Ends the connection, waking a thread blocked reading it.
+  fn close(&self);
+}
+
+#[cfg(target_os = "linux")]
+impl RelayStream for std::os::unix::net::UnixStream {
+  fn try_clone(&self) -> io::Result<Self> {
+    std::os::unix::net::UnixStream::try_clone(self)
+  }
+
+  fn close(&self) {
+    let _ = self.shutdown(std::net::Shutdown::Both);
+  }
+}
+
+#[cfg(windows)]
+impl RelayStream for crate::pipe::PipeStream {
+  fn try_clone(&self) -> io::Result<Self> {
+    crate::pipe::PipeStream::try_clone(self)
+  }
+
+  fn close(&self) {
+    crate::pipe::PipeStream::close(self)
+  }
+}
+
+/// Starts a loopback server and a [`Relay`] that feeds it the clients
+/// `accept` returns, with the process behind each if known.
+#[cfg(any(target_os = "linux", windows))]
+fn serve_relay<C: RelayStream>(
+  mut accept: impl FnMut() -> io::Result<(C, Option<PeerProcess>)> + Send + 'static,
+  timeout: Duration,
+) -> Result<(Server, Option<Relay>), String> {
+  let server = bind_tcp(SocketAddr::from(([127, 0, 0, 1], 0)), timeout)?;
+  let upstream = server
+    .server_addr()
+    .to_ip()
+    .ok_or_else(|| "relay server has no address".to_string())?;
+  let clients = Relayed::default();
+  let stopping = Arc::new(AtomicBool::new(false));
+  let thread = {
+    let (clients, stopping) = (Arc::clone(&clients), Arc::clone(&stopping));
+    thread::spawn(move || loop {
+      let accepted = accept();
+      if stopping.load(Ordering::SeqCst) {
+        return;
+      }
+      let Ok((client, process)) = accepted else {
+        // Keeps a failure that repeats, such as running out of handles,
+        // from spinning.
+        thread::sleep(Duration::from_millis(100));
+        continue;
+      };
+      if let Err(e) = relay_connection(client, process, upstream, &clients) {
+        eprintln!("extension server: relay failed: {e}");
+      }
+    })
+  };
+  Ok((server, Some(Relay { clients, stopping, thread })))
+}
+
+/// Connects `client` to the server at `upstream`, records its `process`
+/// under the port it connects from, and copies both ways until either side
+/// closes. The server's socket timeout ends idle clients.
+#[cfg(any(target_os = "linux", windows))]
+fn relay_connection<C: RelayStream>(
+  client: C,
+  process: Option<PeerProcess>,
+  upstream: SocketAddr,
+  clients: &Relayed,
+) -> io::Result<()> {
+  use std::net::{Shutdown, TcpStream};
+
+  let server = TcpStream::connect(upstream)?;
+  let port = server.local_addr()?.port();
+  // Recorded before anything is copied, so the server cannot see the
+  // request first.
+  if let Ok(mut clients) = clients.lock() {
+    clients.insert(port, process);
+  }
+
+  let (mut from_client, mut to_server) = (client.try_clone()?, server.try_clone()?);
+  let upload = thread::spawn(move || {
+    let _ = io::copy(&mut from_client, &mut to_server);
+    let _ = to_server.shutdown(Shutdown::Write);
+  });
+  let clients = Arc::clone(clients);
   thread::spawn(move || {
-    for request in server.incoming_requests() {
-      handle_request(&state, request);
+    let (mut server, mut client) = (server, client);
+    let _ = io::copy(&mut server, &mut client);
+    client.close();
+    let _ = upload.join();
+    // Before `server` is dropped, so the port is not reused meanwhile.
+    if let Ok(mut clients) = clients.lock() {
+      clients.remove(&port);
     }
   });
+  Ok(())
 }
 
-fn handle_request(state: &AppState, request: Request) {
+fn handle_request(app: &AppHandle, state: &AppState, relayed: Option<&Relayed>, mut request: Request) {
+  if bypasses_relay(relayed, &request) {
+    respond_json(request, StatusCode(403), json!({ "error": "connect through the bridge socket" }));
+    return;
+  }
+  if request.url().len() > MAX_URL_BYTES {
+    respond_json(request, StatusCode(414), json!({ "error": "url too long" }));
+    return;
+  }
+  match request.body_length() {
+    Some(length) if length > MAX_BODY_BYTES => {
+      respond_json(request, StatusCode(413), json!({ "error": "request body too large" }));
+      return;
+    }
+    // Chunked bodies have no length to check up front.
+    None if header_value(&request, "Transfer-Encoding").is_some() => {
+      respond_json(request, StatusCode(411), json!({ "error": "content-length required" }));
+      return;
+    }
+    _ => {}
+  }
+
   if *request.method() == Method::Options {
     respond_json(request, StatusCode(204), json!({}));
     return;
@@ -103,22 +599,33 @@ fn handle_request(state: &AppState, request: Request) {
 
   let (path, query) = split_path_query(request.url());
 
-  match (request.method(), path) {
-    (&Method::Get, "/v1/status") => {
-      if let Err(err) = ensure_authorized(state, &request) {
+  match (request.method(), route(path)) {
+    (&Method::Get, Some("/v1/capabilities")) => {
+      if let Err(err) = ensure_authorized(app, state, &request, TokenScope::Status) {
         respond_auth_error(request, err);
         return;
       }
-      state.heartbeat();
+      let sessions = state
+        .extension_config
+        .lock()
+        .is_ok_and(|config| config.session_hours.is_some());
+      respond_json(request, StatusCode(200), json!(Capabilities::current(sessions)));
+    }
+    (&Method::Get, Some("/v1/status")) => {
+      if let Err(err) = ensure_authorized(app, state, &request, TokenScope::Status) {
+        respond_auth_error(request, err);
+        return;
+      }
+      state.touch(ActivitySource::Extension);
       let locked = is_locked(state);
       respond_json(request, StatusCode(200), json!({ "locked": locked }));
     }
-    (&Method::Get, "/v1/entries") => {
-      if let Err(err) = ensure_authorized(state, &request) {
+    (&Method::Get, Some("/v1/entries")) => {
+      if let Err(err) = ensure_authorized(app, state, &request, TokenScope::ReadEntries) {
         respond_auth_error(request, err);
         return;
       }
-      state.heartbeat();
+      state.touch(ActivitySource::Extension);
       if is_locked(state) {
         respond_json(
           request,
@@ -152,19 +659,24 @@ fn handle_request(state: &AppState, request: Request) {
         }
       };
 
-      let entries_guard = match state.entries.lock() {
+      if is_excluded(state, &target_host) {
+        respond_excluded(request);
+        return;
+      }
+
+      let vault_guard = match state.vault.lock() {
         Ok(g) => g,
         Err(_) => {
           respond_json(
             request,
             StatusCode(500),
-            json!({ "error": "entries mutex poisoned" }),
+            json!({ "error": "vault mutex poisoned" }),
           );
           return;
         }
       };
-      let entries = match entries_guard.as_ref() {
-        Some(entries) => entries,
+      let data = match vault_guard.as_ref() {
+        Some(data) => data,
         None => {
           respond_json(
             request,
@@ -174,27 +686,102 @@ fn handle_request(state: &AppState, request: Request) {
           return;
         }
       };
+      let mut index_guard = match state.host_index.lock() {
+        Ok(g) => g,
+        Err(_) => {
+          respond_json(
+            request,
+            StatusCode(500),
+            json!({ "error": "host index mutex poisoned" }),
+          );
+          return;
+        }
+      };
+      if index_guard.as_ref().is_some_and(|index| !index.is_current(data)) {
+        *index_guard = None;
+      }
+      let index = index_guard.get_or_insert_with(|| HostIndex::build(data));
 
-      let matches: Vec<ExtensionEntry> = entries
-        .iter()
-        .filter_map(|entry| {
-          let entry_host = normalize_host(entry.url.as_str())?;
-          if host_matches(&entry_host, &target_host) {
-            Some(ExtensionEntry::from(entry))
-          } else {
-            None
-          }
-        })
+      let matches: Vec<ExtensionEntry> = index
+        .matching(&target_host)
+        .into_iter()
+        .filter_map(|id| data.entries.iter().find(|entry| entry.id == id))
+        .filter(|entry| !entry.hidden)
+        .map(|entry| ExtensionEntry::new(entry, state.presenting()))
         .collect();
 
-      respond_json(request, StatusCode(200), json!({ "entries": matches }));
+      // No direct match: make sure this isn't a lookalike of a site we hold
+      // credentials for before the extension offers anything.
+      let warning = if matches.is_empty() {
+        phishing::check_lookalike(&target_host, index.hosts().iter().map(String::as_str))
+      } else {
+        None
+      };
+
+      respond_json(
+        request,
+        StatusCode(200),
+        json!({ "entries": matches, "warning": warning }),
+      );
+    }
+    (&Method::Get, Some("/v1/exists")) => {
+      if let Err(err) = ensure_authorized(app, state, &request, TokenScope::ReadEntries) {
+        respond_auth_error(request, err);
+        return;
+      }
+      state.touch(ActivitySource::Extension);
+      let params = parse_query(query);
+      let url = match params.get("url") {
+        Some(value) if normalize_host(value).is_some() => value,
+        _ => {
+          respond_json(
+            request,
+            StatusCode(400),
+            json!({ "error": "a valid url is required" }),
+          );
+          return;
+        }
+      };
+      let username = params.get("username").map(String::as_str).unwrap_or("");
+      if normalize_host(url).is_some_and(|host| is_excluded(state, &host)) {
+        respond_excluded(request);
+        return;
+      }
+
+      let vault_guard = match state.vault.lock() {
+        Ok(g) => g,
+        Err(_) => {
+          respond_json(
+            request,
+            StatusCode(500),
+            json!({ "error": "vault mutex poisoned" }),
+          );
+          return;
+        }
+      };
+      let Some(data) = vault_guard.as_ref() else {
+        respond_json(
+          request,
+          StatusCode(423),
+          json!({ "error": "vault is locked" }),
+        );
+        return;
+      };
+      let existing = import::find_same_login(&data.entries, url, username)
+        .filter(|entry| !entry.hidden)
+        .map(|entry| ExtensionEntry::new(entry, state.presenting()));
+      respond_json(
+        request,
+        StatusCode(200),
+        json!({ "exists": existing.is_some(), "entry": existing }),
+      );
     }
-    (&Method::Get, "/v1/secret") => {
-      if let Err(err) = ensure_authorized(state, &request) {
+    (&Method::Get, Some("/v1/secret")) => {
+      if let Err(err) = ensure_authorized(app, state, &request, TokenScope::Full) {
         respond_auth_error(request, err);
         return;
       }
-      state.heartbeat();
+      state.touch(ActivitySource::Extension);
       if is_locked(state) {
         respond_json(
           request,
@@ -203,6 +790,21 @@ fn handle_request(state: &AppState, request: Request) {
         );
         return;
       }
+      if state.presenting() {
+        respond_presenting(request);
+        return;
+      }
+      let client = match verify_client(state, relayed, &request) {
+        Ok(client) => client,
+        Err(process) => {
+          respond_client_rejected(state, request, process);
+          return;
+        }
+      };
+      if let Err(origin) = ensure_origin_approved(app, state, &request, client.as_ref()) {
+        respond_origin_not_approved(request, origin);
+        return;
+      }
       let params = parse_query(query);
       let entry_id = match params.get("id") {
         Some(value) if !value.trim().is_empty() => value,
@@ -216,19 +818,19 @@ fn handle_request(state: &AppState, request: Request) {
         }
       };
 
-      let entries_guard = match state.entries.lock() {
+      let vault_guard = match state.vault.lock() {
         Ok(g) => g,
         Err(_) => {
           respond_json(
             request,
             StatusCode(500),
-            json!({ "error": "entries mutex poisoned" }),
+            json!({ "error": "vault mutex poisoned" }),
           );
           return;
         }
       };
-      let entries = match entries_guard.as_ref() {
-        Some(entries) => entries,
+      let data = match vault_guard.as_ref() {
+        Some(data) => data,
         None => {
           respond_json(
             request,
@@ -239,7 +841,21 @@ fn handle_request(state: &AppState, request: Request) {
         }
       };
 
-      let mut secret = match entries.iter().find(|entry| entry.id == *entry_id) {
+      let excluded = data
+        .find_entry(entry_id)
+        .and_then(|entry| normalize_host(&entry.url))
+        .is_some_and(|host| is_excluded(state, &host));
+      if excluded {
+        drop(vault_guard);
+        respond_excluded(request);
+        return;
+      }
+
+      let title = data.find_entry(entry_id).map(|entry| entry.title.clone()).unwrap_or_default();
+      // Hidden entries are never matched, so the extension has no business
+      // asking for them.
+      let hidden = data.find_entry(entry_id).is_some_and(|entry| entry.hidden);
+      let mut secret = match data.resolve_password_source(entry_id).filter(|_| !hidden) {
         Some(entry) => entry.password.clone(),
         None => {
           respond_json(
@@ -250,17 +866,367 @@ fn handle_request(state: &AppState, request: Request) {
           return;
         }
       };
+      drop(vault_guard);
+
+      if let Err(err) = commands::record_secret_use(Some(app), state, entry_id, "extension") {
+        eprintln!("extension server: failed to record secret use: {err}");
+      }
+      audit::record(
+        state,
+        AuditEvent::new(AuditKind::ExtensionSecretRead)
+          .entry(entry_id)
+          .detail("password")
+          .client(client.as_ref().map(|p| p.name.as_str())),
+      );
+      announce_fill(app, state, &request, entry_id, &title, "password", client.as_ref());
 
       let payload = json!({ "password": secret });
       secret.zeroize();
       respond_json(request, StatusCode(200), payload);
     }
+    (&Method::Get, Some(IDENTITY_ENDPOINT)) => {
+      if let Err(err) = ensure_authorized(app, state, &request, TokenScope::Full) {
+        respond_auth_error(request, err);
+        return;
+      }
+      state.touch(ActivitySource::Extension);
+      if is_locked(state) {
+        respond_json(
+          request,
+          StatusCode(423),
+          json!({ "error": "vault is locked" }),
+        );
+        return;
+      }
+      if state.presenting() {
+        respond_presenting(request);
+        return;
+      }
+      let client = match verify_client(state, relayed, &request) {
+        Ok(client) => client,
+        Err(process) => {
+          respond_client_rejected(state, request, process);
+          return;
+        }
+      };
+      if let Err(origin) = ensure_origin_approved(app, state, &request, client.as_ref()) {
+        respond_origin_not_approved(request, origin);
+        return;
+      }
+      let params = parse_query(query);
+      let fields: Vec<&str> = params
+        .get("fields")
+        .map(|value| {
+          value
+            .split(',')
+            .map(str::trim)
+            .filter(|f| !f.is_empty())
+            .collect()
+        })
+        .unwrap_or_default();
+      if fields.is_empty() {
+        respond_json(
+          request,
+          StatusCode(400),
+          json!({ "error": "fields is required" }),
+        );
+        return;
+      }
+      if let Some(unknown) = fields.iter().find(|f| !IdentityFields::FIELD_NAMES.contains(f)) {
+        respond_json(
+          request,
+          StatusCode(400),
+          json!({ "error": format!("unknown field: {unknown}") }),
+        );
+        return;
+      }
+
+      let vault_guard = match state.vault.lock() {
+        Ok(g) => g,
+        Err(_) => {
+          respond_json(
+            request,
+            StatusCode(500),
+            json!({ "error": "vault mutex poisoned" }),
+          );
+          return;
+        }
+      };
+      let data = match vault_guard.as_ref() {
+        Some(data) => data,
+        None => {
+          respond_json(
+            request,
+            StatusCode(423),
+            json!({ "error": "vault is locked" }),
+          );
+          return;
+        }
+      };
+
+      // An explicit id picks one identity; otherwise use the first one.
+      let requested_id = params.get("id").filter(|id| !id.trim().is_empty());
+      let identity = data.entries.iter().find_map(|entry| match &entry.kind {
+        EntryKind::Identity(identity)
+          if !entry.hidden && requested_id.is_none_or(|id| *id == entry.id) =>
+        {
+          Some((entry.id.clone(), entry.title.clone(), identity))
+        }
+        _ => None,
+      });
+      let (entry_id, title, identity) = match identity {
+        Some(found) => found,
+        None => {
+          respond_json(
+            request,
+            StatusCode(404),
+            json!({ "error": "identity not found" }),
+          );
+          return;
+        }
+      };
+
+      let values: HashMap<&str, &str> = fields
+        .iter()
+        .filter_map(|field| Some((*field, identity.get(field)?)))
+        .collect();
+      let payload = json!({ "id": entry_id, "fields": values });
+      drop(vault_guard);
+
+      if let Err(err) = commands::record_secret_use(Some(app), state, &entry_id, "extension") {
+        eprintln!("extension server: failed to record identity use: {err}");
+      }
+      audit::record(
+        state,
+        AuditEvent::new(AuditKind::ExtensionSecretRead)
+          .entry(&entry_id)
+          .detail("identity")
+          .client(client.as_ref().map(|p| p.name.as_str())),
+      );
+      announce_fill(app, state, &request, &entry_id, &title, "identity", client.as_ref());
+      respond_json(request, StatusCode(200), payload);
+    }
+    (&Method::Post, Some(TOTP_ENDPOINT)) => {
+      let Some(entry_id) = totp_entry_id(path).map(str::to_string) else {
+        respond_json(request, StatusCode(404), json!({ "error": "not found" }));
+        return;
+      };
+      if let Err(err) = ensure_authorized(app, state, &request, TokenScope::Full) {
+        respond_auth_error(request, err);
+        return;
+      }
+      state.touch(ActivitySource::Extension);
+      if is_locked(state) {
+        respond_json(
+          request,
+          StatusCode(423),
+          json!({ "error": "vault is locked" }),
+        );
+        return;
+      }
+      let client = match verify_client(state, relayed, &request) {
+        Ok(client) => client,
+        Err(process) => {
+          respond_client_rejected(state, request, process);
+          return;
+        }
+      };
+      if let Err(origin) = ensure_origin_approved(app, state, &request, client.as_ref()) {
+        respond_origin_not_approved(request, origin);
+        return;
+      }
+
+      let mut body = String::new();
+      let read = io::Read::read_to_string(request.as_reader(), &mut body);
+      let push = read
+        .ok()
+        .and_then(|_| serde_json::from_str::<TotpPush>(&body).ok());
+      body.zeroize();
+      let Some(mut push) = push else {
+        respond_json(
+          request,
+          StatusCode(400),
+          json!({ "error": "expected a JSON body with uri" }),
+        );
+        return;
+      };
+      let uri = push.uri.trim().to_string();
+      push.uri.zeroize();
+      let invalid = if uri.starts_with("otpauth://totp/") {
+        totp::validate(&uri).err()
+      } else {
+        Some("uri must be an otpauth://totp/ URI".to_string())
+      };
+      if let Some(error) = invalid {
+        respond_json(request, StatusCode(400), json!({ "error": error }));
+        return;
+      }
+
+      let existing = match state.vault.lock() {
+        Ok(guard) => guard
+          .as_ref()
+          .and_then(|data| data.entries.iter().find(|e| e.id == entry_id))
+          .map(Entry::has_totp),
+        Err(_) => None,
+      };
+      match existing {
+        None => {
+          respond_json(
+            request,
+            StatusCode(404),
+            json!({ "error": "entry not found" }),
+          );
+          return;
+        }
+        Some(true) if !push.replace => {
+          respond_json(
+            request,
+            StatusCode(409),
+            json!({ "error": "entry already has a TOTP secret", "has_totp": true }),
+          );
+          return;
+        }
+        Some(_) => {}
+      }
+
+      if let Err(err) = commands::set_entry_totp(app, state, &entry_id, uri, push.replace) {
+        respond_json(request, StatusCode(500), json!({ "error": err }));
+        return;
+      }
+      audit::record(
+        state,
+        AuditEvent::new(AuditKind::EntryUpdated)
+          .entry(&entry_id)
+          .detail("totp from extension")
+          .client(client.as_ref().map(|p| p.name.as_str())),
+      );
+      respond_json(
+        request,
+        StatusCode(200),
+        json!({ "id": entry_id, "has_totp": true }),
+      );
+    }
+    (&Method::Post, Some("/v1/session")) => {
+      match ensure_authorized(app, state, &request, TokenScope::Status) {
+        Ok(Access::Token(TokenScope::Full)) => {}
+        Ok(_) => {
+          respond_auth_error(request, AuthError::OutOfScope);
+          return;
+        }
+        Err(err) => {
+          respond_auth_error(request, err);
+          return;
+        }
+      }
+      let required = state
+        .extension_config
+        .lock()
+        .is_ok_and(|config| config.session_hours.is_some());
+      if !required {
+        respond_json(
+          request,
+          StatusCode(409),
+          json!({ "error": "sessions are not required" }),
+        );
+        return;
+      }
+      let client = match verify_client(state, relayed, &request) {
+        Ok(client) => client,
+        Err(process) => {
+          respond_client_rejected(state, request, process);
+          return;
+        }
+      };
+      let origin = request_origin(&request);
+      let Ok((session_request, new)) = state
+        .bridge_sessions
+        .lock()
+        .map(|mut sessions| sessions.request(&origin, client, Utc::now()))
+      else {
+        respond_json(
+          request,
+          StatusCode(500),
+          json!({ "error": "session mutex poisoned" }),
+        );
+        return;
+      };
+      if new {
+        let _ = app.emit(SESSION_REQUESTED_EVENT, &session_request);
+      }
+      respond_json(
+        request,
+        StatusCode(202),
+        json!({ "status": "pending", "request": session_request.id }),
+      );
+    }
+    (&Method::Get, Some("/v1/session")) => {
+      match ensure_authorized(app, state, &request, TokenScope::Status) {
+        Ok(Access::Token(TokenScope::Full)) => {}
+        Ok(_) => {
+          respond_auth_error(request, AuthError::OutOfScope);
+          return;
+        }
+        Err(err) => {
+          respond_auth_error(request, err);
+          return;
+        }
+      }
+      let params = parse_query(query);
+      let id = params.get("request").map(String::as_str).unwrap_or_default();
+      let collected = match state.bridge_sessions.lock() {
+        Ok(mut sessions) => sessions.collect(id),
+        Err(_) => Collect::Unknown,
+      };
+      match collected {
+        Collect::Pending => respond_json(request, StatusCode(202), json!({ "status": "pending" })),
+        Collect::Ready(session) => respond_json(
+          request,
+          StatusCode(200),
+          json!({
+            "status": "approved",
+            "token": session.token,
+            "expires_at": session.expires_at,
+          }),
+        ),
+        Collect::Unknown => respond_json(
+          request,
+          StatusCode(404),
+          json!({ "error": "session request denied or not found", "reauthorize": true }),
+        ),
+      }
+    }
     _ => {
       respond_json(request, StatusCode(404), json!({ "error": "not found" }));
     }
   }
 }
 
+/// Body of `POST /v1/entries/{id}/totp`.
+#[derive(Deserialize)]
+struct TotpPush {
+  /// `otpauth://totp/...` URI from the site's enrollment QR code.
+  uri: String,
+  /// Overwrite a TOTP secret the entry already has.
+  #[serde(default)]
+  replace: bool,
+}
+
+/// The [`ENDPOINTS`] pattern that serves `path`, if any.
+fn route(path: &str) -> Option<&'static str> {
+  if totp_entry_id(path).is_some() {
+    return Some(TOTP_ENDPOINT);
+  }
+  ENDPOINTS.iter().copied().find(|endpoint| *endpoint == path)
+}
+
+/// The entry id in a `/v1/entries/{id}/totp` path.
+fn totp_entry_id(path: &str) -> Option<&str> {
+  path
+    .strip_prefix("/v1/entries/")?
+    .strip_suffix("/totp")
+    .filter(|id| !id.is_empty() && !id.contains('/'))
+}
+
 fn split_path_query(url: &str) -> (&str, Option<&str>) {
   match url.split_once('?') {
     Some((path, query)) => (path, Some(query)),
@@ -277,7 +1243,7 @@ fn parse_query(query: Option<&str>) -> HashMap<String, String> {
   }
 }
 
-fn normalize_host(raw: &str) -> Option<String> {
+pub(crate) fn normalize_host(raw: &str) -> Option<String> {
   let trimmed = raw.trim();
   if trimmed.is_empty() {
     return None;
@@ -292,7 +1258,7 @@ fn normalize_host(raw: &str) -> Option<String> {
     .and_then(|url| url.host_str().map(|host| host.to_lowercase()))
 }
 
-fn host_matches(entry_host: &str, target_host: &str) -> bool {
+pub(crate) fn host_matches(entry_host: &str, target_host: &str) -> bool {
   let entry = entry_host.strip_prefix("www.").unwrap_or(entry_host);
   let target = target_host.strip_prefix("www.").unwrap_or(target_host);
   if entry == target {
@@ -301,6 +1267,37 @@ fn host_matches(entry_host: &str, target_host: &str) -> bool {
   target.ends_with(&format!(".{entry}"))
 }
 
+/// True if `host` is one of `exclusions` or a subdomain of one.
+pub(crate) fn is_autofill_excluded(exclusions: &[String], host: &str) -> bool {
+  exclusions.iter().any(|excluded| host_matches(excluded, host))
+}
+
+/// Checks the autofill deny-list in settings; a poisoned lock counts as
+/// excluded.
+fn is_excluded(state: &AppState, host: &str) -> bool {
+  match state.settings.lock() {
+    Ok(settings) => is_autofill_excluded(&settings.autofill_exclusions, host),
+    Err(_) => true,
+  }
+}
+
+/// Secrets are withheld while the desktop app is in presentation mode.
+fn respond_presenting(request: Request) {
+  respond_json(
+    request,
+    StatusCode(423),
+    json!({ "error": "presentation mode is on", "presenting": true }),
+  );
+}
+
+fn respond_excluded(request: Request) {
+  respond_json(
+    request,
+    StatusCode(403),
+    json!({ "error": "autofill is turned off for this site", "excluded": true }),
+  );
+}
+
 fn is_locked(state: &AppState) -> bool {
   match state.session.lock() {
     Ok(guard) => guard.is_none(),
@@ -308,23 +1305,301 @@ fn is_locked(state: &AppState) -> bool {
   }
 }
 
-#[derive(Debug)]
+/// Identifies the process behind the connection (see [`peer`]), through
+/// `relayed` for the Unix socket, and rejects it, returning it as the error,
+/// unless it is a known browser or listed in
+/// [`ExtensionConfig::allowed_processes`]. A client that cannot be
+/// identified is rejected without a process, except on platforms where no
+/// client can be, which rely on the origin check alone.
+fn verify_client(
+  state: &AppState,
+  relayed: Option<&Relayed>,
+  request: &Request,
+) -> Result<Option<PeerProcess>, Option<PeerProcess>> {
+  if !peer::SUPPORTED {
+    return Ok(None);
+  }
+  let (port, allowed) = state
+    .extension_config
+    .lock()
+    .map(|config| (config.port, config.allowed_processes.clone()))
+    .map_err(|_| None)?;
+  let client = request.remote_addr().ok_or(None)?;
+  let process = match relayed {
+    Some(relayed) => relayed
+      .lock()
+      .ok()
+      .and_then(|clients| clients.get(&client.port()).cloned().flatten()),
+    None => peer::identify(*client, port),
+  };
+  match process {
+    Some(process) if process.is_trusted(&allowed) => Ok(Some(process)),
+    process => Err(process),
+  }
+}
+
+/// True if the bridge is served through a [`Relay`] and `request` did not
+/// come through it: the loopback server behind the relay is reachable by
+/// any local process, and only the relay's own connections may use it.
+fn bypasses_relay(relayed: Option<&Relayed>, request: &Request) -> bool {
+  let Some(relayed) = relayed else {
+    return false;
+  };
+  let Some(client) = request.remote_addr() else {
+    return true;
+  };
+  !client.ip().is_loopback() || !relayed.lock().is_ok_and(|clients| clients.contains_key(&client.port()))
+}
+
+fn respond_client_rejected(state: &AppState, request: Request, process: Option<PeerProcess>) {
+  let name = process.map(|process| process.name);
+  audit::record(
+    state,
+    AuditEvent::new(AuditKind::ExtensionClientRejected).client(Some(name.as_deref().unwrap_or("unknown"))),
+  );
+  let error = match name {
+    Some(_) => "client is not a known browser; allow it in extension.json",
+    None => "client process could not be identified",
+  };
+  respond_json(request, StatusCode(403), json!({ "error": error, "process": name }));
+}
+
+/// Checks that the client's origin was approved from the desktop app.
+///
+/// Unapproved origins are queued once (see [`queue_origin`]), with the
+/// `process` that asked, and announced via [`ORIGIN_APPROVAL_EVENT`]; the
+/// rejected origin is returned as the error.
+fn ensure_origin_approved(
+  app: &AppHandle,
+  state: &AppState,
+  request: &Request,
+  process: Option<&PeerProcess>,
+) -> Result<(), String> {
+  let origin = request_origin(request);
+
+  let approved = match state.extension_config.lock() {
+    Ok(config) => config.is_origin_approved(&origin),
+    Err(_) => false,
+  };
+  if approved {
+    return Ok(());
+  }
+
+  let queued = match state.pending_origins.lock() {
+    Ok(mut pending) => queue_origin(&mut pending, &origin, process, Utc::now()),
+    Err(_) => None,
+  };
+  if let Some(request) = queued {
+    let _ = app.emit(ORIGIN_APPROVAL_EVENT, request);
+  }
+  Err(origin)
+}
+
+/// Adds `origin` to the approval queue unless it is already waiting, and
+/// returns the new request. Expired requests are dropped first, and the
+/// oldest once the queue is full, so clients cannot grow it without bound.
+fn queue_origin(
+  pending: &mut Vec<PendingOrigin>,
+  origin: &str,
+  process: Option<&PeerProcess>,
+  now: DateTime<Utc>,
+) -> Option<PendingOrigin> {
+  prune_pending_origins(pending, now);
+  if pending.iter().any(|p| p.origin == origin) {
+    return None;
+  }
+  if pending.len() >= MAX_PENDING_ORIGINS {
+    pending.remove(0);
+  }
+  let request = PendingOrigin {
+    origin: origin.to_string(),
+    process: process.cloned(),
+    requested_at: now,
+  };
+  pending.push(request.clone());
+  Some(request)
+}
+
+/// Drops origins that have waited longer than [`PENDING_ORIGIN_TTL`].
+pub(crate) fn prune_pending_origins(pending: &mut Vec<PendingOrigin>, now: DateTime<Utc>) {
+  pending.retain(|p| now - p.requested_at < PENDING_ORIGIN_TTL);
+}
+
+/// A secret handed to the extension, as shown in the desktop app.
+#[derive(Clone, Debug, Serialize)]
+pub struct AutofillEvent {
+  pub entry_id: String,
+  pub title: String,
+  /// `"password"` or `"identity"`.
+  pub field: &'static str,
+  /// Browser (or other client) that asked, when it could be identified.
+  pub client: Option<String>,
+  pub origin: String,
+  pub at: DateTime<Utc>,
+}
+
+/// Tells the desktop app, and the OS if the notification policy opts in,
+/// that `field` of an entry was filled.
+fn announce_fill(
+  app: &AppHandle,
+  state: &AppState,
+  request: &Request,
+  entry_id: &str,
+  title: &str,
+  field: &'static str,
+  client: Option<&PeerProcess>,
+) {
+  let client = client.map(|p| p.display_name().to_string());
+  let what = if field == "password" { "Password" } else { "Identity details" };
+  let summary = format!("{what} filled in {}.", client.as_deref().unwrap_or("the browser"));
+  let _ = app.emit(
+    AUTOFILL_EVENT,
+    AutofillEvent {
+      entry_id: entry_id.to_string(),
+      title: title.to_string(),
+      field,
+      client,
+      origin: request_origin(request),
+      at: Utc::now(),
+    },
+  );
+  notifications::notify(app, state, Notice::new(NoticeKind::Autofill, summary).titles(vec![title.to_string()]));
+}
+
+/// The client's `Origin` header, or [`NO_ORIGIN`].
+fn request_origin(request: &Request) -> String {
+  header_value(request, "Origin")
+    .map(|value| value.trim().to_string())
+    .filter(|value| !value.is_empty())
+    .unwrap_or_else(|| NO_ORIGIN.to_string())
+}
+
+fn respond_origin_not_approved(request: Request, origin: String) {
+  respond_json(
+    request,
+    StatusCode(403),
+    json!({
+      "error": "origin not approved; approve it in The Organizer",
+      "origin": origin,
+    }),
+  );
+}
+
+#[derive(Clone, Copy, Debug)]
 enum AuthError {
   Disabled,
   Missing,
   Invalid,
+  /// A valid token whose scope does not cover the endpoint.
+  OutOfScope,
+  /// The pairing token was used where sessions are required.
+  SessionRequired,
+  SessionExpired,
 }
 
-fn ensure_authorized(state: &AppState, request: &Request) -> Result<(), AuthError> {
-  let config = state.extension_config.lock().map_err(|_| AuthError::Disabled)?;
+/// What an authorized request presented.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Access {
+  Token(TokenScope),
+  /// A desktop-approved session token.
+  Session,
+}
+
+/// Checks that the bridge is enabled (applying its auto-disable policy
+/// first) and the request carries a token or session whose scope covers
+/// `required`, and records the use.
+fn ensure_authorized(
+  app: &AppHandle,
+  state: &AppState,
+  request: &Request,
+  required: TokenScope,
+) -> Result<Access, AuthError> {
+  enforce_auto_disable(app, state);
+  let mut config = state.extension_config.lock().map_err(|_| AuthError::Disabled)?;
   if !config.enabled {
     return Err(AuthError::Disabled);
   }
   let token = request_token(request).ok_or(AuthError::Missing)?;
-  if token != config.token {
-    return Err(AuthError::Invalid);
+  let now = Utc::now();
+  let access = match config.token_scope(&token) {
+    Some(scope) => Access::Token(scope),
+    None => match state.bridge_sessions.lock().map(|sessions| sessions.check(&token, now)) {
+      Ok(SessionCheck::Valid) => Access::Session,
+      Ok(SessionCheck::Expired) => return Err(AuthError::SessionExpired),
+      _ => return Err(AuthError::Invalid),
+    },
+  };
+  let scope = match access {
+    // While sessions are required, the pairing token only opens them.
+    Access::Token(TokenScope::Full) if config.session_hours.is_some() => TokenScope::Status,
+    Access::Token(scope) => scope,
+    Access::Session => TokenScope::Full,
+  };
+  if scope < required {
+    return Err(match access {
+      Access::Token(TokenScope::Full) => AuthError::SessionRequired,
+      _ => AuthError::OutOfScope,
+    });
   }
-  Ok(())
+
+  if config.last_used_at.is_none_or(|at| now - at >= USE_RECORD_INTERVAL) {
+    config.last_used_at = Some(now);
+    let snapshot = config.clone();
+    drop(config);
+    if let Err(err) = save_config(app, &snapshot) {
+      eprintln!("extension server: failed to record use: {err}");
+    }
+  }
+  Ok(access)
+}
+
+/// Why `config` calls for the bridge to turn itself off at `now`, given how
+/// long the vault has been locked (`None` while unlocked).
+fn auto_disable_reason(
+  config: &ExtensionConfig,
+  now: DateTime<Utc>,
+  locked_for: Option<Duration>,
+) -> Option<AutoDisableReason> {
+  if !config.enabled {
+    return None;
+  }
+  if let (Some(days), Some(last_used)) = (config.disable_after_idle_days, config.last_used_at) {
+    if now - last_used >= chrono::Duration::days(i64::from(days)) {
+      return Some(AutoDisableReason::Unused);
+    }
+  }
+  if let (Some(minutes), Some(locked_for)) = (config.disable_after_locked_minutes, locked_for) {
+    if locked_for >= Duration::from_secs(u64::from(minutes) * 60) {
+      return Some(AutoDisableReason::LockedTooLong);
+    }
+  }
+  None
+}
+
+/// Turns the bridge off if its auto-disable policy says so, saving the
+/// config and emitting [`AUTO_DISABLED_EVENT`]. It stays off until it is
+/// re-enabled from the desktop app. Called before every request and by the
+/// app's periodic checks.
+pub fn enforce_auto_disable(app: &AppHandle, state: &AppState) {
+  let locked_for = if is_locked(state) {
+    state.locked_at.lock().ok().map(|at| at.elapsed())
+  } else {
+    None
+  };
+  let Ok(mut config) = state.extension_config.lock() else {
+    return;
+  };
+  let Some(reason) = auto_disable_reason(&config, Utc::now(), locked_for) else {
+    return;
+  };
+  config.enabled = false;
+  config.auto_disabled = Some(reason);
+  let snapshot = config.clone();
+  drop(config);
+  if let Err(err) = save_config(app, &snapshot) {
+    eprintln!("extension server: failed to save auto-disable: {err}");
+  }
+  let _ = app.emit(AUTO_DISABLED_EVENT, reason);
 }
 
 fn request_token(request: &Request) -> Option<String> {
@@ -362,8 +1637,16 @@ fn respond_auth_error(request: Request, err: AuthError) {
     AuthError::Disabled => (StatusCode(423), "extension disabled"),
     AuthError::Missing => (StatusCode(401), "missing token"),
     AuthError::Invalid => (StatusCode(401), "invalid token"),
+    AuthError::OutOfScope => (StatusCode(403), "token scope does not allow this request"),
+    AuthError::SessionRequired => (StatusCode(401), "session required"),
+    AuthError::SessionExpired => (StatusCode(401), "session expired"),
   };
-  respond_json(request, status, json!({ "error": message }));
+  if matches!(err, AuthError::SessionRequired | AuthError::SessionExpired) {
+    // The extension asks the user to reauthorize through `/v1/session`.
+    respond_json(request, status, json!({ "error": message, "reauthorize": true }));
+  } else {
+    respond_json(request, status, json!({ "error": message }));
+  }
 }
 
 fn respond_json(request: Request, status: StatusCode, body: serde_json::Value) {
@@ -386,3 +1669,156 @@ fn respond_json(request: Request, status: StatusCode, body: serde_json::Value) {
 fn header(name: &str, value: &str) -> Header {
   Header::from_bytes(name, value).unwrap()
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::models::ScopedToken;
+  use std::io::{Read, Write};
+  use std::net::TcpStream;
+  use std::time::Instant;
+
+  #[test]
+  fn stalled_clients_are_disconnected() {
+    let server = bind_tcp(SocketAddr::from(([127, 0, 0, 1], 0)), Duration::from_millis(200)).unwrap();
+    let address = server.server_addr().to_ip().unwrap();
+
+    let mut client = TcpStream::connect(address).unwrap();
+    client.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    client.write_all(b"GET /v1/status HTTP/1.1\r\nHost: 127.0.0.1\r\n").unwrap();
+    let started = Instant::now();
+    let _ = client.read_to_end(&mut Vec::new());
+    assert!(started.elapsed() < Duration::from_secs(2));
+  }
+
+  #[test]
+  fn scoped_tokens_never_reach_secrets() {
+    let mut config = ExtensionConfig::new();
+    config.scoped_tokens.push(ScopedToken {
+      token: "automation".into(),
+      scope: TokenScope::ReadEntries,
+      label: "Keyboard Maestro".into(),
+      created_at: Utc::now(),
+    });
+    let main = config.token.clone();
+    assert_eq!(config.token_scope(&main), Some(TokenScope::Full));
+    assert_eq!(config.token_scope("automation"), Some(TokenScope::ReadEntries));
+    assert_eq!(config.token_scope("other"), None);
+    assert!(TokenScope::ReadEntries >= TokenScope::Status);
+    assert!(TokenScope::ReadEntries < TokenScope::Full);
+  }
+
+  #[test]
+  fn capabilities_list_the_routed_endpoints() {
+    let capabilities = Capabilities::current(false);
+    for endpoint in capabilities.endpoints {
+      let path = endpoint.replace("{id}", "entry-1");
+      assert_eq!(route(&path), Some(*endpoint), "{path} is not routed");
+    }
+    assert_eq!(totp_entry_id("/v1/entries/entry-1/totp"), Some("entry-1"));
+    assert_eq!(route("/v1/entries/a/b/totp"), None);
+    assert_eq!(route("/v1/nope"), None);
+
+    let features = capabilities.features;
+    assert!(features.totp && features.identities && !features.save && !features.sessions);
+    assert!(Capabilities::current(true).features.sessions);
+  }
+
+  #[test]
+  fn pending_origins_are_deduplicated_capped_and_expire() {
+    let now = Utc::now();
+    let mut pending = Vec::new();
+    assert!(queue_origin(&mut pending, "moz-extension://a", None, now).is_some());
+    assert!(queue_origin(&mut pending, "moz-extension://a", None, now).is_none());
+    assert_eq!(pending.len(), 1);
+
+    for i in 0..MAX_PENDING_ORIGINS {
+      queue_origin(&mut pending, &format!("chrome-extension://{i}"), None, now);
+    }
+    assert_eq!(pending.len(), MAX_PENDING_ORIGINS);
+    assert!(pending.iter().all(|p| p.origin != "moz-extension://a"));
+
+    let later = now + PENDING_ORIGIN_TTL;
+    let request = queue_origin(&mut pending, "chrome-extension://0", None, later).expect("queued again");
+    assert_eq!(request.requested_at, later);
+    assert_eq!(pending.len(), 1);
+  }
+
+  #[test]
+  fn exclusions_cover_subdomains_only() {
+    let exclusions = ["bank.example".to_string(), "sso.corp.example".to_string()];
+    assert!(is_autofill_excluded(&exclusions, "bank.example"));
+    assert!(is_autofill_excluded(&exclusions, "www.bank.example"));
+    assert!(is_autofill_excluded(&exclusions, "login.bank.example"));
+    assert!(!is_autofill_excluded(&exclusions, "mybank.example"));
+    assert!(!is_autofill_excluded(&exclusions, "corp.example"));
+  }
+
+  #[test]
+  fn auto_disables_when_unused_or_locked_too_long() {
+    let now = Utc::now();
+    let mut config = ExtensionConfig {
+      enabled: true,
+      last_used_at: Some(now - chrono::Duration::days(3)),
+      ..ExtensionConfig::new()
+    };
+    let locked = |minutes: u64| Some(Duration::from_secs(minutes * 60));
+    assert_eq!(auto_disable_reason(&config, now, locked(600)), None);
+
+    config.disable_after_idle_days = Some(7);
+    config.disable_after_locked_minutes = Some(60);
+    assert_eq!(auto_disable_reason(&config, now, None), None);
+    assert_eq!(auto_disable_reason(&config, now, locked(59)), None);
+    assert_eq!(auto_disable_reason(&config, now, locked(60)), Some(AutoDisableReason::LockedTooLong));
+    assert_eq!(
+      auto_disable_reason(&config, now + chrono::Duration::days(4), None),
+      Some(AutoDisableReason::Unused)
+    );
+
+    config.enabled = false;
+    assert_eq!(auto_disable_reason(&config, now, locked(600)), None);
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn socket_is_private_to_the_user() {
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    let dir = std::env::temp_dir().join(format!("the-organizer-bridge-{}", std::process::id()));
+    let path = dir.join(SOCKET_FILENAME);
+    // A socket file left behind by an earlier run is replaced.
+    fs::create_dir_all(&dir).unwrap();
+    drop(UnixListener::bind(&path).unwrap());
+
+    let (server, relay) = bind_socket(&path, Duration::from_secs(1)).unwrap();
+    let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+    assert_eq!((mode(&dir), mode(&path)), (0o700, 0o600));
+    assert!(bind_socket(&path, Duration::from_secs(1)).is_err());
+
+    let mut client = UnixStream::connect(&path).unwrap();
+    client.write_all(b"GET /v1/nope HTTP/1.1\r\n\r\n").unwrap();
+    let request = server.recv().unwrap();
+    assert_eq!(request.url(), "/v1/nope");
+    // On Linux the request arrives through the relay, which knows the
+    // process that connected: this test.
+    if let Some(relay) = relay {
+      let port = request.remote_addr().unwrap().port();
+      {
+        let clients = relay.clients.lock().unwrap();
+        assert_eq!(
+          clients.get(&port).and_then(|process| process.as_ref()).map(|process| process.pid),
+          Some(std::process::id())
+        );
+      }
+      assert!(!bypasses_relay(Some(&relay.clients), &request));
+
+      // The loopback server behind the relay refuses direct connections.
+      let mut direct = std::net::TcpStream::connect(server.server_addr().to_ip().unwrap()).unwrap();
+      direct.write_all(b"GET /v1/status HTTP/1.1\r\n\r\n").unwrap();
+      let request = server.recv().unwrap();
+      assert!(bypasses_relay(Some(&relay.clients), &request));
+    }
+    fs::remove_dir_all(dir).unwrap();
+  }
+}
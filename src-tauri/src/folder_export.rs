@@ -0,0 +1,255 @@
+//! Folders exported to their own password-protected vault files.
+//!
+//! A folder (e.g. "Shared with spouse") can be exported as a portable vault
+//! file locked with a password of its own, for someone who should see only
+//! that folder. The key is derived from that password once and kept in
+//! `meta.folder_exports` inside the encrypted vault, so the file can be
+//! written again on request, or every few days, without asking for the
+//! password. Each export replaces the file atomically under the same vault
+//! id, so the recipient keeps opening the same vault with fresh contents.
+//! Scheduled exports run from the inactivity monitor while the vault is
+//! unlocked.
+
+use crate::backup::write_atomically;
+use crate::commands;
+use crate::models::{AppState, Entry, ExportKey, FolderExport, VaultData, VaultMeta};
+use crate::notifications::{self, Notice, NoticeKind};
+use crate::persist::Changes;
+use crate::sync::{from_hex, to_hex};
+use crate::vault::{self, CipherSuite, VaultHeader};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::path::Path;
+use tauri::AppHandle;
+use zeroize::Zeroizing;
+
+/// Longest schedule accepted, in days.
+pub const MAX_EVERY_DAYS: u32 = 365;
+
+/// Wait before retrying a failed scheduled export.
+const RETRY_AFTER_MINUTES: i64 = 60;
+
+/// A folder export as shown to the frontend, without its key.
+#[derive(Clone, Debug, Serialize)]
+pub struct FolderExportStatus {
+  pub id: String,
+  pub folder: String,
+  pub path: String,
+  pub every_days: Option<u32>,
+  pub last_run: Option<DateTime<Utc>>,
+  pub last_error: Option<String>,
+  pub next_run: Option<DateTime<Utc>>,
+}
+
+impl FolderExportStatus {
+  pub fn new(export: &FolderExport, now: DateTime<Utc>) -> Self {
+    Self {
+      id: export.id.clone(),
+      folder: export.folder.clone(),
+      path: export.path.clone(),
+      every_days: export.every_days,
+      last_run: export.last_run,
+      last_error: export.last_error.clone(),
+      next_run: next_run(export, now),
+    }
+  }
+}
+
+pub fn validate_every_days(every_days: Option<u32>) -> Result<(), String> {
+  match every_days {
+    Some(days) if days == 0 || days > MAX_EVERY_DAYS => {
+      Err(format!("re-export every 1 to {MAX_EVERY_DAYS} days"))
+    }
+    _ => Ok(()),
+  }
+}
+
+/// When `export` is next due: right away if it never ran, an hour after a
+/// failure, otherwise `every_days` after the last run. `None` if it is not
+/// scheduled.
+pub fn next_run(export: &FolderExport, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+  let days = export.every_days?;
+  let Some(last_run) = export.last_run else {
+    return Some(now);
+  };
+  Some(match export.last_error {
+    Some(_) => last_run + Duration::minutes(RETRY_AFTER_MINUTES),
+    None => last_run + Duration::days(i64::from(days)),
+  })
+}
+
+/// Derives the key of a new export file from its password.
+pub fn new_key(password: &str, cipher: CipherSuite) -> Result<ExportKey, String> {
+  let header = VaultHeader::new(cipher);
+  let key = Zeroizing::new(header.derive_key(password).map_err(|e| format!("kdf: {:?}", e))?);
+  Ok(export_key(&header, &key))
+}
+
+/// `key` and the KDF details of `header`, to keep in the vault.
+pub(crate) fn export_key(header: &VaultHeader, key: &[u8; 32]) -> ExportKey {
+  ExportKey {
+    cipher: header.cipher,
+    kdf: header.kdf.clone(),
+    salt: to_hex(&header.salt),
+    key: to_hex(key),
+  }
+}
+
+/// A header and key that seal a file openable with the password `key` was
+/// derived from.
+pub(crate) fn sealing(key: &ExportKey) -> Result<(VaultHeader, Zeroizing<[u8; 32]>), String> {
+  let mut header = VaultHeader::with_kdf(key.cipher, key.kdf.clone());
+  header.salt = from_hex(&key.salt)?;
+  Ok((header, Zeroizing::new(from_hex::<32>(&key.key)?)))
+}
+
+/// Seals the entries filed in the export's folder as a vault of their own,
+/// with linked passwords copied in. Returns the file and its entry count.
+pub fn seal(data: &VaultData, export: &FolderExport) -> Result<(Vec<u8>, usize), String> {
+  let mut entries: Vec<Entry> = data
+    .entries
+    .iter()
+    .filter(|e| e.folder.as_deref() == Some(export.folder.as_str()))
+    .cloned()
+    .collect();
+  for entry in &mut entries {
+    if let Some(target) = entry.linked_to.take() {
+      entry.password = data.find_entry(&target).map(|t| t.password.clone()).unwrap_or_default();
+    }
+  }
+  let count = entries.len();
+
+  let (header, key) = sealing(&export.key)?;
+  let copy = VaultData {
+    meta: VaultMeta {
+      id: export.id.clone(),
+      ..VaultMeta::new()
+    },
+    entries,
+    trash: Vec::new(),
+    tombstones: Vec::new(),
+    legacy_payload: false,
+  };
+  let bytes = vault::seal(&copy, &header, &key).map_err(|e| format!("seal: {:?}", e))?;
+  Ok((bytes, count))
+}
+
+/// Writes the export with id `id` and records the outcome in it. Returns
+/// the number of entries written.
+pub fn run(data: &mut VaultData, id: &str, now: DateTime<Utc>) -> Result<usize, String> {
+  let index = data
+    .meta
+    .folder_exports
+    .iter()
+    .position(|e| e.id == id)
+    .ok_or_else(|| "folder export not found".to_string())?;
+  let export = data.meta.folder_exports[index].clone();
+  let result = seal(data, &export).and_then(|(bytes, count)| {
+    write_atomically(Path::new(&export.path), &bytes).map_err(|e| format!("write {} failed: {e}", export.path))?;
+    Ok(count)
+  });
+  let record = &mut data.meta.folder_exports[index];
+  record.last_run = Some(now);
+  record.last_error = result.as_ref().err().cloned();
+  result
+}
+
+/// Writes every scheduled export that is due. Called by the inactivity
+/// monitor while the vault is unlocked.
+pub fn maybe_run(state: &AppState, app: &AppHandle) {
+  let Some(store) = state.current_store() else {
+    return;
+  };
+  // Lock order: session, then vault.
+  let Ok(session_guard) = state.session.lock() else {
+    return;
+  };
+  let Ok(mut vault_guard) = state.vault.lock() else {
+    return;
+  };
+  let (Some(session), Some(data)) = (session_guard.as_ref(), vault_guard.as_mut()) else {
+    return;
+  };
+  let now = Utc::now();
+  let due: Vec<(String, String)> = data
+    .meta
+    .folder_exports
+    .iter()
+    .filter(|e| next_run(e, now).is_some_and(|at| at <= now))
+    .map(|e| (e.id.clone(), e.folder.clone()))
+    .collect();
+  if due.is_empty() {
+    return;
+  }
+
+  let mut failed = Vec::new();
+  for (id, folder) in due {
+    if let Err(err) = run(data, &id, now) {
+      eprintln!("folder export: {err}");
+      failed.push(folder);
+    }
+  }
+  commands::persist_changes(Some(app), state, store.as_ref(), data, session, Changes::All);
+  drop(vault_guard);
+  drop(session_guard);
+
+  if !failed.is_empty() {
+    let summary = match failed.len() {
+      1 => "A folder could not be re-exported.".to_string(),
+      n => format!("{n} folders could not be re-exported."),
+    };
+    notifications::notify(app, state, Notice::new(NoticeKind::ExportFailure, summary).titles(failed));
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn seals_only_the_folder_under_its_own_key() {
+    let mut shared = Entry::new("Bank".into(), "us".into(), String::new(), String::new(), String::new());
+    shared.folder = Some("Shared with spouse".into());
+    let mut private = Entry::new("Mail".into(), "me".into(), "hunter2".into(), String::new(), String::new());
+    private.folder = None;
+    shared.linked_to = Some(private.id.clone());
+    let data = VaultData {
+      meta: VaultMeta::new(),
+      entries: vec![shared, private],
+      trash: Vec::new(),
+      tombstones: Vec::new(),
+      legacy_payload: false,
+    };
+
+    let mut header = VaultHeader::new(CipherSuite::default());
+    header.kdf.memory_kib = 64;
+    header.kdf.iterations = 1;
+    let key = header.derive_key("spouse password").unwrap();
+    let export = FolderExport {
+      id: "f6a1c1c2-0000-4000-8000-000000000001".into(),
+      folder: "Shared with spouse".into(),
+      path: "/tmp/shared.vault".into(),
+      every_days: Some(7),
+      key: export_key(&header, &key),
+      last_run: None,
+      last_error: None,
+    };
+
+    let (bytes, count) = seal(&data, &export).unwrap();
+    assert_eq!(count, 1);
+    let (opened, _, _) = vault::open(&bytes, "spouse password").unwrap();
+    assert_eq!(opened.meta.id, export.id);
+    assert_eq!(opened.entries.len(), 1);
+    assert_eq!(opened.entries[0].password, "hunter2", "linked password is copied in");
+    assert_eq!(opened.entries[0].linked_to, None);
+    assert!(vault::open(&bytes, "master password").is_err());
+
+    let now = Utc::now();
+    assert_eq!(next_run(&export, now), Some(now));
+    let ran = FolderExport {
+      last_run: Some(now),
+      ..export
+    };
+    assert_eq!(next_run(&ran, now), Some(now + Duration::days(7)));
+  }
+}
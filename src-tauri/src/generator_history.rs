@@ -0,0 +1,241 @@
+//! Recently generated passwords, so one typed into a sign-up form is not
+//! lost when its entry is never saved.
+//!
+//! The frontend records each password it generates with
+//! `record_generated_password`. The history keeps the newest
+//! [`GeneratorHistoryPolicy::size`] of them sealed with XChaCha20-Poly1305
+//! under a random key that exists only in memory, so they do not sit in
+//! memory as plaintext between uses. Listing returns labels and times only;
+//! `copy_generated` opens one straight into the clipboard.
+//!
+//! The history and its key are dropped on lock. With
+//! [`GeneratorHistoryPolicy::persist`], the passwords are also kept in the
+//! vault metadata, encrypted with the rest of the vault, and restored on the
+//! next unlock.
+//!
+//! Lock order: vault → generator history.
+
+use crate::models::GeneratedPassword;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use zeroize::Zeroizing;
+
+/// Most passwords the history can be set to keep.
+pub const MAX_HISTORY_SIZE: usize = 50;
+
+/// Longest label kept with a password, in characters.
+pub const MAX_LABEL_CHARS: usize = 200;
+
+const NONCE_LEN: usize = 24;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GeneratorHistoryPolicy {
+  /// Passwords kept, newest first; 0 turns the history off.
+  pub size: usize,
+  /// Keep the history in the vault across locks.
+  pub persist: bool,
+}
+
+impl Default for GeneratorHistoryPolicy {
+  fn default() -> Self {
+    Self { size: 10, persist: false }
+  }
+}
+
+impl GeneratorHistoryPolicy {
+  pub fn validate(&self) -> Result<(), String> {
+    if self.size > MAX_HISTORY_SIZE {
+      return Err(format!("the generator history keeps at most {MAX_HISTORY_SIZE} passwords"));
+    }
+    Ok(())
+  }
+}
+
+/// A history item as listed to the frontend, without the password.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct GeneratedInfo {
+  /// Position in the history, 0 for the newest; passed to `copy_generated`.
+  pub index: usize,
+  pub label: Option<String>,
+  pub generated_at: DateTime<Utc>,
+  /// Length in characters.
+  pub length: usize,
+}
+
+struct Sealed {
+  /// `[24B nonce][ciphertext+tag]`.
+  sealed: Vec<u8>,
+  label: Option<String>,
+  generated_at: DateTime<Utc>,
+  length: usize,
+}
+
+pub struct GeneratorHistory {
+  key: Zeroizing<[u8; 32]>,
+  /// Newest first.
+  items: VecDeque<Sealed>,
+  /// Unlock generation the history belongs to, once it has been restored
+  /// from the vault (see [`GeneratorHistory::needs_restore`]).
+  restored_for: Option<u64>,
+}
+
+impl Default for GeneratorHistory {
+  fn default() -> Self {
+    let mut key = Zeroizing::new([0u8; 32]);
+    rand::thread_rng().fill_bytes(key.as_mut());
+    Self {
+      key,
+      items: VecDeque::new(),
+      restored_for: None,
+    }
+  }
+}
+
+impl GeneratorHistory {
+  fn cipher(&self) -> XChaCha20Poly1305 {
+    XChaCha20Poly1305::new(self.key.as_slice().into())
+  }
+
+  /// Adds `password` as the newest item, keeping at most `size`.
+  pub fn push(
+    &mut self,
+    password: &str,
+    label: Option<String>,
+    generated_at: DateTime<Utc>,
+    size: usize,
+  ) -> Result<(), String> {
+    if size == 0 {
+      return Ok(());
+    }
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let ciphertext = self
+      .cipher()
+      .encrypt(XNonce::from_slice(&nonce), password.as_bytes())
+      .map_err(|_| "generator history: encryption failed".to_string())?;
+    let mut sealed = nonce.to_vec();
+    sealed.extend_from_slice(&ciphertext);
+    let label = label
+      .map(|l| l.trim().chars().take(MAX_LABEL_CHARS).collect::<String>())
+      .filter(|l| !l.is_empty());
+    self.items.push_front(Sealed {
+      sealed,
+      label,
+      generated_at,
+      length: password.chars().count(),
+    });
+    self.items.truncate(size);
+    Ok(())
+  }
+
+  pub fn list(&self) -> Vec<GeneratedInfo> {
+    self
+      .items
+      .iter()
+      .enumerate()
+      .map(|(index, item)| GeneratedInfo {
+        index,
+        label: item.label.clone(),
+        generated_at: item.generated_at,
+        length: item.length,
+      })
+      .collect()
+  }
+
+  /// The password at `index` (0 for the newest).
+  pub fn open(&self, index: usize) -> Result<Zeroizing<String>, String> {
+    let item = self
+      .items
+      .get(index)
+      .ok_or_else(|| "generated password not found".to_string())?;
+    let (nonce, ciphertext) = item.sealed.split_at(NONCE_LEN);
+    let plain = Zeroizing::new(
+      self
+        .cipher()
+        .decrypt(XNonce::from_slice(nonce), ciphertext)
+        .map_err(|_| "generator history: decryption failed".to_string())?,
+    );
+    let text = std::str::from_utf8(&plain).map_err(|_| "generator history: invalid password".to_string())?;
+    Ok(Zeroizing::new(text.to_string()))
+  }
+
+  pub fn truncate(&mut self, size: usize) {
+    self.items.truncate(size);
+  }
+
+  /// Drops every item and replaces the key.
+  pub fn clear(&mut self) {
+    *self = Self::default();
+  }
+
+  /// Whether the history has yet to be restored from the vault for unlock
+  /// `generation`.
+  pub fn needs_restore(&self, generation: u64) -> bool {
+    self.restored_for != Some(generation)
+  }
+
+  /// Replaces the history with `saved` (newest first) for unlock
+  /// `generation`.
+  pub fn restore(&mut self, saved: &[GeneratedPassword], generation: u64, size: usize) -> Result<(), String> {
+    self.clear();
+    for item in saved.iter().take(size).rev() {
+      self.push(&item.password, item.label.clone(), item.generated_at, size)?;
+    }
+    self.restored_for = Some(generation);
+    Ok(())
+  }
+
+  /// The history in plaintext, newest first, for the vault metadata.
+  pub fn export(&self) -> Result<Vec<GeneratedPassword>, String> {
+    self
+      .items
+      .iter()
+      .enumerate()
+      .map(|(index, item)| {
+        Ok(GeneratedPassword {
+          password: self.open(index)?.to_string(),
+          label: item.label.clone(),
+          generated_at: item.generated_at,
+        })
+      })
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn keeps_the_newest_passwords_sealed() {
+    let now = Utc::now();
+    let mut history = GeneratorHistory::default();
+    for n in 0..4 {
+      history.push(&format!("pw-{n}"), Some(format!("site {n}")), now, 3).unwrap();
+    }
+    let listed = history.list();
+    assert_eq!(listed.len(), 3);
+    assert_eq!((listed[0].index, listed[0].label.as_deref(), listed[0].length), (0, Some("site 3"), 4));
+    assert_eq!(history.open(2).unwrap().as_str(), "pw-1");
+    assert!(history.open(3).is_err());
+    assert!(!history.items[0].sealed.windows(4).any(|w| w == b"pw-3"));
+
+    // A restored history keeps its order and gets a fresh key.
+    let saved = history.export().unwrap();
+    let mut restored = GeneratorHistory::default();
+    assert!(restored.needs_restore(7));
+    restored.restore(&saved, 7, 2).unwrap();
+    assert!(!restored.needs_restore(7));
+    assert_eq!(restored.open(0).unwrap().as_str(), "pw-3");
+    assert_eq!(restored.list().len(), 2);
+
+    history.push("ignored", None, now, 0).unwrap();
+    assert_eq!(history.list().len(), 3);
+    assert!(GeneratorHistoryPolicy { size: 51, persist: false }.validate().is_err());
+  }
+}
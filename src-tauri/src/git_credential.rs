@@ -0,0 +1,180 @@
+//! The git-credential protocol (`get`, `store`, `erase`) over vault entries.
+//!
+//! git writes `key=value` lines describing a remote to the helper's stdin and
+//! reads the same format back (see `git help credential`). Entries match a
+//! request when their URL has the same host, ignoring `www.` and the port,
+//! and, if git names one, the same username. The path is ignored, so one
+//! entry serves every repository on a host.
+//!
+//! Only the attributes below are read; others, such as `wwwauth[]`, are
+//! skipped. This module only applies requests to decrypted [`VaultData`];
+//! reading the vault and saving it is up to the [`crate::cli`] mode.
+
+use crate::extension::normalize_host;
+use crate::history;
+use crate::models::{Entry, EntryKind, VaultData};
+use chrono::{DateTime, Utc};
+use zeroize::Zeroizing;
+
+/// Tag given to entries added by `store`, so they can be found in the app.
+pub const GIT_TAG: &str = "git";
+
+/// A credential as described by git.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Credential {
+  pub protocol: String,
+  pub host: String,
+  pub username: Option<String>,
+  pub password: Option<Zeroizing<String>>,
+}
+
+/// Reads attributes up to the first blank line or the end of `input`.
+pub fn parse(input: &str) -> Credential {
+  let mut credential = Credential::default();
+  for line in input.lines().take_while(|line| !line.is_empty()) {
+    let Some((key, value)) = line.split_once('=') else {
+      continue;
+    };
+    match key {
+      "protocol" => credential.protocol = value.to_string(),
+      "host" => credential.host = value.to_string(),
+      "username" => credential.username = Some(value.to_string()),
+      "password" => credential.password = Some(Zeroizing::new(value.to_string())),
+      _ => {}
+    }
+  }
+  credential
+}
+
+/// Formats the answer to `get`.
+pub fn format(username: &str, password: &str) -> Zeroizing<String> {
+  Zeroizing::new(format!("username={username}\npassword={password}\n"))
+}
+
+fn bare_host(raw: &str) -> Option<String> {
+  let host = normalize_host(raw)?;
+  Some(host.strip_prefix("www.").map(str::to_string).unwrap_or(host))
+}
+
+/// Login entries matching `credential`, in vault order.
+pub fn matching<'a>(entries: &'a [Entry], credential: &Credential) -> Vec<&'a Entry> {
+  let Some(host) = bare_host(&credential.host) else {
+    return Vec::new();
+  };
+  entries
+    .iter()
+    .filter(|e| matches!(e.kind, EntryKind::Login))
+    .filter(|e| bare_host(&e.url).as_deref() == Some(host.as_str()))
+    .filter(|e| credential.username.as_deref().is_none_or(|u| e.username == u))
+    .collect()
+}
+
+/// Answers `get` with the first matching entry's username and password,
+/// following password links. `None` lets git try its next helper or prompt.
+pub fn get(data: &VaultData, credential: &Credential) -> Option<Zeroizing<String>> {
+  matching(&data.entries, credential).into_iter().find_map(|entry| {
+    let source = data.resolve_password_source(&entry.id)?;
+    (!source.password.is_empty()).then(|| format(&entry.username, &source.password))
+  })
+}
+
+/// Applies `store`: updates the matching entry's password or adds a login
+/// for the host. Returns the id of the changed entry, or `None` when nothing
+/// changed, e.g. git confirming a password it just got from `get`.
+///
+/// Entries that share another entry's password are left alone; the link is
+/// managed in the app.
+pub fn store(data: &mut VaultData, credential: &Credential, history_limit: usize) -> Option<String> {
+  let username = credential.username.as_deref()?;
+  let password = credential.password.as_ref()?;
+  if credential.host.is_empty() {
+    return None;
+  }
+  let existing = matching(&data.entries, credential).first().map(|e| e.id.clone());
+  match existing {
+    Some(id) => {
+      let entry = data.entries.iter_mut().find(|e| e.id == id)?;
+      if entry.linked_to.is_some() || entry.password == password.as_str() {
+        return None;
+      }
+      let before = entry.clone();
+      entry.password = password.to_string();
+      entry.touch();
+      history::record(entry, &before, None, history_limit);
+      Some(id)
+    }
+    None => {
+      let protocol = if credential.protocol.is_empty() { "https" } else { &credential.protocol };
+      let mut entry = Entry::new(
+        credential.host.clone(),
+        username.to_string(),
+        password.to_string(),
+        format!("{protocol}://{}", credential.host),
+        String::new(),
+      );
+      entry.tags.push(GIT_TAG.to_string());
+      let id = entry.id.clone();
+      data.entries.push(entry);
+      Some(id)
+    }
+  }
+}
+
+/// Applies `erase`: moves the matching entries to the trash, where they can
+/// be restored from the app. Needs a username, so one request never clears a
+/// whole host, and when git passes the rejected password only entries still
+/// holding it are erased. Entries other entries link to are kept. Returns
+/// how many entries were moved.
+pub fn erase(data: &mut VaultData, credential: &Credential, now: DateTime<Utc>) -> usize {
+  if credential.username.is_none() {
+    return 0;
+  }
+  let ids: Vec<String> = matching(&data.entries, credential)
+    .into_iter()
+    .filter(|e| credential.password.as_ref().is_none_or(|p| e.linked_to.is_none() && e.password == p.as_str()))
+    .filter(|e| data.entries_linking_to(&e.id).is_empty())
+    .map(|e| e.id.clone())
+    .collect();
+  ids.iter().filter(|id| data.trash_entry(id, now)).count()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn login(url: &str, username: &str, password: &str) -> Entry {
+    Entry::new("t".into(), username.into(), password.into(), url.into(), String::new())
+  }
+
+  #[test]
+  fn gets_stores_and_erases_by_host() {
+    let request = parse("protocol=https\nhost=github.com\nwwwauth[]=Basic realm=\"GitHub\"\n\nhost=ignored\n");
+    assert_eq!(request.host, "github.com");
+    assert_eq!(request.username, None);
+
+    let mut data = VaultData::default();
+    data.entries.push(login("https://gitlab.com", "me", "gl"));
+    data.entries.push(login("https://www.github.com/login", "me", "gh"));
+    assert_eq!(get(&data, &request).unwrap().as_str(), "username=me\npassword=gh\n");
+    assert!(get(&data, &parse("host=gist.github.com\n")).is_none());
+    assert!(get(&data, &parse("host=github.com\nusername=other\n")).is_none());
+
+    // Confirming the same password changes nothing; a new one is stored.
+    let same = parse("protocol=https\nhost=github.com\nusername=me\npassword=gh\n");
+    assert_eq!(store(&mut data, &same, 10), None);
+    let rotated = parse("protocol=https\nhost=github.com\nusername=me\npassword=new\n");
+    let id = store(&mut data, &rotated, 10).unwrap();
+    assert_eq!(data.find_entry(&id).unwrap().password, "new");
+    assert_eq!(data.find_entry(&id).unwrap().history.len(), 1);
+    let added = store(&mut data, &parse("protocol=https\nhost=example.com:8443\nusername=ci\npassword=tok\n"), 10).unwrap();
+    let entry = data.find_entry(&added).unwrap();
+    assert_eq!((entry.url.as_str(), entry.tags.as_slice()), ("https://example.com:8443", [GIT_TAG.to_string()].as_slice()));
+
+    let now = Utc::now();
+    assert_eq!(erase(&mut data, &parse("host=github.com\n"), now), 0);
+    assert_eq!(erase(&mut data, &parse("host=github.com\nusername=me\npassword=stale\n"), now), 0);
+    assert_eq!(erase(&mut data, &parse("host=github.com\nusername=me\npassword=new\n"), now), 1);
+    assert!(get(&data, &request).is_none());
+    assert_eq!(data.trash.len(), 1);
+  }
+}
@@ -0,0 +1,149 @@
+//! Field-level change history of entries.
+//!
+//! Each edit appends an [`EntryChange`] to `Entry::history` listing the
+//! fields it changed. Non-secret fields keep their old and new values;
+//! secrets (the password, TOTP secret, and type-specific fields such as a
+//! card number) only record whether they were set, so the history never
+//! holds a previous secret. The history travels with the entry, so merges
+//! and shared vaults carry it along with the version they keep. The number
+//! of changes kept per entry is set by `RetentionPolicy::max_history_versions`.
+
+use crate::models::{Entry, EntryChange, FieldChange};
+
+/// Fields that differ between `before` and `after`.
+pub fn diff(before: &Entry, after: &Entry) -> Vec<FieldChange> {
+  let mut fields = Vec::new();
+  let mut text = |field: &str, old: &str, new: &str| {
+    if old != new {
+      fields.push(FieldChange::Text {
+        field: field.to_string(),
+        old: old.to_string(),
+        new: new.to_string(),
+      });
+    }
+  };
+  text("title", &before.title, &after.title);
+  text("username", &before.username, &after.username);
+  text("url", &before.url, &after.url);
+  text("notes", &before.notes, &after.notes);
+  text("type", before.kind.type_name(), after.kind.type_name());
+  text("folder", before.folder.as_deref().unwrap_or(""), after.folder.as_deref().unwrap_or(""));
+  text("tags", &before.tags.join(", "), &after.tags.join(", "));
+  text("hidden", &before.hidden.to_string(), &after.hidden.to_string());
+  text(
+    "linked_to",
+    before.linked_to.as_deref().unwrap_or(""),
+    after.linked_to.as_deref().unwrap_or(""),
+  );
+
+  let mut secret = |field: &str, changed: bool, was_set: bool, is_set: bool| {
+    if changed {
+      fields.push(FieldChange::Secret {
+        field: field.to_string(),
+        was_set,
+        is_set,
+      });
+    }
+  };
+  secret(
+    "password",
+    before.password != after.password,
+    !before.password.is_empty(),
+    !after.password.is_empty(),
+  );
+  secret(
+    "totp_secret",
+    before.totp_secret != after.totp_secret,
+    before.has_totp(),
+    after.has_totp(),
+  );
+  // Type-specific fields hold secrets (card numbers, private keys); a type
+  // change is already recorded above.
+  let details_changed = before.kind.type_name() == after.kind.type_name()
+    && serde_json::to_value(&before.kind).ok() != serde_json::to_value(&after.kind).ok();
+  secret("details", details_changed, true, true);
+  fields
+}
+
+/// Appends the fields changed from `before` to `entry`'s history, under the
+/// revision and time `entry` was last touched, keeping at most `limit`
+/// changes. Records nothing if no field changed.
+pub fn record(entry: &mut Entry, before: &Entry, device: Option<&str>, limit: usize) {
+  let fields = diff(before, entry);
+  if fields.is_empty() {
+    return;
+  }
+  entry.history.push(EntryChange {
+    revision: entry.revision,
+    at: entry.updated_at,
+    device: device.map(String::from),
+    fields,
+  });
+  trim(entry, limit);
+}
+
+/// Drops the oldest changes beyond `limit`. Returns how many were dropped.
+pub fn trim(entry: &mut Entry, limit: usize) -> usize {
+  let excess = entry.history.len().saturating_sub(limit);
+  entry.history.drain(..excess);
+  excess
+}
+
+/// [`trim`]s every entry in `entries`. Returns how many changes were dropped.
+pub fn trim_all(entries: &mut [Entry], limit: usize) -> usize {
+  entries.iter_mut().map(|entry| trim(entry, limit)).sum()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn secrets_are_recorded_by_presence_only() {
+    let before = Entry::new("Bank".into(), "alice".into(), "old-secret".into(), "https://bank.example".into(), String::new());
+    let mut entry = before.clone();
+    entry.url = "https://login.bank.example".into();
+    entry.password = "new-secret".into();
+    entry.totp_secret = Some("JBSWY3DPEHPK3PXP".into());
+    entry.touch();
+    record(&mut entry, &before, Some("Laptop"), 10);
+
+    let change = &entry.history[0];
+    assert_eq!((change.revision, change.device.as_deref()), (1, Some("Laptop")));
+    assert_eq!(
+      change.fields,
+      [
+        FieldChange::Text {
+          field: "url".into(),
+          old: "https://bank.example".into(),
+          new: "https://login.bank.example".into(),
+        },
+        FieldChange::Secret {
+          field: "password".into(),
+          was_set: true,
+          is_set: true,
+        },
+        FieldChange::Secret {
+          field: "totp_secret".into(),
+          was_set: false,
+          is_set: true,
+        },
+      ]
+    );
+    let serialized = serde_json::to_string(&entry.history).unwrap();
+    assert!(!serialized.contains("old-secret") && !serialized.contains("new-secret") && !serialized.contains("JBSWY3DP"));
+
+    let unchanged = entry.clone();
+    entry.touch();
+    record(&mut entry, &unchanged, None, 10);
+    assert_eq!(entry.history.len(), 1);
+
+    let before = entry.clone();
+    entry.title = "Bank (old)".into();
+    entry.touch();
+    record(&mut entry, &before, None, 1);
+    assert_eq!(entry.history.len(), 1);
+    assert_eq!(entry.history[0].revision, 3);
+    assert_eq!(trim(&mut entry, 0), 1);
+  }
+}
@@ -0,0 +1,196 @@
+//! Opt-in hooks run when the vault is locked, unlocked, or saved.
+//!
+//! Power users can have a program started or a local webhook called on each
+//! change of vault state, e.g. to mount an encrypted volume on unlock and
+//! unmount it on lock. A hook runs whatever the user configured with the
+//! user's rights, so setting hooks requires acknowledging that, and the
+//! frontend must show the warning first.
+//!
+//! Payloads never carry vault data: only the event name and time, given to
+//! programs as `ORGANIZER_EVENT` and `ORGANIZER_EVENT_AT` and to webhooks as
+//! a JSON body `{"event", "at"}`. Programs must be absolute paths and are
+//! started without a shell. Webhooks must point at this machine
+//! (`localhost`, `127.0.0.1`, or `[::1]`) and are never redirected.
+//!
+//! Hooks run on their own threads, so a slow or failing hook never delays
+//! the vault; failures are only logged.
+
+use crate::models::AppState;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+use url::Url;
+
+/// Most hooks that can be configured.
+pub const MAX_HOOKS: usize = 20;
+
+/// How long a webhook may take to answer.
+const WEBHOOK_TIMEOUT_SECS: u64 = 5;
+
+/// Vault state changes hooks can run on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VaultEvent {
+  Lock,
+  Unlock,
+  /// Changes were written to the store.
+  Save,
+}
+
+impl VaultEvent {
+  pub fn name(self) -> &'static str {
+    match self {
+      VaultEvent::Lock => "lock",
+      VaultEvent::Unlock => "unlock",
+      VaultEvent::Save => "save",
+    }
+  }
+}
+
+/// What a hook does.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HookAction {
+  /// Starts `program` with `args`, without a shell.
+  Exec {
+    program: PathBuf,
+    #[serde(default)]
+    args: Vec<String>,
+  },
+  /// POSTs the event to a URL on this machine.
+  Webhook { url: String },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hook {
+  pub event: VaultEvent,
+  pub action: HookAction,
+}
+
+/// JSON body of webhook calls.
+#[derive(Serialize)]
+struct Payload {
+  event: VaultEvent,
+  at: DateTime<Utc>,
+}
+
+/// Parses a webhook URL, accepting only http(s) URLs on this machine.
+fn webhook_url(raw: &str) -> Result<Url, String> {
+  let url = Url::parse(raw.trim()).map_err(|e| format!("invalid webhook URL: {e}"))?;
+  if !matches!(url.scheme(), "http" | "https") {
+    return Err("webhook URLs must use http or https".to_string());
+  }
+  if !matches!(url.host_str(), Some("localhost" | "127.0.0.1" | "[::1]")) {
+    return Err("webhooks can only call localhost".to_string());
+  }
+  Ok(url)
+}
+
+pub fn validate(hooks: &[Hook]) -> Result<(), String> {
+  if hooks.len() > MAX_HOOKS {
+    return Err(format!("at most {MAX_HOOKS} hooks can be set"));
+  }
+  for hook in hooks {
+    match &hook.action {
+      HookAction::Exec { program, .. } if !program.is_absolute() => {
+        return Err(format!("hook program {} must be an absolute path", program.display()));
+      }
+      HookAction::Exec { .. } => {}
+      HookAction::Webhook { url } => {
+        webhook_url(url)?;
+      }
+    }
+  }
+  Ok(())
+}
+
+/// Runs the configured hooks for `event` in the background.
+pub fn fire(state: &AppState, event: VaultEvent) {
+  let hooks: Vec<Hook> = match state.settings.lock() {
+    Ok(settings) => settings.hooks.iter().filter(|h| h.event == event).cloned().collect(),
+    Err(_) => return,
+  };
+  if hooks.is_empty() {
+    return;
+  }
+  let at = Utc::now();
+  thread::spawn(move || {
+    for hook in hooks {
+      if let Err(err) = run(&hook.action, event, at) {
+        eprintln!("hook ({}): {err}", event.name());
+      }
+    }
+  });
+}
+
+fn run(action: &HookAction, event: VaultEvent, at: DateTime<Utc>) -> Result<(), String> {
+  match action {
+    HookAction::Exec { program, args } => {
+      let mut child = Command::new(program)
+        .args(args)
+        .env("ORGANIZER_EVENT", event.name())
+        .env("ORGANIZER_EVENT_AT", at.to_rfc3339())
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("failed to start {}: {e}", program.display()))?;
+      // Reap the process without holding up the next hook.
+      thread::spawn(move || {
+        let _ = child.wait();
+      });
+      Ok(())
+    }
+    HookAction::Webhook { url } => {
+      let url = webhook_url(url)?;
+      let agent = ureq::AgentBuilder::new()
+        .timeout(Duration::from_secs(WEBHOOK_TIMEOUT_SECS))
+        .redirects(0)
+        .build();
+      let body = serde_json::to_string(&Payload { event, at }).map_err(|e| format!("serialize: {e}"))?;
+      agent
+        .post(url.as_str())
+        .set("Content-Type", "application/json")
+        .send_string(&body)
+        .map_err(|e| format!("webhook failed: {e}"))?;
+      Ok(())
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn only_local_webhooks_and_absolute_programs_are_accepted() {
+    let hook = |action: HookAction| Hook {
+      event: VaultEvent::Unlock,
+      action,
+    };
+    let webhook = |url: &str| hook(HookAction::Webhook { url: url.to_string() });
+    assert!(validate(&[webhook("http://127.0.0.1:8080/vault"), webhook("http://localhost/x")]).is_ok());
+    assert!(validate(&[webhook("http://[::1]:9000/")]).is_ok());
+    assert!(validate(&[webhook("https://example.com/hook")]).is_err());
+    assert!(validate(&[webhook("http://127.0.0.1.example.com/")]).is_err());
+    assert!(validate(&[webhook("file:///tmp/x")]).is_err());
+
+    let exec = |program: &str| {
+      hook(HookAction::Exec {
+        program: PathBuf::from(program),
+        args: Vec::new(),
+      })
+    };
+    assert!(validate(&[exec("mount-vault.sh")]).is_err());
+    #[cfg(unix)]
+    assert!(validate(&[exec("/usr/local/bin/mount-vault")]).is_ok());
+
+    let parsed: Hook =
+      serde_json::from_str(r#"{"event":"lock","action":{"type":"exec","program":"/bin/umount"}}"#).unwrap();
+    assert_eq!(parsed.event, VaultEvent::Lock);
+    assert!(validate(&vec![parsed; MAX_HOOKS + 1]).is_err());
+  }
+}
@@ -0,0 +1,122 @@
+//! Entry ids by host, for the browser extension's `/v1/entries` lookups.
+//!
+//! Parsing every entry's URL on each request is slow for large vaults and
+//! holds the vault mutex while it runs. The index is built on the first
+//! lookup after unlock, rebuilt by [`crate::persist::save`] whenever entries
+//! change, and dropped on lock, so a request only parses its own URL.
+//!
+//! Lock order: vault → host index.
+
+use crate::extension::normalize_host;
+use crate::models::{AppState, VaultData};
+use std::collections::HashMap;
+
+#[derive(Debug, Default)]
+pub struct HostIndex {
+  /// Vault and entry count the index was built from; a mismatch means the
+  /// vault was replaced or changed without a rebuild.
+  vault_id: String,
+  entry_count: usize,
+  /// Entry ids by host without a leading `www.`, in vault order.
+  by_host: HashMap<String, Vec<String>>,
+  /// Every distinct entry host, for lookalike checks.
+  hosts: Vec<String>,
+}
+
+impl HostIndex {
+  pub fn build(data: &VaultData) -> Self {
+    let mut index = Self {
+      vault_id: data.meta.id.clone(),
+      entry_count: data.entries.len(),
+      ..Self::default()
+    };
+    for entry in &data.entries {
+      let Some(host) = normalize_host(&entry.url) else {
+        continue;
+      };
+      let key = host.strip_prefix("www.").unwrap_or(&host).to_string();
+      index.by_host.entry(key).or_default().push(entry.id.clone());
+      if !index.hosts.contains(&host) {
+        index.hosts.push(host);
+      }
+    }
+    index
+  }
+
+  /// True if the index still describes `data`.
+  pub fn is_current(&self, data: &VaultData) -> bool {
+    self.vault_id == data.meta.id && self.entry_count == data.entries.len()
+  }
+
+  /// Ids of entries whose host matches `target_host` (see
+  /// [`crate::extension::host_matches`]): the host itself first, then each
+  /// parent domain.
+  pub fn matching(&self, target_host: &str) -> Vec<&str> {
+    let target = target_host.strip_prefix("www.").unwrap_or(target_host);
+    let parents = target.match_indices('.').map(|(at, _)| &target[at + 1..]);
+    std::iter::once(target)
+      .chain(parents)
+      .filter_map(|host| self.by_host.get(host))
+      .flatten()
+      .map(String::as_str)
+      .collect()
+  }
+
+  pub fn hosts(&self) -> &[String] {
+    &self.hosts
+  }
+}
+
+/// Rebuilds the index after entries in `data` changed, if one has been built
+/// since unlock (otherwise the next lookup builds it).
+pub fn refresh(state: &AppState, data: &VaultData) {
+  if let Ok(mut index) = state.host_index.lock() {
+    if index.is_some() {
+      *index = Some(HostIndex::build(data));
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::extension::host_matches;
+  use crate::models::Entry;
+
+  #[test]
+  fn lookups_agree_with_host_matches() {
+    let urls = [
+      "https://example.com/login",
+      "www.example.com",
+      "https://accounts.example.com",
+      "https://example.org",
+      "not a url",
+    ];
+    let mut data = VaultData {
+      entries: urls
+        .iter()
+        .map(|url| Entry::new(url.to_string(), String::new(), String::new(), url.to_string(), String::new()))
+        .collect(),
+      ..VaultData::default()
+    };
+    let index = HostIndex::build(&data);
+
+    for target in ["example.com", "www.example.com", "mail.accounts.example.com", "example.org", "badexample.com"] {
+      let mut expected: Vec<&str> = data
+        .entries
+        .iter()
+        .filter(|entry| normalize_host(&entry.url).is_some_and(|host| host_matches(&host, target)))
+        .map(|entry| entry.id.as_str())
+        .collect();
+      let mut found = index.matching(target);
+      expected.sort_unstable();
+      found.sort_unstable();
+      assert_eq!(found, expected, "{target}");
+    }
+    assert_eq!(index.hosts().len(), 4);
+
+    assert!(index.is_current(&data));
+    data.entries.pop();
+    assert!(!index.is_current(&data));
+  }
+}
@@ -0,0 +1,344 @@
+//! Logins saved by Chrome and Edge.
+//!
+//! Both keep logins in the profile's `Login Data` SQLite database, with each
+//! password encrypted under a per-install key:
+//!
+//! - Windows: `v10` values use AES-256-GCM under a key kept in `Local State`,
+//!   itself protected with DPAPI for the signed-in user; older values are
+//!   DPAPI blobs.
+//! - macOS: `v10` values use AES-128-CBC under a key derived from the
+//!   "Chrome Safe Storage" keychain item, which shows the keychain's own
+//!   consent prompt.
+//! - Linux: `v10` values use a fixed key; `v11` values use a key from the
+//!   desktop keyring, read with `secret-tool`.
+//!
+//! `v20` values (app-bound encryption) can only be decrypted by the browser
+//! itself and are reported as skipped. The database is opened read-only and
+//! immutable, so the browser may keep running.
+
+use super::{BrowserProfile, ImportSource, ImportedItem, Importer, Skipped};
+use rusqlite::{Connection, OpenFlags};
+use std::fs;
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
+
+const LOGIN_DATA: &str = "Login Data";
+const LOCAL_STATE: &str = "Local State";
+
+/// A key for one value prefix: AES-256-GCM on Windows, AES-128-CBC elsewhere.
+enum Key {
+  #[cfg(windows)]
+  Gcm(Zeroizing<[u8; 32]>),
+  #[cfg(not(windows))]
+  Cbc(Zeroizing<[u8; 16]>),
+}
+
+/// Keys for the `v10` and `v11` prefixes; `None` where this install has none.
+#[derive(Default)]
+struct Keys {
+  v10: Option<Key>,
+  v11: Option<Key>,
+}
+
+/// The browser's user data directory, holding `Local State` and one
+/// directory per profile.
+fn user_data_dir(source: ImportSource) -> Option<PathBuf> {
+  let (windows, macos, linux) = match source {
+    ImportSource::Chrome => ("Google/Chrome/User Data", "Google/Chrome", "google-chrome"),
+    ImportSource::Edge => ("Microsoft/Edge/User Data", "Microsoft Edge", "microsoft-edge"),
+    _ => return None,
+  };
+  if cfg!(windows) {
+    dirs::data_local_dir().map(|d| d.join(windows))
+  } else if cfg!(target_os = "macos") {
+    dirs::data_dir().map(|d| d.join(macos))
+  } else {
+    dirs::config_dir().map(|d| d.join(linux))
+  }
+}
+
+/// Profiles of `source` that have saved logins, named as in the browser.
+pub fn profiles(source: ImportSource) -> Vec<BrowserProfile> {
+  let Some(root) = user_data_dir(source) else {
+    return Vec::new();
+  };
+  let Ok(dirs) = fs::read_dir(&root) else {
+    return Vec::new();
+  };
+  let local_state: serde_json::Value = fs::read_to_string(root.join(LOCAL_STATE))
+    .ok()
+    .and_then(|raw| serde_json::from_str(&raw).ok())
+    .unwrap_or_default();
+  let mut profiles: Vec<BrowserProfile> = dirs
+    .flatten()
+    .map(|d| d.path())
+    .filter(|path| path.join(LOGIN_DATA).is_file())
+    .map(|path| {
+      let dir_name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+      let name = local_state["profile"]["info_cache"][&dir_name]["name"]
+        .as_str()
+        .map(String::from)
+        .unwrap_or(dir_name);
+      BrowserProfile {
+        source,
+        name: format!("{} – {name}", source.name()),
+        path,
+      }
+    })
+    .collect();
+  profiles.sort_by(|a, b| a.name.cmp(&b.name));
+  profiles
+}
+
+/// The saved logins of a Chrome or Edge profile.
+pub struct Chromium {
+  pub source: ImportSource,
+  pub profile: PathBuf,
+}
+
+impl Importer for Chromium {
+  fn source(&self) -> ImportSource {
+    self.source
+  }
+
+  fn read(&self) -> Result<Vec<Result<ImportedItem, Skipped>>, String> {
+    read_logins(self.source, &self.profile)
+  }
+}
+
+fn read_logins(source: ImportSource, profile_dir: &Path) -> Result<Vec<Result<ImportedItem, Skipped>>, String> {
+  let db_path = profile_dir.join(LOGIN_DATA);
+  if !db_path.is_file() {
+    return Err(format!("no saved logins found in {}", profile_dir.display()));
+  }
+  let keys = keys(source, profile_dir.parent().unwrap_or(profile_dir))?;
+
+  let uri = url::Url::from_file_path(&db_path).map_err(|()| "invalid profile path".to_string())?;
+  let conn = Connection::open_with_flags(
+    format!("{uri}?immutable=1"),
+    OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_URI,
+  )
+  .map_err(|e| format!("open {LOGIN_DATA}: {e}"))?;
+  let mut stmt = conn
+    .prepare("SELECT origin_url, username_value, password_value FROM logins WHERE blacklisted_by_user = 0")
+    .map_err(|e| format!("read {LOGIN_DATA}: {e}"))?;
+  let rows = stmt
+    .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, Vec<u8>>(2)?)))
+    .map_err(|e| format!("read {LOGIN_DATA}: {e}"))?;
+
+  let mut items = Vec::new();
+  for row in rows {
+    let (url, username, value) = row.map_err(|e| format!("read {LOGIN_DATA}: {e}"))?;
+    let password = decrypt(&keys, &value)
+      .and_then(|plain| String::from_utf8(plain.to_vec()).map_err(|_| "the password is not valid text".to_string()));
+    items.push(match password {
+      Ok(password) => Ok(ImportedItem {
+        title: String::new(),
+        url,
+        username,
+        password,
+        notes: String::new(),
+        totp: String::new(),
+      }),
+      Err(reason) => Err(Skipped { item: url, reason }),
+    });
+  }
+  Ok(items)
+}
+
+fn decrypt(keys: &Keys, value: &[u8]) -> Result<Zeroizing<Vec<u8>>, String> {
+  let (key, body) = match value.get(..3) {
+    Some(b"v10") => (keys.v10.as_ref(), &value[3..]),
+    Some(b"v11") => (keys.v11.as_ref(), &value[3..]),
+    Some(b"v20") => {
+      return Err("protected by the browser's app-bound encryption; export it from the browser instead".to_string())
+    }
+    _ => return legacy_decrypt(value),
+  };
+  let key = key.ok_or_else(|| "the key for this password is not available".to_string())?;
+  key
+    .decrypt(body)
+    .map(Zeroizing::new)
+    .map_err(|()| "the password could not be decrypted".to_string())
+}
+
+impl Key {
+  #[cfg(windows)]
+  fn decrypt(&self, body: &[u8]) -> Result<Vec<u8>, ()> {
+    use aes_gcm::aead::Aead;
+    use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
+    let Key::Gcm(key) = self;
+    if body.len() < 12 {
+      return Err(());
+    }
+    let (nonce, ciphertext) = body.split_at(12);
+    Aes256Gcm::new(key.as_slice().into())
+      .decrypt(Nonce::from_slice(nonce), ciphertext)
+      .map_err(|_| ())
+  }
+
+  #[cfg(not(windows))]
+  fn decrypt(&self, body: &[u8]) -> Result<Vec<u8>, ()> {
+    use cbc::cipher::block_padding::Pkcs7;
+    use cbc::cipher::{BlockDecryptMut, KeyIvInit};
+    let Key::Cbc(key) = self;
+    cbc::Decryptor::<aes::Aes128>::new(key.as_slice().into(), &[b' '; 16].into())
+      .decrypt_padded_vec_mut::<Pkcs7>(body)
+      .map_err(|_| ())
+  }
+}
+
+/// AES-128 key derived from a "Safe Storage" password as the browser does.
+#[cfg(not(windows))]
+fn cbc_key(password: &[u8], iterations: u32) -> Key {
+  let mut key = Zeroizing::new([0u8; 16]);
+  pbkdf2::pbkdf2_hmac::<sha1::Sha1>(password, b"saltysalt", iterations, key.as_mut());
+  Key::Cbc(key)
+}
+
+#[cfg(windows)]
+fn keys(_source: ImportSource, user_data: &Path) -> Result<Keys, String> {
+  use base64::Engine;
+  let raw = fs::read_to_string(user_data.join(LOCAL_STATE)).map_err(|e| format!("read {LOCAL_STATE}: {e}"))?;
+  let state: serde_json::Value = serde_json::from_str(&raw).map_err(|e| format!("parse {LOCAL_STATE}: {e}"))?;
+  let Some(encoded) = state["os_crypt"]["encrypted_key"].as_str() else {
+    return Ok(Keys::default());
+  };
+  let wrapped = base64::engine::general_purpose::STANDARD
+    .decode(encoded)
+    .map_err(|e| format!("parse {LOCAL_STATE}: {e}"))?;
+  let wrapped = wrapped
+    .strip_prefix(b"DPAPI")
+    .ok_or_else(|| format!("unrecognized key in {LOCAL_STATE}"))?;
+  let unwrapped = dpapi::unprotect(wrapped)?;
+  let key: [u8; 32] = unwrapped
+    .as_slice()
+    .try_into()
+    .map_err(|_| format!("unrecognized key in {LOCAL_STATE}"))?;
+  Ok(Keys {
+    v10: Some(Key::Gcm(Zeroizing::new(key))),
+    v11: None,
+  })
+}
+
+#[cfg(target_os = "macos")]
+fn keys(source: ImportSource, _user_data: &Path) -> Result<Keys, String> {
+  let (service, account) = match source {
+    ImportSource::Edge => ("Microsoft Edge Safe Storage", "Microsoft Edge"),
+    _ => ("Chrome Safe Storage", "Chrome"),
+  };
+  // Asks the user, through the keychain's own prompt, to allow access.
+  let output = std::process::Command::new("security")
+    .args(["find-generic-password", "-w", "-s", service, "-a", account])
+    .output()
+    .map_err(|e| format!("keychain: {e}"))?;
+  if !output.status.success() {
+    return Err(format!("access to \"{service}\" in the keychain was denied"));
+  }
+  let password = Zeroizing::new(output.stdout);
+  Ok(Keys {
+    v10: Some(cbc_key(password.trim_ascii_end(), 1003)),
+    v11: None,
+  })
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn keys(source: ImportSource, _user_data: &Path) -> Result<Keys, String> {
+  let application = match source {
+    ImportSource::Edge => "microsoft-edge",
+    _ => "chrome",
+  };
+  // Unavailable without a keyring; only `v11` values need it.
+  let keyring = std::process::Command::new("secret-tool")
+    .args(["lookup", "application", application])
+    .output()
+    .ok()
+    .filter(|output| output.status.success() && !output.stdout.is_empty())
+    .map(|output| {
+      let password = Zeroizing::new(output.stdout);
+      cbc_key(password.trim_ascii_end(), 1)
+    });
+  Ok(Keys {
+    v10: Some(cbc_key(b"peanuts", 1)),
+    v11: keyring,
+  })
+}
+
+#[cfg(windows)]
+fn legacy_decrypt(value: &[u8]) -> Result<Zeroizing<Vec<u8>>, String> {
+  dpapi::unprotect(value)
+}
+
+#[cfg(not(windows))]
+fn legacy_decrypt(_value: &[u8]) -> Result<Zeroizing<Vec<u8>>, String> {
+  Err("the password uses an unrecognized encryption".to_string())
+}
+
+#[cfg(windows)]
+mod dpapi {
+  use windows_sys::Win32::Foundation::LocalFree;
+  use windows_sys::Win32::Security::Cryptography::{CryptUnprotectData, CRYPT_INTEGER_BLOB};
+  use zeroize::Zeroizing;
+
+  /// Decrypts a blob protected for the signed-in user.
+  pub fn unprotect(data: &[u8]) -> Result<Zeroizing<Vec<u8>>, String> {
+    let input = CRYPT_INTEGER_BLOB {
+      cbData: u32::try_from(data.len()).map_err(|_| "DPAPI: blob too large".to_string())?,
+      pbData: data.as_ptr() as *mut u8,
+    };
+    let mut output = CRYPT_INTEGER_BLOB {
+      cbData: 0,
+      pbData: std::ptr::null_mut(),
+    };
+    // SAFETY: `input` points at `data` for the duration of the call, and
+    // `output` is allocated by the call and freed below after copying.
+    let ok = unsafe {
+      CryptUnprotectData(
+        &input,
+        std::ptr::null_mut(),
+        std::ptr::null(),
+        std::ptr::null(),
+        std::ptr::null(),
+        0,
+        &mut output,
+      )
+    };
+    if ok == 0 || output.pbData.is_null() {
+      return Err("DPAPI could not decrypt the key for this Windows user".to_string());
+    }
+    // SAFETY: on success `output` holds `cbData` bytes owned by us.
+    let plain = unsafe {
+      let bytes = std::slice::from_raw_parts_mut(output.pbData, output.cbData as usize);
+      let plain = Zeroizing::new(bytes.to_vec());
+      zeroize::Zeroize::zeroize(bytes);
+      LocalFree(output.pbData.cast());
+      plain
+    };
+    Ok(plain)
+  }
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+  use super::*;
+  use cbc::cipher::block_padding::Pkcs7;
+  use cbc::cipher::{BlockEncryptMut, KeyIvInit};
+
+  #[test]
+  fn decrypts_v10_values_and_reports_app_bound_ones() {
+    let key = [0x11u8; 16];
+    let ciphertext =
+      cbc::Encryptor::<aes::Aes128>::new(&key.into(), &[b' '; 16].into()).encrypt_padded_vec_mut::<Pkcs7>(b"hunter2");
+    let keys = Keys {
+      v10: Some(Key::Cbc(Zeroizing::new(key))),
+      v11: None,
+    };
+    let value = [b"v10".as_slice(), &ciphertext].concat();
+    assert_eq!(decrypt(&keys, &value).unwrap().as_slice(), b"hunter2");
+
+    let v11 = [b"v11".as_slice(), &ciphertext].concat();
+    assert!(decrypt(&keys, &v11).unwrap_err().contains("not available"));
+    assert!(decrypt(&keys, b"v20\x01\x02").unwrap_err().contains("app-bound"));
+    assert!(matches!(cbc_key(b"peanuts", 1), Key::Cbc(_)));
+  }
+}
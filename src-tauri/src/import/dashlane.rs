@@ -0,0 +1,152 @@
+//! Dashlane exports.
+//!
+//! - CSV: `credentials.csv` from the exported archive, with a header row
+//!   (`username`, `username2`, `username3`, `title`, `password`, `note`,
+//!   `url`, `category`, and `otpSecret` or `otpUrl`).
+//! - JSON: the older export, whose `AUTHENTIFIANT` array holds the logins.
+//!
+//! Only logins are imported; secure notes, payments, and IDs are separate
+//! files or arrays and are left out.
+
+use super::{read_export, ImportSource, ImportedItem, Importer, Skipped};
+use serde::Deserialize;
+use std::path::PathBuf;
+
+/// A Dashlane export file, CSV or JSON (told apart by its content).
+pub struct Dashlane {
+  pub path: PathBuf,
+}
+
+impl Importer for Dashlane {
+  fn source(&self) -> ImportSource {
+    ImportSource::Dashlane
+  }
+
+  fn read(&self) -> Result<Vec<Result<ImportedItem, Skipped>>, String> {
+    let raw = read_export(&self.path)?;
+    if raw.trim_start().starts_with('{') {
+      parse_json(&raw)
+    } else {
+      parse_csv(&raw)
+    }
+  }
+}
+
+fn parse_csv(raw: &str) -> Result<Vec<Result<ImportedItem, Skipped>>, String> {
+  let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(raw.as_bytes());
+  let headers = reader
+    .headers()
+    .map_err(|e| format!("parse Dashlane export: {e}"))?
+    .clone();
+  let column = |name: &str| headers.iter().position(|h| h.trim() == name);
+  let Some(password_column) = column("password") else {
+    return Err("not a Dashlane credentials.csv export (no password column)".to_string());
+  };
+  let username_columns: Vec<usize> = ["username", "username2", "username3"]
+    .iter()
+    .filter_map(|name| column(name))
+    .collect();
+  let (title, note, url) = (column("title"), column("note"), column("url"));
+  let otp = column("otpUrl").or_else(|| column("otpSecret"));
+
+  let mut items = Vec::new();
+  for (row, record) in reader.records().enumerate() {
+    let record = match record {
+      Ok(record) => record,
+      Err(e) => {
+        items.push(Err(Skipped {
+          item: format!("row {}", row + 2),
+          reason: format!("unreadable row: {e}"),
+        }));
+        continue;
+      }
+    };
+    let field = |index: Option<usize>| index.and_then(|i| record.get(i)).unwrap_or_default().to_string();
+    let username = username_columns
+      .iter()
+      .map(|&i| field(Some(i)))
+      .find(|u| !u.trim().is_empty())
+      .unwrap_or_default();
+    items.push(Ok(ImportedItem {
+      title: field(title),
+      url: field(url),
+      username,
+      password: field(Some(password_column)),
+      notes: field(note),
+      totp: field(otp),
+    }));
+  }
+  Ok(items)
+}
+
+#[derive(Deserialize)]
+struct JsonExport {
+  #[serde(rename = "AUTHENTIFIANT", default)]
+  logins: Vec<JsonLogin>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonLogin {
+  #[serde(default)]
+  title: String,
+  #[serde(default)]
+  domain: String,
+  #[serde(default)]
+  login: String,
+  #[serde(default)]
+  email: String,
+  #[serde(default)]
+  secondary_login: String,
+  #[serde(default)]
+  password: String,
+  #[serde(default)]
+  note: String,
+}
+
+fn parse_json(raw: &str) -> Result<Vec<Result<ImportedItem, Skipped>>, String> {
+  let export: JsonExport = serde_json::from_str(raw).map_err(|e| format!("parse Dashlane export: {e}"))?;
+  let items = export
+    .logins
+    .into_iter()
+    .map(|login| {
+      let username = [login.login, login.email, login.secondary_login]
+        .into_iter()
+        .find(|u| !u.trim().is_empty())
+        .unwrap_or_default();
+      Ok(ImportedItem {
+        title: login.title,
+        url: login.domain,
+        username,
+        password: login.password,
+        notes: login.note,
+        totp: String::new(),
+      })
+    })
+    .collect();
+  Ok(items)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn reads_csv_and_json_exports() {
+    let csv = "username,username2,username3,title,password,note,url,category,otpUrl\n\
+               ,me@example.com,,Mail,hunter2,\"line one, line two\",https://mail.example.com,Email,otpauth://totp/Mail?secret=JBSWY3DPEHPK3PXP\n";
+    let items = parse_csv(csv).unwrap();
+    assert_eq!(items.len(), 1);
+    let login = items[0].as_ref().unwrap();
+    assert_eq!(login.username, "me@example.com");
+    assert_eq!(login.notes, "line one, line two");
+    assert!(login.totp.starts_with("otpauth://"));
+    assert!(parse_csv("title,url\nMail,https://mail.example.com\n").is_err());
+
+    let json = r#"{"AUTHENTIFIANT":[{"title":"Shop","domain":"shop.example.com","login":"","email":"me@example.com","password":"s3cret"}],"SECURENOTE":[]}"#;
+    let items = parse_json(json).unwrap();
+    let login = items[0].as_ref().unwrap();
+    assert_eq!(login.username, "me@example.com");
+    assert_eq!(login.url, "shop.example.com");
+  }
+}
@@ -0,0 +1,132 @@
+//! Enpass JSON exports.
+//!
+//! Each item has a title, a note, and a list of typed fields. Items with a
+//! password field are imported as logins: the first `username` (or `email`)
+//! field is the username and the first `url` field the site. Other
+//! non-sensitive fields are appended to the notes so nothing visible is
+//! lost. Items in the Enpass trash are left out, and items without a
+//! password (cards, notes, identities) are reported as skipped.
+
+use super::{read_export, ImportSource, ImportedItem, Importer, Skipped};
+use serde::Deserialize;
+use std::path::PathBuf;
+
+/// An Enpass JSON export file.
+pub struct Enpass {
+  pub path: PathBuf,
+}
+
+impl Importer for Enpass {
+  fn source(&self) -> ImportSource {
+    ImportSource::Enpass
+  }
+
+  fn read(&self) -> Result<Vec<Result<ImportedItem, Skipped>>, String> {
+    parse(&read_export(&self.path)?)
+  }
+}
+
+#[derive(Deserialize)]
+struct Export {
+  #[serde(default)]
+  items: Vec<Item>,
+}
+
+#[derive(Deserialize)]
+struct Item {
+  #[serde(default)]
+  title: String,
+  #[serde(default)]
+  note: String,
+  #[serde(default)]
+  trashed: u8,
+  #[serde(default)]
+  fields: Vec<Field>,
+}
+
+#[derive(Deserialize)]
+struct Field {
+  #[serde(default)]
+  label: String,
+  #[serde(rename = "type", default)]
+  kind: String,
+  #[serde(default)]
+  value: String,
+  #[serde(default)]
+  deleted: u8,
+  #[serde(default)]
+  sensitive: u8,
+}
+
+fn parse(raw: &str) -> Result<Vec<Result<ImportedItem, Skipped>>, String> {
+  let export: Export = serde_json::from_str(raw).map_err(|e| format!("parse Enpass export: {e}"))?;
+  let mut items = Vec::new();
+  for item in export.items.into_iter().filter(|item| item.trashed == 0) {
+    let mut fields: Vec<Field> = item
+      .fields
+      .into_iter()
+      .filter(|f| f.deleted == 0 && !f.value.trim().is_empty())
+      .collect();
+    let mut take = |kind: &str| {
+      fields
+        .iter()
+        .position(|f| f.kind == kind)
+        .map(|i| fields.remove(i).value)
+    };
+    let Some(password) = take("password") else {
+      items.push(Err(Skipped {
+        item: item.title,
+        reason: "not a login (no password field)".to_string(),
+      }));
+      continue;
+    };
+    let username = take("username").or_else(|| take("email")).unwrap_or_default();
+    let url = take("url").unwrap_or_default();
+    let totp = take("totp").unwrap_or_default();
+
+    let mut notes = item.note;
+    for field in fields.iter().filter(|f| f.sensitive == 0) {
+      if !notes.is_empty() {
+        notes.push('\n');
+      }
+      notes.push_str(&format!("{}: {}", field.label, field.value));
+    }
+    items.push(Ok(ImportedItem {
+      title: item.title,
+      url,
+      username,
+      password,
+      notes,
+      totp,
+    }));
+  }
+  Ok(items)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn imports_items_with_passwords_and_keeps_visible_extras() {
+    let json = r#"{"folders":[],"items":[
+      {"title":"Mail","note":"work","category":"login","trashed":0,"fields":[
+        {"label":"E-mail","type":"email","value":"me@example.com","deleted":0,"sensitive":0},
+        {"label":"Password","type":"password","value":"hunter2","deleted":0,"sensitive":1},
+        {"label":"Website","type":"url","value":"https://mail.example.com","deleted":0,"sensitive":0},
+        {"label":"Security answer","type":"text","value":"blue","deleted":0,"sensitive":1},
+        {"label":"Phone","type":"phone","value":"555-0100","deleted":0,"sensitive":0}]},
+      {"title":"Visa","category":"creditcard","trashed":0,"fields":[
+        {"label":"Number","type":"ccNumber","value":"4111111111111111","deleted":0,"sensitive":1}]},
+      {"title":"Old","category":"login","trashed":1,"fields":[
+        {"label":"Password","type":"password","value":"x","deleted":0,"sensitive":1}]}
+    ]}"#;
+    let items = parse(json).unwrap();
+    assert_eq!(items.len(), 2);
+    let login = items[0].as_ref().unwrap();
+    assert_eq!(login.username, "me@example.com");
+    assert_eq!(login.url, "https://mail.example.com");
+    assert_eq!(login.notes, "work\nPhone: 555-0100");
+    assert_eq!(items[1].as_ref().err().unwrap().item, "Visa");
+  }
+}
@@ -0,0 +1,358 @@
+//! Logins saved by Firefox.
+//!
+//! Firefox keeps logins in the profile's `logins.json`, with usernames and
+//! passwords encrypted (3DES or AES-256-CBC) under a key stored in the NSS
+//! database `key4.db`. That key is in turn encrypted under the profile's
+//! primary password, empty unless the user set one:
+//!
+//! - `metaData` row `password` holds a known value ("password-check")
+//!   encrypted the same way, used to tell a wrong primary password apart;
+//! - `nssPrivate` holds the encrypted key itself.
+//!
+//! Both use PKCS#5 PBES2 (PBKDF2-SHA256 and AES-256-CBC) since Firefox 75,
+//! or NSS's older SHA-1/3DES scheme. The structures are DER-encoded.
+
+use super::{BrowserProfile, ImportSource, ImportedItem, Importer, Skipped};
+use base64::Engine;
+use cbc::cipher::block_padding::Pkcs7;
+use cbc::cipher::{BlockDecryptMut, KeyIvInit};
+use hmac::{Hmac, Mac};
+use rusqlite::{Connection, OpenFlags, OptionalExtension};
+use serde::Deserialize;
+use sha1::{Digest, Sha1};
+use std::fs;
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
+
+const KEY_DB: &str = "key4.db";
+const LOGINS: &str = "logins.json";
+
+/// CKA_ID of the key that encrypts logins.
+const LOGIN_KEY_ID: [u8; 16] = [0xf8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1];
+
+// DER-encoded object identifiers.
+const OID_PBES2: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x05, 0x0d];
+const OID_PBKDF2: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x05, 0x0c];
+const OID_SHA1_3DES: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x0c, 0x05, 0x01, 0x03];
+const OID_AES256_CBC: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x01, 0x2a];
+const OID_3DES_CBC: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x03, 0x07];
+
+const SEQUENCE: u8 = 0x30;
+const OCTET_STRING: u8 = 0x04;
+const OID: u8 = 0x06;
+const INTEGER: u8 = 0x02;
+
+/// Error when the profile is protected and the password given is wrong or
+/// missing; the UI asks for the primary password on this message.
+pub const PRIMARY_PASSWORD_ERROR: &str = "the Firefox primary password is incorrect";
+
+fn firefox_dir() -> Option<PathBuf> {
+  if cfg!(windows) {
+    dirs::config_dir().map(|d| d.join("Mozilla/Firefox"))
+  } else if cfg!(target_os = "macos") {
+    dirs::data_dir().map(|d| d.join("Firefox"))
+  } else {
+    dirs::home_dir().map(|d| d.join(".mozilla/firefox"))
+  }
+}
+
+/// Profiles listed in `profiles.ini` that have saved logins.
+pub fn profiles() -> Vec<BrowserProfile> {
+  let Some(root) = firefox_dir() else {
+    return Vec::new();
+  };
+  let Ok(ini) = fs::read_to_string(root.join("profiles.ini")) else {
+    return Vec::new();
+  };
+  let mut profiles = Vec::new();
+  // Each `[ProfileN]` section has `Name`, `Path`, and `IsRelative`.
+  for section in ini.split("\n[").filter(|s| s.starts_with("Profile")) {
+    let value = |key: &str| {
+      section
+        .lines()
+        .find_map(|line| line.trim().strip_prefix(key)?.strip_prefix('='))
+        .map(str::trim)
+    };
+    let Some(path) = value("Path") else {
+      continue;
+    };
+    let path = if value("IsRelative") == Some("0") {
+      PathBuf::from(path)
+    } else {
+      root.join(path)
+    };
+    if path.join(LOGINS).is_file() && path.join(KEY_DB).is_file() {
+      profiles.push(BrowserProfile {
+        source: ImportSource::Firefox,
+        name: format!("Firefox – {}", value("Name").unwrap_or("default")),
+        path,
+      });
+    }
+  }
+  profiles
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Logins {
+  logins: Vec<StoredLogin>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredLogin {
+  hostname: String,
+  encrypted_username: String,
+  encrypted_password: String,
+}
+
+/// The saved logins of a Firefox profile.
+pub struct Firefox {
+  pub profile: PathBuf,
+  /// Unlocks the profile's key; empty if the profile has none.
+  pub primary_password: Zeroizing<String>,
+}
+
+impl Importer for Firefox {
+  fn source(&self) -> ImportSource {
+    ImportSource::Firefox
+  }
+
+  fn read(&self) -> Result<Vec<Result<ImportedItem, Skipped>>, String> {
+    read_logins(&self.profile, &self.primary_password)
+  }
+}
+
+fn read_logins(profile_dir: &Path, primary_password: &str) -> Result<Vec<Result<ImportedItem, Skipped>>, String> {
+  let key = login_key(&profile_dir.join(KEY_DB), primary_password.as_bytes())?;
+  let raw = Zeroizing::new(fs::read_to_string(profile_dir.join(LOGINS)).map_err(|e| format!("read {LOGINS}: {e}"))?);
+  let stored: Logins = serde_json::from_str(&raw).map_err(|e| format!("parse {LOGINS}: {e}"))?;
+
+  let field = |encrypted: &str| -> Result<String, String> {
+    let plain = decrypt_field(&key, encrypted)?;
+    String::from_utf8(plain.to_vec()).map_err(|_| "the login is not valid text".to_string())
+  };
+  let items = stored
+    .logins
+    .into_iter()
+    .map(|login| {
+      match field(&login.encrypted_username).and_then(|u| Ok((u, field(&login.encrypted_password)?))) {
+        Ok((username, password)) => Ok(ImportedItem {
+          title: String::new(),
+          url: login.hostname,
+          username,
+          password,
+          notes: String::new(),
+          totp: String::new(),
+        }),
+        Err(reason) => Err(Skipped {
+          item: login.hostname,
+          reason,
+        }),
+      }
+    })
+    .collect();
+  Ok(items)
+}
+
+/// Checks the primary password against `key4.db` and returns the key that
+/// encrypts the logins.
+fn login_key(path: &Path, primary_password: &[u8]) -> Result<Zeroizing<Vec<u8>>, String> {
+  let uri = url::Url::from_file_path(path).map_err(|()| "invalid profile path".to_string())?;
+  let conn = Connection::open_with_flags(
+    format!("{uri}?immutable=1"),
+    OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_URI,
+  )
+  .map_err(|e| format!("open {KEY_DB}: {e}"))?;
+  let db_error = |e: rusqlite::Error| format!("read {KEY_DB}: {e}");
+
+  let (global_salt, check): (Vec<u8>, Vec<u8>) = conn
+    .query_row("SELECT item1, item2 FROM metaData WHERE id = 'password'", [], |row| {
+      Ok((row.get(0)?, row.get(1)?))
+    })
+    .map_err(db_error)?;
+  match decrypt_pbe(&check, &global_salt, primary_password) {
+    Ok(plain) if plain.as_slice() == b"password-check" => {}
+    _ => return Err(PRIMARY_PASSWORD_ERROR.to_string()),
+  }
+
+  let wrapped: Option<Vec<u8>> = conn
+    .query_row("SELECT a11 FROM nssPrivate WHERE a102 = ?1", [LOGIN_KEY_ID.as_slice()], |row| row.get(0))
+    .optional()
+    .map_err(db_error)?;
+  let wrapped = wrapped.ok_or_else(|| format!("{KEY_DB} holds no login key"))?;
+  decrypt_pbe(&wrapped, &global_salt, primary_password)
+}
+
+/// Reads one DER element of type `tag` from the front of `input`.
+fn take<'a>(input: &mut &'a [u8], tag: u8) -> Result<&'a [u8], String> {
+  let invalid = || "unrecognized key data".to_string();
+  let (&found, rest) = input.split_first().ok_or_else(invalid)?;
+  let (&first, mut rest) = rest.split_first().ok_or_else(invalid)?;
+  if found != tag {
+    return Err(invalid());
+  }
+  let len = if first < 0x80 {
+    first as usize
+  } else {
+    let count = (first & 0x7f) as usize;
+    if count == 0 || count > 4 || rest.len() < count {
+      return Err(invalid());
+    }
+    let (digits, tail) = rest.split_at(count);
+    rest = tail;
+    digits.iter().fold(0usize, |len, &d| (len << 8) | d as usize)
+  };
+  if rest.len() < len {
+    return Err(invalid());
+  }
+  let (content, tail) = rest.split_at(len);
+  *input = tail;
+  Ok(content)
+}
+
+fn take_u32(input: &mut &[u8]) -> Result<u32, String> {
+  let digits = take(input, INTEGER)?;
+  if digits.len() > 5 {
+    return Err("unrecognized key data".to_string());
+  }
+  Ok(digits.iter().fold(0u32, |n, &d| (n << 8) | d as u32))
+}
+
+/// Decrypts a password-based encryption blob from `key4.db`.
+fn decrypt_pbe(blob: &[u8], global_salt: &[u8], password: &[u8]) -> Result<Zeroizing<Vec<u8>>, String> {
+  let failed = || PRIMARY_PASSWORD_ERROR.to_string();
+  let mut outer = blob;
+  let mut outer = take(&mut outer, SEQUENCE)?;
+  let mut algorithm = take(&mut outer, SEQUENCE)?;
+  let ciphertext = take(&mut outer, OCTET_STRING)?;
+  let oid = take(&mut algorithm, OID)?;
+  let mut params = take(&mut algorithm, SEQUENCE)?;
+
+  let hashed_password = Zeroizing::new(Sha1::new().chain_update(global_salt).chain_update(password).finalize().to_vec());
+
+  if oid == OID_PBES2 {
+    let mut kdf = take(&mut params, SEQUENCE)?;
+    if take(&mut kdf, OID)? != OID_PBKDF2 {
+      return Err("unsupported key derivation in key4.db".to_string());
+    }
+    let mut kdf_params = take(&mut kdf, SEQUENCE)?;
+    let salt = take(&mut kdf_params, OCTET_STRING)?;
+    let iterations = take_u32(&mut kdf_params)?;
+    let mut cipher = take(&mut params, SEQUENCE)?;
+    if take(&mut cipher, OID)? != OID_AES256_CBC {
+      return Err("unsupported cipher in key4.db".to_string());
+    }
+    let iv = take(&mut cipher, OCTET_STRING)?;
+    // NSS stores a 14-byte IV; the full IV includes its DER header.
+    let iv: [u8; 16] = match iv.len() {
+      14 => [[OCTET_STRING, 14].as_slice(), iv].concat().try_into().map_err(|_| failed())?,
+      _ => iv.try_into().map_err(|_| failed())?,
+    };
+    let mut key = Zeroizing::new([0u8; 32]);
+    pbkdf2::pbkdf2_hmac::<sha2::Sha256>(hashed_password.as_slice(), salt, iterations, key.as_mut());
+    return cbc::Decryptor::<aes::Aes256>::new(key.as_slice().into(), &iv.into())
+      .decrypt_padded_vec_mut::<Pkcs7>(ciphertext)
+      .map(Zeroizing::new)
+      .map_err(|_| failed());
+  }
+
+  if oid == OID_SHA1_3DES {
+    let entry_salt = take(&mut params, OCTET_STRING)?;
+    let mut padded_salt = [0u8; 20];
+    let n = entry_salt.len().min(20);
+    padded_salt[..n].copy_from_slice(&entry_salt[..n]);
+    let combined = Zeroizing::new(Sha1::new().chain_update(hashed_password.as_slice()).chain_update(entry_salt).finalize().to_vec());
+    let hmac = |parts: &[&[u8]]| -> Result<Zeroizing<Vec<u8>>, String> {
+      let mut mac = Hmac::<Sha1>::new_from_slice(&combined).map_err(|_| failed())?;
+      parts.iter().for_each(|p| mac.update(p));
+      Ok(Zeroizing::new(mac.finalize().into_bytes().to_vec()))
+    };
+    let k1 = hmac(&[&padded_salt, entry_salt])?;
+    let tk = hmac(&[&padded_salt])?;
+    let k2 = hmac(&[&tk, entry_salt])?;
+    let derived = Zeroizing::new([k1.as_slice(), k2.as_slice()].concat());
+    let (key, iv) = (&derived[..24], &derived[derived.len() - 8..]);
+    return cbc::Decryptor::<des::TdesEde3>::new(key.into(), iv.into())
+      .decrypt_padded_vec_mut::<Pkcs7>(ciphertext)
+      .map(Zeroizing::new)
+      .map_err(|_| failed());
+  }
+
+  Err("unsupported encryption in key4.db".to_string())
+}
+
+/// Decrypts a base64 `encryptedUsername` or `encryptedPassword`.
+fn decrypt_field(key: &[u8], encoded: &str) -> Result<Zeroizing<Vec<u8>>, String> {
+  let failed = || "the login could not be decrypted".to_string();
+  let der = base64::engine::general_purpose::STANDARD
+    .decode(encoded)
+    .map_err(|_| failed())?;
+  let mut input = der.as_slice();
+  let mut outer = take(&mut input, SEQUENCE).map_err(|_| failed())?;
+  take(&mut outer, OCTET_STRING).map_err(|_| failed())?;
+  let mut algorithm = take(&mut outer, SEQUENCE).map_err(|_| failed())?;
+  let oid = take(&mut algorithm, OID).map_err(|_| failed())?;
+  let iv = take(&mut algorithm, OCTET_STRING).map_err(|_| failed())?;
+  let ciphertext = take(&mut outer, OCTET_STRING).map_err(|_| failed())?;
+
+  let plain = if oid == OID_3DES_CBC && key.len() >= 24 && iv.len() == 8 {
+    cbc::Decryptor::<des::TdesEde3>::new(key[..24].into(), iv.into()).decrypt_padded_vec_mut::<Pkcs7>(ciphertext)
+  } else if oid == OID_AES256_CBC && key.len() >= 32 && iv.len() == 16 {
+    cbc::Decryptor::<aes::Aes256>::new(key[..32].into(), iv.into()).decrypt_padded_vec_mut::<Pkcs7>(ciphertext)
+  } else {
+    return Err("the login uses an unsupported cipher".to_string());
+  };
+  plain.map(Zeroizing::new).map_err(|_| failed())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use cbc::cipher::BlockEncryptMut;
+
+  fn der(tag: u8, content: &[u8]) -> Vec<u8> {
+    let len = match content.len() {
+      n @ 0..=0x7f => vec![n as u8],
+      n => vec![0x82, (n >> 8) as u8, n as u8],
+    };
+    [&[tag][..], &len, content].concat()
+  }
+
+  /// A PBES2 blob as NSS writes it, with a 14-byte IV.
+  fn pbes2(plain: &[u8], global_salt: &[u8], password: &[u8]) -> Vec<u8> {
+    let (salt, iterations, iv14) = ([7u8; 32], 10u8, [9u8; 14]);
+    let hashed = Sha1::new().chain_update(global_salt).chain_update(password).finalize();
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<sha2::Sha256>(&hashed, &salt, iterations as u32, &mut key);
+    let iv: [u8; 16] = [[OCTET_STRING, 14].as_slice(), &iv14].concat().try_into().unwrap();
+    let ciphertext = cbc::Encryptor::<aes::Aes256>::new(&key.into(), &iv.into()).encrypt_padded_vec_mut::<Pkcs7>(plain);
+    let kdf_params = [der(OCTET_STRING, &salt), der(INTEGER, &[iterations]), der(INTEGER, &[32])].concat();
+    let kdf = [der(OID, OID_PBKDF2), der(SEQUENCE, &kdf_params)].concat();
+    let cipher = [der(OID, OID_AES256_CBC), der(OCTET_STRING, &iv14)].concat();
+    let params = [der(SEQUENCE, &kdf), der(SEQUENCE, &cipher)].concat();
+    let algorithm = [der(OID, OID_PBES2), der(SEQUENCE, &params)].concat();
+    der(SEQUENCE, &[der(SEQUENCE, &algorithm), der(OCTET_STRING, &ciphertext)].concat())
+  }
+
+  #[test]
+  fn unlocks_the_key_with_the_primary_password_and_decrypts_logins() {
+    let check = pbes2(b"password-check", b"salt", b"primary");
+    assert_eq!(decrypt_pbe(&check, b"salt", b"primary").unwrap().as_slice(), b"password-check");
+    assert!(!matches!(decrypt_pbe(&check, b"salt", b""), Ok(plain) if plain.as_slice() == b"password-check"));
+
+    let key = [3u8; 32];
+    let wrapped = pbes2(&key, b"salt", b"primary");
+    let key = decrypt_pbe(&wrapped, b"salt", b"primary").unwrap();
+
+    let iv = [5u8; 16];
+    let ciphertext = cbc::Encryptor::<aes::Aes256>::new(key[..32].into(), &iv.into()).encrypt_padded_vec_mut::<Pkcs7>(b"hunter2");
+    let algorithm = [der(OID, OID_AES256_CBC), der(OCTET_STRING, &iv)].concat();
+    let field = der(
+      SEQUENCE,
+      &[der(OCTET_STRING, &LOGIN_KEY_ID), der(SEQUENCE, &algorithm), der(OCTET_STRING, &ciphertext)].concat(),
+    );
+    let encoded = base64::engine::general_purpose::STANDARD.encode(field);
+    assert_eq!(decrypt_field(&key, &encoded).unwrap().as_slice(), b"hunter2");
+  }
+}
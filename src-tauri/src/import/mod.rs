@@ -0,0 +1,294 @@
+//! Importing logins from other password stores.
+//!
+//! Each source is an [`Importer`] that only parses: it reads the source
+//! straight into memory as [`ImportedItem`]s, so no plaintext export has to
+//! be written to disk first. Everything after that is shared: [`read_batch`]
+//! drops repeats into an [`ImportBatch`], [`preview`] compares it with the
+//! vault, and [`into_entries`] turns the chosen items into entries. A new
+//! format only needs a parser.
+//!
+//! The batch is held in [`crate::models::AppState`] (zeroized on drop,
+//! cleared on lock) while the user reviews the preview; only the logins
+//! they keep become entries.
+
+pub mod chromium;
+pub mod dashlane;
+pub mod enpass;
+pub mod firefox;
+pub mod system;
+
+use crate::extension::normalize_host;
+use crate::limits::Limits;
+use crate::models::Entry;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+/// Where a batch of logins came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportSource {
+  Chrome,
+  Edge,
+  Firefox,
+  /// macOS keychain.
+  Keychain,
+  /// Windows Credential Manager.
+  CredentialManager,
+  /// Dashlane CSV or JSON export.
+  Dashlane,
+  /// Enpass JSON export.
+  Enpass,
+}
+
+impl ImportSource {
+  pub fn name(self) -> &'static str {
+    match self {
+      ImportSource::Chrome => "Chrome",
+      ImportSource::Edge => "Edge",
+      ImportSource::Firefox => "Firefox",
+      ImportSource::Keychain => "Keychain",
+      ImportSource::CredentialManager => "Credential Manager",
+      ImportSource::Dashlane => "Dashlane",
+      ImportSource::Enpass => "Enpass",
+    }
+  }
+}
+
+/// A browser profile with saved logins.
+#[derive(Clone, Debug, Serialize)]
+pub struct BrowserProfile {
+  pub source: ImportSource,
+  pub name: String,
+  pub path: PathBuf,
+}
+
+/// Browser profiles on this machine that have a login store.
+pub fn browser_profiles() -> Vec<BrowserProfile> {
+  let mut profiles = chromium::profiles(ImportSource::Chrome);
+  profiles.extend(chromium::profiles(ImportSource::Edge));
+  profiles.extend(firefox::profiles());
+  profiles
+}
+
+/// A login read from another store.
+#[derive(Clone, Default, Zeroize, ZeroizeOnDrop)]
+pub struct ImportedItem {
+  pub title: String,
+  pub url: String,
+  pub username: String,
+  pub password: String,
+  pub notes: String,
+  /// TOTP secret or `otpauth://` URI; empty if none.
+  pub totp: String,
+}
+
+/// An item that could not be read, e.g. one the browser encrypts with a key
+/// only it can use.
+#[derive(Clone, Debug, Serialize)]
+pub struct Skipped {
+  /// Site or title, for the user to recognise it.
+  pub item: String,
+  pub reason: String,
+}
+
+/// A source of logins to import.
+pub trait Importer {
+  fn source(&self) -> ImportSource;
+  /// Reads every item in the source, in order. Items that cannot be read
+  /// are returned as [`Skipped`] so the user sees what was left out; an
+  /// `Err` means the source itself could not be read.
+  fn read(&self) -> Result<Vec<Result<ImportedItem, Skipped>>, String>;
+}
+
+/// Runs `importer` and collects its items into a batch.
+pub fn read_batch(importer: &dyn Importer) -> Result<ImportBatch, String> {
+  let mut logins = Vec::new();
+  let mut skipped = Vec::new();
+  for item in importer.read()? {
+    match item {
+      Ok(login) => logins.push(login),
+      Err(skip) => skipped.push(skip),
+    }
+  }
+  Ok(ImportBatch::new(importer.source(), logins, skipped))
+}
+
+/// Logins read from one source, waiting to be imported.
+pub struct ImportBatch {
+  pub source: ImportSource,
+  pub logins: Vec<ImportedItem>,
+  pub skipped: Vec<Skipped>,
+}
+
+impl ImportBatch {
+  /// Builds a batch, dropping logins repeated within the source (browsers
+  /// keep one row per form, so the same login often appears twice).
+  pub fn new(source: ImportSource, logins: Vec<ImportedItem>, skipped: Vec<Skipped>) -> Self {
+    let mut kept: Vec<ImportedItem> = Vec::with_capacity(logins.len());
+    for mut login in logins {
+      if login.title.trim().is_empty() {
+        login.title = default_title(&login.url);
+      }
+      let repeated = kept
+        .iter()
+        .any(|k| same_login(&k.url, &k.username, &login.url, &login.username) && k.password == login.password);
+      if !repeated {
+        kept.push(login);
+      }
+    }
+    Self { source, logins: kept, skipped }
+  }
+}
+
+/// Reads an export file from another password manager, within the vault's
+/// file size limit.
+pub fn read_export(path: &Path) -> Result<Zeroizing<String>, String> {
+  let size = fs::metadata(path).map_err(|e| format!("read export failed: {e}"))?.len();
+  Limits::default()
+    .check_file_size(size)
+    .map_err(|limit| format!("import rejected: {limit}"))?;
+  let raw = fs::read_to_string(path).map_err(|e| format!("read export failed: {e}"))?;
+  Ok(Zeroizing::new(raw))
+}
+
+/// One login in an [`ImportPreview`], without its password.
+#[derive(Clone, Debug, Serialize)]
+pub struct ImportItem {
+  /// Position in the batch, passed back to pick the logins to import.
+  pub index: usize,
+  pub title: String,
+  pub url: String,
+  pub username: String,
+  /// Entry with the same site and username, if the vault has one.
+  pub duplicate_of: Option<String>,
+  /// Whether that entry also has the same password.
+  pub same_password: bool,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ImportPreview {
+  pub source: ImportSource,
+  pub items: Vec<ImportItem>,
+  pub skipped: Vec<Skipped>,
+}
+
+fn default_title(url: &str) -> String {
+  match normalize_host(url) {
+    Some(host) => host.strip_prefix("www.").unwrap_or(&host).to_string(),
+    None if !url.trim().is_empty() => url.trim().to_string(),
+    None => "Imported login".to_string(),
+  }
+}
+
+/// Same site (ignoring scheme, path, and `www.`) and same username.
+fn same_login(url_a: &str, user_a: &str, url_b: &str, user_b: &str) -> bool {
+  let site = |url: &str| normalize_host(url).map(|h| h.strip_prefix("www.").unwrap_or(&h).to_string());
+  site(url_a) == site(url_b) && user_a.trim().eq_ignore_ascii_case(user_b.trim())
+}
+
+/// The entry stored for the same site and username, if any. The browser
+/// extension's save check uses this too, so both agree on duplicates.
+pub fn find_same_login<'a>(entries: &'a [Entry], url: &str, username: &str) -> Option<&'a Entry> {
+  entries
+    .iter()
+    .filter(|e| !e.url.trim().is_empty())
+    .find(|e| same_login(&e.url, &e.username, url, username))
+}
+
+/// Lists the batch for review, marking logins the vault already has.
+pub fn preview(batch: &ImportBatch, existing: &[Entry]) -> ImportPreview {
+  let items = batch
+    .logins
+    .iter()
+    .enumerate()
+    .map(|(index, login)| {
+      let duplicate = find_same_login(existing, &login.url, &login.username);
+      ImportItem {
+        index,
+        title: login.title.clone(),
+        url: login.url.clone(),
+        username: login.username.clone(),
+        duplicate_of: duplicate.map(|e| e.id.clone()),
+        same_password: duplicate.is_some_and(|e| e.password == login.password),
+      }
+    })
+    .collect();
+  ImportPreview {
+    source: batch.source,
+    items,
+    skipped: batch.skipped.clone(),
+  }
+}
+
+/// Turns the logins at `indices` into new entries tagged with the source.
+pub fn into_entries(mut batch: ImportBatch, indices: &[usize]) -> Result<Vec<Entry>, String> {
+  let tag = batch.source.name().to_lowercase().replace(' ', "-");
+  let mut entries = Vec::with_capacity(indices.len());
+  for &index in indices {
+    let login = batch
+      .logins
+      .get_mut(index)
+      .ok_or_else(|| format!("no login at position {index}"))?;
+    let mut entry = Entry::new(
+      std::mem::take(&mut login.title),
+      std::mem::take(&mut login.username),
+      std::mem::take(&mut login.password),
+      std::mem::take(&mut login.url),
+      std::mem::take(&mut login.notes),
+    );
+    entry.totp_secret = Some(std::mem::take(&mut login.totp)).filter(|t| !t.trim().is_empty());
+    entry.tags = vec![format!("imported-{tag}")];
+    entries.push(entry);
+  }
+  Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn login(url: &str, username: &str, password: &str) -> ImportedItem {
+    ImportedItem {
+      title: String::new(),
+      url: url.into(),
+      username: username.into(),
+      password: password.into(),
+      notes: String::new(),
+      totp: String::new(),
+    }
+  }
+
+  #[test]
+  fn preview_marks_logins_the_vault_already_has() {
+    let existing = vec![Entry::new(
+      "Mail".into(),
+      "Me@example.com".into(),
+      "hunter2".into(),
+      "https://mail.example.com".into(),
+      String::new(),
+    )];
+    let batch = ImportBatch::new(
+      ImportSource::Chrome,
+      vec![
+        login("https://www.mail.example.com/login", "me@example.com", "hunter2"),
+        login("https://mail.example.com/", "me@example.com", "hunter2"),
+        login("https://shop.example.com", "me@example.com", "s3cret"),
+      ],
+      Vec::new(),
+    );
+    assert_eq!(batch.logins.len(), 2, "repeated rows are dropped");
+
+    let preview = preview(&batch, &existing);
+    assert_eq!(preview.items[0].title, "mail.example.com");
+    assert_eq!(preview.items[0].duplicate_of.as_deref(), Some(existing[0].id.as_str()));
+    assert!(preview.items[0].same_password);
+    assert_eq!(preview.items[1].duplicate_of, None);
+
+    let entries = into_entries(batch, &[1]).unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].title, "shop.example.com");
+    assert_eq!(entries[0].tags, vec!["imported-chrome".to_string()]);
+  }
+}
@@ -0,0 +1,239 @@
+//! Web logins saved in the operating system's credential store.
+//!
+//! - macOS: internet passwords in the login keychain. The item list is read
+//!   with `security dump-keychain`, which shows no secrets; each password is
+//!   then read with `security find-internet-password`, so the keychain asks
+//!   the user to allow or deny every item.
+//! - Windows: the "Web Credentials" in Credential Manager, read through the
+//!   `PasswordVault` API.
+//!
+//! Other platforms have no such store to import from.
+
+use super::{ImportSource, ImportedItem, Importer, Skipped};
+
+/// The operating system's credential store.
+pub struct SystemStore(ImportSource);
+
+/// The credential store on this platform, if it has one.
+pub fn store() -> Option<SystemStore> {
+  if cfg!(target_os = "macos") {
+    Some(SystemStore(ImportSource::Keychain))
+  } else if cfg!(windows) {
+    Some(SystemStore(ImportSource::CredentialManager))
+  } else {
+    None
+  }
+}
+
+impl Importer for SystemStore {
+  fn source(&self) -> ImportSource {
+    self.0
+  }
+
+  fn read(&self) -> Result<Vec<Result<ImportedItem, Skipped>>, String> {
+    read_logins()
+  }
+}
+
+/// An internet password item, without its secret.
+#[cfg(any(target_os = "macos", test))]
+#[derive(Debug, Default, PartialEq, Eq)]
+struct KeychainItem {
+  server: String,
+  account: String,
+  protocol: String,
+  port: u32,
+}
+
+#[cfg(any(target_os = "macos", test))]
+impl KeychainItem {
+  fn url(&self) -> String {
+    let scheme = match self.protocol.as_str() {
+      "htps" => "https",
+      "http" => "http",
+      other => other.trim(),
+    };
+    let port = match self.port {
+      0 => String::new(),
+      port => format!(":{port}"),
+    };
+    format!("{scheme}://{}{port}", self.server)
+  }
+}
+
+/// Decodes a `dump-keychain` attribute value: `"text"`, `0x<hex>  "text"`,
+/// or `<NULL>`.
+#[cfg(any(target_os = "macos", test))]
+fn attribute_value(raw: &str) -> Option<String> {
+  let raw = raw.trim();
+  if let Some(quoted) = raw.strip_prefix('"') {
+    return quoted.strip_suffix('"').map(String::from);
+  }
+  let hex = raw.strip_prefix("0x")?.split_whitespace().next()?;
+  let bytes = (0..hex.len() / 2)
+    .map(|i| u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16))
+    .collect::<Result<Vec<u8>, _>>()
+    .ok()?;
+  Some(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Internet password items listed by `security dump-keychain`.
+#[cfg(any(target_os = "macos", test))]
+fn parse_dump(dump: &str) -> Vec<KeychainItem> {
+  let mut items = Vec::new();
+  for record in dump.split("keychain: ").skip(1) {
+    if !record.lines().any(|line| line.trim() == "class: \"inet\"") {
+      continue;
+    }
+    let mut item = KeychainItem::default();
+    for line in record.lines() {
+      let line = line.trim();
+      let Some((name, raw)) = line.split_once('=') else {
+        continue;
+      };
+      let name = name.split('<').next().unwrap_or_default();
+      if name == "\"port\"" {
+        let hex = raw.trim().trim_start_matches("0x");
+        item.port = u32::from_str_radix(hex, 16).unwrap_or(0);
+        continue;
+      }
+      let Some(value) = attribute_value(raw) else {
+        continue;
+      };
+      match name {
+        "\"srvr\"" => item.server = value,
+        "\"acct\"" => item.account = value,
+        "\"ptcl\"" => item.protocol = value,
+        _ => {}
+      }
+    }
+    if !item.server.is_empty() {
+      items.push(item);
+    }
+  }
+  items
+}
+
+#[cfg(target_os = "macos")]
+fn read_logins() -> Result<Vec<Result<ImportedItem, Skipped>>, String> {
+  use std::process::Command;
+  use zeroize::Zeroizing;
+
+  let dump = Command::new("security")
+    .arg("dump-keychain")
+    .output()
+    .map_err(|e| format!("keychain: {e}"))?;
+  if !dump.status.success() {
+    return Err("the keychain could not be listed".to_string());
+  }
+  let mut items = Vec::new();
+  for item in parse_dump(&String::from_utf8_lossy(&dump.stdout)) {
+    let mut lookup = Command::new("security");
+    lookup.args(["find-internet-password", "-w", "-s", &item.server]);
+    if !item.account.is_empty() {
+      lookup.args(["-a", &item.account]);
+    }
+    // Shows the keychain's own prompt for this item.
+    let password = lookup
+      .output()
+      .ok()
+      .filter(|output| output.status.success())
+      .map(|output| Zeroizing::new(output.stdout))
+      .and_then(|stdout| String::from_utf8(stdout.strip_suffix(b"\n").unwrap_or(&stdout).to_vec()).ok());
+    items.push(match password {
+      Some(password) => Ok(ImportedItem {
+        title: String::new(),
+        url: item.url(),
+        username: item.account,
+        password,
+        notes: String::new(),
+        totp: String::new(),
+      }),
+      None => Err(Skipped {
+        item: item.server,
+        reason: "access was denied or the item could not be read".to_string(),
+      }),
+    });
+  }
+  Ok(items)
+}
+
+#[cfg(windows)]
+fn read_logins() -> Result<Vec<Result<ImportedItem, Skipped>>, String> {
+  use windows::core::HRESULT;
+  use windows::Security::Credentials::PasswordVault;
+
+  // Returned by `RetrieveAll` when there are no web credentials.
+  const ELEMENT_NOT_FOUND: HRESULT = HRESULT(0x8007_0490_u32 as i32);
+
+  let vault = PasswordVault::new().map_err(|e| format!("Credential Manager: {e}"))?;
+  let credentials = match vault.RetrieveAll() {
+    Ok(credentials) => credentials,
+    Err(e) if e.code() == ELEMENT_NOT_FOUND => return Ok(Vec::new()),
+    Err(e) => return Err(format!("Credential Manager: {e}")),
+  };
+  let mut items = Vec::new();
+  for credential in &credentials {
+    let resource = credential.Resource().map(|r| r.to_string()).unwrap_or_default();
+    let password = credential
+      .RetrievePassword()
+      .and_then(|()| credential.Password())
+      .map(|p| p.to_string());
+    items.push(match password {
+      Ok(password) => Ok(ImportedItem {
+        title: String::new(),
+        url: resource,
+        username: credential.UserName().map(|u| u.to_string()).unwrap_or_default(),
+        password,
+        notes: String::new(),
+        totp: String::new(),
+      }),
+      Err(e) => Err(Skipped {
+        item: resource,
+        reason: format!("the password could not be read: {e}"),
+      }),
+    });
+  }
+  Ok(items)
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn read_logins() -> Result<Vec<Result<ImportedItem, Skipped>>, String> {
+  Err("this platform has no credential store to import from".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parses_internet_passwords_from_a_keychain_dump() {
+    let dump = r#"keychain: "/Users/me/Library/Keychains/login.keychain-db"
+version: 512
+class: "genp"
+attributes:
+    "svce"<blob>="Chrome Safe Storage"
+keychain: "/Users/me/Library/Keychains/login.keychain-db"
+version: 512
+class: "inet"
+attributes:
+    0x00000007 <blob>="example.com"
+    "acct"<blob>=0x6DC3A9406578616D706C652E636F6D  "m\303\251@example.com"
+    "path"<blob>=<NULL>
+    "port"<uint32>=0x00001F90
+    "ptcl"<uint32>="htps"
+    "srvr"<blob>="example.com"
+"#;
+    let items = parse_dump(dump);
+    assert_eq!(
+      items,
+      vec![KeychainItem {
+        server: "example.com".into(),
+        account: "mé@example.com".into(),
+        protocol: "htps".into(),
+        port: 8080,
+      }]
+    );
+    assert_eq!(items[0].url(), "https://example.com:8080");
+  }
+}
@@ -1,10 +1,41 @@
 //! The Organizer - Rust backend library for the password manager.
 //!
-//! This crate provides the core functionality for The Organizer password manager:
+//! This crate provides the desktop app on top of `organizer_core`, which
+//! owns the vault format, cryptography, and data model:
 //!
+//! - [`activity`] - Activity tracking by source for auto-lock
+//! - [`audit`] - Append-only log of notable activity (no secrets)
+//! - [`bridge_session`] - Time-boxed extension sessions re-approved from the desktop
+//! - [`cli`] - Command-line modes such as `run-with-env`
+//! - [`clipboard`] - Clipboard copy with automatic clearing
 //! - [`commands`] - Tauri IPC command handlers for frontend communication
-//! - [`models`] - Data structures and application state management
-//! - [`vault`] - Encryption, decryption, and key derivation
+//! - [`convert`] - Vault, CSV, and KeePass file conversion outside the live vault
+//! - [`crash_recovery`] - Vault checks after a run that did not shut down cleanly
+//! - [`devsecrets`] - `.env` export and environment injection for API keys
+//! - [`docker_credential`] - docker credential helper requests answered from vault entries
+//! - [`folder_export`] - Folders exported to their own password-protected files
+//! - [`generator_history`] - Recently generated passwords, sealed in memory
+//! - [`git_credential`] - git credential helper requests answered from vault entries
+//! - [`history`] - Field-level change history of entries
+//! - [`hooks`] - Opt-in programs and local webhooks run on lock, unlock, and save
+//! - [`host_index`] - Entry ids by host for the extension bridge
+//! - [`import`] - Importing logins from browsers and other password stores
+//! - [`models`] - Application state (re-exports the core data model)
+//! - [`monitor`] - Inactivity monitor for auto-lock, restarted by a watchdog if it stops
+//! - [`os_session`] - Locking when the OS session is switched away or locked
+//! - [`password_policy`] - Named password rules checked when passwords are set
+//! - [`peer`] - Identifying the local process behind a bridge connection
+//! - [`persist`] - Vault writes with a retry queue for failed saves
+//! - [`phishing`] - Lookalike-domain detection for the extension bridge
+//! - [`quick`] - Quick-access search window summoned by a global hotkey
+//! - [`sequence`] - Username, password, and TOTP copy sequences
+//! - [`security`] - Local vault health checks (stale accounts, missing 2FA, expiring keys)
+//! - [`settings`] - Persisted lockout and auto-lock policy
+//! - [`shared_vault`] - Vault files shared between people and merged into a folder
+//! - [`shutdown`] - Flushing, locking, and clearing the clipboard on exit
+//! - [`sshkeys`] - In-process SSH keypair generation
+//! - [`totp`] - TOTP codes (RFC 6238) from stored secrets
+//! - [`journal`], [`limits`], [`store`], [`vault`] - Re-exported from `organizer_core`
 //!
 //! # Architecture
 //!
@@ -15,10 +46,7 @@
 //! commands.rs ─── IPC handlers
 //!     │
 //!     ▼
-//! vault.rs ────── Encryption/decryption
-//!     │
-//!     ▼
-//! models.rs ───── Data structures
+//! organizer-core ─ vault.rs (encryption), models.rs (data structures)
 //!     │
 //!     ▼
 //! Disk ────────── vault.dat (encrypted)
@@ -34,10 +62,57 @@
 
 use tauri::Wry;
 
+pub mod activity;
+pub mod audit;
+pub mod backup;
+pub mod breach;
+pub mod bridge_session;
+pub mod cli;
+pub mod clipboard;
 pub mod commands;
+pub mod convert;
+pub mod crash_recovery;
+pub mod device;
+pub mod devsecrets;
+pub mod docker_credential;
+pub mod diagnostics;
 pub mod extension;
+pub mod folder_export;
+pub mod generator_history;
+pub mod git_credential;
+pub mod history;
+pub mod hooks;
+pub mod host_index;
+pub mod import;
+pub mod merge;
 pub mod models;
-pub mod vault;
+pub mod monitor;
+pub mod notifications;
+pub mod ordering;
+pub mod os_session;
+pub mod password_policy;
+pub mod peer;
+pub mod persist;
+pub mod phishing;
+#[cfg(windows)]
+pub mod pipe;
+pub mod quick;
+pub mod reveal;
+pub mod scheduled_audit;
+pub mod searches;
+pub mod security;
+pub mod sequence;
+pub mod settings;
+pub mod shared_vault;
+pub mod shred;
+pub mod shutdown;
+pub mod sshkeys;
+pub mod sync;
+pub mod totp;
+
+#[cfg(feature = "sqlite")]
+pub use organizer_core::sqlite_store;
+pub use organizer_core::{journal, limits, store, vault};
 
 /// Creates the Tauri invoke handler with all registered commands.
 ///
@@ -56,10 +131,134 @@ pub fn create_invoke_handler() -> impl Fn(tauri::ipc::Invoke<Wry>) -> bool + Sen
         commands::update_entry,
         commands::delete_entry,
         commands::copy_secret,
+        commands::copy_field,
         commands::export_vault,
         commands::import_vault,
         commands::get_extension_config,
         commands::set_extension_enabled,
-        commands::rotate_extension_token
+        commands::rotate_extension_token,
+        commands::create_extension_token,
+        commands::revoke_extension_token,
+        commands::set_extension_transport,
+        commands::set_extension_auto_disable,
+        commands::add_autofill_exclusion,
+        commands::remove_autofill_exclusion,
+        commands::get_extension_endpoint,
+        commands::generate_emergency_kit,
+        commands::get_stale_entries,
+        commands::get_2fa_suggestions,
+        commands::get_pending_extension_origins,
+        commands::approve_extension_origin,
+        commands::revoke_extension_origin,
+        commands::set_extension_session_hours,
+        commands::get_pending_extension_sessions,
+        commands::approve_extension_session,
+        commands::deny_extension_session,
+        commands::end_extension_sessions,
+        commands::generate_ssh_key,
+        commands::get_ssh_public_key,
+        commands::get_expiring_api_keys,
+        commands::export_env,
+        commands::run_with_secrets,
+        commands::migrate_vault_to_sqlite,
+        commands::retry_save,
+        commands::get_vault_stats,
+        commands::get_activity,
+        commands::get_settings,
+        commands::set_lockout_policy,
+        commands::set_retention_policy,
+        commands::set_vault_hooks,
+        commands::set_password_policies,
+        commands::check_password_policy,
+        commands::record_generated_password,
+        commands::get_generator_history,
+        commands::copy_generated,
+        commands::clear_generator_history,
+        commands::set_generator_history,
+        commands::set_copy_lock,
+        commands::set_privacy_mode,
+        commands::expand_entry,
+        commands::search_entries,
+        commands::set_presentation_mode,
+        commands::get_presentation_mode,
+        commands::get_canary_entry,
+        commands::set_canary_entry,
+        commands::set_lockout_preset,
+        commands::get_unlock_history,
+        commands::quick_search,
+        commands::quick_copy,
+        commands::hide_quick_window,
+        commands::copy_sequence,
+        commands::next_in_sequence,
+        commands::cancel_copy_sequence,
+        commands::reveal_field,
+        commands::get_entry_activity,
+        commands::get_activity_feed,
+        commands::conceal_all,
+        commands::set_reveal_policy,
+        commands::get_vault_hint,
+        commands::set_vault_hint,
+        commands::set_password_reminder,
+        commands::run_diagnostics,
+        commands::get_recovery_report,
+        commands::dismiss_recovery_report,
+        commands::set_breach_dataset,
+        commands::check_breached_passwords,
+        commands::get_security_report,
+        commands::estimate_password_strength,
+        commands::set_scheduled_audit,
+        commands::get_security_audit,
+        commands::set_notification_policy,
+        commands::reorder_entries,
+        commands::restore_entry,
+        commands::get_recently_modified,
+        commands::get_recently_deleted,
+        commands::get_saved_searches,
+        commands::save_search,
+        commands::delete_saved_search,
+        commands::run_saved_search,
+        commands::save_vault,
+        commands::set_save_mode,
+        commands::compact_vault,
+        commands::rotate_vault_key,
+        commands::merge_vault,
+        commands::enable_sync,
+        commands::disable_sync,
+        commands::get_sync_status,
+        commands::sync_now,
+        commands::start_device_enrollment,
+        commands::poll_device_enrollment,
+        commands::confirm_device_enrollment,
+        commands::join_sync,
+        commands::finish_join_sync,
+        commands::start_lan_sync,
+        commands::stop_lan_sync,
+        commands::discover_lan_peers,
+        commands::connect_lan_peer,
+        commands::confirm_lan_peer,
+        commands::set_backup_schedule,
+        commands::get_backup_status,
+        commands::preview_backup,
+        commands::restore_backup,
+        commands::list_browser_profiles,
+        commands::preview_browser_import,
+        commands::commit_import,
+        commands::cancel_import,
+        commands::get_system_import_source,
+        commands::preview_system_import,
+        commands::preview_file_import,
+        commands::convert_file,
+        commands::export_folder,
+        commands::list_folder_exports,
+        commands::run_folder_export,
+        commands::set_folder_export_schedule,
+        commands::remove_folder_export,
+        commands::create_shared_vault,
+        commands::join_shared_vault,
+        commands::list_shared_vaults,
+        commands::sync_shared_vault,
+        commands::leave_shared_vault,
+        commands::get_entry_history,
+        commands::get_storage_timings
     ]
 }
@@ -4,30 +4,60 @@
 //! - Initializes the application state
 //! - Registers all IPC command handlers
 //! - Starts the inactivity monitor for auto-lock functionality
+//! - Shuts down cleanly on exit (see [`the_organizer::shutdown`])
+//!
+//! Command-line modes (see [`the_organizer::cli`]) run instead of the GUI
+//! when requested by the first argument.
 //!
 //! # Auto-Lock
 //!
-//! A background thread monitors user inactivity. If the vault is unlocked
-//! and no user interaction occurs for 5 minutes, the vault is automatically
-//! locked to protect sensitive data.
+//! A background thread monitors inactivity. If the vault is unlocked and no
+//! activity source (UI, extension, CLI) has been active within its timeout
+//! (5 minutes by default), the vault is automatically locked to protect
+//! sensitive data. Until then, the same thread runs the opt-in weekly
+//! security audit once the UI is idle. Each pass records a heartbeat; the
+//! event loop restarts the monitor if it panics or stalls (see
+//! [`the_organizer::monitor`]). Switching to another OS user locks
+//! right away (see [`the_organizer::os_session`]).
 
-use std::thread;
-use std::time::{Duration, Instant};
-use tauri::Manager;
+use tauri::{Manager, RunEvent, WindowEvent};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, ShortcutState};
 
+use the_organizer::backup;
+use the_organizer::cli;
+use the_organizer::crash_recovery;
 use the_organizer::create_invoke_handler;
+use the_organizer::device;
 use the_organizer::extension;
-use the_organizer::models::{AppState, INACTIVITY_POLL_SECS, INACTIVITY_TIMEOUT_SECS};
+use the_organizer::models::AppState;
+use the_organizer::monitor;
+use the_organizer::os_session;
+use the_organizer::persist;
+use the_organizer::quick;
+use the_organizer::settings;
+use the_organizer::shutdown;
 
 fn main() {
+  if let Some(code) = cli::run_from_args() {
+    std::process::exit(code);
+  }
+
   let builder = tauri::Builder::default()
     .plugin(tauri_plugin_dialog::init())
+    .plugin(tauri_plugin_notification::init())
+    .plugin(
+      tauri_plugin_global_shortcut::Builder::new()
+        .with_handler(|app, _shortcut, event| {
+          if event.state() == ShortcutState::Pressed {
+            quick::show_window(app);
+          }
+        })
+        .build(),
+    )
     .manage(AppState::default())
     .invoke_handler(create_invoke_handler())
     .setup(|app| {
       let state: AppState = app.state::<AppState>().inner().clone();
-      let poll = Duration::from_secs(INACTIVITY_POLL_SECS);
-      let timeout = Duration::from_secs(INACTIVITY_TIMEOUT_SECS);
 
       match extension::load_or_create_config(&app.handle()) {
         Ok(config) => {
@@ -39,39 +69,83 @@ fn main() {
           eprintln!("extension config load failed: {err}");
         }
       }
-      extension::start_extension_server(&app.handle(), state.clone());
-
-      thread::spawn(move || loop {
-        thread::sleep(poll);
-
-        let last = match state.last_interaction.lock() {
-          Ok(g) => *g,
-          Err(_) => {
-            // Poisoned mutex: safest behavior is to lock.
-            state.lock_now();
-            continue;
+      match device::load_or_create(&app.handle()) {
+        Ok(identity) => {
+          if let Ok(mut guard) = state.device.lock() {
+            *guard = Some(identity);
           }
-        };
-
-        let is_unlocked = match state.session.lock() {
-          Ok(g) => g.is_some(),
-          Err(_) => {
-            state.lock_now();
-            continue;
+        }
+        Err(err) => {
+          eprintln!("device identity unavailable: {err}");
+        }
+      }
+      match backup::load_status(&app.handle()) {
+        Ok(status) => {
+          if let Ok(mut guard) = state.backup.lock() {
+            *guard = status;
           }
-        };
-
-        if is_unlocked && Instant::now().duration_since(last) > timeout {
-          state.lock_now();
         }
-      });
+        Err(err) => {
+          eprintln!("backup status unavailable: {err}");
+        }
+      }
+      match settings::load(&app.handle()) {
+        Ok(loaded) => {
+          if let Ok(mut guard) = state.settings.lock() {
+            *guard = loaded;
+          }
+        }
+        Err(err) => {
+          eprintln!("settings load failed, using defaults: {err}");
+        }
+      }
+      // After the backup status, which the report points to.
+      if let Err(err) = crash_recovery::start(&app.handle(), &state) {
+        eprintln!("crash recovery check failed: {err}");
+      }
+      extension::start_extension_server(&app.handle(), state.clone());
+      os_session::start(&app.handle(), state.clone());
+
+      // Another app may already own the hotkey; the quick window is optional.
+      if let Err(err) = app.global_shortcut().register(quick::DEFAULT_SHORTCUT) {
+        eprintln!("quick access shortcut not registered: {err}");
+      }
+
+      monitor::start(&app.handle(), &state);
 
       Ok(())
     });
 
   // Do not unwrap/expect.
-  let result = builder.run(tauri::generate_context!());
-  if let Err(e) = result {
-    eprintln!("tauri run error: {e}");
-  }
+  let app = match builder.build(tauri::generate_context!()) {
+    Ok(app) => app,
+    Err(e) => {
+      eprintln!("tauri run error: {e}");
+      return;
+    }
+  };
+  app.run(|handle, event| {
+    let state = handle.state::<AppState>();
+    match event {
+      RunEvent::Exit => shutdown::run(handle, state.inner()),
+      // Quitting would discard changes that could not be written; the main
+      // window reports the failure and offers a retry or a lock without them.
+      RunEvent::WindowEvent {
+        label,
+        event: WindowEvent::CloseRequested { api, .. },
+        ..
+      } if label == "main" => {
+        if persist::flush(state.inner(), Some(handle)).is_err() {
+          api.prevent_close();
+        }
+      }
+      // The hidden quick window would otherwise keep the app running.
+      RunEvent::WindowEvent {
+        label,
+        event: WindowEvent::Destroyed,
+        ..
+      } if label == "main" => handle.exit(0),
+      _ => monitor::supervise(handle, state.inner()),
+    }
+  });
 }
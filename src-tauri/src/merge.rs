@@ -0,0 +1,271 @@
+//! Merging another copy of the vault into the unlocked one.
+//!
+//! Entries are matched by id. Without revision history, "changed" means
+//! edited since the last merge with the same peer
+//! (`VaultMeta::last_merged`): a side that changed wins over one that did
+//! not. If both changed (or this is the first merge with the peer) and the
+//! versions differ, the newer version keeps the id and the other is kept as
+//! a conflict copy titled "<title> (conflict from <device> <date>)", so a
+//! merge never silently discards an edit.
+//!
+//! Deletions travel as tombstones (`VaultData::tombstones`): an entry the
+//! other side deleted is moved to the trash here unless it was edited after
+//! the deletion, and an entry deleted here is not brought back by a copy
+//! that still has it. Tombstones from both sides are kept, except for
+//! entries that outlived them.
+//!
+//! Merging is symmetric: when two devices merge each other's copies at the
+//! same time (LAN sync), both pick the same winner and give the conflict
+//! copy the same id, so they end up with the same entries.
+
+use crate::models::{Entry, VaultData};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use uuid::Builder;
+
+/// Who the two sides of a merge are.
+pub struct MergeSides<'a> {
+  /// Stable id of the other copy (its device id, or its vault id).
+  pub peer_id: &'a str,
+  /// Names used in conflict copy titles.
+  pub local_label: &'a str,
+  pub remote_label: &'a str,
+}
+
+/// An entry edited on both sides, and the copy that keeps the losing version.
+#[derive(Clone, Debug, Serialize)]
+pub struct MergeConflict {
+  pub entry_id: String,
+  pub copy_id: String,
+  pub copy_title: String,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct MergeReport {
+  /// Entries that only existed in the other copy.
+  pub added: usize,
+  /// Entries replaced by the other copy's newer version.
+  pub updated: usize,
+  /// Entries deleted in the other copy, now in the trash.
+  pub removed: usize,
+  pub conflicts: Vec<MergeConflict>,
+}
+
+/// The entry without its edit and usage times, revision, and history, for
+/// comparing versions.
+pub(crate) fn content(entry: &Entry) -> serde_json::Value {
+  let mut value = serde_json::to_value(entry).unwrap_or_default();
+  if let Some(map) = value.as_object_mut() {
+    map.remove("last_used_at");
+    map.remove("updated_at");
+    map.remove("revision");
+    map.remove("history");
+  }
+  value
+}
+
+/// A revision above both versions', so the local counter never goes back
+/// when the other version is taken.
+fn next_revision(ours: &Entry, theirs: &Entry) -> u64 {
+  ours.revision.max(theirs.revision).saturating_add(1)
+}
+
+/// `loser` titled as a conflict copy from `label`, under an id derived from
+/// the version it holds.
+fn conflict_copy(mut loser: Entry, label: &str) -> Entry {
+  let digest = Sha256::new()
+    .chain_update(loser.id.as_bytes())
+    .chain_update(content(&loser).to_string().as_bytes())
+    .finalize();
+  let mut bytes = [0u8; 16];
+  bytes.copy_from_slice(&digest[..16]);
+  loser.id = Builder::from_random_bytes(bytes).into_uuid().to_string();
+  loser.title = format!(
+    "{} (conflict from {} {})",
+    loser.title,
+    label,
+    loser.updated_at.format("%Y-%m-%d %H:%M")
+  );
+  loser
+}
+
+/// Merges `remote` into `local`. Entries only in `remote` are added unless
+/// they were deleted here and not edited after the deletion.
+pub fn merge(local: &mut VaultData, mut remote: VaultData, sides: &MergeSides<'_>, now: DateTime<Utc>) -> MergeReport {
+  let since = local.meta.last_merged.get(sides.peer_id).copied();
+  let changed = |e: &Entry| since.is_none_or(|t| e.updated_at > t);
+  let mut report = MergeReport::default();
+
+  for theirs in std::mem::take(&mut remote.entries) {
+    let Some(index) = local.entries.iter().position(|e| e.id == theirs.id) else {
+      if local.deleted_at(&theirs.id).is_none_or(|at| theirs.updated_at > at) {
+        local.trash.retain(|t| t.entry.id != theirs.id);
+        local.entries.push(theirs);
+        report.added += 1;
+      }
+      continue;
+    };
+
+    let ours = &mut local.entries[index];
+    let last_used_at = ours.last_used_at.max(theirs.last_used_at);
+    let (our_content, their_content) = (content(ours), content(&theirs));
+    if our_content == their_content {
+      ours.last_used_at = last_used_at;
+      continue;
+    }
+    // Equal times go to the more edited version, then by content, so both
+    // sides of a merge agree.
+    let theirs_newer = (theirs.updated_at, theirs.revision, their_content.to_string())
+      > (ours.updated_at, ours.revision, our_content.to_string());
+    let revision = next_revision(ours, &theirs);
+    let take_theirs = match (changed(ours), changed(&theirs)) {
+      (true, false) => false,
+      (false, true) => true,
+      (false, false) => theirs_newer,
+      (true, true) => {
+        let (winner, loser, label) = if theirs_newer {
+          (theirs, ours.clone(), sides.local_label)
+        } else {
+          (ours.clone(), theirs, sides.remote_label)
+        };
+        let copy = conflict_copy(loser, label);
+        report.conflicts.push(MergeConflict {
+          entry_id: winner.id.clone(),
+          copy_id: copy.id.clone(),
+          copy_title: copy.title.clone(),
+        });
+        local.entries[index] = winner;
+        local.entries[index].last_used_at = last_used_at;
+        local.entries[index].revision = revision;
+        local.entries.push(copy);
+        continue;
+      }
+    };
+    if take_theirs {
+      *ours = theirs;
+      ours.last_used_at = last_used_at;
+      ours.revision = revision;
+      report.updated += 1;
+    }
+  }
+
+  // Deletions from the other side, after the entries so that links the
+  // other side removed first are gone. A linked entry stays.
+  for tombstone in std::mem::take(&mut remote.tombstones) {
+    let outlived = local
+      .find_entry(&tombstone.id)
+      .is_some_and(|e| e.updated_at > tombstone.deleted_at);
+    if !outlived && local.entries_linking_to(&tombstone.id).is_empty() {
+      if local.trash_entry(&tombstone.id, tombstone.deleted_at) {
+        report.removed += 1;
+      }
+      local.record_deletion(&tombstone.id, tombstone.deleted_at);
+    }
+  }
+  let entries = &local.entries;
+  local.tombstones.retain(|t| !entries.iter().any(|e| e.id == t.id));
+
+  local.meta.last_merged.insert(sides.peer_id.to_string(), now);
+  report
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use chrono::Duration;
+
+  fn ids(data: &VaultData) -> Vec<String> {
+    let mut ids: Vec<String> = data.entries.iter().map(|e| e.id.clone()).collect();
+    ids.sort();
+    ids
+  }
+
+  #[test]
+  fn edits_on_both_sides_keep_a_conflict_copy() {
+    let desktop = MergeSides {
+      peer_id: "laptop",
+      local_label: "DESKTOP",
+      remote_label: "LAPTOP-WORK",
+    };
+    let laptop = MergeSides {
+      peer_id: "desktop",
+      local_label: "LAPTOP-WORK",
+      remote_label: "DESKTOP",
+    };
+    let t0 = Utc::now();
+    let mut local = VaultData::default();
+    let mut entry = Entry::new("Bank".into(), "me".into(), "old".into(), String::new(), String::new());
+    entry.updated_at = t0;
+    local.entries.push(entry.clone());
+
+    // First merge: the versions differ and there is no common point.
+    let mut remote = VaultData::default();
+    entry.password = "laptop".into();
+    entry.updated_at = t0 + Duration::minutes(1);
+    remote.entries.push(entry.clone());
+    let mut other_side = remote.clone();
+    merge(&mut other_side, local.clone(), &laptop, t0 + Duration::minutes(2));
+    let report = merge(&mut local, remote.clone(), &desktop, t0 + Duration::minutes(2));
+    assert_eq!(report.conflicts.len(), 1);
+    assert_eq!(local.entries[0].password, "laptop");
+    assert!(local.entries[1].title.starts_with("Bank (conflict from DESKTOP "));
+    assert_eq!(local.entries[1].password, "old");
+    // Merging the other way round gives the same entries.
+    assert_eq!(ids(&local), ids(&other_side));
+
+    // Only the remote changed since: it wins without a copy.
+    entry.password = "newer".into();
+    entry.updated_at = t0 + Duration::minutes(3);
+    remote.entries = vec![entry];
+    let revision = local.entries[0].revision;
+    let report = merge(&mut local, remote, &desktop, t0 + Duration::minutes(4));
+    assert!(report.conflicts.is_empty());
+    assert_eq!(report.updated, 1);
+    assert_eq!(local.entries[0].password, "newer");
+    assert!(local.entries[0].revision > revision, "revisions never go back");
+    assert_eq!(local.entries.len(), 2);
+  }
+
+  #[test]
+  fn deletions_are_not_undone_by_a_copy_that_still_has_the_entry() {
+    let sides = MergeSides {
+      peer_id: "laptop",
+      local_label: "DESKTOP",
+      remote_label: "LAPTOP",
+    };
+    let t0 = Utc::now();
+    let mut local = VaultData::default();
+    for title in ["Bank", "Mail"] {
+      let mut entry = Entry::new(title.into(), "me".into(), "pw".into(), String::new(), String::new());
+      entry.updated_at = t0;
+      local.entries.push(entry);
+    }
+    let mut remote = local.clone();
+    let (bank, mail) = (local.entries[0].id.clone(), local.entries[1].id.clone());
+
+    // Deleted here, trash emptied: the tombstone keeps it deleted.
+    local.trash_entry(&bank, t0 + Duration::minutes(1));
+    local.trash.clear();
+    // Deleted on the other side: moved to the trash here.
+    remote.trash_entry(&mail, t0 + Duration::minutes(1));
+    let report = merge(&mut local, remote, &sides, t0 + Duration::minutes(2));
+    assert_eq!(report.added, 0);
+    assert_eq!(report.removed, 1);
+    assert!(local.entries.is_empty());
+    assert_eq!(local.trash[0].entry.id, mail);
+    assert_eq!(local.tombstones.len(), 2);
+
+    // An edit after the deletion brings the entry back.
+    let mut edited = Entry::new("Bank".into(), "me".into(), "new".into(), String::new(), String::new());
+    edited.id = bank.clone();
+    edited.updated_at = t0 + Duration::minutes(3);
+    let remote = VaultData {
+      entries: vec![edited],
+      ..VaultData::default()
+    };
+    merge(&mut local, remote, &sides, t0 + Duration::minutes(4));
+    assert_eq!(ids(&local), vec![bank.clone()]);
+    assert!(local.tombstones.iter().all(|t| t.id != bank));
+  }
+}
@@ -1,59 +1,144 @@
-//! Core data structures and application state for The Organizer.
+//! Application state for The Organizer.
 //!
-//! This module defines all the fundamental types used throughout the application:
-//! - [`Entry`] - A password entry with secure memory handling
+//! The vault data model ([`Entry`], [`VaultData`], ...) lives in
+//! `organizer_core` and is re-exported here. This module adds the
+//! app-specific types:
 //! - [`VaultSession`] - Active session containing the derived encryption key
 //! - [`FailedAttemptTracker`] - Rate limiting for failed unlock attempts
 //! - [`AppState`] - Central application state shared across threads
 //!
 //! # Security
 //!
-//! - All sensitive data implements [`Zeroize`] to securely clear memory on drop
 //! - The master password is never stored; only the derived key is kept in memory
 //! - Session keys are wrapped in [`Zeroizing`] for automatic secure cleanup
 
+pub use organizer_core::models::*;
+
+use crate::activity::{Activity, ActivitySource};
+use crate::backup::BackupStatus;
+use crate::bridge_session::BridgeSessions;
+use crate::crash_recovery::RecoveryReport;
+use crate::device::DeviceIdentity;
+use crate::diagnostics::StorageTimings;
+use crate::extension::BridgeServer;
+use crate::generator_history::GeneratorHistory;
+use crate::hooks::{self, VaultEvent};
+use crate::host_index::HostIndex;
+use crate::import::ImportBatch;
+use crate::monitor::Watchdog;
+use crate::peer::PeerProcess;
+use crate::persist::PendingWrites;
+use crate::reveal::RevealTracker;
+use crate::scheduled_audit::SecurityAudit;
+use crate::sequence::CopySequence;
+use crate::sync::lan::{Advertiser, LanSession};
+use crate::sync::Enrollment;
+use crate::settings::{LockoutPolicy, Settings};
+use crate::store::VaultStore;
+use crate::vault::{VaultHeader, WriterStamp};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::Instant;
 use uuid::Uuid;
-use zeroize::Zeroize;
 use zeroize::Zeroizing;
 
-/// Filename for the encrypted vault file.
-pub const VAULT_FILENAME: &str = "vault.dat";
-
-/// Current vault file format version (v1).
-pub const VAULT_FORMAT_VERSION: u8 = 0x01;
-
-/// Length of the salt used for key derivation (32 bytes).
-pub const SALT_LEN: usize = 32;
-
-/// Length of the nonce used for XChaCha20-Poly1305 encryption (24 bytes).
-pub const NONCE_LEN: usize = 24;
-
 /// How often the inactivity monitor checks for timeout (10 seconds).
 pub const INACTIVITY_POLL_SECS: u64 = 10;
 
 /// Auto-lock timeout duration (5 minutes of inactivity).
 pub const INACTIVITY_TIMEOUT_SECS: u64 = 300;
 
-/// Maximum failed unlock attempts before lockout.
-pub const MAX_FAILED_ATTEMPTS: u32 = 5;
-
-/// Duration of lockout after exceeding failed attempts (30 seconds).
-pub const LOCKOUT_DURATION_SECS: u64 = 30;
-
 /// Default port for the browser extension local API bridge.
 pub const EXTENSION_DEFAULT_PORT: u16 = 17832;
 
+/// How clients reach the browser extension bridge.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BridgeTransport {
+  /// HTTP on `127.0.0.1:<port>`, which any local process can connect to.
+  #[default]
+  Tcp,
+  /// HTTP on a Unix domain socket, or on Windows a named pipe, that only
+  /// the current user can open.
+  Socket,
+}
+
+/// Why the extension bridge turned itself off.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AutoDisableReason {
+  /// No request for `disable_after_idle_days`.
+  Unused,
+  /// The vault stayed locked for `disable_after_locked_minutes`.
+  LockedTooLong,
+}
+
+/// What a bridge token may do. Scopes are ordered: each allows everything
+/// the ones before it do.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenScope {
+  /// `/v1/status` and `/v1/capabilities` only.
+  Status,
+  /// Also entry matches and `/v1/exists`, but never secrets or identities.
+  ReadEntries,
+  /// Every endpoint.
+  Full,
+}
+
+/// An extra bridge token with a limited scope, e.g. for automation tools.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScopedToken {
+  pub token: String,
+  pub scope: TokenScope,
+  /// Name shown in the app, e.g. "Keyboard Maestro".
+  pub label: String,
+  pub created_at: DateTime<Utc>,
+}
+
 /// Configuration for the browser extension integration.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ExtensionConfig {
   pub enabled: bool,
   pub token: String,
   pub port: u16,
+  /// Takes effect the next time the app starts.
+  #[serde(default)]
+  pub transport: BridgeTransport,
+  /// Client origins (the `Origin` header, e.g. `chrome-extension://<id>`)
+  /// approved from the desktop app to receive secrets and identity data.
+  #[serde(default)]
+  pub approved_origins: Vec<String>,
+  /// Executable names or full paths besides known browsers allowed to read
+  /// secrets, for clients such as a native messaging host (see
+  /// [`crate::peer`]).
+  #[serde(default)]
+  pub allowed_processes: Vec<String>,
+  /// Days without an authorized request after which the bridge disables
+  /// itself (`None`: never).
+  #[serde(default)]
+  pub disable_after_idle_days: Option<u32>,
+  /// Minutes the vault may stay locked before the bridge disables itself
+  /// (`None`: never).
+  #[serde(default)]
+  pub disable_after_locked_minutes: Option<u32>,
+  /// Last authorized request, or when the bridge was enabled (recorded at
+  /// most hourly).
+  #[serde(default)]
+  pub last_used_at: Option<DateTime<Utc>>,
+  /// Set when the bridge disabled itself; cleared when it is re-enabled.
+  #[serde(default)]
+  pub auto_disabled: Option<AutoDisableReason>,
+  /// Tokens besides `token` (which has the full scope).
+  #[serde(default)]
+  pub scoped_tokens: Vec<ScopedToken>,
+  /// Hours a desktop-approved session lasts (see [`crate::bridge_session`]).
+  /// While set, full-scope tokens only reach the status endpoints and
+  /// session requests. `None`: sessions are off.
+  #[serde(default)]
+  pub session_hours: Option<u32>,
 }
 
 impl ExtensionConfig {
@@ -62,78 +147,45 @@ impl ExtensionConfig {
       enabled: false,
       token: Uuid::new_v4().to_string(),
       port: EXTENSION_DEFAULT_PORT,
+      transport: BridgeTransport::default(),
+      approved_origins: Vec::new(),
+      allowed_processes: Vec::new(),
+      disable_after_idle_days: None,
+      disable_after_locked_minutes: None,
+      last_used_at: None,
+      auto_disabled: None,
+      scoped_tokens: Vec::new(),
+      session_hours: None,
     }
   }
-}
 
-impl Default for ExtensionConfig {
-  fn default() -> Self {
-    Self::new()
-  }
-}
-
-/// A password entry stored in the vault.
-///
-/// Each entry contains credentials for a single account or service.
-/// Sensitive fields (especially `password`) are securely zeroized when
-/// the entry is dropped, preventing them from lingering in memory.
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct Entry {
-  /// Unique identifier (UUID v4).
-  pub id: String,
-  /// Display name for the entry.
-  pub title: String,
-  /// Username or email for the account.
-  pub username: String,
-  /// The secret password (zeroized on drop).
-  pub password: String,
-  /// URL of the service or website.
-  pub url: String,
-  /// Additional notes about the entry.
-  pub notes: String,
-  /// Timestamp when the entry was created.
-  pub created_at: DateTime<Utc>,
-  /// Timestamp of the last modification.
-  pub updated_at: DateTime<Utc>,
-}
-
-impl Entry {
-  /// Creates a new entry with a generated UUID and current timestamp.
-  pub fn new(title: String, username: String, password: String, url: String, notes: String) -> Self {
-    let now = Utc::now();
-    Self {
-      id: Uuid::new_v4().to_string(),
-      title,
-      username,
-      password,
-      url,
-      notes,
-      created_at: now,
-      updated_at: now,
+  /// The scope of `token`, or `None` if it is not a bridge token.
+  pub fn token_scope(&self, token: &str) -> Option<TokenScope> {
+    if token == self.token {
+      return Some(TokenScope::Full);
     }
+    self.scoped_tokens.iter().find(|t| t.token == token).map(|t| t.scope)
   }
 
-  /// Updates the `updated_at` timestamp to the current time.
-  pub fn touch(&mut self) {
-    self.updated_at = Utc::now();
+  /// Returns true if `origin` has been approved from the desktop app.
+  pub fn is_origin_approved(&self, origin: &str) -> bool {
+    self.approved_origins.iter().any(|o| o == origin)
   }
 }
 
-impl Zeroize for Entry {
-  fn zeroize(&mut self) {
-    self.id.zeroize();
-    self.title.zeroize();
-    self.username.zeroize();
-    self.password.zeroize();
-    self.url.zeroize();
-    self.notes.zeroize();
+impl Default for ExtensionConfig {
+  fn default() -> Self {
+    Self::new()
   }
 }
 
-impl Drop for Entry {
-  fn drop(&mut self) {
-    self.zeroize();
-  }
+/// An extension client origin waiting for approval, with the process that
+/// asked when it could be identified.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct PendingOrigin {
+  pub origin: String,
+  pub process: Option<PeerProcess>,
+  pub requested_at: DateTime<Utc>,
 }
 
 /// An active vault session containing the derived encryption key.
@@ -143,17 +195,17 @@ impl Drop for Entry {
 /// wrapped in [`Zeroizing`] for secure cleanup on drop.
 #[derive(Clone)]
 pub struct VaultSession {
-  /// Salt used for key derivation (stored in the vault file).
-  pub salt: [u8; SALT_LEN],
+  /// Header of the open vault: salt, KDF parameters, and cipher suite.
+  pub header: VaultHeader,
   /// Derived 256-bit encryption key (zeroized on drop).
   pub key: Zeroizing<[u8; 32]>,
 }
 
 impl VaultSession {
-  /// Creates a new vault session with the given salt and key.
-  pub fn new(salt: [u8; SALT_LEN], key_bytes: [u8; 32]) -> Self {
+  /// Creates a new vault session with the given header and key.
+  pub fn new(header: VaultHeader, key_bytes: [u8; 32]) -> Self {
     Self {
-      salt,
+      header,
       key: Zeroizing::new(key_bytes),
     }
   }
@@ -169,21 +221,24 @@ impl VaultSession {
 }
 
 /// Tracks failed unlock attempts for rate limiting.
-/// After MAX_FAILED_ATTEMPTS, enforces a cooldown period.
+/// After the policy's `max_attempts`, enforces a cooldown period.
 #[derive(Clone, Debug, Default)]
 pub struct FailedAttemptTracker {
   pub count: u32,
   pub locked_until: Option<Instant>,
+  /// Lockouts since the last successful unlock, for exponential backoff.
+  pub lockouts: u32,
 }
 
 impl FailedAttemptTracker {
   /// Records a failed unlock attempt. Returns lockout duration if threshold exceeded.
-  pub fn record_failure(&mut self) -> Option<u64> {
+  pub fn record_failure(&mut self, policy: &LockoutPolicy) -> Option<u64> {
     self.count += 1;
-    if self.count >= MAX_FAILED_ATTEMPTS {
-      let lockout_until = Instant::now() + std::time::Duration::from_secs(LOCKOUT_DURATION_SECS);
-      self.locked_until = Some(lockout_until);
-      Some(LOCKOUT_DURATION_SECS)
+    if self.count >= policy.max_attempts {
+      let secs = policy.lockout_for(self.lockouts);
+      self.locked_until = Some(Instant::now() + std::time::Duration::from_secs(secs));
+      self.lockouts = self.lockouts.saturating_add(1);
+      Some(secs)
     } else {
       None
     }
@@ -209,6 +264,7 @@ impl FailedAttemptTracker {
   pub fn reset(&mut self) {
     self.count = 0;
     self.locked_until = None;
+    self.lockouts = 0;
   }
 }
 
@@ -218,58 +274,233 @@ impl FailedAttemptTracker {
 /// The state is managed by Tauri and accessed via `State<AppState>` in commands.
 #[derive(Clone)]
 pub struct AppState {
-  /// Unlocked entries (zeroized via `Entry::Drop` when cleared).
-  pub entries: Arc<Mutex<Option<Vec<Entry>>>>,
+  /// Unlocked vault contents (entries zeroized via `Entry::Drop` when cleared).
+  pub vault: Arc<Mutex<Option<VaultData>>>,
 
   /// Active session with derived key (cleared on lock).
   pub session: Arc<Mutex<Option<VaultSession>>>,
 
-  /// Timestamp of last user interaction (for auto-lock timeout).
-  pub last_interaction: Arc<Mutex<Instant>>,
+  /// Last activity per source (for auto-lock timeout and diagnostics).
+  pub activity: Arc<Activity>,
+
+  /// Lockout and auto-lock policy (see [`crate::settings`]).
+  pub settings: Arc<Mutex<Settings>>,
+
+  /// Running username/password/TOTP copy sequence, if any (no secrets).
+  pub copy_sequence: Arc<Mutex<Option<CopySequence>>>,
 
-  /// Cached vault file path (resolved once on first access).
-  pub vault_path: Arc<Mutex<Option<PathBuf>>>,
+  /// Revealed secrets waiting to be hidden again (ids and field names only).
+  pub reveals: Arc<Mutex<RevealTracker>>,
+
+  /// Set once the first copy has checked for clipboard history managers.
+  pub clipboard_history_checked: Arc<AtomicBool>,
+
+  /// Result of the last scheduled security audit (cleared on lock).
+  pub security_audit: Arc<Mutex<Option<SecurityAudit>>>,
+
+  /// Storage backend for the encrypted vault (resolved once on first access,
+  /// or supplied up front via [`AppState::with_store`]).
+  pub store: Arc<Mutex<Option<Arc<dyn VaultStore>>>>,
 
   /// Rate limiting tracker for failed unlock attempts.
   pub failed_attempts: Arc<Mutex<FailedAttemptTracker>>,
 
   /// Browser extension integration settings.
   pub extension_config: Arc<Mutex<ExtensionConfig>>,
+
+  /// Extension client origins waiting for approval (not persisted).
+  pub pending_origins: Arc<Mutex<Vec<PendingOrigin>>>,
+
+  /// Time-boxed extension sessions and requests for them (not persisted).
+  pub bridge_sessions: Arc<Mutex<BridgeSessions>>,
+
+  /// Vault changes whose write failed, waiting for a retry.
+  pub pending_writes: Arc<Mutex<PendingWrites>>,
+
+  /// This app install, stamped into vault headers it writes (loaded at
+  /// startup; `None` if it could not be read or created).
+  pub device: Arc<Mutex<Option<DeviceIdentity>>>,
+
+  /// Device enrollment in progress, with its ephemeral keys (not persisted).
+  pub enrollment: Arc<Mutex<Option<Enrollment>>>,
+
+  /// mDNS advertisement while this install is visible for LAN sync.
+  pub lan_advertiser: Arc<Mutex<Option<Advertiser>>>,
+
+  /// LAN peer connected and waiting for its code to be confirmed.
+  pub lan_session: Arc<Mutex<Option<LanSession>>>,
+
+  /// Outcome of the latest scheduled backups (loaded at startup).
+  pub backup: Arc<Mutex<BackupStatus>>,
+
+  /// Logins read for import and waiting for review (cleared on lock).
+  pub pending_import: Arc<Mutex<Option<ImportBatch>>>,
+
+  /// Set while an unlocked vault's entries are still being decrypted in the
+  /// background; `vault` stays `None` until they all are.
+  pub entries_loading: Arc<AtomicBool>,
+
+  /// Incremented on every unlock and lock, so a background entry load can
+  /// tell that the session it started for has ended.
+  pub unlock_generation: Arc<AtomicU64>,
+
+  /// Latest vault load and save times, for diagnostics.
+  pub storage_timings: Arc<Mutex<StorageTimings>>,
+
+  /// When the vault was last locked (app start until the first lock), for
+  /// the extension's auto-disable policy.
+  pub locked_at: Arc<Mutex<Instant>>,
+
+  /// Entry ids by host for the extension bridge (cleared on lock).
+  pub host_index: Arc<Mutex<Option<HostIndex>>>,
+
+  /// Recently generated passwords, sealed (cleared on lock).
+  pub generator_history: Arc<Mutex<GeneratorHistory>>,
+
+  /// Secrets copied since the last unlock or UI interaction, for
+  /// [`Settings::copy_lock_after`].
+  pub copies_since_interaction: Arc<AtomicU32>,
+
+  /// Set while the user is screen sharing; blocks copies and reveals and
+  /// masks usernames. Kept across locks, cleared on restart.
+  pub presentation_mode: Arc<AtomicBool>,
+
+  /// Heartbeat of the inactivity monitor, which is restarted if it stops
+  /// (see [`crate::monitor`]).
+  pub watchdog: Arc<Watchdog>,
+
+  /// The extension bridge while it is serving (see
+  /// [`crate::extension::stop_extension_server`]).
+  pub bridge_server: Arc<Mutex<Option<BridgeServer>>>,
+
+  /// Findings after the previous run ended without a clean shutdown, until
+  /// dismissed (see [`crate::crash_recovery`]).
+  pub recovery_report: Arc<Mutex<Option<RecoveryReport>>>,
 }
 
 impl Default for AppState {
   fn default() -> Self {
     Self {
-      entries: Arc::new(Mutex::new(None)),
+      vault: Arc::new(Mutex::new(None)),
       session: Arc::new(Mutex::new(None)),
-      last_interaction: Arc::new(Mutex::new(Instant::now())),
-      vault_path: Arc::new(Mutex::new(None)),
+      activity: Arc::new(Activity::default()),
+      settings: Arc::new(Mutex::new(Settings::default())),
+      copy_sequence: Arc::new(Mutex::new(None)),
+      reveals: Arc::new(Mutex::new(RevealTracker::default())),
+      clipboard_history_checked: Arc::new(AtomicBool::new(false)),
+      security_audit: Arc::new(Mutex::new(None)),
+      store: Arc::new(Mutex::new(None)),
       failed_attempts: Arc::new(Mutex::new(FailedAttemptTracker::default())),
       extension_config: Arc::new(Mutex::new(ExtensionConfig::default())),
+      pending_origins: Arc::new(Mutex::new(Vec::new())),
+      bridge_sessions: Arc::new(Mutex::new(BridgeSessions::default())),
+      pending_writes: Arc::new(Mutex::new(PendingWrites::default())),
+      device: Arc::new(Mutex::new(None)),
+      enrollment: Arc::new(Mutex::new(None)),
+      lan_advertiser: Arc::new(Mutex::new(None)),
+      lan_session: Arc::new(Mutex::new(None)),
+      backup: Arc::new(Mutex::new(BackupStatus::default())),
+      pending_import: Arc::new(Mutex::new(None)),
+      entries_loading: Arc::new(AtomicBool::new(false)),
+      unlock_generation: Arc::new(AtomicU64::new(0)),
+      storage_timings: Arc::new(Mutex::new(StorageTimings::default())),
+      locked_at: Arc::new(Mutex::new(Instant::now())),
+      host_index: Arc::new(Mutex::new(None)),
+      generator_history: Arc::new(Mutex::new(GeneratorHistory::default())),
+      copies_since_interaction: Arc::new(AtomicU32::new(0)),
+      presentation_mode: Arc::new(AtomicBool::new(false)),
+      watchdog: Arc::new(Watchdog::default()),
+      bridge_server: Arc::new(Mutex::new(None)),
+      recovery_report: Arc::new(Mutex::new(None)),
     }
   }
 }
 
 impl AppState {
+  /// Creates state bound to a specific storage backend instead of the
+  /// default vault file, e.g. a [`crate::store::MemoryStore`] in tests.
+  pub fn with_store(store: Arc<dyn VaultStore>) -> Self {
+    let state = Self::default();
+    if let Ok(mut guard) = state.store.lock() {
+      *guard = Some(store);
+    }
+    state
+  }
+
+  /// Returns the storage backend if one has been resolved.
+  pub fn current_store(&self) -> Option<Arc<dyn VaultStore>> {
+    self.store.lock().ok()?.clone()
+  }
+
+  /// Writer stamp for headers written now by this install.
+  pub fn writer_stamp(&self) -> Option<WriterStamp> {
+    self.device.lock().ok()?.as_ref().map(DeviceIdentity::stamp)
+  }
+
   /// Immediately locks the vault, clearing all sensitive data.
   ///
-  /// Lock order: session → entries (prevents deadlocks).
+  /// Unsaved changes are discarded; see [`crate::persist::flush_and_lock`]
+  /// and [`crate::persist::lock`].
+  ///
+  /// Lock order: session → vault (prevents deadlocks).
   pub fn lock_now(&self) {
+    self.unlock_generation.fetch_add(1, Ordering::SeqCst);
+    self.entries_loading.store(false, Ordering::SeqCst);
+    let mut was_unlocked = false;
     if let Ok(mut s) = self.session.lock() {
-      *s = None;
+      was_unlocked = s.take().is_some();
+    }
+    if let Ok(mut v) = self.vault.lock() {
+      *v = None;
+    }
+    if let Ok(mut p) = self.pending_writes.lock() {
+      p.clear();
+    }
+    if let Ok(mut a) = self.security_audit.lock() {
+      *a = None;
+    }
+    if let Ok(mut i) = self.pending_import.lock() {
+      *i = None;
+    }
+    if let Ok(mut h) = self.host_index.lock() {
+      *h = None;
     }
-    if let Ok(mut e) = self.entries.lock() {
-      *e = None;
+    if let Ok(mut g) = self.generator_history.lock() {
+      g.clear();
     }
-    if let Ok(mut t) = self.last_interaction.lock() {
-      *t = Instant::now();
+    if let Ok(mut at) = self.locked_at.lock() {
+      *at = Instant::now();
+    }
+    self.activity.reset();
+    self.copies_since_interaction.store(0, Ordering::SeqCst);
+    if was_unlocked {
+      hooks::fire(self, VaultEvent::Lock);
     }
   }
 
-  /// Updates the last interaction timestamp, resetting the auto-lock timer.
+  /// Records UI activity, resetting the auto-lock timer.
   pub fn heartbeat(&self) {
-    if let Ok(mut t) = self.last_interaction.lock() {
-      *t = Instant::now();
-    }
+    self.touch(ActivitySource::Ui);
+  }
+
+  /// Records activity from `source`, resetting its auto-lock timer.
+  pub fn touch(&self, source: ActivitySource) {
+    self.activity.record(source);
+  }
+
+  /// Records a user interaction other than copying a secret, restarting the
+  /// count for [`Settings::copy_lock_after`].
+  pub fn note_interaction(&self) {
+    self.copies_since_interaction.store(0, Ordering::SeqCst);
+  }
+
+  /// Counts a secret copy; returns the copies since the last interaction.
+  pub fn record_copy(&self) -> u32 {
+    self.copies_since_interaction.fetch_add(1, Ordering::SeqCst).saturating_add(1)
+  }
+
+  /// Whether presentation mode is on.
+  pub fn presenting(&self) -> bool {
+    self.presentation_mode.load(Ordering::SeqCst)
   }
 }
@@ -0,0 +1,224 @@
+//! The inactivity monitor and its watchdog.
+//!
+//! The monitor thread polls every [`INACTIVITY_POLL_SECS`]: it auto-locks an
+//! idle vault and runs the scheduled audit, exports, shared vault refresh,
+//! backups and extension auto-disable. If it panicked or hung, auto-lock
+//! would silently stop working, so each pass records a heartbeat in
+//! [`Watchdog`]. [`supervise`] is called from Tauri's event loop and from
+//! the frontend heartbeat; when the thread has ended or its heartbeat is
+//! older than [`STALL_AFTER`], a new monitor is started and the restart is
+//! kept in [`MonitorHealth`] for `run_diagnostics`.
+//!
+//! Each monitor carries a generation number. A hung monitor that later
+//! resumes sees a newer generation and exits, so only one keeps running.
+
+use crate::backup;
+use crate::extension;
+use crate::folder_export;
+use crate::models::{AppState, INACTIVITY_POLL_SECS};
+use crate::persist;
+use crate::scheduled_audit;
+use crate::shared_vault;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::any::Any;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+
+/// A monitor whose last pass is older than this is considered hung. Passes
+/// can include a backup or an audit, so this is well above the poll interval.
+pub const STALL_AFTER: Duration = Duration::from_secs(INACTIVITY_POLL_SECS * 12);
+
+/// Restarts of the monitor in this run of the app, for diagnostics.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct MonitorHealth {
+  pub restarts: u32,
+  pub last_restart: Option<DateTime<Utc>>,
+  /// Why the monitor was last restarted (its panic message, or a stall).
+  pub last_failure: Option<String>,
+}
+
+/// Heartbeat and handle of the running monitor.
+#[derive(Debug)]
+pub struct Watchdog {
+  epoch: Instant,
+  /// Milliseconds since `epoch` at the monitor's last pass.
+  beat_ms: AtomicU64,
+  /// Generation of the monitor that should be running; 0 before the first.
+  generation: AtomicU64,
+  thread: Mutex<Option<JoinHandle<()>>>,
+  health: Mutex<MonitorHealth>,
+}
+
+impl Default for Watchdog {
+  fn default() -> Self {
+    Self {
+      epoch: Instant::now(),
+      beat_ms: AtomicU64::new(0),
+      generation: AtomicU64::new(0),
+      thread: Mutex::new(None),
+      health: Mutex::new(MonitorHealth::default()),
+    }
+  }
+}
+
+impl Watchdog {
+  fn now_ms(&self) -> u64 {
+    self.epoch.elapsed().as_millis() as u64
+  }
+
+  /// Records a pass of the monitor.
+  pub fn beat(&self) {
+    self.beat_ms.store(self.now_ms(), Ordering::Relaxed);
+  }
+
+  /// Time since the monitor's last pass.
+  pub fn since_beat(&self) -> Duration {
+    let last = self.beat_ms.load(Ordering::Relaxed);
+    Duration::from_millis(self.now_ms().saturating_sub(last))
+  }
+
+  fn is_current(&self, generation: u64) -> bool {
+    self.generation.load(Ordering::SeqCst) == generation
+  }
+
+  pub fn health(&self) -> MonitorHealth {
+    self.health.lock().map(|h| h.clone()).unwrap_or_default()
+  }
+
+  fn record_restart(&self, reason: String) {
+    if let Ok(mut health) = self.health.lock() {
+      health.restarts += 1;
+      health.last_restart = Some(Utc::now());
+      health.last_failure = Some(reason);
+    }
+  }
+}
+
+/// Starts the monitor. Called once at startup; later starts go through
+/// [`supervise`].
+pub fn start(app: &AppHandle, state: &AppState) {
+  let Ok(mut slot) = state.watchdog.thread.lock() else {
+    return;
+  };
+  *slot = Some(spawn(app, state));
+}
+
+/// Stops the monitor after its current pass, e.g. on exit, so [`supervise`]
+/// does not start another.
+pub fn stop(state: &AppState) {
+  state.watchdog.generation.fetch_add(1, Ordering::SeqCst);
+  if let Ok(mut slot) = state.watchdog.thread.lock() {
+    slot.take();
+  }
+}
+
+/// Restarts the monitor if it has ended or stopped beating.
+pub fn supervise(app: &AppHandle, state: &AppState) {
+  let watchdog = &state.watchdog;
+  let Ok(mut slot) = watchdog.thread.lock() else {
+    return;
+  };
+  let reason = match slot.take() {
+    // Not started yet.
+    None => return,
+    Some(handle) if handle.is_finished() => match handle.join() {
+      Err(payload) => format!("panicked: {}", panic_message(payload.as_ref())),
+      Ok(()) => "stopped".to_string(),
+    },
+    Some(handle) if watchdog.since_beat() > STALL_AFTER => {
+      // Left running; it exits after its current pass.
+      drop(handle);
+      format!("no heartbeat for {} s", watchdog.since_beat().as_secs())
+    }
+    Some(handle) => {
+      *slot = Some(handle);
+      return;
+    }
+  };
+  eprintln!("inactivity monitor {reason}; restarting");
+  watchdog.record_restart(reason);
+  *slot = Some(spawn(app, state));
+}
+
+fn spawn(app: &AppHandle, state: &AppState) -> JoinHandle<()> {
+  let generation = state.watchdog.generation.fetch_add(1, Ordering::SeqCst) + 1;
+  state.watchdog.beat();
+  let app = app.clone();
+  let state = state.clone();
+  thread::spawn(move || run(&app, &state, generation))
+}
+
+fn run(app: &AppHandle, state: &AppState, generation: u64) {
+  let poll = Duration::from_secs(INACTIVITY_POLL_SECS);
+  loop {
+    state.watchdog.beat();
+    thread::sleep(poll);
+    if !state.watchdog.is_current(generation) {
+      return;
+    }
+
+    let is_unlocked = match state.session.lock() {
+      Ok(g) => g.is_some(),
+      Err(_) => {
+        // Poisoned mutex: safest behavior is to lock.
+        state.lock_now();
+        continue;
+      }
+    };
+    let policy = match state.settings.lock() {
+      Ok(g) => g.auto_lock,
+      Err(_) => {
+        state.lock_now();
+        continue;
+      }
+    };
+
+    if is_unlocked && state.activity.is_idle(&policy) {
+      if let Some(failure) = persist::lock(state, Some(app)) {
+        eprintln!("auto-lock: unsaved changes not written: {}", failure.error);
+      }
+    } else if is_unlocked {
+      scheduled_audit::maybe_run(state, app);
+      folder_export::maybe_run(state, app);
+      shared_vault::maybe_run(state, app);
+    }
+    backup::maybe_run(state, app);
+    extension::enforce_auto_disable(app, state);
+  }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+  if let Some(message) = payload.downcast_ref::<&str>() {
+    message.to_string()
+  } else if let Some(message) = payload.downcast_ref::<String>() {
+    message.clone()
+  } else {
+    "unknown panic".to_string()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn only_the_latest_generation_keeps_running() {
+    let watchdog = Watchdog::default();
+    assert!(watchdog.since_beat() < STALL_AFTER);
+    let first = watchdog.generation.fetch_add(1, Ordering::SeqCst) + 1;
+    assert!(watchdog.is_current(first));
+    let second = watchdog.generation.fetch_add(1, Ordering::SeqCst) + 1;
+    assert!(!watchdog.is_current(first));
+    assert!(watchdog.is_current(second));
+
+    let payload = thread::spawn(|| panic!("poisoned settings")).join().unwrap_err();
+    watchdog.record_restart(format!("panicked: {}", panic_message(payload.as_ref())));
+    let health = watchdog.health();
+    assert_eq!(health.restarts, 1);
+    assert_eq!(health.last_failure.as_deref(), Some("panicked: poisoned settings"));
+  }
+}
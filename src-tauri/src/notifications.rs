@@ -0,0 +1,183 @@
+//! OS notifications behind a privacy policy.
+//!
+//! Notifications show on the lock screen and in notification history, so a
+//! [`Notice`] never carries secrets, and entry titles are only shown when
+//! [`NotificationPolicy::show_titles`] allows it. Without that, the user
+//! sees a count ("2 API keys expire soon") and opens the app for details.
+
+use crate::models::AppState;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use tauri_plugin_notification::NotificationExt;
+
+/// Titles listed in one notification before "and N more".
+const MAX_LISTED_TITLES: usize = 3;
+
+/// What a notification is about; each kind can be muted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoticeKind {
+  /// API keys or the master password due for renewal.
+  ExpiryReminder,
+  /// Outcome of a sync with another device.
+  SyncResult,
+  /// New issues found by the scheduled security audit.
+  AuditFinding,
+  /// An automatic backup could not be written.
+  BackupFailure,
+  /// An automatic backup was written.
+  BackupResult,
+  /// A scheduled folder export could not be written.
+  ExportFailure,
+  /// The browser extension filled a secret; only shown with
+  /// [`NotificationPolicy::autofill`].
+  Autofill,
+  /// The canary entry was read. Always shown: it is the point of the
+  /// tripwire.
+  CanaryTripped,
+}
+
+impl NoticeKind {
+  fn heading(self) -> &'static str {
+    match self {
+      NoticeKind::ExpiryReminder => "Renewal reminder",
+      NoticeKind::SyncResult => "Vault sync",
+      NoticeKind::AuditFinding => "Security audit",
+      NoticeKind::BackupFailure => "Backup failed",
+      NoticeKind::BackupResult => "Backup complete",
+      NoticeKind::ExportFailure => "Folder export failed",
+      NoticeKind::Autofill => "Autofill",
+      NoticeKind::CanaryTripped => "Canary entry accessed",
+    }
+  }
+}
+
+/// Which notifications are shown, and how much they may reveal.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationPolicy {
+  pub enabled: bool,
+  /// Include entry titles. Off by default: titles name the user's accounts.
+  pub show_titles: bool,
+  pub muted: Vec<NoticeKind>,
+  /// Also notify on every extension fill. Off by default: fills are
+  /// frequent, and the app shows them anyway.
+  pub autofill: bool,
+}
+
+impl Default for NotificationPolicy {
+  fn default() -> Self {
+    Self {
+      enabled: true,
+      show_titles: false,
+      muted: Vec::new(),
+      autofill: false,
+    }
+  }
+}
+
+/// A notification before the policy is applied.
+#[derive(Clone, Debug)]
+pub struct Notice {
+  pub kind: NoticeKind,
+  /// Generic text, safe to show anywhere (no titles, no secrets).
+  pub summary: String,
+  /// Entry titles the notice is about, shown only if the policy allows.
+  pub titles: Vec<String>,
+}
+
+impl Notice {
+  pub fn new(kind: NoticeKind, summary: impl Into<String>) -> Self {
+    Self {
+      kind,
+      summary: summary.into(),
+      titles: Vec::new(),
+    }
+  }
+
+  pub fn titles(mut self, titles: Vec<String>) -> Self {
+    self.titles = titles;
+    self
+  }
+
+  /// Heading and body under `policy`, or `None` if it must not be shown.
+  pub fn render(&self, policy: &NotificationPolicy) -> Option<(String, String)> {
+    if self.kind == NoticeKind::CanaryTripped {
+      return Some((self.kind.heading().to_string(), self.summary.clone()));
+    }
+    if !policy.enabled || policy.muted.contains(&self.kind) || (self.kind == NoticeKind::Autofill && !policy.autofill) {
+      return None;
+    }
+    let mut body = self.summary.clone();
+    if policy.show_titles && !self.titles.is_empty() {
+      let listed = self.titles.iter().take(MAX_LISTED_TITLES).cloned().collect::<Vec<_>>().join(", ");
+      body.push_str(&format!(" ({listed}"));
+      let more = self.titles.len().saturating_sub(MAX_LISTED_TITLES);
+      if more > 0 {
+        body.push_str(&format!(" and {more} more"));
+      }
+      body.push(')');
+    }
+    Some((self.kind.heading().to_string(), body))
+  }
+}
+
+/// Shows `notice` if the notification policy allows it. Failures are
+/// logged; a notification is never worth failing an operation over.
+pub fn notify(app: &AppHandle, state: &AppState, notice: Notice) {
+  let Ok(settings) = state.settings.lock() else {
+    return;
+  };
+  let Some((heading, body)) = notice.render(&settings.notifications) else {
+    return;
+  };
+  drop(settings);
+  if let Err(err) = app.notification().builder().title(heading).body(body).show() {
+    eprintln!("notification failed: {err}");
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn titles_only_with_consent_and_muted_kinds_are_dropped() {
+    let titles = ["Bank", "Mail", "Cloud", "Shop"].map(String::from).to_vec();
+    let notice = Notice::new(NoticeKind::ExpiryReminder, "4 API keys expire soon.").titles(titles);
+
+    let private = NotificationPolicy::default();
+    assert_eq!(notice.render(&private).unwrap().1, "4 API keys expire soon.");
+
+    let open = NotificationPolicy {
+      show_titles: true,
+      ..NotificationPolicy::default()
+    };
+    assert_eq!(
+      notice.render(&open).unwrap().1,
+      "4 API keys expire soon. (Bank, Mail, Cloud and 1 more)"
+    );
+
+    let muted = NotificationPolicy {
+      muted: vec![NoticeKind::ExpiryReminder],
+      ..open
+    };
+    assert!(notice.render(&muted).is_none());
+
+    let fill = Notice::new(NoticeKind::Autofill, "Password filled in Firefox.").titles(vec!["GitHub".into()]);
+    assert!(fill.render(&open).is_none());
+    let announced = NotificationPolicy {
+      autofill: true,
+      ..open
+    };
+    assert_eq!(fill.render(&announced).unwrap().1, "Password filled in Firefox. (GitHub)");
+
+    let silenced = NotificationPolicy {
+      enabled: false,
+      muted: vec![NoticeKind::CanaryTripped],
+      ..NotificationPolicy::default()
+    };
+    let canary = Notice::new(NoticeKind::CanaryTripped, "The canary entry was copied.").titles(vec!["Bank".into()]);
+    assert_eq!(canary.render(&silenced).unwrap().1, "The canary entry was copied.");
+  }
+}
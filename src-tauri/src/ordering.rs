@@ -0,0 +1,98 @@
+//! Manual entry order within folders.
+//!
+//! The order is kept in the encrypted vault metadata as a list of entry ids
+//! per folder. Lists are only rewritten by `reorder_entries`, so they may
+//! name entries that were since deleted or moved; those ids are ignored,
+//! and entries missing from their folder's list sort after the listed ones
+//! in their stored order.
+
+use crate::models::{Entry, VaultData};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+
+/// Order requested from `get_entries`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryOrder {
+  /// Order of insertion into the vault.
+  #[default]
+  Stored,
+  /// Grouped by folder, each folder in the user's manual order.
+  Manual,
+}
+
+/// Trims a folder name; blank names mean the top level.
+pub fn normalize_folder(folder: Option<String>) -> Option<String> {
+  folder.map(|f| f.trim().to_string()).filter(|f| !f.is_empty())
+}
+
+/// Key of `folder` in `VaultMeta::manual_order`.
+fn folder_key(folder: Option<&str>) -> &str {
+  folder.unwrap_or("")
+}
+
+/// Stores `ordered_ids` as the manual order of `folder`. Every id must be
+/// an entry in that folder; entries left out keep sorting after the listed
+/// ones.
+pub fn reorder(data: &mut VaultData, folder: Option<&str>, ordered_ids: Vec<String>) -> Result<(), String> {
+  let mut seen = HashSet::new();
+  for id in &ordered_ids {
+    let entry = data.find_entry(id).ok_or_else(|| format!("entry not found: {id}"))?;
+    if entry.folder.as_deref() != folder {
+      return Err(format!("entry {id} is not in this folder"));
+    }
+    if !seen.insert(id.as_str()) {
+      return Err(format!("entry {id} is listed twice"));
+    }
+  }
+  let key = folder_key(folder).to_string();
+  if ordered_ids.is_empty() {
+    data.meta.manual_order.remove(&key);
+  } else {
+    data.meta.manual_order.insert(key, ordered_ids);
+  }
+  Ok(())
+}
+
+/// Entries grouped by folder (top level first, then by name), each folder
+/// in manual order.
+pub fn manually_ordered(data: &VaultData) -> Vec<&Entry> {
+  let rank: HashMap<(&str, &str), usize> = data
+    .meta
+    .manual_order
+    .iter()
+    .flat_map(|(folder, ids)| ids.iter().enumerate().map(move |(i, id)| ((folder.as_str(), id.as_str()), i)))
+    .collect();
+  let mut entries: Vec<&Entry> = data.entries.iter().collect();
+  // Stable, so unlisted entries keep their stored order.
+  entries.sort_by_key(|e| {
+    let folder = folder_key(e.folder.as_deref());
+    (folder, rank.get(&(folder, e.id.as_str())).copied().unwrap_or(usize::MAX))
+  });
+  entries
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn listed_entries_come_first_within_their_folder() {
+    let mut data = VaultData::default();
+    for (title, folder) in [("a", None), ("b", Some("work")), ("c", None), ("d", None), ("e", Some("work"))] {
+      let mut entry = Entry::new(title.into(), String::new(), String::new(), String::new(), String::new());
+      entry.folder = folder.map(String::from);
+      data.entries.push(entry);
+    }
+    let id = |i: usize| data.entries[i].id.clone();
+    let (a, b, c, d, e) = (id(0), id(1), id(2), id(3), id(4));
+
+    reorder(&mut data, None, vec![d.clone(), a.clone()]).unwrap();
+    reorder(&mut data, Some("work"), vec![e.clone(), b.clone()]).unwrap();
+    let titles: Vec<&str> = manually_ordered(&data).iter().map(|e| e.title.as_str()).collect();
+    assert_eq!(titles, ["d", "a", "c", "e", "b"]);
+
+    assert!(reorder(&mut data, None, vec![b]).is_err());
+    assert!(reorder(&mut data, None, vec![c.clone(), c]).is_err());
+  }
+}
@@ -0,0 +1,222 @@
+//! Locks the vault when the desktop session stops being the user's: another
+//! OS user is switched to, the session is locked, or it is disconnected.
+//! This is independent of the idle timer in [`crate::activity`], which could
+//! otherwise leave the vault open for minutes behind someone else's login.
+//!
+//! - Windows: `WTSRegisterSessionNotification` on a message-only window.
+//! - macOS: `NSWorkspaceSessionDidResignActiveNotification`, posted when
+//!   fast user switching moves to another user.
+//! - Linux: logind's `Lock` signal for this session, and its `Active`
+//!   property turning false when another session takes over the seat.
+//!
+//! Without a watcher (no logind, an unsupported platform) the idle timer
+//! still applies; the failure is only logged.
+
+use crate::models::AppState;
+use crate::persist;
+use serde::Serialize;
+use std::thread;
+use tauri::{AppHandle, Emitter};
+
+/// Emitted with the [`SessionChange`] after the watcher locked the vault.
+pub const SESSION_LOCK_EVENT: &str = "vault://session-lock";
+
+/// What happened to the OS session.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionChange {
+  /// Another user was switched to, or the session was disconnected.
+  Switched,
+  /// The OS locked the session.
+  Locked,
+  /// The user is logging off.
+  LoggedOff,
+}
+
+type Handler = Box<dyn Fn(SessionChange) + Send + Sync>;
+
+/// Starts watching the OS session on a background thread.
+pub fn start(app: &AppHandle, state: AppState) {
+  let app = app.clone();
+  thread::spawn(move || {
+    let handler: Handler = Box::new(move |change| lock_for(&app, &state, change));
+    if let Err(err) = platform::watch(handler) {
+      eprintln!("session watcher unavailable: {err}");
+    }
+  });
+}
+
+fn lock_for(app: &AppHandle, state: &AppState, change: SessionChange) {
+  let unlocked = state.session.lock().map(|s| s.is_some()).unwrap_or(true);
+  if !unlocked {
+    return;
+  }
+  // Locks even if unsaved changes cannot be written; those are reported
+  // on their own via the save-failed event.
+  if let Some(failure) = persist::lock(state, Some(app)) {
+    eprintln!("session lock: unsaved changes not written: {}", failure.error);
+  }
+  if let Ok(mut slot) = state.copy_sequence.lock() {
+    *slot = None;
+  }
+  let _ = app.emit(SESSION_LOCK_EVENT, change);
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+  use super::{Handler, SessionChange};
+  use std::sync::Arc;
+  use std::thread;
+  use zbus::blocking::{Connection, Proxy};
+  use zbus::zvariant::OwnedObjectPath;
+
+  const LOGIN1: &str = "org.freedesktop.login1";
+  const SESSION_INTERFACE: &str = "org.freedesktop.login1.Session";
+
+  /// Blocks while logind reports changes to the session this process runs in.
+  pub fn watch(handler: Handler) -> Result<(), String> {
+    let conn = Connection::system().map_err(|e| format!("system bus: {e}"))?;
+    // `session/auto` resolves to the caller's session, but signals are sent
+    // from the session's own path, so look that up by id.
+    let current = Proxy::new(&conn, LOGIN1, "/org/freedesktop/login1/session/auto", SESSION_INTERFACE)
+      .map_err(|e| format!("logind: {e}"))?;
+    let id: String = current.get_property("Id").map_err(|e| format!("no login session: {e}"))?;
+    let manager = Proxy::new(&conn, LOGIN1, "/org/freedesktop/login1", "org.freedesktop.login1.Manager")
+      .map_err(|e| format!("logind: {e}"))?;
+    let path: OwnedObjectPath = manager
+      .call("GetSession", &(id.as_str(),))
+      .map_err(|e| format!("logind: {e}"))?;
+    let session = Proxy::new(&conn, LOGIN1, path, SESSION_INTERFACE).map_err(|e| format!("logind: {e}"))?;
+
+    let handler = Arc::new(handler);
+    let locks = session.receive_signal("Lock").map_err(|e| format!("logind: {e}"))?;
+    let on_lock = Arc::clone(&handler);
+    thread::spawn(move || {
+      for _ in locks {
+        on_lock(SessionChange::Locked);
+      }
+    });
+    for change in session.receive_property_changed::<bool>("Active") {
+      if change.get().is_ok_and(|active| !active) {
+        handler(SessionChange::Switched);
+      }
+    }
+    Err("logind connection closed".to_string())
+  }
+}
+
+#[cfg(windows)]
+mod platform {
+  use super::{Handler, SessionChange};
+  use std::ptr;
+  use std::sync::OnceLock;
+  use windows_sys::Win32::Foundation::{HWND, LPARAM, LRESULT, WPARAM};
+  use windows_sys::Win32::System::LibraryLoader::GetModuleHandleW;
+  use windows_sys::Win32::System::RemoteDesktop::{WTSRegisterSessionNotification, NOTIFY_FOR_THIS_SESSION};
+  use windows_sys::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DefWindowProcW, DispatchMessageW, GetMessageW, RegisterClassW, HWND_MESSAGE, MSG, WM_WTSSESSION_CHANGE,
+    WNDCLASSW, WTS_CONSOLE_DISCONNECT, WTS_REMOTE_DISCONNECT, WTS_SESSION_LOCK, WTS_SESSION_LOGOFF,
+  };
+
+  static HANDLER: OnceLock<Handler> = OnceLock::new();
+
+  unsafe extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if msg == WM_WTSSESSION_CHANGE {
+      let change = match wparam as u32 {
+        WTS_CONSOLE_DISCONNECT | WTS_REMOTE_DISCONNECT => Some(SessionChange::Switched),
+        WTS_SESSION_LOCK => Some(SessionChange::Locked),
+        WTS_SESSION_LOGOFF => Some(SessionChange::LoggedOff),
+        _ => None,
+      };
+      if let (Some(change), Some(handler)) = (change, HANDLER.get()) {
+        handler(change);
+      }
+      return 0;
+    }
+    DefWindowProcW(hwnd, msg, wparam, lparam)
+  }
+
+  /// Blocks in the message loop of a hidden window registered for session
+  /// notifications.
+  pub fn watch(handler: Handler) -> Result<(), String> {
+    HANDLER
+      .set(handler)
+      .map_err(|_| "session watcher already running".to_string())?;
+    let class: Vec<u16> = "OrganizerSessionWatcher\0".encode_utf16().collect();
+    // SAFETY: the class name outlives the window, and the window and its
+    // message loop stay on this thread.
+    unsafe {
+      let instance = GetModuleHandleW(ptr::null());
+      let mut window_class: WNDCLASSW = std::mem::zeroed();
+      window_class.lpfnWndProc = Some(window_proc);
+      window_class.hInstance = instance;
+      window_class.lpszClassName = class.as_ptr();
+      if RegisterClassW(&window_class) == 0 {
+        return Err("RegisterClassW failed".to_string());
+      }
+      let hwnd = CreateWindowExW(
+        0,
+        class.as_ptr(),
+        ptr::null(),
+        0,
+        0,
+        0,
+        0,
+        0,
+        HWND_MESSAGE,
+        ptr::null_mut(),
+        instance,
+        ptr::null(),
+      );
+      if hwnd.is_null() {
+        return Err("CreateWindowExW failed".to_string());
+      }
+      if WTSRegisterSessionNotification(hwnd, NOTIFY_FOR_THIS_SESSION) == 0 {
+        return Err("WTSRegisterSessionNotification failed".to_string());
+      }
+      let mut msg: MSG = std::mem::zeroed();
+      while GetMessageW(&mut msg, ptr::null_mut(), 0, 0) > 0 {
+        DispatchMessageW(&msg);
+      }
+    }
+    Err("session message loop ended".to_string())
+  }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+  use super::{Handler, SessionChange};
+  use block2::RcBlock;
+  use objc2_app_kit::{NSWorkspace, NSWorkspaceSessionDidResignActiveNotification};
+  use objc2_foundation::NSNotification;
+  use std::ptr::NonNull;
+
+  /// Registers for the workspace notification; it is delivered on the main
+  /// thread for the life of the app.
+  pub fn watch(handler: Handler) -> Result<(), String> {
+    let center = NSWorkspace::sharedWorkspace().notificationCenter();
+    let block = RcBlock::new(move |_: NonNull<NSNotification>| handler(SessionChange::Switched));
+    // SAFETY: the name is a valid framework constant, no object filter is
+    // given, and the block only calls a Send + Sync handler.
+    let observer = unsafe {
+      center.addObserverForName_object_queue_usingBlock(
+        Some(NSWorkspaceSessionDidResignActiveNotification),
+        None,
+        None,
+        &block,
+      )
+    };
+    // Removing the observer is never needed; keep it registered.
+    std::mem::forget(observer);
+    Ok(())
+  }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+mod platform {
+  use super::Handler;
+
+  pub fn watch(_handler: Handler) -> Result<(), String> {
+    Err("not supported on this platform".to_string())
+  }
+}
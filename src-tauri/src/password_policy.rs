@@ -0,0 +1,214 @@
+//! Named password policies, e.g. to follow workplace rules.
+//!
+//! Policies are kept in settings and at most one is active. While one is,
+//! every password set through `add_entry` or `update_entry` is checked
+//! against it, and the frontend's generator asks `check_password_policy`
+//! before offering a password. A rejected save fails with
+//! [`violations_error`], which the frontend parses back into the list of
+//! [`Violation`]s. Violations never contain the password.
+
+use crate::models::VaultData;
+use serde::{Deserialize, Serialize};
+
+/// Most policies that can be saved.
+pub const MAX_POLICIES: usize = 20;
+
+/// Most banned words per policy.
+pub const MAX_BANNED_WORDS: usize = 200;
+
+/// Accepted range for [`PasswordPolicy::min_length`].
+pub const MIN_LENGTH_RANGE: (usize, usize) = (1, 256);
+
+/// Prefix of the error returned when a password breaks the active policy.
+pub const VIOLATIONS_PREFIX: &str = "policy: ";
+
+/// Kinds of characters a policy can require.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CharClass {
+  Lowercase,
+  Uppercase,
+  Digit,
+  /// Anything that is not an ASCII letter or digit.
+  Symbol,
+}
+
+impl CharClass {
+  fn matches(self, c: char) -> bool {
+    match self {
+      CharClass::Lowercase => c.is_lowercase(),
+      CharClass::Uppercase => c.is_uppercase(),
+      CharClass::Digit => c.is_ascii_digit(),
+      CharClass::Symbol => !c.is_ascii_alphanumeric(),
+    }
+  }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PasswordPolicy {
+  pub name: String,
+  /// Minimum length in characters.
+  pub min_length: usize,
+  pub required_classes: Vec<CharClass>,
+  /// Words the password may not contain, ignoring case.
+  pub banned_words: Vec<String>,
+  /// Reject a password already used by another entry.
+  pub no_reuse: bool,
+}
+
+impl Default for PasswordPolicy {
+  fn default() -> Self {
+    Self {
+      name: String::new(),
+      min_length: 12,
+      required_classes: Vec::new(),
+      banned_words: Vec::new(),
+      no_reuse: false,
+    }
+  }
+}
+
+/// One way a password breaks a policy.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "code", rename_all = "snake_case")]
+pub enum Violation {
+  TooShort { min: usize, actual: usize },
+  MissingClass { class: CharClass },
+  BannedWord { word: String },
+  /// Other entries already use the password.
+  Reused { entry_ids: Vec<String> },
+}
+
+impl PasswordPolicy {
+  pub fn validate(&self) -> Result<(), String> {
+    if self.name.trim().is_empty() {
+      return Err("password policies need a name".to_string());
+    }
+    let (min, max) = MIN_LENGTH_RANGE;
+    if !(min..=max).contains(&self.min_length) {
+      return Err(format!("min_length must be between {min} and {max}"));
+    }
+    if self.banned_words.len() > MAX_BANNED_WORDS {
+      return Err(format!("at most {MAX_BANNED_WORDS} banned words per policy"));
+    }
+    if self.banned_words.iter().any(|w| w.trim().is_empty()) {
+      return Err("banned words cannot be empty".to_string());
+    }
+    Ok(())
+  }
+
+  /// Ways `password` breaks this policy, for entry `entry_id` (`None` for a
+  /// new entry) in `data`. Empty if it complies.
+  pub fn check(&self, password: &str, data: &VaultData, entry_id: Option<&str>) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    let length = password.chars().count();
+    if length < self.min_length {
+      violations.push(Violation::TooShort {
+        min: self.min_length,
+        actual: length,
+      });
+    }
+    for &class in &self.required_classes {
+      if !password.chars().any(|c| class.matches(c)) {
+        violations.push(Violation::MissingClass { class });
+      }
+    }
+    let lowered = password.to_lowercase();
+    for word in &self.banned_words {
+      if lowered.contains(&word.trim().to_lowercase()) {
+        violations.push(Violation::BannedWord { word: word.clone() });
+      }
+    }
+    if self.no_reuse {
+      // Linked entries keep no password of their own, so sharing one by
+      // link is never counted.
+      let entry_ids: Vec<String> = data
+        .entries
+        .iter()
+        .filter(|e| Some(e.id.as_str()) != entry_id)
+        .filter(|e| !e.password.is_empty() && e.password == password)
+        .map(|e| e.id.clone())
+        .collect();
+      if !entry_ids.is_empty() {
+        violations.push(Violation::Reused { entry_ids });
+      }
+    }
+    violations
+  }
+}
+
+/// Checks the policy list from settings: valid policies, unique names, and
+/// an `active` name that exists.
+pub fn validate_all(policies: &[PasswordPolicy], active: Option<&str>) -> Result<(), String> {
+  if policies.len() > MAX_POLICIES {
+    return Err(format!("at most {MAX_POLICIES} password policies can be saved"));
+  }
+  for (index, policy) in policies.iter().enumerate() {
+    policy.validate()?;
+    if policies[..index].iter().any(|p| p.name == policy.name) {
+      return Err(format!("duplicate password policy '{}'", policy.name));
+    }
+  }
+  if let Some(name) = active {
+    if !policies.iter().any(|p| p.name == name) {
+      return Err(format!("no password policy named '{name}'"));
+    }
+  }
+  Ok(())
+}
+
+/// The error for a rejected password: [`VIOLATIONS_PREFIX`] followed by the
+/// violations as JSON.
+pub fn violations_error(violations: &[Violation]) -> String {
+  let json = serde_json::to_string(violations).unwrap_or_else(|_| "[]".to_string());
+  format!("{VIOLATIONS_PREFIX}{json}")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::models::Entry;
+
+  #[test]
+  fn reports_each_violation() {
+    let policy = PasswordPolicy {
+      name: "Work".to_string(),
+      min_length: 10,
+      required_classes: vec![CharClass::Uppercase, CharClass::Digit, CharClass::Symbol],
+      banned_words: vec!["Acme".to_string()],
+      no_reuse: true,
+    };
+    assert!(policy.validate().is_ok());
+    let mut data = VaultData::default();
+    let other = Entry::new("Mail".into(), String::new(), "acme-2024".into(), String::new(), String::new());
+    let other_id = other.id.clone();
+    data.entries.push(other);
+
+    assert_eq!(
+      policy.check("acme-2024", &data, None),
+      vec![
+        Violation::TooShort { min: 10, actual: 9 },
+        Violation::MissingClass {
+          class: CharClass::Uppercase
+        },
+        Violation::BannedWord {
+          word: "Acme".to_string()
+        },
+        Violation::Reused {
+          entry_ids: vec![other_id.clone()]
+        },
+      ]
+    );
+    // Updating the entry that holds the password is not reuse.
+    assert_eq!(policy.check("acme-2024", &data, Some(&other_id)).len(), 3);
+    assert!(policy.check("Kx7#mQ2!vR9p", &data, None).is_empty());
+
+    let error = violations_error(&[Violation::TooShort { min: 10, actual: 9 }]);
+    assert_eq!(error, r#"policy: [{"code":"too_short","min":10,"actual":9}]"#);
+
+    assert!(validate_all(&[policy.clone(), policy.clone()], None).is_err());
+    assert!(validate_all(std::slice::from_ref(&policy), Some("Home")).is_err());
+    assert!(validate_all(&[policy], Some("Work")).is_ok());
+  }
+}
@@ -0,0 +1,232 @@
+//! Identifies the local process behind an extension bridge connection.
+//!
+//! On Linux a TCP client is found through `/proc`: its socket is looked up
+//! in `/proc/net/tcp` (and `tcp6`) by the connection's port pair, and the
+//! socket's inode is then matched against the descriptors under
+//! `/proc/<pid>/fd`. Clients of the Unix socket transport are identified by
+//! the socket's peer credentials (`SO_PEERCRED`) as they connect. Either
+//! way, a socket owned by another user is never identified. Other platforms
+//! report no process ([`SUPPORTED`] is false).
+//!
+//! A browser is only recognized by its executable's full path: the file
+//! name must be a known browser's, installed under one of
+//! [`INSTALL_DIRS`], so a copy named `chrome` in the home directory is not.
+
+use serde::Serialize;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+/// True where connecting processes can be identified. There, clients that
+/// cannot be are refused secrets.
+pub const SUPPORTED: bool = cfg!(target_os = "linux");
+
+/// Executable names of browsers the extension runs in (lowercase, without
+/// `.exe`), with the browser's display name. Extension requests come from
+/// the browser's network process, which runs the same executable.
+const BROWSERS: &[(&str, &str)] = &[
+  ("chrome", "Chrome"),
+  ("google-chrome", "Chrome"),
+  ("chromium", "Chromium"),
+  ("chromium-browser", "Chromium"),
+  ("msedge", "Edge"),
+  ("microsoft-edge", "Edge"),
+  ("brave", "Brave"),
+  ("brave-browser", "Brave"),
+  ("vivaldi", "Vivaldi"),
+  ("vivaldi-bin", "Vivaldi"),
+  ("opera", "Opera"),
+  ("thorium", "Thorium"),
+  ("firefox", "Firefox"),
+  ("firefox-bin", "Firefox"),
+  ("librewolf", "LibreWolf"),
+];
+
+/// Directories that package managers, snaps, and flatpaks (`/app` inside
+/// the sandbox) install browsers under, writable only by root.
+const INSTALL_DIRS: &[&str] = &["/usr", "/opt", "/snap", "/app", "/var/lib/flatpak"];
+
+/// A process connected to the bridge.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct PeerProcess {
+  pub pid: u32,
+  /// Executable file name.
+  pub name: String,
+  /// Full path of the executable.
+  pub path: PathBuf,
+}
+
+impl PeerProcess {
+  /// True if this is a known browser or one of the `allowed` processes,
+  /// given by executable name or full path.
+  pub fn is_trusted(&self, allowed: &[String]) -> bool {
+    let name = self.executable();
+    self.browser().is_some()
+      || allowed
+        .iter()
+        .any(|a| a.eq_ignore_ascii_case(&name) || Path::new(a) == self.path)
+  }
+
+  /// The browser's display name ("Firefox"), or the executable name for
+  /// other clients.
+  pub fn display_name(&self) -> &str {
+    self.browser().unwrap_or(&self.name)
+  }
+
+  fn browser(&self) -> Option<&'static str> {
+    if !INSTALL_DIRS.iter().any(|dir| self.path.starts_with(dir)) {
+      return None;
+    }
+    let name = self.executable();
+    BROWSERS.iter().find(|(exe, _)| *exe == name).map(|(_, display)| *display)
+  }
+
+  /// Lowercase executable name without `.exe`.
+  fn executable(&self) -> String {
+    let name = self.name.to_lowercase();
+    name.strip_suffix(".exe").map(str::to_string).unwrap_or(name)
+  }
+}
+
+/// The process that opened the TCP connection from `client` to the bridge
+/// listening on `bridge_port`, if it can be determined.
+#[cfg(target_os = "linux")]
+pub fn identify(client: SocketAddr, bridge_port: u16) -> Option<PeerProcess> {
+  use std::fs;
+
+  let (uid, inode) = ["/proc/net/tcp", "/proc/net/tcp6"]
+    .iter()
+    .filter_map(|path| fs::read_to_string(path).ok())
+    .find_map(|table| find_socket(&table, client.port(), bridge_port))?;
+  if uid != current_uid() {
+    return None;
+  }
+  let target = format!("socket:[{inode}]");
+
+  for proc_entry in fs::read_dir("/proc").ok()?.flatten() {
+    let Some(pid) = proc_entry.file_name().to_str().and_then(|s| s.parse::<u32>().ok()) else {
+      continue;
+    };
+    let Ok(fds) = fs::read_dir(proc_entry.path().join("fd")) else {
+      continue;
+    };
+    let owns_socket = fds
+      .flatten()
+      .any(|fd| fs::read_link(fd.path()).is_ok_and(|link| link.as_os_str() == target.as_str()));
+    if owns_socket {
+      return process(pid);
+    }
+  }
+  None
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn identify(_client: SocketAddr, _bridge_port: u16) -> Option<PeerProcess> {
+  None
+}
+
+/// The process at the other end of a connection to the bridge socket, from
+/// its peer credentials.
+#[cfg(target_os = "linux")]
+pub fn peer_cred(stream: &std::os::unix::net::UnixStream) -> Option<PeerProcess> {
+  use std::os::fd::AsRawFd;
+
+  let mut cred = libc::ucred { pid: 0, uid: 0, gid: 0 };
+  let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+  // SAFETY: `cred` and `len` describe a writable `ucred`, which is what
+  // SO_PEERCRED fills in, and the descriptor stays open for the call.
+  let result = unsafe {
+    libc::getsockopt(
+      stream.as_raw_fd(),
+      libc::SOL_SOCKET,
+      libc::SO_PEERCRED,
+      (&mut cred as *mut libc::ucred).cast(),
+      &mut len,
+    )
+  };
+  if result != 0 || cred.pid <= 0 || cred.uid != current_uid() {
+    return None;
+  }
+  process(cred.pid as u32)
+}
+
+#[cfg(target_os = "linux")]
+fn current_uid() -> u32 {
+  // SAFETY: getuid has no preconditions and cannot fail.
+  unsafe { libc::getuid() }
+}
+
+/// Process `pid`, named after the executable it runs.
+#[cfg(target_os = "linux")]
+fn process(pid: u32) -> Option<PeerProcess> {
+  let exe = std::fs::read_link(format!("/proc/{pid}/exe")).ok()?;
+  // An executable replaced by an update shows as "chrome (deleted)".
+  let path = match exe.to_str().and_then(|p| p.strip_suffix(" (deleted)")) {
+    Some(path) => PathBuf::from(path),
+    None => exe,
+  };
+  let name = path.file_name()?.to_string_lossy().into_owned();
+  Some(PeerProcess { pid, name, path })
+}
+
+/// Owner uid and inode of the socket in a `/proc/net/tcp` table whose local
+/// port is `local_port` and remote port is `remote_port`.
+#[cfg(any(target_os = "linux", test))]
+fn find_socket(table: &str, local_port: u16, remote_port: u16) -> Option<(u32, u64)> {
+  let port = |address: &str| {
+    let (_, port) = address.rsplit_once(':')?;
+    u16::from_str_radix(port, 16).ok()
+  };
+  table.lines().skip(1).find_map(|line| {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() < 10 || port(fields[1])? != local_port || port(fields[2])? != remote_port {
+      return None;
+    }
+    let uid = fields[7].parse().ok()?;
+    let inode = fields[9].parse().ok().filter(|inode| *inode != 0)?;
+    Some((uid, inode))
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn finds_client_socket_and_trusts_browsers() {
+    let table = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 0100007F:4568 00000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 41001 1 0000000000000000 100 0 0 10 0
+   1: 0100007F:4568 0100007F:C350 01 00000000:00000000 00:00000000 00000000  1000        0 41002 1 0000000000000000 20 4 30 10 -1
+   2: 0100007F:C350 0100007F:4568 01 00000000:00000000 00:00000000 00000000  1000        0 41003 1 0000000000000000 20 4 30 10 -1";
+    // The client's side of 127.0.0.1:50000 -> 127.0.0.1:17768.
+    assert_eq!(find_socket(table, 50000, 17768), Some((1000, 41003)));
+    assert_eq!(find_socket(table, 50001, 17768), None);
+
+    let process = |path: &str| {
+      let path = PathBuf::from(path);
+      let name = path.file_name().unwrap().to_string_lossy().into_owned();
+      PeerProcess { pid: 1, name, path }
+    };
+    assert!(process("/opt/google/chrome/chrome").is_trusted(&[]));
+    assert!(process("/usr/lib/firefox/firefox-bin").is_trusted(&[]));
+    assert!(!process("/usr/bin/python3").is_trusted(&[]));
+    assert!(process("/usr/bin/python3").is_trusted(&["Python3".into()]));
+    assert!(process("/home/me/bin/host").is_trusted(&["/home/me/bin/host".into()]));
+    assert_eq!(process("/usr/lib/firefox/firefox-bin").display_name(), "Firefox");
+    assert_eq!(process("/usr/bin/python3").display_name(), "python3");
+
+    // A browser's name is not enough outside the install directories.
+    assert!(!process("/home/me/.cache/chrome").is_trusted(&[]));
+    assert!(!process("/tmp/firefox").is_trusted(&[]));
+    assert_eq!(process("/tmp/firefox").display_name(), "firefox");
+  }
+
+  #[cfg(target_os = "linux")]
+  #[test]
+  fn socket_peers_are_identified_by_credentials() {
+    let (ours, theirs) = std::os::unix::net::UnixStream::pair().unwrap();
+    let peer = peer_cred(&ours).expect("peer");
+    assert_eq!(peer.pid, std::process::id());
+    assert_eq!(Some(peer.path), std::env::current_exe().ok());
+    drop(theirs);
+  }
+}
@@ -0,0 +1,460 @@
+//! Writing vault changes to the store, with a retry queue for failed writes.
+//!
+//! Commands change the in-memory vault first and then persist it. If the
+//! write fails (disk full, permissions, a sync client holding the file), the
+//! in-memory state stays authoritative: the affected entries are queued in
+//! [`PendingWrites`], the frontend is told via [`SAVE_FAILED_EVENT`], and
+//! every later save (or an explicit `retry_save`) writes the queued entries
+//! along with its own.
+//!
+//! In [`SaveMode::Explicit`] changes are only queued, [`DIRTY_EVENT`] keeps
+//! the frontend's unsaved-changes indicator current, and the queue is
+//! written by `save_vault`, on lock, and on exit.
+//!
+//! Locking from the UI waits for the queue to be written ([`flush_and_lock`]).
+//! Locks triggered for security (idle, OS session, copy count, exit) cannot
+//! wait: [`lock`] locks regardless, after appending whatever could not be
+//! written to the encrypted journal, which the next unlock replays.
+//!
+//! Every write's duration is kept for diagnostics (see
+//! [`crate::diagnostics::StorageTimings`]), and every save refreshes the
+//! extension's [`crate::host_index`].
+//!
+//! Lock order: session → vault → pending writes → storage timings.
+
+use crate::diagnostics::SaveTiming;
+use crate::hooks::{self, VaultEvent};
+use crate::host_index;
+use crate::journal::{Journal, JournalOp};
+use crate::models::{AppState, VaultData, VaultSession};
+use crate::settings::SaveMode;
+use crate::store::VaultStore;
+use crate::vault;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::time::Instant;
+use tauri::{AppHandle, Emitter};
+
+/// Emitted with a [`SaveFailure`] payload whenever a write fails.
+pub const SAVE_FAILED_EVENT: &str = "vault://save-failed";
+
+/// Emitted with a [`DirtyState`] payload when changes are queued in
+/// explicit save mode, and when queued changes have been written.
+pub const DIRTY_EVENT: &str = "vault://dirty";
+
+/// Changes held in memory that have not reached the store yet.
+#[derive(Debug, Default)]
+pub struct PendingWrites {
+  /// Entries to rewrite (or delete, if no longer in the vault).
+  ids: BTreeSet<String>,
+  /// The whole vault must be rewritten.
+  full: bool,
+  /// Most recent write error, cleared by a successful save.
+  pub last_error: Option<String>,
+}
+
+impl PendingWrites {
+  /// True if some in-memory change has not been written.
+  pub fn is_dirty(&self) -> bool {
+    self.full || !self.ids.is_empty()
+  }
+
+  /// Number of queued writes (a full rewrite counts once).
+  pub fn len(&self) -> usize {
+    if self.full {
+      1
+    } else {
+      self.ids.len()
+    }
+  }
+
+  pub fn is_empty(&self) -> bool {
+    !self.is_dirty()
+  }
+
+  fn queue(&mut self, changes: Changes<'_>) {
+    match changes {
+      Changes::All => self.full = true,
+      Changes::Entries(ids) => self.ids.extend(ids.iter().map(|id| id.to_string())),
+    }
+  }
+
+  /// Forgets all queued writes, e.g. after a full save or on lock.
+  pub fn clear(&mut self) {
+    self.ids.clear();
+    self.full = false;
+    self.last_error = None;
+  }
+}
+
+/// Payload of [`SAVE_FAILED_EVENT`].
+#[derive(Clone, Debug, Serialize)]
+pub struct SaveFailure {
+  pub error: String,
+  /// Writes now waiting for a retry.
+  pub pending: usize,
+  /// Set when the vault was locked anyway (see [`lock`]); the writes are
+  /// then no longer waiting in memory.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub locked: Option<UnsavedOnLock>,
+}
+
+/// What happened to changes that could not be written before a [`lock`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnsavedOnLock {
+  /// Appended to the journal; the next unlock writes them.
+  Journaled,
+  /// Neither written nor journaled, and discarded.
+  Lost,
+}
+
+/// Payload of [`DIRTY_EVENT`].
+#[derive(Clone, Debug, Serialize)]
+pub struct DirtyState {
+  pub dirty: bool,
+  pub pending: usize,
+}
+
+/// Which entries a save covers.
+#[derive(Clone, Copy, Debug)]
+pub enum Changes<'a> {
+  Entries(&'a [&'a str]),
+  All,
+}
+
+/// Persists `changes` according to the save mode: queues them in explicit
+/// mode, otherwise writes them (see [`write`]).
+pub fn save(
+  state: &AppState,
+  app: Option<&AppHandle>,
+  store: &dyn VaultStore,
+  data: &VaultData,
+  session: &VaultSession,
+  changes: Changes<'_>,
+) -> Result<(), String> {
+  host_index::refresh(state, data);
+  let mode = state.settings.lock().map(|s| s.save_mode).unwrap_or_default();
+  if mode == SaveMode::Auto {
+    return write(state, app, store, data, session, changes);
+  }
+  let mut pending = match state.pending_writes.lock() {
+    Ok(guard) => guard,
+    Err(_) => return Err("pending writes mutex poisoned".to_string()),
+  };
+  pending.queue(changes);
+  let dirty = DirtyState {
+    dirty: true,
+    pending: pending.len(),
+  };
+  drop(pending);
+  if let Some(app) = app {
+    let _ = app.emit(DIRTY_EVENT, dirty);
+  }
+  Ok(())
+}
+
+/// Writes `changes` plus anything still queued from earlier failures or
+/// explicit save mode.
+///
+/// On failure the changes are queued, [`SAVE_FAILED_EVENT`] is emitted (when
+/// an `app` handle is available), and the error is returned for logging;
+/// callers keep their in-memory changes either way.
+pub fn write(
+  state: &AppState,
+  app: Option<&AppHandle>,
+  store: &dyn VaultStore,
+  data: &VaultData,
+  session: &VaultSession,
+  changes: Changes<'_>,
+) -> Result<(), String> {
+  let mut pending = match state.pending_writes.lock() {
+    Ok(guard) => guard,
+    Err(_) => return Err("pending writes mutex poisoned".to_string()),
+  };
+  pending.queue(changes);
+
+  let started = Instant::now();
+  let result = if pending.full {
+    vault::save_to_store(store, data, &session.header, session.key_bytes())
+  } else {
+    let ids: Vec<&str> = pending.ids.iter().map(String::as_str).collect();
+    vault::save_entries(store, data, &session.header, session.key_bytes(), &ids)
+  };
+  if let Ok(mut timings) = state.storage_timings.lock() {
+    timings.last_save = Some(SaveTiming {
+      at: chrono::Utc::now(),
+      ms: started.elapsed().as_millis() as u64,
+      full: pending.full,
+    });
+  }
+
+  match result {
+    Ok(()) => {
+      pending.clear();
+      drop(pending);
+      hooks::fire(state, VaultEvent::Save);
+      Ok(())
+    }
+    Err(e) => {
+      let error = format!("save: {:?}", e);
+      pending.last_error = Some(error.clone());
+      let failure = SaveFailure {
+        error: error.clone(),
+        pending: pending.len(),
+        locked: None,
+      };
+      drop(pending);
+      if let Some(app) = app {
+        let _ = app.emit(SAVE_FAILED_EVENT, failure);
+      }
+      Err(error)
+    }
+  }
+}
+
+/// Writes queued changes, if any.
+pub fn flush(state: &AppState, app: Option<&AppHandle>) -> Result<(), String> {
+  let dirty = state.pending_writes.lock().map(|p| p.is_dirty()).unwrap_or(false);
+  if !dirty {
+    return Ok(());
+  }
+  let store = state
+    .current_store()
+    .ok_or_else(|| "vault store not resolved".to_string())?;
+
+  let session_guard = state.session.lock().map_err(|_| "session mutex poisoned".to_string())?;
+  let session = session_guard.as_ref().ok_or_else(|| "vault is locked".to_string())?;
+  let vault_guard = state.vault.lock().map_err(|_| "vault mutex poisoned".to_string())?;
+  let data = vault_guard.as_ref().ok_or_else(|| "vault is locked".to_string())?;
+
+  write(state, app, store.as_ref(), data, session, Changes::Entries(&[]))?;
+  if let Some(app) = app {
+    let _ = app.emit(DIRTY_EVENT, DirtyState { dirty: false, pending: 0 });
+  }
+  Ok(())
+}
+
+/// Writes queued changes, then locks. If the final write fails, the vault
+/// stays unlocked with the changes still queued ([`SAVE_FAILED_EVENT`] was
+/// emitted by the write) and the error is returned, so the user can retry
+/// or lock without them.
+pub fn flush_and_lock(state: &AppState, app: Option<&AppHandle>) -> Result<(), String> {
+  flush(state, app)?;
+  state.lock_now();
+  Ok(())
+}
+
+/// Writes queued changes and locks, even if the write fails. Changes that
+/// could not be written are appended to the journal for the next unlock,
+/// or discarded if that fails too (record stores have no journal); either
+/// way [`SAVE_FAILED_EVENT`] reports them with `locked` set, and the
+/// failure is returned for logging.
+///
+/// For locks that must not be held up; the UI's lock uses
+/// [`flush_and_lock`].
+pub fn lock(state: &AppState, app: Option<&AppHandle>) -> Option<SaveFailure> {
+  let failure = flush(state, app).err().map(|error| {
+    let pending = state.pending_writes.lock().map(|p| p.len()).unwrap_or(0);
+    let locked = match journal_pending(state) {
+      Ok(()) => UnsavedOnLock::Journaled,
+      Err(err) => {
+        eprintln!("{err}");
+        UnsavedOnLock::Lost
+      }
+    };
+    SaveFailure {
+      error,
+      pending,
+      locked: Some(locked),
+    }
+  });
+  state.lock_now();
+  if let (Some(app), Some(failure)) = (app, &failure) {
+    let _ = app.emit(SAVE_FAILED_EVENT, failure.clone());
+  }
+  failure
+}
+
+/// Appends the queued changes to the journal of the current store: the
+/// queued entries, or the whole vault if a full write is queued.
+fn journal_pending(state: &AppState) -> Result<(), String> {
+  let store = state
+    .current_store()
+    .ok_or_else(|| "vault store not resolved".to_string())?;
+  let journal = Journal::for_store(store.as_ref()).ok_or_else(|| "unsaved changes discarded: no journal".to_string())?;
+
+  let session_guard = state.session.lock().map_err(|_| "session mutex poisoned".to_string())?;
+  let session = session_guard.as_ref().ok_or_else(|| "vault is locked".to_string())?;
+  let vault_guard = state.vault.lock().map_err(|_| "vault mutex poisoned".to_string())?;
+  let data = vault_guard.as_ref().ok_or_else(|| "vault is locked".to_string())?;
+  let pending = state
+    .pending_writes
+    .lock()
+    .map_err(|_| "pending writes mutex poisoned".to_string())?;
+
+  let ops = if pending.full {
+    vec![JournalOp::Vault {
+      data: Box::new(data.clone()),
+    }]
+  } else {
+    let ids: Vec<&str> = pending.ids.iter().map(String::as_str).collect();
+    JournalOp::for_entries(data, &ids)
+  };
+  journal
+    .append(&ops, &data.meta.id, session.header.cipher, session.key_bytes())
+    .map_err(|e| format!("unsaved changes discarded: journal: {:?}", e))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::models::Entry;
+  use crate::store::MemoryStore;
+  use crate::vault::VaultHeader;
+  use std::io;
+  use std::path::{Path, PathBuf};
+  use std::sync::atomic::{AtomicBool, Ordering};
+  use std::sync::Arc;
+
+  /// Memory store whose writes fail while `broken` is set. With a `path`,
+  /// it has a journal there like a file store.
+  #[derive(Debug, Default)]
+  struct FlakyStore {
+    inner: MemoryStore,
+    broken: AtomicBool,
+    path: Option<PathBuf>,
+  }
+
+  impl VaultStore for FlakyStore {
+    fn exists(&self) -> bool {
+      self.inner.exists()
+    }
+    fn read(&self) -> io::Result<Vec<u8>> {
+      self.inner.read()
+    }
+    fn write(&self, bytes: &[u8]) -> io::Result<()> {
+      if self.broken.load(Ordering::SeqCst) {
+        return Err(io::Error::other("disk full"));
+      }
+      self.inner.write(bytes)
+    }
+    fn location(&self) -> String {
+      self.inner.location()
+    }
+    fn path(&self) -> Option<&Path> {
+      self.path.as_deref()
+    }
+  }
+
+  #[test]
+  fn failed_writes_are_queued_and_retried() {
+    let state = AppState::default();
+    let store = FlakyStore::default();
+    let header = VaultHeader::default();
+    let session = VaultSession::new(header.clone(), header.derive_key("pw").expect("kdf"));
+    let mut data = VaultData::default();
+    save(&state, None, &store, &data, &session, Changes::All).expect("initial save");
+
+    store.broken.store(true, Ordering::SeqCst);
+    let entry = Entry::new("t".into(), String::new(), String::new(), String::new(), String::new());
+    let id = entry.id.clone();
+    data.entries.push(entry);
+    assert!(save(&state, None, &store, &data, &session, Changes::Entries(&[&id])).is_err());
+    {
+      let pending = state.pending_writes.lock().unwrap();
+      assert!(pending.is_dirty());
+      assert!(pending.last_error.as_deref().unwrap_or("").contains("disk full"));
+    }
+
+    store.broken.store(false, Ordering::SeqCst);
+    save(&state, None, &store, &data, &session, Changes::Entries(&[])).expect("retry");
+    assert!(!state.pending_writes.lock().unwrap().is_dirty());
+    let (loaded, _, _) = vault::load_from_store(&store, "pw").expect("load");
+    assert_eq!(loaded.entries.len(), 1);
+
+    // Explicit save mode only queues until the next write.
+    state.settings.lock().unwrap().save_mode = SaveMode::Explicit;
+    data.entries.clear();
+    save(&state, None, &store, &data, &session, Changes::Entries(&[&id])).expect("queue");
+    assert!(state.pending_writes.lock().unwrap().is_dirty());
+    assert_eq!(vault::load_from_store(&store, "pw").expect("load").0.entries.len(), 1);
+    write(&state, None, &store, &data, &session, Changes::Entries(&[])).expect("explicit save");
+    assert!(vault::load_from_store(&store, "pw").expect("load").0.entries.is_empty());
+  }
+
+  #[test]
+  fn locking_waits_for_queued_changes_to_be_written() {
+    let store = Arc::new(FlakyStore::default());
+    let state = AppState::with_store(store.clone());
+    let header = VaultHeader::default();
+    let session = VaultSession::new(header.clone(), header.derive_key("pw").expect("kdf"));
+    let data = VaultData::default();
+    save(&state, None, store.as_ref(), &data, &session, Changes::All).expect("initial save");
+    *state.session.lock().unwrap() = Some(session.clone());
+    *state.vault.lock().unwrap() = Some(data.clone());
+
+    store.broken.store(true, Ordering::SeqCst);
+    assert!(save(&state, None, store.as_ref(), &data, &session, Changes::All).is_err());
+    assert!(flush_and_lock(&state, None).is_err());
+    assert!(state.session.lock().unwrap().is_some());
+    assert!(state.pending_writes.lock().unwrap().is_dirty());
+
+    store.broken.store(false, Ordering::SeqCst);
+    flush_and_lock(&state, None).expect("lock");
+    assert!(state.session.lock().unwrap().is_none());
+    assert!(!state.pending_writes.lock().unwrap().is_dirty());
+  }
+
+  #[test]
+  fn security_locks_journal_what_could_not_be_written() {
+    let path = std::env::temp_dir().join(format!("the-organizer-test-lock-{}.dat", std::process::id()));
+    let store = Arc::new(FlakyStore {
+      path: Some(path.clone()),
+      ..FlakyStore::default()
+    });
+    let state = AppState::with_store(store.clone());
+    let journal = Journal::for_store(store.as_ref()).expect("journal");
+    let _ = journal.clear();
+    let header = VaultHeader::default();
+    let session = VaultSession::new(header.clone(), header.derive_key("pw").expect("kdf"));
+    let mut data = VaultData::default();
+    save(&state, None, store.as_ref(), &data, &session, Changes::All).expect("initial save");
+
+    store.broken.store(true, Ordering::SeqCst);
+    let entry = Entry::new("t".into(), String::new(), String::new(), String::new(), String::new());
+    let id = entry.id.clone();
+    data.entries.push(entry);
+    data.meta.canary_entry = Some(id.clone());
+    assert!(save(&state, None, store.as_ref(), &data, &session, Changes::All).is_err());
+    *state.session.lock().unwrap() = Some(session.clone());
+    *state.vault.lock().unwrap() = Some(data.clone());
+
+    let failure = lock(&state, None).expect("the write failed");
+    assert_eq!(failure.locked, Some(UnsavedOnLock::Journaled));
+    assert!(state.session.lock().unwrap().is_none());
+    assert!(!state.pending_writes.lock().unwrap().is_dirty());
+
+    // The next unlock replays the journal.
+    store.broken.store(false, Ordering::SeqCst);
+    let (mut loaded, header, key) = vault::load_from_store(store.as_ref(), "pw").expect("load");
+    assert!(loaded.entries.is_empty());
+    assert_eq!(vault::recover_journal(store.as_ref(), &mut loaded, &header, &key).expect("replay"), 1);
+    assert!(loaded.find_entry(&id).is_some());
+    assert_eq!(loaded.meta.canary_entry, Some(id));
+
+    // Without a journal the changes are reported lost, and it still locks.
+    let store = Arc::new(FlakyStore::default());
+    let state = AppState::with_store(store.clone());
+    save(&state, None, store.as_ref(), &data, &session, Changes::All).expect("initial save");
+    store.broken.store(true, Ordering::SeqCst);
+    assert!(save(&state, None, store.as_ref(), &data, &session, Changes::All).is_err());
+    *state.session.lock().unwrap() = Some(session);
+    *state.vault.lock().unwrap() = Some(data);
+    assert_eq!(lock(&state, None).and_then(|f| f.locked), Some(UnsavedOnLock::Lost));
+    assert!(state.session.lock().unwrap().is_none());
+    assert!(lock(&state, None).is_none());
+
+    let _ = std::fs::remove_file(&path);
+  }
+}
@@ -0,0 +1,254 @@
+//! Lookalike-domain detection for the extension bridge.
+//!
+//! When the browser asks for matches on a host we have no credentials for,
+//! we compare it against the hosts we *do* have credentials for. A host whose
+//! registrable name (`github` in `login.github.com`) is an edit or two away
+//! from a known one, is a known one under another suffix (`github.co`), or
+//! adds a word to a known one (`paypal-login.net`) is a typosquat; one that
+//! renders the same after folding
+//! confusable characters (homoglyph, including IDN/punycode hosts), is
+//! reported so the extension can warn instead of offering to fill.
+
+use serde::Serialize;
+
+/// Why a host was flagged.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LookalikeKind {
+  /// Visually identical after folding confusable characters.
+  Homoglyph,
+  /// Within a small edit distance of a known host.
+  Typosquat,
+}
+
+/// A suspected phishing host and the known host it imitates.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct LookalikeWarning {
+  pub kind: LookalikeKind,
+  /// The host the browser is on.
+  pub host: String,
+  /// The stored host it resembles.
+  pub similar_to: String,
+}
+
+/// Names shorter than this are too short for edit distance to be meaningful.
+const MIN_TYPOSQUAT_LEN: usize = 5;
+
+/// Names at least this long may be two edits away; shorter ones only one.
+const TWO_EDITS_LEN: usize = 9;
+
+/// Second-level suffixes under which names are registered, as in `co.uk`.
+const SECOND_LEVEL_SUFFIXES: &[&str] = &["ac", "co", "com", "edu", "gov", "net", "org"];
+
+/// Registrable domains that share a name with a well-known site under
+/// another suffix but belong to the same owner, so visiting them is not a
+/// suffix swap.
+const SUFFIX_SWAP_ALLOWED: &[&str] = &["github.io", "gitlab.io"];
+
+/// Checks `target_host` against `known_hosts` (both already lowercased).
+///
+/// Callers should only ask when the target matched no entry directly; an
+/// exact or subdomain match is never reported here.
+pub fn check_lookalike<'a>(
+  target_host: &str,
+  known_hosts: impl IntoIterator<Item = &'a str>,
+) -> Option<LookalikeWarning> {
+  let target = strip_www(target_host);
+  let target_unicode = to_unicode(target);
+  let target_skeleton = skeleton(&target_unicode);
+
+  let mut typosquat: Option<&str> = None;
+  for known in known_hosts {
+    let known = strip_www(known);
+    if known == target {
+      continue;
+    }
+    if skeleton(&to_unicode(known)) == target_skeleton {
+      return Some(LookalikeWarning {
+        kind: LookalikeKind::Homoglyph,
+        host: target.to_string(),
+        similar_to: known.to_string(),
+      });
+    }
+    if typosquat.is_none() && is_typosquat(&target_unicode, known) {
+      typosquat = Some(known);
+    }
+  }
+
+  typosquat.map(|known| LookalikeWarning {
+    kind: LookalikeKind::Typosquat,
+    host: target.to_string(),
+    similar_to: known.to_string(),
+  })
+}
+
+fn strip_www(host: &str) -> &str {
+  host.strip_prefix("www.").unwrap_or(host)
+}
+
+/// Decodes punycode (`xn--`) labels so homoglyphs can be compared.
+fn to_unicode(host: &str) -> String {
+  let (decoded, result) = idna::domain_to_unicode(host);
+  if result.is_ok() {
+    decoded
+  } else {
+    host.to_string()
+  }
+}
+
+/// True if the registrable name of `target` imitates that of `known`:
+/// - close but not equal, whatever the suffix (`githuub.com`);
+/// - equal under another suffix (`paypal.co` for `paypal.com`), unless the
+///   target is in [`SUFFIX_SWAP_ALLOWED`];
+/// - the known name plus a hyphenated word (`paypal-login.net`).
+///
+/// Subdomains are left out, so `gist.github.com` is not taken for
+/// `github.com`, and `gitlab.com` is not taken for it just because both end
+/// in `.com`.
+fn is_typosquat(target: &str, known: &str) -> bool {
+  let (target_name, target_suffix) = split_registrable(target);
+  let (known_name, known_suffix) = split_registrable(known);
+  let len = known_name.chars().count();
+  if len < MIN_TYPOSQUAT_LEN {
+    return false;
+  }
+  if target_name == known_name {
+    let domain = format!("{target_name}.{target_suffix}");
+    return target_suffix != known_suffix && !SUFFIX_SWAP_ALLOWED.contains(&domain.as_str());
+  }
+  if target_name.split('-').any(|word| word == known_name) {
+    return true;
+  }
+  let max_distance = if len >= TWO_EDITS_LEN { 2 } else { 1 };
+  levenshtein(target_name, known_name) <= max_distance
+}
+
+/// Splits a host into the label its owner registered and the suffix after
+/// it: the top-level domain, or a two-letter country's second level
+/// (`example` and `co.uk` for `a.example.co.uk`). A single label has no
+/// suffix.
+fn split_registrable(host: &str) -> (&str, &str) {
+  let labels: Vec<&str> = host.split('.').collect();
+  let suffix_len = match labels.as_slice() {
+    [.., _, second, top] if top.len() == 2 && SECOND_LEVEL_SUFFIXES.contains(second) => 2,
+    _ => 1,
+  };
+  let Some(index) = labels.len().checked_sub(suffix_len + 1) else {
+    return (host, "");
+  };
+  let suffix_start: usize = labels[..=index].iter().map(|label| label.len() + 1).sum();
+  (labels[index], &host[suffix_start..])
+}
+
+/// Folds characters that commonly impersonate each other onto one form.
+fn skeleton(host: &str) -> String {
+  let folded: String = host
+    .chars()
+    .map(|c| match c {
+      'а' | 'α' | '@' => 'a',
+      'с' | 'ϲ' => 'c',
+      'ԁ' => 'd',
+      'е' | 'ε' | '3' => 'e',
+      'ɡ' => 'g',
+      'һ' => 'h',
+      'і' | 'ι' | 'ı' => 'i',
+      '1' | '|' | 'ӏ' => 'l',
+      'ј' => 'j',
+      'к' | 'κ' => 'k',
+      'м' => 'm',
+      'п' | 'η' => 'n',
+      'о' | 'ο' | '0' => 'o',
+      'р' | 'ρ' => 'p',
+      'ѕ' | '5' => 's',
+      'т' | 'τ' => 't',
+      'υ' | 'ս' => 'u',
+      'ν' => 'v',
+      'ԝ' | 'ω' => 'w',
+      'х' | 'χ' => 'x',
+      'у' | 'γ' => 'y',
+      other => other,
+    })
+    .collect();
+  folded.replace("rn", "m").replace("vv", "w")
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+  let a: Vec<char> = a.chars().collect();
+  let b: Vec<char> = b.chars().collect();
+  let mut prev: Vec<usize> = (0..=b.len()).collect();
+  let mut curr = vec![0; b.len() + 1];
+
+  for (i, ca) in a.iter().enumerate() {
+    curr[0] = i + 1;
+    for (j, cb) in b.iter().enumerate() {
+      let cost = usize::from(ca != cb);
+      curr[j + 1] = (prev[j + 1] + 1).min(curr[j] + 1).min(prev[j] + cost);
+    }
+    std::mem::swap(&mut prev, &mut curr);
+  }
+  prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn flags_typosquats() {
+    let warning = check_lookalike("paypa1-secure.com", ["paypal-secure.com"]).expect("warning");
+    assert_eq!(warning.similar_to, "paypal-secure.com");
+
+    let warning = check_lookalike("githuub.com", ["github.com"]).expect("warning");
+    assert_eq!(warning.kind, LookalikeKind::Typosquat);
+
+    let warning = check_lookalike("login.barclaya.co.uk", ["barclays.co.uk"]).expect("warning");
+    assert_eq!(warning.similar_to, "barclays.co.uk");
+  }
+
+  #[test]
+  fn flags_suffix_swaps_and_added_words() {
+    for host in ["paypal.co", "paypal.com.co", "paypal-login.net", "secure-paypal.com"] {
+      let warning = check_lookalike(host, ["www.paypal.com"]).expect(host);
+      assert_eq!(warning.kind, LookalikeKind::Typosquat);
+      assert_eq!(warning.similar_to, "paypal.com");
+    }
+    assert!(check_lookalike("github.co", ["github.com"]).is_some());
+    assert!(check_lookalike("barclays.com", ["barclays.co.uk"]).is_some());
+    // Allowed siblings and words that merely contain the name.
+    assert!(check_lookalike("github.io", ["github.com"]).is_none());
+    assert!(check_lookalike("paypalmeetup.com", ["paypal.com"]).is_none());
+  }
+
+  #[test]
+  fn compares_only_registrable_names() {
+    // Two edits apart, but "gitlab" and "github" are too short for two.
+    assert!(check_lookalike("gitlab.com", ["github.com"]).is_none());
+    assert!(check_lookalike("gist.github.com", ["github.com"]).is_none());
+    assert_eq!(split_registrable("a.b.example.co.uk"), ("example", "co.uk"));
+    assert_eq!(split_registrable("example.com"), ("example", "com"));
+    assert_eq!(split_registrable("localhost"), ("localhost", ""));
+  }
+
+  #[test]
+  fn flags_punycode_homoglyphs() {
+    // "аpple.com" with a Cyrillic "а".
+    let warning = check_lookalike("xn--pple-43d.com", ["www.apple.com"]).expect("warning");
+    assert_eq!(warning.kind, LookalikeKind::Homoglyph);
+    assert_eq!(warning.similar_to, "apple.com");
+
+    let warning = check_lookalike("rnicrosoft.com", ["microsoft.com"]).expect("warning");
+    assert_eq!(warning.kind, LookalikeKind::Homoglyph);
+
+    // "lіnkedin.com" with a Cyrillic "і" imitates an "i", not an "l".
+    let warning = check_lookalike("l\u{456}nkedin.com", ["linkedin.com"]).expect("warning");
+    assert_eq!(warning.kind, LookalikeKind::Homoglyph);
+    assert_ne!(skeleton("\u{456}"), skeleton("l"));
+  }
+
+  #[test]
+  fn ignores_unrelated_and_identical_hosts() {
+    assert!(check_lookalike("example.org", ["github.com", "apple.com"]).is_none());
+    assert!(check_lookalike("www.github.com", ["github.com"]).is_none());
+    assert!(check_lookalike("x.io", ["y.io"]).is_none());
+  }
+}
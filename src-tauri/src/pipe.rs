@@ -0,0 +1,276 @@
+//! Named pipe transport for the extension bridge on Windows.
+//!
+//! The pipe's DACL grants access to the current user's SID only, and remote
+//! clients are rejected, so like the Unix socket only the user's own
+//! processes can connect. The first instance is created with
+//! `FILE_FLAG_FIRST_PIPE_INSTANCE`: if another process already holds the
+//! name, binding fails instead of sharing it.
+//!
+//! Handles are opened for overlapped I/O. The bridge's relay reads and
+//! writes each connection from two threads, which synchronous pipe handles
+//! would serialize into a deadlock.
+
+use std::io;
+use std::os::windows::io::{AsRawHandle, FromRawHandle, OwnedHandle};
+use std::ptr;
+use std::sync::Arc;
+use windows_sys::Win32::Foundation::{
+  LocalFree, ERROR_BROKEN_PIPE, ERROR_IO_PENDING, ERROR_PIPE_CONNECTED, HANDLE, INVALID_HANDLE_VALUE,
+};
+use windows_sys::Win32::Security::Authorization::{
+  ConvertSidToStringSidW, ConvertStringSecurityDescriptorToSecurityDescriptorW, SDDL_REVISION_1,
+};
+use windows_sys::Win32::Security::{
+  GetTokenInformation, TokenUser, PSECURITY_DESCRIPTOR, SECURITY_ATTRIBUTES, TOKEN_QUERY, TOKEN_USER,
+};
+use windows_sys::Win32::Storage::FileSystem::{
+  FlushFileBuffers, ReadFile, WriteFile, FILE_FLAG_FIRST_PIPE_INSTANCE, FILE_FLAG_OVERLAPPED, PIPE_ACCESS_DUPLEX,
+};
+use windows_sys::Win32::System::Pipes::{
+  ConnectNamedPipe, CreateNamedPipeW, PIPE_READMODE_BYTE, PIPE_REJECT_REMOTE_CLIENTS, PIPE_TYPE_BYTE,
+  PIPE_UNLIMITED_INSTANCES, PIPE_WAIT,
+};
+use windows_sys::Win32::System::Threading::{CreateEventW, GetCurrentProcess, OpenProcessToken};
+use windows_sys::Win32::System::IO::{CancelIoEx, GetOverlappedResult, OVERLAPPED};
+
+const BUFFER_SIZE: u32 = 64 * 1024;
+
+/// Name of the bridge pipe for the current user.
+pub fn bridge_name() -> io::Result<String> {
+  Ok(format!(r"\\.\pipe\the-organizer-bridge-{}", current_user_sid()?))
+}
+
+/// Accepts connections to a named pipe, one instance per client.
+pub struct PipeListener {
+  name: Vec<u16>,
+  security: SecurityDescriptor,
+  /// The instance waiting for the next client.
+  next: Option<OwnedHandle>,
+}
+
+impl PipeListener {
+  /// Creates the first instance of the pipe `name`, owned by and only open
+  /// to the current user.
+  pub fn bind(name: &str) -> io::Result<Self> {
+    let mut listener = Self {
+      name: wide(name),
+      security: SecurityDescriptor::owner_only()?,
+      next: None,
+    };
+    listener.next = Some(listener.instance(true)?);
+    Ok(listener)
+  }
+
+  /// Waits for a client, then readies a new instance for the next one.
+  pub fn accept(&mut self) -> io::Result<PipeStream> {
+    let pipe = match self.next.take() {
+      Some(pipe) => pipe,
+      None => self.instance(false)?,
+    };
+    let handle = pipe.as_raw_handle() as HANDLE;
+    // SAFETY: `handle` stays open for the call, and `overlapped` waits for
+    // the operation before its OVERLAPPED goes out of scope.
+    match overlapped(handle, |ov| unsafe { ConnectNamedPipe(handle, ov) }) {
+      Ok(_) => {}
+      // The client connected between creating the instance and this call.
+      Err(e) if e.raw_os_error() == Some(ERROR_PIPE_CONNECTED as i32) => {}
+      Err(e) => return Err(e),
+    }
+    self.next = self.instance(false).ok();
+    Ok(PipeStream { handle: Arc::new(pipe) })
+  }
+
+  fn instance(&self, first: bool) -> io::Result<OwnedHandle> {
+    let mut open_mode = PIPE_ACCESS_DUPLEX | FILE_FLAG_OVERLAPPED;
+    if first {
+      open_mode |= FILE_FLAG_FIRST_PIPE_INSTANCE;
+    }
+    let attributes = SECURITY_ATTRIBUTES {
+      nLength: std::mem::size_of::<SECURITY_ATTRIBUTES>() as u32,
+      lpSecurityDescriptor: self.security.0,
+      bInheritHandle: 0,
+    };
+    // SAFETY: the name is NUL-terminated and the attributes point to a
+    // security descriptor that lives as long as the listener.
+    let handle = unsafe {
+      CreateNamedPipeW(
+        self.name.as_ptr(),
+        open_mode,
+        PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+        PIPE_UNLIMITED_INSTANCES,
+        BUFFER_SIZE,
+        BUFFER_SIZE,
+        0,
+        &attributes,
+      )
+    };
+    if handle == INVALID_HANDLE_VALUE {
+      return Err(io::Error::last_os_error());
+    }
+    // SAFETY: a valid handle this function exclusively owns.
+    Ok(unsafe { OwnedHandle::from_raw_handle(handle as _) })
+  }
+}
+
+/// A connected client. Clones share the connection, so one thread can read
+/// while another writes.
+pub struct PipeStream {
+  handle: Arc<OwnedHandle>,
+}
+
+impl PipeStream {
+  pub fn try_clone(&self) -> io::Result<Self> {
+    Ok(Self {
+      handle: Arc::clone(&self.handle),
+    })
+  }
+
+  /// Waits for the client to read what was written, then cancels reads
+  /// and writes in progress on any clone. The connection closes when the
+  /// last clone is dropped.
+  pub fn close(&self) {
+    // SAFETY: flushing and cancelling I/O on an open handle.
+    unsafe {
+      FlushFileBuffers(self.raw());
+      CancelIoEx(self.raw(), ptr::null());
+    }
+  }
+
+  fn raw(&self) -> HANDLE {
+    self.handle.as_raw_handle() as HANDLE
+  }
+}
+
+impl io::Read for PipeStream {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    let handle = self.raw();
+    let len = u32::try_from(buf.len()).unwrap_or(u32::MAX);
+    // SAFETY: `buf` outlives the wait for the operation in `overlapped`.
+    match overlapped(handle, |ov| unsafe { ReadFile(handle, buf.as_mut_ptr(), len, ptr::null_mut(), ov) }) {
+      // The client closed its end.
+      Err(e) if e.raw_os_error() == Some(ERROR_BROKEN_PIPE as i32) => Ok(0),
+      result => result.map(|n| n as usize),
+    }
+  }
+}
+
+impl io::Write for PipeStream {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    let handle = self.raw();
+    let len = u32::try_from(buf.len()).unwrap_or(u32::MAX);
+    // SAFETY: `buf` outlives the wait for the operation in `overlapped`.
+    overlapped(handle, |ov| unsafe { WriteFile(handle, buf.as_ptr(), len, ptr::null_mut(), ov) }).map(|n| n as usize)
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    Ok(())
+  }
+}
+
+/// Starts an overlapped operation with `start` and waits for it, returning
+/// the bytes transferred.
+fn overlapped(handle: HANDLE, start: impl FnOnce(*mut OVERLAPPED) -> i32) -> io::Result<u32> {
+  // SAFETY: creates an unnamed manual-reset event, owned below.
+  let event = unsafe { CreateEventW(ptr::null(), 1, 0, ptr::null()) };
+  if event.is_null() {
+    return Err(io::Error::last_os_error());
+  }
+  // SAFETY: a valid handle this function exclusively owns.
+  let event = unsafe { OwnedHandle::from_raw_handle(event as _) };
+  // SAFETY: all-zero is a valid OVERLAPPED.
+  let mut ov: OVERLAPPED = unsafe { std::mem::zeroed() };
+  ov.hEvent = event.as_raw_handle() as HANDLE;
+  if start(&mut ov) == 0 {
+    let error = io::Error::last_os_error();
+    if error.raw_os_error() != Some(ERROR_IO_PENDING as i32) {
+      return Err(error);
+    }
+  }
+  let mut transferred = 0u32;
+  // SAFETY: `ov` belongs to the operation just started on `handle`; waiting
+  // here keeps it and the caller's buffer alive until it completes.
+  if unsafe { GetOverlappedResult(handle, &ov, &mut transferred, 1) } == 0 {
+    return Err(io::Error::last_os_error());
+  }
+  Ok(transferred)
+}
+
+/// A security descriptor from `ConvertStringSecurityDescriptorToSecurityDescriptorW`.
+struct SecurityDescriptor(PSECURITY_DESCRIPTOR);
+
+// SAFETY: the descriptor is only read after creation, and freed once.
+unsafe impl Send for SecurityDescriptor {}
+
+impl SecurityDescriptor {
+  /// Protected DACL with a single entry: full access for the current user.
+  fn owner_only() -> io::Result<Self> {
+    let sddl = wide(&format!("D:P(A;;GA;;;{})", current_user_sid()?));
+    let mut descriptor: PSECURITY_DESCRIPTOR = ptr::null_mut();
+    // SAFETY: the string is NUL-terminated; the descriptor is freed on drop.
+    let converted = unsafe {
+      ConvertStringSecurityDescriptorToSecurityDescriptorW(sddl.as_ptr(), SDDL_REVISION_1, &mut descriptor, ptr::null_mut())
+    };
+    if converted == 0 {
+      return Err(io::Error::last_os_error());
+    }
+    Ok(Self(descriptor))
+  }
+}
+
+impl Drop for SecurityDescriptor {
+  fn drop(&mut self) {
+    // SAFETY: allocated with LocalAlloc by the conversion above.
+    unsafe { LocalFree(self.0) };
+  }
+}
+
+/// The current user's SID, e.g. `S-1-5-21-...`.
+fn current_user_sid() -> io::Result<String> {
+  let mut token: HANDLE = ptr::null_mut();
+  // SAFETY: the pseudo-handle of this process needs no closing; the token
+  // is owned below.
+  if unsafe { OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token) } == 0 {
+    return Err(io::Error::last_os_error());
+  }
+  // SAFETY: a valid handle this function exclusively owns.
+  let token = unsafe { OwnedHandle::from_raw_handle(token as _) };
+
+  let mut len = 0u32;
+  // SAFETY: a size query; it fails with the required length.
+  unsafe { GetTokenInformation(token.as_raw_handle() as HANDLE, TokenUser, ptr::null_mut(), 0, &mut len) };
+  // u64 words keep the TOKEN_USER aligned.
+  let mut buffer = vec![0u64; (len as usize).div_ceil(8)];
+  // SAFETY: the buffer holds at least `len` bytes.
+  let queried = unsafe {
+    GetTokenInformation(
+      token.as_raw_handle() as HANDLE,
+      TokenUser,
+      buffer.as_mut_ptr().cast(),
+      len,
+      &mut len,
+    )
+  };
+  if queried == 0 {
+    return Err(io::Error::last_os_error());
+  }
+  // SAFETY: filled in as a TOKEN_USER, whose SID points into the buffer.
+  let sid = unsafe { (*buffer.as_ptr().cast::<TOKEN_USER>()).User.Sid };
+
+  let mut string: *mut u16 = ptr::null_mut();
+  // SAFETY: `sid` is valid while `buffer` lives; the string is freed below.
+  if unsafe { ConvertSidToStringSidW(sid, &mut string) } == 0 {
+    return Err(io::Error::last_os_error());
+  }
+  // SAFETY: a NUL-terminated string from the conversion above.
+  let sid = unsafe {
+    let len = (0..).take_while(|&i| *string.add(i) != 0).count();
+    let sid = String::from_utf16_lossy(std::slice::from_raw_parts(string, len));
+    LocalFree(string.cast());
+    sid
+  };
+  Ok(sid)
+}
+
+fn wide(s: &str) -> Vec<u16> {
+  s.encode_utf16().chain(std::iter::once(0)).collect()
+}
@@ -0,0 +1,130 @@
+//! Quick-access window: a small search box summoned by a global hotkey.
+//!
+//! The window (label [`QUICK_WINDOW_LABEL`]) is declared hidden in
+//! `tauri.conf.json`; [`DEFAULT_SHORTCUT`] shows it. It talks to the
+//! backend through `quick_search` and `quick_copy`, and never receives
+//! secrets: copying happens in the backend, like `copy_field`.
+
+use crate::models::Entry;
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+/// Label of the quick-access window in `tauri.conf.json`.
+pub const QUICK_WINDOW_LABEL: &str = "quick";
+
+/// Global hotkey that summons the quick-access window.
+pub const DEFAULT_SHORTCUT: &str = "CmdOrCtrl+Shift+Space";
+
+/// Matches returned when the caller gives no limit.
+pub const DEFAULT_QUICK_LIMIT: usize = 8;
+
+/// A search result: enough to pick an entry, no secrets.
+#[derive(Clone, Debug, Serialize)]
+pub struct QuickMatch {
+  pub id: String,
+  pub title: String,
+  pub username: String,
+  pub url: String,
+  pub kind: &'static str,
+}
+
+impl From<&Entry> for QuickMatch {
+  fn from(entry: &Entry) -> Self {
+    Self {
+      id: entry.id.clone(),
+      title: entry.title.clone(),
+      username: entry.username.clone(),
+      url: entry.url.clone(),
+      kind: entry.kind.type_name(),
+    }
+  }
+}
+
+/// How well `entry` matches the lowercased `query`; `None` if it does not.
+/// Title matches beat username, URL, and tag matches.
+pub(crate) fn score(entry: &Entry, query: &str) -> Option<u8> {
+  let title = entry.title.to_lowercase();
+  if title.starts_with(query) {
+    return Some(4);
+  }
+  if title.split_whitespace().any(|word| word.starts_with(query)) {
+    return Some(3);
+  }
+  if title.contains(query) {
+    return Some(2);
+  }
+  let other = [&entry.username, &entry.url].into_iter().chain(entry.tags.iter());
+  other.into_iter().any(|field| field.to_lowercase().contains(query)).then_some(1)
+}
+
+/// Best matches for `query`, ranked by score, then most recently used.
+/// An empty query lists the most recently used entries. Hidden entries are
+/// never matched.
+pub fn search<'a>(entries: &'a [Entry], query: &str, limit: usize) -> Vec<&'a Entry> {
+  let query = query.trim().to_lowercase();
+  let mut matches: Vec<(u8, &Entry)> = entries
+    .iter()
+    .filter(|entry| !entry.hidden)
+    .filter_map(|entry| {
+      if query.is_empty() {
+        return Some((0, entry));
+      }
+      score(entry, &query).map(|s| (s, entry))
+    })
+    .collect();
+  matches.sort_by(|(a_score, a), (b_score, b)| {
+    b_score
+      .cmp(a_score)
+      .then_with(|| b.last_used_at.cmp(&a.last_used_at))
+      .then_with(|| a.title.cmp(&b.title))
+  });
+  matches.into_iter().take(limit).map(|(_, entry)| entry).collect()
+}
+
+/// Shows and focuses the quick-access window.
+pub fn show_window(app: &AppHandle) {
+  let Some(window) = app.get_webview_window(QUICK_WINDOW_LABEL) else {
+    eprintln!("quick access: window '{QUICK_WINDOW_LABEL}' not configured");
+    return;
+  };
+  if let Err(err) = window.show().and_then(|_| window.set_focus()) {
+    eprintln!("quick access: failed to show window: {err}");
+  }
+}
+
+/// Hides the quick-access window, e.g. after a copy or on Escape.
+pub fn hide_window(app: &AppHandle) {
+  if let Some(window) = app.get_webview_window(QUICK_WINDOW_LABEL) {
+    let _ = window.hide();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn entry(title: &str, username: &str) -> Entry {
+    Entry::new(title.into(), username.into(), String::new(), String::new(), String::new())
+  }
+
+  #[test]
+  fn title_prefix_beats_username_and_recent_use_breaks_ties() {
+    let mut entries = vec![
+      entry("Work mail", "bank-admin"),
+      entry("My Bank", "alice"),
+      entry("Bank", "alice"),
+      entry("Bankside Gym", "alice"),
+    ];
+    entries[3].mark_used();
+
+    let titles: Vec<&str> = search(&entries, " BANK ", 10).iter().map(|e| e.title.as_str()).collect();
+    assert_eq!(titles, ["Bankside Gym", "Bank", "My Bank", "Work mail"]);
+    assert_eq!(search(&entries, "bank", 2).len(), 2);
+    assert_eq!(search(&entries, "", 10)[0].title, "Bankside Gym");
+    assert!(search(&entries, "nothing", 10).is_empty());
+
+    entries[2].hidden = true;
+    assert!(search(&entries, "bank", 10).iter().all(|e| e.title != "Bank"));
+    assert!(search(&entries, "", 10).iter().all(|e| !e.hidden));
+  }
+}
@@ -0,0 +1,137 @@
+//! Auto-hide for revealed secrets.
+//!
+//! `reveal_field` registers each reveal here and starts a backend timer; when
+//! it fires, [`CONCEAL_EVENT`] tells the UI to hide the value again. The
+//! delay and whether screen capture is blocked while a secret is visible
+//! come from [`RevealPolicy`], so the frontend cannot extend either.
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+/// Emitted when a revealed secret must be hidden again.
+pub const CONCEAL_EVENT: &str = "vault://conceal";
+
+/// Label of the main window in `tauri.conf.json`.
+pub const MAIN_WINDOW_LABEL: &str = "main";
+
+/// Seconds a revealed secret stays visible, by default.
+pub const DEFAULT_REVEAL_SECS: u64 = 20;
+
+/// Accepted range for [`RevealPolicy::hide_after_secs`].
+pub const REVEAL_SECS_RANGE: (u64, u64) = (1, 300);
+
+/// How long revealed secrets stay visible.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RevealPolicy {
+  pub hide_after_secs: u64,
+  /// Block screenshots and screen recording of the main window while any
+  /// secret is revealed.
+  pub protect_capture: bool,
+}
+
+impl Default for RevealPolicy {
+  fn default() -> Self {
+    Self {
+      hide_after_secs: DEFAULT_REVEAL_SECS,
+      protect_capture: true,
+    }
+  }
+}
+
+impl RevealPolicy {
+  pub fn validate(&self) -> Result<(), String> {
+    let (min, max) = REVEAL_SECS_RANGE;
+    if !(min..=max).contains(&self.hide_after_secs) {
+      return Err(format!("hide_after_secs must be between {min} and {max}"));
+    }
+    Ok(())
+  }
+}
+
+/// A revealed field, as sent with [`CONCEAL_EVENT`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Revealed {
+  pub token: u64,
+  pub entry_id: String,
+  pub field: String,
+}
+
+/// Reveals whose timer has not fired yet, and whether capture protection is
+/// currently switched on for them.
+#[derive(Debug, Default)]
+pub struct RevealTracker {
+  next_token: u64,
+  active: Vec<Revealed>,
+  protecting: bool,
+}
+
+impl RevealTracker {
+  /// Registers a reveal. Returns it and whether capture protection must be
+  /// switched on now.
+  pub fn start(&mut self, entry_id: &str, field: &str, protect: bool) -> (Revealed, bool) {
+    self.next_token += 1;
+    let revealed = Revealed {
+      token: self.next_token,
+      entry_id: entry_id.to_string(),
+      field: field.to_string(),
+    };
+    self.active.push(revealed.clone());
+    let switch_on = protect && !self.protecting;
+    self.protecting |= protect;
+    (revealed, switch_on)
+  }
+
+  /// Ends the reveal `token` (if still active). Returns it and whether
+  /// capture protection must be switched off now.
+  pub fn finish(&mut self, token: u64) -> (Option<Revealed>, bool) {
+    let position = self.active.iter().position(|r| r.token == token);
+    let revealed = position.map(|i| self.active.remove(i));
+    (revealed, self.release())
+  }
+
+  /// Ends every reveal, e.g. when the user hides them or the vault locks.
+  pub fn finish_all(&mut self) -> (Vec<Revealed>, bool) {
+    let revealed = std::mem::take(&mut self.active);
+    (revealed, self.release())
+  }
+
+  fn release(&mut self) -> bool {
+    let switch_off = self.protecting && self.active.is_empty();
+    if switch_off {
+      self.protecting = false;
+    }
+    switch_off
+  }
+}
+
+/// Turns capture protection of the main window on or off.
+pub fn set_capture_protection(app: &AppHandle, on: bool) {
+  let Some(window) = app.get_webview_window(MAIN_WINDOW_LABEL) else {
+    return;
+  };
+  if let Err(err) = window.set_content_protected(on) {
+    eprintln!("reveal: failed to set capture protection: {err}");
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn protection_stays_on_until_the_last_reveal_ends() {
+    let mut tracker = RevealTracker::default();
+    let (first, on) = tracker.start("bank", "password", true);
+    assert!(on);
+    let (second, on) = tracker.start("bank", "pin", true);
+    assert!(!on);
+
+    assert_eq!(tracker.finish(first.token), (Some(first.clone()), false));
+    assert_eq!(tracker.finish(first.token), (None, false));
+    assert_eq!(tracker.finish(second.token), (Some(second), true));
+
+    tracker.start("mail", "password", false);
+    assert!(!tracker.finish_all().1);
+  }
+}
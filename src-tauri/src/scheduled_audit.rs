@@ -0,0 +1,233 @@
+//! Weekly background security audit.
+//!
+//! With the user's consent (`Settings::scheduled_audit`), the inactivity
+//! monitor runs the local security checks once the UI has been idle for a
+//! while, but before auto-lock: weak and reused passwords, missing 2FA,
+//! expiring API keys, and the offline breach dataset if one is configured.
+//! The result is cached until the vault locks, so the dashboard opens
+//! instantly, and issues not seen by the previous audit trigger
+//! [`SECURITY_ISSUES_EVENT`]. The previous audit's issues are kept in the
+//! encrypted vault metadata.
+
+use crate::activity::ActivitySource;
+use crate::breach::{self, BreachedEntry, HibpDataset};
+use crate::commands::persist_changes;
+use crate::models::{AppState, AuditCheckpoint, VaultData};
+use crate::notifications::{self, Notice, NoticeKind};
+use crate::persist::Changes;
+use crate::security::{self, ExpiringApiKey, TwoFactorSuggestion};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::path::Path;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+/// Emitted with a [`NewSecurityIssues`] payload when an audit finds issues
+/// the previous one did not.
+pub const SECURITY_ISSUES_EVENT: &str = "security://new-issues";
+
+/// Days between scheduled audits.
+pub const AUDIT_INTERVAL_DAYS: i64 = 7;
+
+/// UI idle time before a due audit runs.
+pub const AUDIT_IDLE_SECS: u64 = 60;
+
+/// API keys expiring within this many days count as issues.
+pub const EXPIRY_WINDOW_DAYS: u32 = 30;
+
+/// Results of one audit.
+#[derive(Clone, Debug, Serialize)]
+pub struct SecurityAudit {
+  pub ran_at: DateTime<Utc>,
+  pub weak_entry_ids: Vec<String>,
+  pub reused_entry_ids: Vec<String>,
+  pub missing_2fa: Vec<TwoFactorSuggestion>,
+  pub expiring_api_keys: Vec<ExpiringApiKey>,
+  /// Empty when no breach dataset is configured.
+  pub breached: Vec<BreachedEntry>,
+  /// Issue keys not reported by the previous audit.
+  pub new_issues: Vec<String>,
+}
+
+/// Payload of [`SECURITY_ISSUES_EVENT`].
+#[derive(Clone, Debug, Serialize)]
+pub struct NewSecurityIssues {
+  pub count: usize,
+  pub ran_at: DateTime<Utc>,
+}
+
+impl SecurityAudit {
+  /// Runs the local checks against `data`. A breach dataset that cannot be
+  /// read is logged and skipped.
+  pub fn run(data: &VaultData, breach_dataset: Option<&Path>, now: DateTime<Utc>) -> Self {
+    let health = security::password_health(&data.entries);
+    let breached = breach_dataset
+      .map(|path| HibpDataset::open(path).and_then(|mut dataset| breach::find_breached(&data.entries, &mut dataset)))
+      .transpose()
+      .unwrap_or_else(|err| {
+        eprintln!("scheduled audit: breach check skipped: {err}");
+        None
+      })
+      .unwrap_or_default();
+    Self {
+      ran_at: now,
+      weak_entry_ids: health.weak_entry_ids,
+      reused_entry_ids: health.reused_entry_ids,
+      missing_2fa: security::find_missing_2fa(&data.entries),
+      expiring_api_keys: security::find_expiring_api_keys(&data.entries, EXPIRY_WINDOW_DAYS, now),
+      breached,
+      new_issues: Vec::new(),
+    }
+  }
+
+  /// One key per issue, e.g. `weak/<entry id>`.
+  pub fn issue_keys(&self) -> Vec<String> {
+    let ids = |kind: &str, ids: Vec<&str>| ids.into_iter().map(move |id| format!("{kind}/{id}")).collect::<Vec<_>>();
+    [
+      ids("weak", self.weak_entry_ids.iter().map(String::as_str).collect()),
+      ids("reused", self.reused_entry_ids.iter().map(String::as_str).collect()),
+      ids("2fa", self.missing_2fa.iter().map(|s| s.id.as_str()).collect()),
+      ids("expiring", self.expiring_api_keys.iter().map(|k| k.id.as_str()).collect()),
+      ids("breached", self.breached.iter().map(|b| b.id.as_str()).collect()),
+    ]
+    .concat()
+  }
+
+  /// Records issues missing from `previous` in `new_issues` and returns the
+  /// checkpoint for the next audit.
+  pub fn compare(&mut self, previous: Option<&AuditCheckpoint>) -> AuditCheckpoint {
+    let issues = self.issue_keys();
+    self.new_issues = issues
+      .iter()
+      .filter(|key| previous.is_none_or(|p| !p.issues.contains(key)))
+      .cloned()
+      .collect();
+    AuditCheckpoint {
+      ran_at: self.ran_at,
+      issues,
+    }
+  }
+}
+
+impl SecurityAudit {
+  /// Notifications for the new issues: expiring API keys as a reminder,
+  /// everything else as one audit finding. Titles come from `data`.
+  pub fn notices(&self, data: &VaultData) -> Vec<Notice> {
+    let titles = |keys: &[&String]| {
+      let mut titles: Vec<String> = Vec::new();
+      for key in keys {
+        let id = key.split_once('/').map_or(key.as_str(), |(_, id)| id);
+        if let Some(entry) = data.entries.iter().find(|e| e.id == id) {
+          if !titles.contains(&entry.title) {
+            titles.push(entry.title.clone());
+          }
+        }
+      }
+      titles
+    };
+    let (expiring, findings): (Vec<&String>, Vec<&String>) =
+      self.new_issues.iter().partition(|key| key.starts_with("expiring/"));
+
+    let mut notices = Vec::new();
+    if !expiring.is_empty() {
+      let summary = match expiring.len() {
+        1 => "An API key expires soon.".to_string(),
+        n => format!("{n} API keys expire soon."),
+      };
+      notices.push(Notice::new(NoticeKind::ExpiryReminder, summary).titles(titles(&expiring)));
+    }
+    if !findings.is_empty() {
+      let summary = match findings.len() {
+        1 => "The security audit found a new issue.".to_string(),
+        n => format!("The security audit found {n} new issues."),
+      };
+      notices.push(Notice::new(NoticeKind::AuditFinding, summary).titles(titles(&findings)));
+    }
+    notices
+  }
+}
+
+/// An audit is due a week after the last one, or when none has run yet.
+pub fn is_due(last: Option<&AuditCheckpoint>, now: DateTime<Utc>) -> bool {
+  last.is_none_or(|last| now - last.ran_at >= chrono::Duration::days(AUDIT_INTERVAL_DAYS))
+}
+
+/// Called by the inactivity monitor while the vault is unlocked. Runs the
+/// audit if the user opted in, the UI is idle, and either an audit is due
+/// or none has been cached since the unlock.
+pub fn maybe_run(state: &AppState, app: &AppHandle) {
+  let (enabled, breach_dataset) = match state.settings.lock() {
+    Ok(settings) => (settings.scheduled_audit, settings.breach_dataset.clone()),
+    Err(_) => return,
+  };
+  if !enabled || state.activity.idle(ActivitySource::Ui) < Duration::from_secs(AUDIT_IDLE_SECS) {
+    return;
+  }
+  let cached = state.security_audit.lock().map(|audit| audit.is_some()).unwrap_or(true);
+
+  let Some(store) = state.current_store() else {
+    return;
+  };
+  // Lock order: session, then vault.
+  let Ok(session_guard) = state.session.lock() else {
+    return;
+  };
+  let Ok(mut vault_guard) = state.vault.lock() else {
+    return;
+  };
+  let (Some(session), Some(data)) = (session_guard.as_ref(), vault_guard.as_mut()) else {
+    return;
+  };
+  let now = Utc::now();
+  if cached && !is_due(data.meta.last_security_audit.as_ref(), now) {
+    return;
+  }
+
+  let mut audit = SecurityAudit::run(data, breach_dataset.as_deref(), now);
+  data.meta.last_security_audit = Some(audit.compare(data.meta.last_security_audit.as_ref()));
+  let notices = audit.notices(data);
+  persist_changes(Some(app), state, store.as_ref(), data, session, Changes::All);
+  drop(vault_guard);
+  drop(session_guard);
+
+  if !audit.new_issues.is_empty() {
+    let payload = NewSecurityIssues {
+      count: audit.new_issues.len(),
+      ran_at: audit.ran_at,
+    };
+    let _ = app.emit(SECURITY_ISSUES_EVENT, payload);
+  }
+  for notice in notices {
+    notifications::notify(app, state, notice);
+  }
+  if let Ok(mut cache) = state.security_audit.lock() {
+    *cache = Some(audit);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::models::Entry;
+
+  #[test]
+  fn only_issues_missing_from_the_checkpoint_are_new() {
+    let mut data = VaultData::default();
+    for password in ["hunter2", "hunter2"] {
+      data.entries.push(Entry::new("t".into(), "u".into(), password.into(), String::new(), String::new()));
+    }
+    let now = Utc::now();
+
+    let mut first = SecurityAudit::run(&data, None, now);
+    let checkpoint = first.compare(None);
+    assert_eq!(first.new_issues.len(), 4);
+    assert!(!is_due(Some(&checkpoint), now));
+    assert!(is_due(Some(&checkpoint), now + chrono::Duration::days(AUDIT_INTERVAL_DAYS)));
+
+    data.entries[1].password = "Correct-Horse-9-Battery".into();
+    data.entries.push(Entry::new("t".into(), "u".into(), "abc".into(), String::new(), String::new()));
+    let mut second = SecurityAudit::run(&data, None, now);
+    second.compare(Some(&checkpoint));
+    assert_eq!(second.new_issues, [format!("weak/{}", data.entries[2].id)]);
+  }
+}
@@ -0,0 +1,83 @@
+//! Saved searches ("smart folders").
+//!
+//! Filters are stored in the encrypted vault metadata and evaluated here
+//! against the unlocked entries, so the frontend only receives the matches.
+
+use crate::models::{Entry, SearchFilter};
+use crate::quick;
+
+/// Longest accepted saved search name, in characters.
+pub const MAX_SEARCH_NAME_CHARS: usize = 100;
+
+/// Trims the filter's fields and checks the type name.
+pub fn normalize(mut filter: SearchFilter) -> Result<SearchFilter, String> {
+  const TYPES: [&str; 6] = ["login", "identity", "card", "ssh_key", "pgp_key", "api_key"];
+  filter.query = filter.query.trim().to_string();
+  filter.tags = filter.tags.iter().map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).collect();
+  filter.kind = filter.kind.map(|k| k.trim().to_string()).filter(|k| !k.is_empty());
+  filter.folder = filter.folder.map(|f| f.trim().to_string()).filter(|f| !f.is_empty());
+  if let Some(kind) = filter.kind.as_deref() {
+    if !TYPES.contains(&kind) {
+      return Err(format!("unknown entry type: {kind}"));
+    }
+  }
+  Ok(filter)
+}
+
+/// Entries matching every criterion of `filter`. With a query, the best
+/// matches come first (ranked like quick search); otherwise by title.
+/// Hidden entries are never matched.
+pub fn run<'a>(entries: &'a [Entry], filter: &SearchFilter) -> Vec<&'a Entry> {
+  let query = filter.query.trim().to_lowercase();
+  let mut matches: Vec<(u8, &Entry)> = entries
+    .iter()
+    .filter(|e| !e.hidden)
+    .filter(|e| filter.tags.iter().all(|tag| e.has_tag(tag)))
+    .filter(|e| filter.kind.as_deref().is_none_or(|kind| e.kind.type_name() == kind))
+    .filter(|e| filter.folder.is_none() || e.folder == filter.folder)
+    .filter_map(|e| {
+      if query.is_empty() {
+        return Some((0, e));
+      }
+      quick::score(e, &query).map(|s| (s, e))
+    })
+    .collect();
+  matches.sort_by(|(a_score, a), (b_score, b)| {
+    b_score
+      .cmp(a_score)
+      .then_with(|| a.title.to_lowercase().cmp(&b.title.to_lowercase()))
+  });
+  matches.into_iter().map(|(_, entry)| entry).collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn all_criteria_must_match() {
+    let mut entries = Vec::new();
+    for (title, tag, folder) in [("GitHub", "work", Some("dev")), ("GitLab", "work", None), ("Gmail", "home", Some("dev"))] {
+      let mut entry = Entry::new(title.into(), String::new(), String::new(), String::new(), String::new());
+      entry.tags = vec![tag.into()];
+      entry.folder = folder.map(String::from);
+      entries.push(entry);
+    }
+    let titles = |filter: &SearchFilter| run(&entries, filter).iter().map(|e| e.title.clone()).collect::<Vec<_>>();
+
+    assert_eq!(titles(&SearchFilter::default()), ["GitHub", "GitLab", "Gmail"]);
+    let filter = normalize(SearchFilter {
+      query: " git ".into(),
+      tags: vec!["WORK".into()],
+      folder: Some("dev".into()),
+      ..SearchFilter::default()
+    })
+    .unwrap();
+    assert_eq!(titles(&filter), ["GitHub"]);
+    assert!(normalize(SearchFilter {
+      kind: Some("note".into()),
+      ..SearchFilter::default()
+    })
+    .is_err());
+  }
+}
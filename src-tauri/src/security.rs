@@ -0,0 +1,473 @@
+//! Local vault health checks for the security dashboard.
+//!
+//! Everything here runs against the decrypted entries in memory; nothing
+//! leaves the machine and no secret values are returned.
+
+use crate::extension::{host_matches, normalize_host};
+use crate::models::{Entry, EntryKind, SecuritySnapshot};
+use chrono::{DateTime, Months, Utc};
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
+
+/// Minimum time between two security snapshots.
+pub const SNAPSHOT_INTERVAL_HOURS: i64 = 24;
+
+/// Snapshots kept in the vault; older ones are dropped.
+pub const MAX_SECURITY_SNAPSHOTS: usize = 180;
+
+/// An entry whose account has not been used within the staleness window.
+#[derive(Clone, Debug, Serialize)]
+pub struct StaleEntry {
+  pub id: String,
+  pub title: String,
+  pub username: String,
+  pub url: String,
+  pub created_at: DateTime<Utc>,
+  pub last_used_at: Option<DateTime<Utc>>,
+  /// Whole days since the last use (or since creation if never used).
+  pub idle_days: i64,
+}
+
+/// Result of [`find_stale_entries`].
+#[derive(Clone, Debug, Serialize)]
+pub struct StaleEntriesReport {
+  /// Staleness window that was applied, in months.
+  pub months: u32,
+  /// Stale entries, longest idle first.
+  pub entries: Vec<StaleEntry>,
+  /// Number of distinct sites (hosts) across the whole vault.
+  pub total_sites: usize,
+}
+
+/// Lists entries not used for at least `months` months.
+///
+/// Entries that were never used are judged by their creation date, so a
+/// freshly added account is not reported straight away. Hidden entries are
+/// left out.
+pub fn find_stale_entries(entries: &[Entry], months: u32, now: DateTime<Utc>) -> StaleEntriesReport {
+  let cutoff = now.checked_sub_months(Months::new(months)).unwrap_or(DateTime::<Utc>::MIN_UTC);
+
+  let mut stale: Vec<StaleEntry> = entries
+    .iter()
+    .filter(|entry| !entry.hidden)
+    .filter_map(|entry| {
+      let reference = entry.last_used_at.unwrap_or(entry.created_at);
+      if reference > cutoff {
+        return None;
+      }
+      Some(StaleEntry {
+        id: entry.id.clone(),
+        title: entry.title.clone(),
+        username: entry.username.clone(),
+        url: entry.url.clone(),
+        created_at: entry.created_at,
+        last_used_at: entry.last_used_at,
+        idle_days: now.signed_duration_since(reference).num_days(),
+      })
+    })
+    .collect();
+  stale.sort_by_key(|e| Reverse(e.idle_days));
+
+  StaleEntriesReport {
+    months,
+    entries: stale,
+    total_sites: count_sites(entries),
+  }
+}
+
+/// Bundled list of domains known to support TOTP (see the file header).
+const TOTP_SITES: &str = include_str!("data/totp_sites.txt");
+
+/// An entry for a site that supports TOTP but has no secret configured.
+#[derive(Clone, Debug, Serialize)]
+pub struct TwoFactorSuggestion {
+  pub id: String,
+  pub title: String,
+  pub url: String,
+  /// Domain from the bundled dataset that the entry matched.
+  pub domain: String,
+}
+
+fn totp_domains() -> impl Iterator<Item = &'static str> {
+  TOTP_SITES
+    .lines()
+    .map(str::trim)
+    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+}
+
+/// Flags entries for TOTP-capable sites that lack a TOTP secret. Hidden
+/// entries are left out.
+pub fn find_missing_2fa(entries: &[Entry]) -> Vec<TwoFactorSuggestion> {
+  entries
+    .iter()
+    .filter(|entry| !entry.hidden && !entry.has_totp())
+    .filter_map(|entry| {
+      let host = normalize_host(&entry.url)?;
+      let domain = totp_domains().find(|domain| host_matches(domain, &host))?;
+      Some(TwoFactorSuggestion {
+        id: entry.id.clone(),
+        title: entry.title.clone(),
+        url: entry.url.clone(),
+        domain: domain.to_string(),
+      })
+    })
+    .collect()
+}
+
+/// An API key that has expired or will expire soon.
+#[derive(Clone, Debug, Serialize)]
+pub struct ExpiringApiKey {
+  pub id: String,
+  pub title: String,
+  pub service: String,
+  pub environment: String,
+  pub expires_at: DateTime<Utc>,
+  /// Whole days until expiry; negative once expired.
+  pub days_left: i64,
+  pub expired: bool,
+}
+
+/// Lists API keys that expire within `within_days` days (or already have),
+/// soonest first. A window past the last representable date covers every key.
+/// Hidden entries are left out.
+pub fn find_expiring_api_keys(entries: &[Entry], within_days: u32, now: DateTime<Utc>) -> Vec<ExpiringApiKey> {
+  let horizon = chrono::Duration::try_days(i64::from(within_days))
+    .and_then(|window| now.checked_add_signed(window))
+    .unwrap_or(DateTime::<Utc>::MAX_UTC);
+  let mut expiring: Vec<ExpiringApiKey> = entries
+    .iter()
+    .filter(|entry| !entry.hidden)
+    .filter_map(|entry| {
+      let EntryKind::ApiKey(key) = &entry.kind else {
+        return None;
+      };
+      let expires_at = key.expires_at.filter(|at| *at <= horizon)?;
+      Some(ExpiringApiKey {
+        id: entry.id.clone(),
+        title: entry.title.clone(),
+        service: key.service.clone(),
+        environment: key.environment.clone(),
+        expires_at,
+        days_left: expires_at.signed_duration_since(now).num_days(),
+        expired: expires_at <= now,
+      })
+    })
+    .collect();
+  expiring.sort_by_key(|k| k.expires_at);
+  expiring
+}
+
+/// Bundled list of common leaked passwords (see the file header).
+const COMMON_PASSWORDS: &str = include_str!("data/common_passwords.txt");
+
+fn common_passwords() -> &'static HashSet<&'static str> {
+  static SET: OnceLock<HashSet<&'static str>> = OnceLock::new();
+  SET.get_or_init(|| {
+    COMMON_PASSWORDS
+      .lines()
+      .map(str::trim)
+      .filter(|line| !line.is_empty() && !line.starts_with('#'))
+      .collect()
+  })
+}
+
+/// How a password matched the common password list.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommonMatch {
+  /// The password is on the list, ignoring case.
+  Exact,
+  /// The password is a listed one with l33t substitutions, e.g. `p@ssw0rd1`.
+  Leet,
+}
+
+/// Undoes common l33t substitutions; `one` is what `1` stands for.
+fn unleet(text: &str, one: char) -> String {
+  text
+    .chars()
+    .map(|c| match c {
+      '0' => 'o',
+      '1' | '!' => one,
+      '3' => 'e',
+      '4' | '@' => 'a',
+      '5' | '$' => 's',
+      '7' | '+' => 't',
+      '8' => 'b',
+      '|' => 'l',
+      other => other,
+    })
+    .collect()
+}
+
+/// Checks `password` against the bundled common password list. Trailing
+/// digits are kept as typed, so `p@ssw0rd1` matches `password1`.
+pub fn common_password_match(password: &str) -> Option<CommonMatch> {
+  let lowered = password.to_lowercase();
+  let list = common_passwords();
+  if list.contains(lowered.as_str()) {
+    return Some(CommonMatch::Exact);
+  }
+  let (body, suffix) = lowered.split_at(lowered.trim_end_matches(|c: char| c.is_ascii_digit()).len());
+  if !body.chars().any(char::is_alphabetic) {
+    return None;
+  }
+  ['i', 'l']
+    .into_iter()
+    .map(|one| unleet(body, one) + suffix)
+    .any(|candidate| candidate != lowered && list.contains(candidate.as_str()))
+    .then_some(CommonMatch::Leet)
+}
+
+/// Password strength on the same 0-7 scale as the frontend's
+/// `calculateStrength`: length thresholds plus character classes. Common
+/// passwords score 0 however they look.
+pub fn password_score(password: &str) -> u8 {
+  if common_password_match(password).is_some() {
+    return 0;
+  }
+  let len = password.chars().count();
+  let checks = [
+    len >= 8,
+    len >= 12,
+    len >= 20,
+    password.chars().any(|c| c.is_ascii_lowercase()),
+    password.chars().any(|c| c.is_ascii_uppercase()),
+    password.chars().any(|c| c.is_ascii_digit()),
+    password.chars().any(|c| !c.is_ascii_alphanumeric()),
+  ];
+  checks.into_iter().filter(|&passed| passed).count() as u8
+}
+
+/// Result of [`estimate_strength`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct PasswordStrength {
+  /// See [`password_score`].
+  pub score: u8,
+  pub common: Option<CommonMatch>,
+}
+
+pub fn estimate_strength(password: &str) -> PasswordStrength {
+  PasswordStrength {
+    score: password_score(password),
+    common: common_password_match(password),
+  }
+}
+
+/// Weak and reused passwords across the vault. Entries without a password
+/// of their own (linked entries, most non-login kinds) are not counted.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct PasswordHealth {
+  pub weak_entry_ids: Vec<String>,
+  /// Entries sharing their password with at least one other entry.
+  pub reused_entry_ids: Vec<String>,
+}
+
+pub fn password_health(entries: &[Entry]) -> PasswordHealth {
+  let with_password: Vec<&Entry> = entries.iter().filter(|e| !e.password.is_empty()).collect();
+  let mut uses: HashMap<&str, usize> = HashMap::new();
+  for entry in &with_password {
+    *uses.entry(entry.password.as_str()).or_default() += 1;
+  }
+  PasswordHealth {
+    weak_entry_ids: with_password
+      .iter()
+      .filter(|e| password_score(&e.password) <= 2)
+      .map(|e| e.id.clone())
+      .collect(),
+    reused_entry_ids: with_password
+      .iter()
+      .filter(|e| uses[e.password.as_str()] > 1)
+      .map(|e| e.id.clone())
+      .collect(),
+  }
+}
+
+impl PasswordHealth {
+  pub fn snapshot(&self, entries: usize, now: DateTime<Utc>) -> SecuritySnapshot {
+    SecuritySnapshot {
+      taken_at: now,
+      entries,
+      weak: self.weak_entry_ids.len(),
+      reused: self.reused_entry_ids.len(),
+    }
+  }
+}
+
+/// Appends `snapshot` unless the last one is younger than
+/// [`SNAPSHOT_INTERVAL_HOURS`], dropping the oldest beyond
+/// [`MAX_SECURITY_SNAPSHOTS`]. Returns whether the history changed.
+pub fn record_snapshot(history: &mut Vec<SecuritySnapshot>, snapshot: SecuritySnapshot) -> bool {
+  let interval = chrono::Duration::hours(SNAPSHOT_INTERVAL_HOURS);
+  if history.last().is_some_and(|last| snapshot.taken_at - last.taken_at < interval) {
+    return false;
+  }
+  history.push(snapshot);
+  let excess = history.len().saturating_sub(MAX_SECURITY_SNAPSHOTS);
+  history.drain(..excess);
+  true
+}
+
+/// Counts distinct hosts across all entries, ignoring a leading `www.`.
+pub fn count_sites(entries: &[Entry]) -> usize {
+  entries
+    .iter()
+    .filter_map(|entry| normalize_host(&entry.url))
+    .map(|host| host.strip_prefix("www.").map(str::to_string).unwrap_or(host))
+    .collect::<HashSet<_>>()
+    .len()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use chrono::Duration;
+
+  fn entry(url: &str, created_days_ago: i64, used_days_ago: Option<i64>) -> Entry {
+    let now = Utc::now();
+    let mut e = Entry::new(
+      url.to_string(),
+      "user".to_string(),
+      "pw".to_string(),
+      url.to_string(),
+      String::new(),
+    );
+    e.created_at = now - Duration::days(created_days_ago);
+    e.last_used_at = used_days_ago.map(|d| now - Duration::days(d));
+    e
+  }
+
+  #[test]
+  fn reports_unused_and_never_used_entries() {
+    let entries = vec![
+      entry("https://old.example.com", 400, Some(300)),
+      entry("https://fresh.example.com", 400, Some(2)),
+      entry("https://never.example.com", 500, None),
+      entry("https://new.example.com", 3, None),
+    ];
+
+    let report = find_stale_entries(&entries, 6, Utc::now());
+    let urls: Vec<&str> = report.entries.iter().map(|e| e.url.as_str()).collect();
+    assert_eq!(urls, vec!["https://never.example.com", "https://old.example.com"]);
+    assert_eq!(report.total_sites, 4);
+  }
+
+  #[test]
+  fn suggests_2fa_only_for_known_sites_without_secret() {
+    let mut with_totp = entry("https://github.com", 1, None);
+    with_totp.totp_secret = Some("JBSWY3DPEHPK3PXP".to_string());
+    let entries = vec![
+      entry("https://accounts.google.com/signin", 1, None),
+      with_totp,
+      entry("https://intranet.example.com", 1, None),
+    ];
+
+    let suggestions = find_missing_2fa(&entries);
+    assert_eq!(suggestions.len(), 1);
+    assert_eq!(suggestions[0].domain, "google.com");
+  }
+
+  #[test]
+  fn lists_expired_and_soon_expiring_api_keys() {
+    use crate::models::ApiKeyFields;
+
+    let now = Utc::now();
+    let api_key = |days: i64| {
+      let mut e = entry("", 1, None);
+      e.kind = EntryKind::ApiKey(Box::new(ApiKeyFields {
+        service: format!("svc{days}"),
+        expires_at: Some(now + Duration::days(days)),
+        ..Default::default()
+      }));
+      e
+    };
+    let entries = vec![api_key(90), api_key(5), api_key(-3), entry("https://example.com", 1, None)];
+
+    let expiring = find_expiring_api_keys(&entries, 30, now);
+    let services: Vec<&str> = expiring.iter().map(|k| k.service.as_str()).collect();
+    assert_eq!(services, vec!["svc-3", "svc5"]);
+    assert!(expiring[0].expired);
+    assert!(!expiring[1].expired);
+
+    // A window beyond the representable dates doesn't overflow.
+    assert_eq!(find_expiring_api_keys(&entries, u32::MAX, now).len(), 3);
+  }
+
+  #[test]
+  fn reports_leave_out_hidden_entries() {
+    use crate::models::ApiKeyFields;
+
+    let now = Utc::now();
+    let mut stale = entry("https://old.example.com", 400, None);
+    let mut no_2fa = entry("https://github.com", 1, None);
+    let mut api_key = entry("", 1, None);
+    api_key.kind = EntryKind::ApiKey(Box::new(ApiKeyFields {
+      expires_at: Some(now + Duration::days(1)),
+      ..Default::default()
+    }));
+    let mut entries = vec![stale.clone(), no_2fa.clone(), api_key.clone()];
+    assert_eq!(find_stale_entries(&entries, 6, now).entries.len(), 1);
+    assert_eq!(find_missing_2fa(&entries).len(), 1);
+    assert_eq!(find_expiring_api_keys(&entries, 30, now).len(), 1);
+
+    for e in [&mut stale, &mut no_2fa, &mut api_key] {
+      e.hidden = true;
+    }
+    entries = vec![stale, no_2fa, api_key];
+    assert!(find_stale_entries(&entries, 6, now).entries.is_empty());
+    assert!(find_missing_2fa(&entries).is_empty());
+    assert!(find_expiring_api_keys(&entries, 30, now).is_empty());
+  }
+
+  #[test]
+  fn counts_weak_and_reused_passwords_and_spaces_snapshots() {
+    let with_password = |password: &str| {
+      let mut e = entry("https://example.com", 1, None);
+      e.password = password.to_string();
+      e
+    };
+    let entries = vec![
+      with_password("hunter2"),
+      with_password("Correct-Horse-9-Battery"),
+      with_password("Correct-Horse-9-Battery"),
+      with_password(""),
+    ];
+    let health = password_health(&entries);
+    assert_eq!(health.weak_entry_ids, [entries[0].id.clone()]);
+    assert_eq!(health.reused_entry_ids.len(), 2);
+
+    let now = Utc::now();
+    let mut history = Vec::new();
+    assert!(record_snapshot(&mut history, health.snapshot(4, now - Duration::days(2))));
+    assert!(!record_snapshot(&mut history, health.snapshot(4, now - Duration::days(2) + Duration::hours(1))));
+    assert!(record_snapshot(&mut history, health.snapshot(4, now)));
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[1].weak, 1);
+  }
+
+  #[test]
+  fn flags_common_passwords_and_leet_variants() {
+    assert_eq!(common_password_match("Password"), Some(CommonMatch::Exact));
+    assert_eq!(common_password_match("p@ssw0rd1"), Some(CommonMatch::Leet));
+    assert_eq!(common_password_match("L3TM31N"), Some(CommonMatch::Leet));
+    assert_eq!(common_password_match("m0nk3y1"), Some(CommonMatch::Leet));
+    assert_eq!(common_password_match("1111"), Some(CommonMatch::Exact));
+    assert_eq!(common_password_match("31337"), None);
+    assert_eq!(common_password_match("Correct-Horse-9-Battery"), None);
+
+    // Long enough and varied enough to score well, were it not common.
+    let strength = estimate_strength("P@ssw0rd123");
+    assert_eq!((strength.score, strength.common), (0, Some(CommonMatch::Leet)));
+  }
+
+  #[test]
+  fn counts_sites_once_per_host() {
+    let entries = vec![
+      entry("https://www.example.com/login", 1, None),
+      entry("example.com", 1, None),
+      entry("", 1, None),
+    ];
+    assert_eq!(count_sites(&entries), 1);
+  }
+}
@@ -0,0 +1,81 @@
+//! Copy sequences: username, then password, then TOTP code, one clipboard
+//! value at a time, for logins in apps the browser extension cannot reach.
+//!
+//! The sequence only remembers the entry id and the fields still to copy;
+//! each value is read from the vault when its turn comes, so locking the
+//! vault stops a sequence. Steps advance on an explicit `next_in_sequence`
+//! call or after a fixed delay. Paste events cannot be observed portably, so
+//! there is no paste-triggered advance.
+
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Accepted range for the automatic advance delay.
+pub const SEQUENCE_DELAY_RANGE_SECS: (u64, u64) = (1, 120);
+
+static NEXT_TOKEN: AtomicU64 = AtomicU64::new(1);
+
+/// An in-progress copy sequence.
+#[derive(Debug)]
+pub struct CopySequence {
+  pub entry_id: String,
+  /// Distinguishes this sequence from later ones, so a delayed advance for
+  /// a sequence that was replaced or cancelled does nothing.
+  pub token: u64,
+  remaining: VecDeque<&'static str>,
+}
+
+/// What a sequence step copied and what is left.
+#[derive(Clone, Debug, Serialize)]
+pub struct SequenceStep {
+  pub entry_id: String,
+  pub copied: &'static str,
+  pub remaining: Vec<&'static str>,
+}
+
+impl CopySequence {
+  /// Username, password, and (if the entry has one) the TOTP code.
+  pub fn new(entry_id: &str, has_totp: bool) -> Self {
+    let mut remaining = VecDeque::from(["username", "password"]);
+    if has_totp {
+      remaining.push_back("totp");
+    }
+    Self {
+      entry_id: entry_id.to_string(),
+      token: NEXT_TOKEN.fetch_add(1, Ordering::Relaxed),
+      remaining,
+    }
+  }
+
+  /// Takes the next field to copy and describes the step.
+  pub fn advance(&mut self) -> Option<SequenceStep> {
+    let copied = self.remaining.pop_front()?;
+    Some(SequenceStep {
+      entry_id: self.entry_id.clone(),
+      copied,
+      remaining: self.remaining.iter().copied().collect(),
+    })
+  }
+
+  pub fn is_finished(&self) -> bool {
+    self.remaining.is_empty()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn steps_through_username_password_and_totp() {
+    let mut sequence = CopySequence::new("e1", true);
+    let fields: Vec<&str> = std::iter::from_fn(|| sequence.advance()).map(|step| step.copied).collect();
+    assert_eq!(fields, ["username", "password", "totp"]);
+    assert!(sequence.is_finished());
+
+    let mut without_totp = CopySequence::new("e1", false);
+    assert_eq!(without_totp.advance().unwrap().remaining, ["password"]);
+    assert_ne!(without_totp.token, sequence.token);
+  }
+}
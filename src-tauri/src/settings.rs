@@ -0,0 +1,377 @@
+//! User and administrative settings.
+//!
+//! Settings are stored as `settings.json` in the app data directory, next to
+//! the extension config. They hold policy only, never secrets, so they are
+//! readable while the vault is locked. Missing fields take their defaults,
+//! and every update is validated before it is applied or written.
+
+use crate::activity::AutoLockPolicy;
+use crate::backup::BackupSchedule;
+use crate::generator_history::GeneratorHistoryPolicy;
+use crate::hooks::{self, Hook};
+use crate::notifications::NotificationPolicy;
+use crate::password_policy::{self, PasswordPolicy};
+use crate::reveal::RevealPolicy;
+use chrono::{DateTime, Months, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+
+/// Filename for the settings file (stored in the app data directory).
+pub const SETTINGS_FILENAME: &str = "settings.json";
+
+/// Failed unlock attempts allowed before a lockout, by default.
+pub const DEFAULT_MAX_FAILED_ATTEMPTS: u32 = 5;
+
+/// Lockout after too many failed attempts, by default (30 seconds).
+pub const DEFAULT_LOCKOUT_SECS: u64 = 30;
+
+/// Accepted range for [`LockoutPolicy::max_attempts`].
+pub const MAX_ATTEMPTS_RANGE: (u32, u32) = (1, 20);
+
+/// Accepted range for [`LockoutPolicy::lockout_secs`] (up to one hour).
+pub const LOCKOUT_SECS_RANGE: (u64, u64) = (1, 3600);
+
+/// Upper bound for a lockout grown by exponential backoff (one day).
+pub const MAX_BACKOFF_SECS: u64 = 24 * 60 * 60;
+
+/// Accepted range for [`Settings::password_reminder_months`].
+pub const REMINDER_MONTHS_RANGE: (u32, u32) = (1, 36);
+
+/// Tombstones are kept this long by default; see [`RetentionPolicy`].
+pub const DEFAULT_TOMBSTONE_DAYS: u32 = 180;
+
+/// Accepted range for [`RetentionPolicy::tombstone_days`].
+pub const TOMBSTONE_DAYS_RANGE: (u32, u32) = (30, 3650);
+
+/// Field-level changes kept per entry by default; see [`crate::history`].
+pub const DEFAULT_HISTORY_VERSIONS: u32 = 50;
+
+/// Accepted range for [`RetentionPolicy::max_history_versions`].
+pub const HISTORY_VERSIONS_RANGE: (u32, u32) = (0, 1000);
+
+/// Deleted entries stay in the trash this long by default.
+pub const DEFAULT_TRASH_DAYS: u32 = 30;
+
+/// Accepted range for [`RetentionPolicy::trash_days`].
+pub const TRASH_DAYS_RANGE: (u32, u32) = (1, 365);
+
+/// Default size limit of the audit log (1 MiB).
+pub const DEFAULT_AUDIT_LOG_KIB: u32 = 1024;
+
+/// Accepted range for [`RetentionPolicy::max_audit_log_kib`] (64 KiB to 100 MiB).
+pub const AUDIT_LOG_KIB_RANGE: (u32, u32) = (64, 100 * 1024);
+
+/// Accepted range for [`Settings::copy_lock_after`].
+pub const COPY_LOCK_RANGE: (u32, u32) = (1, 100);
+
+/// Most sites that can be excluded from autofill.
+pub const MAX_AUTOFILL_EXCLUSIONS: usize = 500;
+
+/// How failed unlock attempts are rate limited.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LockoutPolicy {
+  /// Failed attempts before a lockout.
+  pub max_attempts: u32,
+  /// Length of the first lockout.
+  pub lockout_secs: u64,
+  /// Double the lockout after each consecutive lockout, up to
+  /// [`MAX_BACKOFF_SECS`]. Reset by a successful unlock.
+  pub exponential_backoff: bool,
+  /// Record lockouts in the audit log (failed attempts always are).
+  pub audit: bool,
+}
+
+impl Default for LockoutPolicy {
+  fn default() -> Self {
+    Self {
+      max_attempts: DEFAULT_MAX_FAILED_ATTEMPTS,
+      lockout_secs: DEFAULT_LOCKOUT_SECS,
+      exponential_backoff: false,
+      audit: false,
+    }
+  }
+}
+
+impl LockoutPolicy {
+  /// Administrative preset: 3 attempts, exponential backoff, audited.
+  pub fn strict() -> Self {
+    Self {
+      max_attempts: 3,
+      lockout_secs: DEFAULT_LOCKOUT_SECS,
+      exponential_backoff: true,
+      audit: true,
+    }
+  }
+
+  /// Returns the named preset (`"standard"` or `"strict"`).
+  pub fn preset(name: &str) -> Option<Self> {
+    match name {
+      "standard" => Some(Self::default()),
+      "strict" => Some(Self::strict()),
+      _ => None,
+    }
+  }
+
+  pub fn validate(&self) -> Result<(), String> {
+    let (min, max) = MAX_ATTEMPTS_RANGE;
+    if !(min..=max).contains(&self.max_attempts) {
+      return Err(format!("max_attempts must be between {min} and {max}"));
+    }
+    let (min, max) = LOCKOUT_SECS_RANGE;
+    if !(min..=max).contains(&self.lockout_secs) {
+      return Err(format!("lockout_secs must be between {min} and {max}"));
+    }
+    Ok(())
+  }
+
+  /// Length of the lockout following `previous` consecutive lockouts.
+  pub fn lockout_for(&self, previous: u32) -> u64 {
+    if !self.exponential_backoff {
+      return self.lockout_secs;
+    }
+    let factor = 1u64.checked_shl(previous.min(63)).unwrap_or(u64::MAX);
+    self.lockout_secs.saturating_mul(factor).min(MAX_BACKOFF_SECS)
+  }
+}
+
+/// How long records of past vault contents are kept.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionPolicy {
+  /// Days a deleted entry's tombstone is kept, so merges with copies that
+  /// still have the entry do not bring it back. Should cover the longest
+  /// time a device may go without syncing.
+  pub tombstone_days: u32,
+  /// Field-level changes kept per entry; older ones are dropped first.
+  /// 0 keeps no history.
+  pub max_history_versions: u32,
+  /// Days a deleted entry stays in the trash before it is purged.
+  pub trash_days: u32,
+  /// Size limit of the audit log in KiB. The oldest events are dropped
+  /// once it is exceeded.
+  pub max_audit_log_kib: u32,
+}
+
+impl Default for RetentionPolicy {
+  fn default() -> Self {
+    Self {
+      tombstone_days: DEFAULT_TOMBSTONE_DAYS,
+      max_history_versions: DEFAULT_HISTORY_VERSIONS,
+      trash_days: DEFAULT_TRASH_DAYS,
+      max_audit_log_kib: DEFAULT_AUDIT_LOG_KIB,
+    }
+  }
+}
+
+impl RetentionPolicy {
+  pub fn validate(&self) -> Result<(), String> {
+    let (min, max) = TOMBSTONE_DAYS_RANGE;
+    if !(min..=max).contains(&self.tombstone_days) {
+      return Err(format!("tombstone_days must be between {min} and {max}"));
+    }
+    let (min, max) = HISTORY_VERSIONS_RANGE;
+    if !(min..=max).contains(&self.max_history_versions) {
+      return Err(format!("max_history_versions must be between {min} and {max}"));
+    }
+    let (min, max) = TRASH_DAYS_RANGE;
+    if !(min..=max).contains(&self.trash_days) {
+      return Err(format!("trash_days must be between {min} and {max}"));
+    }
+    let (min, max) = AUDIT_LOG_KIB_RANGE;
+    if !(min..=max).contains(&self.max_audit_log_kib) {
+      return Err(format!("max_audit_log_kib must be between {min} and {max}"));
+    }
+    Ok(())
+  }
+
+  /// Tombstones recorded before this time can be dropped.
+  pub fn tombstone_cutoff(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+    now - chrono::Duration::days(i64::from(self.tombstone_days))
+  }
+
+  /// Entries trashed before this time can be purged.
+  pub fn trash_cutoff(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+    now - chrono::Duration::days(i64::from(self.trash_days))
+  }
+
+  /// Field-level changes kept per entry.
+  pub fn history_limit(&self) -> usize {
+    self.max_history_versions as usize
+  }
+
+  /// Size limit of the audit log in bytes.
+  pub fn audit_log_limit(&self) -> u64 {
+    u64::from(self.max_audit_log_kib) * 1024
+  }
+}
+
+/// When vault changes are written.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SaveMode {
+  /// After every change.
+  #[default]
+  Auto,
+  /// On `save_vault`, lock, and exit; changes are queued until then.
+  Explicit,
+}
+
+/// All persisted settings.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+  pub lockout: LockoutPolicy,
+  pub auto_lock: AutoLockPolicy,
+  pub reveal: RevealPolicy,
+  /// Remind the user to change the master password this many months after
+  /// the last change. `None` disables the reminder.
+  pub password_reminder_months: Option<u32>,
+  /// Offline Pwned Passwords file for breach checks (see [`crate::breach`]).
+  pub breach_dataset: Option<PathBuf>,
+  /// The user agreed to a weekly background security audit (see
+  /// [`crate::scheduled_audit`]). Off until they opt in.
+  pub scheduled_audit: bool,
+  pub notifications: NotificationPolicy,
+  pub save_mode: SaveMode,
+  pub backup: BackupSchedule,
+  pub retention: RetentionPolicy,
+  /// Hosts (lowercase, without `www.`) for which the extension bridge
+  /// returns no matches or secrets; subdomains are excluded too.
+  pub autofill_exclusions: Vec<String>,
+  /// Programs and local webhooks run on lock, unlock, and save (see
+  /// [`crate::hooks`]). Empty unless the user adds some.
+  pub hooks: Vec<Hook>,
+  /// Named password policies (see [`crate::password_policy`]).
+  pub password_policies: Vec<PasswordPolicy>,
+  /// Name of the policy new and changed passwords must meet, if any.
+  pub active_password_policy: Option<String>,
+  pub generator_history: GeneratorHistoryPolicy,
+  /// Lock the vault after this many secret copies without other
+  /// interaction, e.g. on a shared machine. `None` disables it.
+  pub copy_lock_after: Option<u32>,
+  /// Mask entry titles, usernames, and notes in entry lists until a row is
+  /// expanded, for users who share their screen with the vault open.
+  pub privacy_mode: bool,
+}
+
+impl Settings {
+  pub fn validate(&self) -> Result<(), String> {
+    self.lockout.validate()?;
+    self.reveal.validate()?;
+    self.backup.validate()?;
+    self.retention.validate()?;
+    self.generator_history.validate()?;
+    hooks::validate(&self.hooks)?;
+    password_policy::validate_all(&self.password_policies, self.active_password_policy.as_deref())?;
+    if self.autofill_exclusions.len() > MAX_AUTOFILL_EXCLUSIONS {
+      return Err(format!("at most {MAX_AUTOFILL_EXCLUSIONS} sites can be excluded from autofill"));
+    }
+    if let Some(copies) = self.copy_lock_after {
+      let (min, max) = COPY_LOCK_RANGE;
+      if !(min..=max).contains(&copies) {
+        return Err(format!("copy_lock_after must be between {min} and {max}"));
+      }
+    }
+    if let Some(months) = self.password_reminder_months {
+      let (min, max) = REMINDER_MONTHS_RANGE;
+      if !(min..=max).contains(&months) {
+        return Err(format!("password_reminder_months must be between {min} and {max}"));
+      }
+    }
+    Ok(())
+  }
+
+  /// The password policy in force, if one is active.
+  pub fn active_password_policy(&self) -> Option<&PasswordPolicy> {
+    let name = self.active_password_policy.as_deref()?;
+    self.password_policies.iter().find(|p| p.name == name)
+  }
+
+  /// When a password set at `changed_at` is due for a change, if the
+  /// reminder is enabled.
+  pub fn password_change_due(&self, changed_at: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let months = self.password_reminder_months?;
+    changed_at.checked_add_months(Months::new(months))
+  }
+}
+
+fn settings_path(app: &AppHandle) -> Result<PathBuf, String> {
+  let dir = app
+    .path()
+    .app_data_dir()
+    .map_err(|e| format!("app_data_dir failed: {e}"))?;
+  fs::create_dir_all(&dir).map_err(|e| format!("create_dir_all failed: {e}"))?;
+  Ok(dir.join(SETTINGS_FILENAME))
+}
+
+/// Loads settings, falling back to defaults if none have been saved.
+///
+/// A file that fails validation (e.g. edited by hand) is rejected rather
+/// than partially applied.
+pub fn load(app: &AppHandle) -> Result<Settings, String> {
+  let path = settings_path(app)?;
+  if !path.exists() {
+    return Ok(Settings::default());
+  }
+  let raw = fs::read_to_string(&path).map_err(|e| format!("read settings failed: {e}"))?;
+  let settings: Settings = serde_json::from_str(&raw).map_err(|e| format!("parse settings failed: {e}"))?;
+  settings.validate()?;
+  Ok(settings)
+}
+
+pub fn save(app: &AppHandle, settings: &Settings) -> Result<(), String> {
+  settings.validate()?;
+  let path = settings_path(app)?;
+  let serialized = serde_json::to_string_pretty(settings).map_err(|e| format!("serialize settings failed: {e}"))?;
+  fs::write(&path, serialized).map_err(|e| format!("write settings failed: {e}"))?;
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn strict_preset_backs_off_and_ranges_are_enforced() {
+    let strict = LockoutPolicy::strict();
+    assert!(strict.validate().is_ok());
+    assert_eq!(
+      (0..4).map(|n| strict.lockout_for(n)).collect::<Vec<_>>(),
+      vec![30, 60, 120, 240]
+    );
+    assert_eq!(strict.lockout_for(40), MAX_BACKOFF_SECS);
+    assert_eq!(LockoutPolicy::default().lockout_for(3), DEFAULT_LOCKOUT_SECS);
+
+    let zero = LockoutPolicy {
+      max_attempts: 0,
+      ..LockoutPolicy::default()
+    };
+    assert!(zero.validate().is_err());
+    let settings: Settings = serde_json::from_str(r#"{"lockout":{"max_attempts":3}}"#).unwrap();
+    assert_eq!(settings.lockout.lockout_secs, DEFAULT_LOCKOUT_SECS);
+  }
+
+  #[test]
+  fn password_reminder_falls_due_after_whole_months() {
+    let changed_at: DateTime<Utc> = "2024-01-31T12:00:00Z".parse().unwrap();
+    assert_eq!(Settings::default().password_change_due(changed_at), None);
+
+    let settings = Settings {
+      password_reminder_months: Some(1),
+      ..Settings::default()
+    };
+    // Clamped to the end of the shorter month.
+    assert_eq!(
+      settings.password_change_due(changed_at),
+      Some("2024-02-29T12:00:00Z".parse().unwrap())
+    );
+    let never = Settings {
+      password_reminder_months: Some(0),
+      ..Settings::default()
+    };
+    assert!(never.validate().is_err());
+  }
+}
@@ -0,0 +1,377 @@
+//! Vault files shared between people, e.g. a household's common logins.
+//!
+//! A shared vault is an ordinary vault file at a path everyone involved can
+//! reach (a synced or network folder), locked with a password they share.
+//! Each person links it to a folder of their own vault. Syncing merges the
+//! file into that folder and writes the result back, so anyone can add,
+//! edit, and delete shared entries. The file is laid out for merging:
+//!
+//! - entries keep their own `updated_at`, and [`merge`] picks the newer
+//!   version of each, keeping a conflict copy when both sides edited the
+//!   same entry since the last sync;
+//! - deletions are kept as [`Tombstone`]s, so an entry deleted on one side
+//!   is removed on the other instead of coming back.
+//!
+//! Entries deleted locally are found in the trash, which keeps their
+//! folder, or by their tombstone; entries moved out of the folder count as
+//! deleted from the share. Entries deleted by someone else go to the local
+//! trash. Usage times and links stay local: the file holds copies of linked
+//! passwords and no `last_used_at`. As with folder exports, the file key is
+//! derived once and kept in the vault, so syncing never asks for the shared
+//! password.
+
+use crate::backup::write_atomically;
+use crate::commands::persist_changes;
+use crate::folder_export::{export_key, sealing};
+use crate::history;
+use crate::limits::Limits;
+use crate::merge::{self, MergeReport, MergeSides};
+use crate::models::{AppState, Entry, ExportKey, SharedVault, Tombstone, VaultData, VaultMeta};
+use crate::persist::Changes;
+use crate::vault::{self, VaultError, WriterStamp};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use tauri::AppHandle;
+use zeroize::Zeroizing;
+
+/// Minutes between automatic syncs while the vault is unlocked.
+pub const SYNC_EVERY_MINUTES: i64 = 15;
+
+/// A linked shared vault as shown to the frontend, without its key.
+#[derive(Clone, Debug, Serialize)]
+pub struct SharedVaultStatus {
+  pub id: String,
+  pub folder: String,
+  pub path: String,
+  pub last_synced: Option<DateTime<Utc>>,
+  pub last_attempt: Option<DateTime<Utc>>,
+  pub last_error: Option<String>,
+}
+
+impl SharedVaultStatus {
+  pub fn new(link: &SharedVault, data: &VaultData) -> Self {
+    Self {
+      id: link.id.clone(),
+      folder: link.folder.clone(),
+      path: link.path.clone(),
+      last_synced: data.meta.last_merged.get(&link.id).copied(),
+      last_attempt: link.last_attempt,
+      last_error: link.last_error.clone(),
+    }
+  }
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct SharedSyncReport {
+  #[serde(flatten)]
+  pub merged: MergeReport,
+  /// Whether the shared file was written.
+  pub written: bool,
+}
+
+/// Opens the shared file at `path` with its password, returning its
+/// contents and the key to keep.
+pub fn open_with_password(path: &Path, password: &str) -> Result<(VaultData, ExportKey), String> {
+  let bytes = read(path)?;
+  let (data, header, key) = vault::open_with_limits(&bytes, password, &Limits::default()).map_err(|e| match e {
+    VaultError::Limit(limit) => format!("{} rejected: {limit}", path.display()),
+    _ => format!("could not open {}: wrong password or damaged file", path.display()),
+  })?;
+  let key = Zeroizing::new(key);
+  Ok((data, export_key(&header, &key)))
+}
+
+fn read(path: &Path) -> Result<Zeroizing<Vec<u8>>, String> {
+  let size = fs::metadata(path).map_err(|e| format!("read {}: {e}", path.display()))?.len();
+  Limits::default()
+    .check_file_size(size)
+    .map_err(|limit| format!("{} rejected: {limit}", path.display()))?;
+  Ok(Zeroizing::new(fs::read(path).map_err(|e| format!("read {}: {e}", path.display()))?))
+}
+
+/// Opens the shared file with the stored key. Returns the label of whoever
+/// wrote it last, if known.
+fn open(link: &SharedVault) -> Result<(VaultData, Option<String>), String> {
+  let path = Path::new(&link.path);
+  let bytes = read(path)?;
+  let (_, key) = sealing(&link.key)?;
+  let (data, header) = vault::open_with_key(&bytes, &key, &Limits::default()).map_err(|e| match e {
+    VaultError::Limit(limit) => format!("{} rejected: {limit}", path.display()),
+    _ => format!(
+      "could not open {}: the shared password may have changed; join the shared vault again",
+      path.display()
+    ),
+  })?;
+  if data.meta.id != link.id {
+    return Err(format!("{} now holds a different vault", path.display()));
+  }
+  Ok((data, header.written_by.map(|w| w.label)))
+}
+
+/// Writes `file` to the link's path, sealed with the shared key.
+pub fn write(link: &SharedVault, file: &VaultData, written_by: Option<WriterStamp>) -> Result<(), String> {
+  let (mut header, key) = sealing(&link.key)?;
+  header.written_by = written_by;
+  let bytes = vault::seal(file, &header, &key).map_err(|e| format!("seal: {:?}", e))?;
+  write_atomically(Path::new(&link.path), &bytes).map_err(|e| format!("write {} failed: {e}", link.path))
+}
+
+/// A new shared file holding the entries of `folder`.
+pub fn new_file(data: &VaultData, folder: &str) -> VaultData {
+  VaultData {
+    meta: VaultMeta::new(),
+    entries: data
+      .entries
+      .iter()
+      .filter(|e| e.folder.as_deref() == Some(folder))
+      .map(|e| for_file(data, e))
+      .collect(),
+    ..VaultData::default()
+  }
+}
+
+/// `entry` as stored in the shared file: linked password copied in, no
+/// usage time.
+fn for_file(data: &VaultData, entry: &Entry) -> Entry {
+  let mut entry = entry.clone();
+  if let Some(target) = entry.linked_to.take() {
+    entry.password = data.find_entry(&target).map(|t| t.password.clone()).unwrap_or_default();
+  }
+  entry.last_used_at = None;
+  entry
+}
+
+/// The file's contents, ignoring revisions (which each side counts on its
+/// own) and order.
+fn fingerprint(file: &VaultData) -> String {
+  let mut entries: Vec<(&str, DateTime<Utc>, serde_json::Value)> = file
+    .entries
+    .iter()
+    .map(|e| (e.id.as_str(), e.updated_at, merge::content(e)))
+    .collect();
+  entries.sort_by(|a, b| a.0.cmp(b.0));
+  let mut tombstones: Vec<&Tombstone> = file.tombstones.iter().collect();
+  tombstones.sort_by(|a, b| a.id.cmp(&b.id));
+  serde_json::json!([entries, tombstones]).to_string()
+}
+
+/// Merges the shared `file` into the linked folder of `data`. Returns the
+/// report and the new contents of the file, or `None` if it is unchanged.
+pub fn merge_into(
+  data: &mut VaultData,
+  link: &SharedVault,
+  file: VaultData,
+  sides: &MergeSides<'_>,
+  now: DateTime<Utc>,
+) -> (SharedSyncReport, Option<VaultData>) {
+  let before = fingerprint(&file);
+  let in_folder = |e: &Entry| e.folder.as_deref() == Some(link.folder.as_str());
+
+  // The folder as a vault of its own, with the deletions made here.
+  let mut view = VaultData {
+    entries: data.entries.iter().filter(|e| in_folder(e)).map(|e| for_file(data, e)).collect(),
+    ..VaultData::default()
+  };
+  for trashed in data.trash.iter().filter(|t| in_folder(&t.entry)) {
+    view.record_deletion(&trashed.entry.id, trashed.deleted_at);
+  }
+  for shared in &file.entries {
+    if let Some(at) = data.tombstones.iter().find(|t| t.id == shared.id).map(|t| t.deleted_at) {
+      view.record_deletion(&shared.id, at);
+    }
+    if let Some(moved) = data.find_entry(&shared.id).filter(|e| !in_folder(e)) {
+      view.record_deletion(&shared.id, moved.updated_at);
+    }
+  }
+  if let Some(&since) = data.meta.last_merged.get(&link.id) {
+    view.meta.last_merged.insert(link.id.clone(), since);
+  }
+  let meta = file.meta.clone();
+  let merged = merge::merge(&mut view, file, sides, now);
+  data.meta.last_merged.insert(link.id.clone(), now);
+
+  // Entries deleted by someone else.
+  for trashed in &view.trash {
+    if data.entries_linking_to(&trashed.entry.id).is_empty() {
+      data.trash_entry(&trashed.entry.id, trashed.deleted_at);
+    }
+  }
+  // The merged versions, keeping local links and usage times where the
+  // content did not change.
+  for merged in &view.entries {
+    match data.entries.iter().position(|e| e.id == merged.id) {
+      Some(index) => {
+        let local = &data.entries[index];
+        if merge::content(&for_file(data, local)) != merge::content(merged) {
+          let (last_used_at, revision) = (local.last_used_at, local.revision.max(merged.revision) + 1);
+          data.entries[index] = merged.clone();
+          data.entries[index].last_used_at = last_used_at;
+          data.entries[index].revision = revision;
+        }
+      }
+      None => {
+        data.trash.retain(|t| t.entry.id != merged.id);
+        data.tombstones.retain(|t| t.id != merged.id);
+        data.entries.push(merged.clone());
+      }
+    }
+  }
+
+  let file = VaultData {
+    meta,
+    entries: view.entries,
+    tombstones: view.tombstones,
+    ..VaultData::default()
+  };
+  let written = fingerprint(&file) != before;
+  (SharedSyncReport { merged, written }, written.then_some(file))
+}
+
+/// Syncs the shared vault `id` with its file, recording the attempt in the
+/// link.
+pub fn sync(state: &AppState, data: &mut VaultData, id: &str, now: DateTime<Utc>) -> Result<SharedSyncReport, String> {
+  let index = data
+    .meta
+    .shared_vaults
+    .iter()
+    .position(|l| l.id == id)
+    .ok_or_else(|| "shared vault not found".to_string())?;
+  let link = data.meta.shared_vaults[index].clone();
+  let result = open(&link).and_then(|(file, writer)| {
+    let stamp = state.writer_stamp();
+    let local_label = stamp.as_ref().map(|w| w.label.clone()).unwrap_or_else(|| "this device".to_string());
+    let remote_label = writer.unwrap_or_else(|| "the shared vault".to_string());
+    let sides = MergeSides {
+      peer_id: &link.id,
+      local_label: &local_label,
+      remote_label: &remote_label,
+    };
+    let (report, file) = merge_into(data, &link, file, &sides, now);
+    let retention = state.settings.lock().map(|s| s.retention).unwrap_or_default();
+    history::trim_all(&mut data.entries, retention.history_limit());
+    if let Some(mut file) = file {
+      file.purge_tombstones(retention.tombstone_cutoff(now));
+      write(&link, &file, stamp)?;
+    }
+    Ok(report)
+  });
+  let record = &mut data.meta.shared_vaults[index];
+  record.last_attempt = Some(now);
+  record.last_error = result.as_ref().err().cloned();
+  result
+}
+
+/// Syncs every shared vault not synced in the last
+/// [`SYNC_EVERY_MINUTES`]. Called by the inactivity monitor while the vault
+/// is unlocked.
+pub fn maybe_run(state: &AppState, app: &AppHandle) {
+  let Some(store) = state.current_store() else {
+    return;
+  };
+  // Lock order: session, then vault.
+  let Ok(session_guard) = state.session.lock() else {
+    return;
+  };
+  let Ok(mut vault_guard) = state.vault.lock() else {
+    return;
+  };
+  let (Some(session), Some(data)) = (session_guard.as_ref(), vault_guard.as_mut()) else {
+    return;
+  };
+  let now = Utc::now();
+  let due: Vec<String> = data
+    .meta
+    .shared_vaults
+    .iter()
+    .filter(|l| l.last_attempt.is_none_or(|at| now - at >= Duration::minutes(SYNC_EVERY_MINUTES)))
+    .map(|l| l.id.clone())
+    .collect();
+  if due.is_empty() {
+    return;
+  }
+  for id in due {
+    if let Err(err) = sync(state, data, &id, now) {
+      eprintln!("shared vault: {err}");
+    }
+  }
+  persist_changes(Some(app), state, store.as_ref(), data, session, Changes::All);
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::vault::{CipherSuite, VaultHeader};
+
+  fn sides(label: &str) -> MergeSides<'_> {
+    MergeSides {
+      peer_id: "shared",
+      local_label: label,
+      remote_label: "other",
+    }
+  }
+
+  fn home(data: &VaultData) -> Vec<(String, String)> {
+    let mut entries: Vec<(String, String)> = data
+      .entries
+      .iter()
+      .filter(|e| e.folder.as_deref() == Some("Home"))
+      .map(|e| (e.title.clone(), e.password.clone()))
+      .collect();
+    entries.sort();
+    entries
+  }
+
+  #[test]
+  fn edits_and_deletions_reach_the_other_side() {
+    let header = VaultHeader::new(CipherSuite::default());
+    let t0 = Utc::now();
+    let mut alice = VaultData::default();
+    for title in ["Wifi", "Netflix"] {
+      let mut entry = Entry::new(title.into(), "home".into(), "old".into(), String::new(), String::new());
+      entry.folder = Some("Home".into());
+      entry.updated_at = t0;
+      alice.entries.push(entry);
+    }
+    let mut file = new_file(&alice, "Home");
+    file.meta.id = "shared".into();
+    let link = SharedVault {
+      id: "shared".into(),
+      folder: "Home".into(),
+      path: String::new(),
+      key: export_key(&header, &[0; 32]),
+      last_attempt: None,
+      last_error: None,
+    };
+
+    // Bob joins with an empty vault and gets both entries.
+    let mut bob = VaultData::default();
+    let (report, written) = merge_into(&mut bob, &link, file.clone(), &sides("BOB"), t0);
+    assert_eq!(report.merged.added, 2);
+    assert!(written.is_none());
+    assert_eq!(home(&bob), home(&alice));
+
+    // Bob changes the wifi password; Alice deletes Netflix.
+    let wifi = bob.entries.iter_mut().find(|e| e.title == "Wifi").unwrap();
+    wifi.password = "new".into();
+    wifi.updated_at = t0 + Duration::minutes(1);
+    let netflix = alice.entries.iter().find(|e| e.title == "Netflix").unwrap().id.clone();
+    alice.trash_entry(&netflix, t0 + Duration::minutes(1));
+    alice.trash.clear();
+    alice.meta.last_merged.insert("shared".into(), t0);
+
+    let (_, written) = merge_into(&mut alice, &link, file, &sides("ALICE"), t0 + Duration::minutes(2));
+    let file = written.expect("the deletion is written");
+    assert_eq!(file.tombstones.len(), 1);
+    let (report, written) = merge_into(&mut bob, &link, file, &sides("BOB"), t0 + Duration::minutes(3));
+    assert_eq!(report.merged.removed, 1);
+    assert!(report.merged.conflicts.is_empty());
+    assert_eq!(home(&bob), vec![("Wifi".to_string(), "new".to_string())]);
+    assert_eq!(bob.trash.len(), 1);
+
+    let (_, written) = merge_into(&mut alice, &link, written.unwrap(), &sides("ALICE"), t0 + Duration::minutes(4));
+    assert!(written.is_none());
+    assert_eq!(home(&alice), home(&bob));
+  }
+}
@@ -0,0 +1,48 @@
+//! Overwriting files before deleting them.
+//!
+//! Used for superseded vault copies (e.g. `vault.dat.bak` left by the SQLite
+//! migration). On SSDs and copy-on-write or journaling filesystems the old
+//! blocks may survive the overwrite, so this reduces rather than removes
+//! what a forensic read of the disk can recover; the copies are encrypted
+//! either way.
+
+use rand::RngCore;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/// Bytes overwritten per write call.
+const CHUNK: usize = 64 * 1024;
+
+/// Overwrites `path` with random bytes, syncs it, and removes it.
+pub fn shred_file(path: &Path) -> io::Result<()> {
+  let mut file = OpenOptions::new().write(true).open(path)?;
+  let len = file.metadata()?.len();
+  file.seek(SeekFrom::Start(0))?;
+
+  let mut buf = vec![0u8; CHUNK];
+  let mut remaining = len;
+  while remaining > 0 {
+    let n = remaining.min(CHUNK as u64) as usize;
+    rand::thread_rng().fill_bytes(&mut buf[..n]);
+    file.write_all(&buf[..n])?;
+    remaining -= n as u64;
+  }
+  file.sync_all()?;
+  drop(file);
+  fs::remove_file(path)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn removes_the_file() {
+    let path = std::env::temp_dir().join(format!("the-organizer-shred-{}.bak", std::process::id()));
+    fs::write(&path, vec![7u8; CHUNK + 10]).unwrap();
+    shred_file(&path).unwrap();
+    assert!(!path.exists());
+    assert!(shred_file(&path).is_err());
+  }
+}
@@ -0,0 +1,39 @@
+//! Orderly shutdown when the app quits.
+//!
+//! Run from Tauri's `Exit` event. Closing the main window exits the app even
+//! while the hidden quick-access window is still open, so this always runs
+//! on a normal quit. A crash or a killed process skips it; see
+//! [`crate::clipboard`] for what that leaves behind.
+
+use crate::clipboard;
+use crate::crash_recovery;
+use crate::extension;
+use crate::models::AppState;
+use crate::monitor;
+use crate::persist;
+use tauri::AppHandle;
+
+/// Stops background work, writes pending changes, locks the vault, clears
+/// a secret still waiting on the clipboard, and marks the run as cleanly
+/// shut down. Safe to call more than once.
+pub fn run(app: &AppHandle, state: &AppState) {
+  // Stopped first so neither locks or writes the vault concurrently.
+  monitor::stop(state);
+  extension::stop_extension_server(state);
+
+  // Writes changes held back in explicit save mode or queued for retry,
+  // then drops the session key and decrypted entries. Closing the main
+  // window already waited for a successful write, so a failure here is a
+  // quit from elsewhere and cannot be held up; the changes are journaled
+  // for the next unlock if possible.
+  if let Some(failure) = persist::lock(state, Some(app)) {
+    eprintln!("exit: unsaved changes not written: {}", failure.error);
+  }
+  if let Ok(mut sequence) = state.copy_sequence.lock() {
+    *sequence = None;
+  }
+
+  clipboard::clear_pending();
+  // Last, so a crash during any of the above is still detected next launch.
+  crash_recovery::finish(app);
+}
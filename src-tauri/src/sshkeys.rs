@@ -0,0 +1,72 @@
+//! SSH keypair generation for SshKey entries.
+//!
+//! Keys are generated in-process and encoded straight into the entry, so the
+//! private key only ever exists in memory and inside the encrypted vault.
+
+use crate::models::SshKeyFields;
+use rand::rngs::OsRng;
+use ssh_key::{Algorithm, HashAlg, LineEnding, PrivateKey};
+
+/// Generates a new keypair. `algorithm` is `ed25519` or `rsa` (4096-bit).
+pub fn generate(algorithm: &str, comment: &str) -> Result<SshKeyFields, String> {
+  let algorithm = match algorithm.trim().to_lowercase().as_str() {
+    "ed25519" => Algorithm::Ed25519,
+    "rsa" => Algorithm::Rsa { hash: None },
+    other => return Err(format!("unsupported ssh key algorithm: {other}")),
+  };
+
+  let mut key =
+    PrivateKey::random(&mut OsRng, algorithm).map_err(|e| format!("ssh key generation failed: {e}"))?;
+  key.set_comment(comment);
+  to_fields(&key)
+}
+
+/// Builds entry fields from an unencrypted OpenSSH private key.
+pub fn from_private_key(pem: &str) -> Result<SshKeyFields, String> {
+  let key = PrivateKey::from_openssh(pem.trim()).map_err(|e| format!("invalid ssh private key: {e}"))?;
+  if key.is_encrypted() {
+    return Err("passphrase-protected ssh keys are not supported; export it without a passphrase".to_string());
+  }
+  to_fields(&key)
+}
+
+fn to_fields(key: &PrivateKey) -> Result<SshKeyFields, String> {
+  let private_key = key
+    .to_openssh(LineEnding::LF)
+    .map_err(|e| format!("ssh key encoding failed: {e}"))?;
+  let public_key = key
+    .public_key()
+    .to_openssh()
+    .map_err(|e| format!("ssh key encoding failed: {e}"))?;
+
+  Ok(SshKeyFields {
+    algorithm: key.algorithm().as_str().to_string(),
+    private_key: private_key.to_string(),
+    public_key,
+    fingerprint: key.fingerprint(HashAlg::Sha256).to_string(),
+    comment: key.comment().to_string(),
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn generated_ed25519_key_round_trips() {
+    let fields = generate("ed25519", "me@laptop").expect("generate");
+    assert_eq!(fields.algorithm, "ssh-ed25519");
+    assert!(fields.public_key.starts_with("ssh-ed25519 "));
+    assert!(fields.public_key.ends_with(" me@laptop"));
+    assert!(fields.fingerprint.starts_with("SHA256:"));
+
+    let parsed = from_private_key(&fields.private_key).expect("parse");
+    assert_eq!(parsed.public_key, fields.public_key);
+    assert_eq!(parsed.fingerprint, fields.fingerprint);
+  }
+
+  #[test]
+  fn rejects_unknown_algorithms() {
+    assert!(generate("dsa", "").is_err());
+  }
+}
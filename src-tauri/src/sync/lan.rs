@@ -0,0 +1,303 @@
+//! Peer-to-peer sync with another install on the local network.
+//!
+//! An install that is "visible" listens on a random TCP port and advertises
+//! it over mDNS as `_torg-sync._tcp.local.` with its device id and label.
+//! The other install browses for it and connects. Both sides send a
+//! [`Hello`] with an ephemeral public key, derive the pairing key, and show
+//! the same six-digit code ([`super::pairing`]). Once the user confirms the
+//! code on a device, it sends its sealed vault file, sealed again under the
+//! pairing key, and merges the copy it receives. Nothing leaves the local
+//! network, and the vault only leaves the machine encrypted under its own
+//! key.
+
+use super::pairing::{Handshake, PairingKeys, Role, CIPHERTEXT_LEN, ENCAPSULATION_KEY_LEN};
+use super::relay::Hello;
+use super::to_hex;
+use crate::device::DeviceIdentity;
+use crate::models::AppState;
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use serde::Serialize;
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+/// mDNS service type advertised by visible installs.
+pub const SERVICE_TYPE: &str = "_torg-sync._tcp.local.";
+
+/// Event emitted with a [`LanPeer`] and the verification code when another
+/// install connects to this one.
+pub const LAN_REQUEST_EVENT: &str = "lan-sync://request";
+
+/// How long a connected peer may take to confirm its code.
+const CONFIRM_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Largest frame accepted from a peer: a vault file plus pairing overhead.
+const MAX_FRAME_BYTES: u64 = crate::limits::DEFAULT_MAX_FILE_BYTES + 1024;
+
+/// An install found on the local network.
+#[derive(Clone, Debug, Serialize)]
+pub struct LanPeer {
+  pub device_id: String,
+  pub label: String,
+  #[serde(skip)]
+  pub addresses: Vec<SocketAddr>,
+}
+
+/// This install's mDNS advertisement and listener.
+pub struct Advertiser {
+  daemon: ServiceDaemon,
+  fullname: String,
+  stop: Arc<AtomicBool>,
+}
+
+impl Advertiser {
+  pub fn stop(self) {
+    self.stop.store(true, Ordering::Relaxed);
+    let _ = self.daemon.unregister(&self.fullname);
+    let _ = self.daemon.shutdown();
+  }
+}
+
+/// A connected peer whose code has not been confirmed yet.
+pub struct LanSession {
+  stream: TcpStream,
+  keys: PairingKeys,
+  pub peer: DeviceIdentity,
+}
+
+impl LanSession {
+  /// Exchanges hellos on a fresh connection and derives the pairing keys.
+  fn handshake(mut stream: TcpStream, role: Role, identity: &DeviceIdentity) -> Result<Self, String> {
+    stream
+      .set_read_timeout(Some(CONFIRM_TIMEOUT))
+      .map_err(|e| format!("lan sync: {e}"))?;
+    let handshake = Handshake::new(role);
+    let public = to_hex(&handshake.public());
+    // The listener speaks first, since the other side encapsulates to its
+    // key.
+    let (keys, peer) = match handshake.encapsulation_key() {
+      Some(encapsulation_key) => {
+        send_hello(&mut stream, public, &encapsulation_key, identity)?;
+        let (theirs, peer) = receive_hello(&mut stream)?;
+        let ciphertext = theirs.kem::<CIPHERTEXT_LEN>()?;
+        (handshake.finish(theirs.public_key()?, &ciphertext)?, peer)
+      }
+      None => {
+        let (theirs, peer) = receive_hello(&mut stream)?;
+        let encapsulation_key = theirs.kem::<ENCAPSULATION_KEY_LEN>()?;
+        let (keys, ciphertext) = handshake.accept(theirs.public_key()?, &encapsulation_key)?;
+        send_hello(&mut stream, public, &ciphertext, identity)?;
+        (keys, peer)
+      }
+    };
+    Ok(Self { stream, keys, peer })
+  }
+
+  pub fn verification_code(&self) -> &str {
+    &self.keys.code
+  }
+
+  /// Sends `vault` (a sealed vault file, or nothing if this install has no
+  /// vault yet) and returns what the peer sent once its user confirmed.
+  pub fn exchange(mut self, vault: &[u8]) -> Result<Vec<u8>, String> {
+    let sealed = self.keys.seal(vault)?;
+    write_frame(&mut self.stream, &sealed).map_err(|e| format!("lan sync: {e}"))?;
+    let theirs = read_frame(&mut self.stream).map_err(|e| match e.kind() {
+      io::ErrorKind::UnexpectedEof => "the other device cancelled".to_string(),
+      io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => "the other device did not confirm in time".to_string(),
+      _ => format!("lan sync: {e}"),
+    })?;
+    Ok(self.keys.open(&theirs)?.to_vec())
+  }
+}
+
+fn send_hello(stream: &mut TcpStream, public: String, kem: &[u8], identity: &DeviceIdentity) -> Result<(), String> {
+  let hello = Hello {
+    public,
+    kem: Some(to_hex(kem)),
+    device: Some(identity.clone()),
+  };
+  let hello = serde_json::to_vec(&hello).map_err(|e| format!("serialize failed: {e}"))?;
+  write_frame(stream, &hello).map_err(|e| format!("lan sync: {e}"))
+}
+
+fn receive_hello(stream: &mut TcpStream) -> Result<(Hello, DeviceIdentity), String> {
+  let theirs = read_frame(stream).map_err(|e| format!("lan sync: {e}"))?;
+  let theirs: Hello = serde_json::from_slice(&theirs).map_err(|e| format!("invalid hello from peer: {e}"))?;
+  let peer = theirs
+    .device
+    .clone()
+    .ok_or_else(|| "the other device did not identify itself".to_string())?;
+  Ok((theirs, peer))
+}
+
+fn write_frame(stream: &mut TcpStream, bytes: &[u8]) -> io::Result<()> {
+  let len = u32::try_from(bytes.len()).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "frame too large"))?;
+  stream.write_all(&len.to_be_bytes())?;
+  stream.write_all(bytes)?;
+  stream.flush()
+}
+
+fn read_frame(stream: &mut TcpStream) -> io::Result<Vec<u8>> {
+  let mut len = [0u8; 4];
+  stream.read_exact(&mut len)?;
+  let len = u32::from_be_bytes(len) as u64;
+  if len > MAX_FRAME_BYTES {
+    return Err(io::Error::new(io::ErrorKind::InvalidData, "frame too large"));
+  }
+  let mut bytes = Vec::new();
+  stream.take(len).read_to_end(&mut bytes)?;
+  if bytes.len() as u64 != len {
+    return Err(io::ErrorKind::UnexpectedEof.into());
+  }
+  Ok(bytes)
+}
+
+/// Starts listening and advertising this install. An incoming connection
+/// becomes the pending session in `state.lan_session` and is announced via
+/// [`LAN_REQUEST_EVENT`]; connections arriving while one is pending are
+/// refused.
+pub fn advertise(app: &AppHandle, state: AppState, identity: DeviceIdentity) -> Result<Advertiser, String> {
+  let listener = TcpListener::bind(("0.0.0.0", 0)).map_err(|e| format!("lan sync: {e}"))?;
+  let port = listener.local_addr().map_err(|e| format!("lan sync: {e}"))?.port();
+  listener.set_nonblocking(true).map_err(|e| format!("lan sync: {e}"))?;
+
+  let daemon = ServiceDaemon::new().map_err(|e| format!("mdns: {e}"))?;
+  let host = format!("torg-{}.local.", &identity.id[..8.min(identity.id.len())]);
+  let properties = [("id", identity.id.as_str()), ("label", identity.label.as_str())];
+  let info = ServiceInfo::new(SERVICE_TYPE, &identity.id, &host, "", port, &properties[..])
+    .map_err(|e| format!("mdns: {e}"))?
+    .enable_addr_auto();
+  let fullname = info.get_fullname().to_string();
+  daemon.register(info).map_err(|e| format!("mdns: {e}"))?;
+
+  let stop = Arc::new(AtomicBool::new(false));
+  let stopped = stop.clone();
+  let app = app.clone();
+  thread::spawn(move || {
+    while !stopped.load(Ordering::Relaxed) {
+      let stream = match listener.accept() {
+        Ok((stream, _)) => stream,
+        Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+          thread::sleep(Duration::from_millis(250));
+          continue;
+        }
+        Err(e) => {
+          eprintln!("lan sync: {e}");
+          continue;
+        }
+      };
+      if state.lan_session.lock().map(|s| s.is_some()).unwrap_or(true) {
+        continue;
+      }
+      let session = stream
+        .set_nonblocking(false)
+        .map_err(|e| format!("lan sync: {e}"))
+        .and_then(|()| LanSession::handshake(stream, Role::Host, &identity));
+      match session {
+        Ok(session) => {
+          let request = LanRequest {
+            device_id: session.peer.id.clone(),
+            label: session.peer.label.clone(),
+            verification_code: session.verification_code().to_string(),
+          };
+          if let Ok(mut pending) = state.lan_session.lock() {
+            *pending = Some(session);
+            let _ = app.emit(LAN_REQUEST_EVENT, request);
+          }
+        }
+        Err(err) => eprintln!("{err}"),
+      }
+    }
+  });
+
+  Ok(Advertiser { daemon, fullname, stop })
+}
+
+/// A connected peer, shown with the code to compare.
+#[derive(Clone, Debug, Serialize)]
+pub struct LanRequest {
+  pub device_id: String,
+  pub label: String,
+  pub verification_code: String,
+}
+
+/// Browses the local network for visible installs for up to `timeout`.
+pub fn discover(own_id: &str, timeout: Duration) -> Result<Vec<LanPeer>, String> {
+  let daemon = ServiceDaemon::new().map_err(|e| format!("mdns: {e}"))?;
+  let events = daemon.browse(SERVICE_TYPE).map_err(|e| format!("mdns: {e}"))?;
+  let deadline = Instant::now() + timeout;
+  let mut peers: Vec<LanPeer> = Vec::new();
+  while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+    let Ok(event) = events.recv_timeout(remaining) else {
+      break;
+    };
+    let ServiceEvent::ServiceResolved(info) = event else {
+      continue;
+    };
+    let Some(device_id) = info.get_property_val_str("id").map(String::from) else {
+      continue;
+    };
+    if device_id == own_id || peers.iter().any(|p| p.device_id == device_id) {
+      continue;
+    }
+    let mut addresses: Vec<IpAddr> = info.get_addresses().iter().copied().collect();
+    // Prefer IPv4; link-local IPv6 addresses need a scope to connect.
+    addresses.sort_by_key(|ip| ip.is_ipv6());
+    peers.push(LanPeer {
+      device_id,
+      label: info.get_property_val_str("label").unwrap_or("Unknown device").to_string(),
+      addresses: addresses.into_iter().map(|ip| SocketAddr::new(ip, info.get_port())).collect(),
+    });
+  }
+  let _ = daemon.stop_browse(SERVICE_TYPE);
+  let _ = daemon.shutdown();
+  peers.sort_by(|a, b| a.label.cmp(&b.label));
+  Ok(peers)
+}
+
+/// Connects to a discovered peer and completes the handshake.
+pub fn connect(peer: &LanPeer, identity: &DeviceIdentity) -> Result<LanSession, String> {
+  let mut last_error = "the device has no reachable address".to_string();
+  for address in &peer.addresses {
+    match TcpStream::connect_timeout(address, Duration::from_secs(5)) {
+      Ok(stream) => return LanSession::handshake(stream, Role::Guest, identity),
+      Err(e) => last_error = format!("lan sync: {e}"),
+    }
+  }
+  Err(last_error)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn both_ends_see_the_same_code_and_exchange_vaults() {
+    let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+    let peer = LanPeer {
+      device_id: "host".into(),
+      label: "DESKTOP".into(),
+      addresses: vec![listener.local_addr().unwrap()],
+    };
+    let host = thread::spawn(move || {
+      let (stream, _) = listener.accept().unwrap();
+      let session = LanSession::handshake(stream, Role::Host, &DeviceIdentity::new(Some("DESKTOP".into()))).unwrap();
+      let code = session.verification_code().to_string();
+      (code, session.exchange(b"desktop vault").unwrap())
+    });
+    let session = connect(&peer, &DeviceIdentity::new(Some("LAPTOP".into()))).unwrap();
+    assert_eq!(session.peer.label, "DESKTOP");
+    let code = session.verification_code().to_string();
+    let received = session.exchange(b"laptop vault").unwrap();
+
+    let (host_code, host_received) = host.join().unwrap();
+    assert_eq!(code, host_code);
+    assert_eq!(received, b"desktop vault");
+    assert_eq!(host_received, b"laptop vault");
+  }
+}
@@ -0,0 +1,47 @@
+//! Syncing the vault between devices.
+//!
+//! Devices only ever exchange the sealed vault file, never the vault key or
+//! decrypted entries; each side opens the other's copy with its own session
+//! key and merges it (see [`crate::merge`]). A new device is enrolled by
+//! key agreement plus a verification code the user compares on both screens
+//! ([`pairing`]), after which it receives the relay secret ([`relay`]).
+//! Two installs on the same network can also sync directly ([`lan`]).
+
+pub mod lan;
+pub mod pairing;
+pub mod relay;
+
+use crate::device::DeviceIdentity;
+use pairing::{Handshake, PairingKeys, Role};
+
+/// A device enrollment in progress. Kept in memory only; restarting the app
+/// abandons it.
+pub struct Enrollment {
+  pub role: Role,
+  /// Relay URL the enrollment blobs live on.
+  pub relay: String,
+  /// Normalized pairing code.
+  pub code: String,
+  /// Until the other side's public key arrives.
+  pub handshake: Option<Handshake>,
+  /// Once the other side's public key has arrived.
+  pub keys: Option<PairingKeys>,
+  /// On the host, the device asking to join.
+  pub device: Option<DeviceIdentity>,
+}
+
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+  bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+pub(crate) fn from_hex<const N: usize>(hex: &str) -> Result<[u8; N], String> {
+  let invalid = || "invalid hex value".to_string();
+  if hex.len() != N * 2 || !hex.is_ascii() {
+    return Err(invalid());
+  }
+  let mut out = [0u8; N];
+  for (i, byte) in out.iter_mut().enumerate() {
+    *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).map_err(|_| invalid())?;
+  }
+  Ok(out)
+}
@@ -0,0 +1,239 @@
+//! Key agreement and verification codes for enrolling a device.
+//!
+//! The two devices run a hybrid key exchange over a channel that is not
+//! trusted (the relay, or the local network). The host sends an ephemeral
+//! X25519 public key and an ML-KEM-768 encapsulation key; the guest answers
+//! with its own X25519 public key and a shared secret encapsulated to the
+//! host's key. Both derive the same pairing key and a six-digit
+//! verification code from the two shared secrets and everything exchanged,
+//! so a recorded enrollment stays sealed unless both X25519 and ML-KEM are
+//! broken. The user compares the codes on the two screens before anything
+//! is sent under the pairing key, so a channel that swapped keys shows up
+//! as mismatched codes.
+
+use chacha20poly1305::aead::Aead;
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use hmac::{Hmac, Mac};
+use ml_kem::kem::{Decapsulate, Encapsulate};
+use ml_kem::{Ciphertext, EncodedSizeUser, KemCore, MlKem768};
+use rand::RngCore;
+use sha2::Sha256;
+use x25519_dalek::{EphemeralSecret, PublicKey};
+use zeroize::Zeroizing;
+
+const NONCE_LEN: usize = 24;
+
+/// Length of the host's ML-KEM-768 encapsulation key.
+pub const ENCAPSULATION_KEY_LEN: usize = 1184;
+
+/// Length of the guest's ML-KEM-768 ciphertext.
+pub const CIPHERTEXT_LEN: usize = 1088;
+
+type DecapsulationKey = <MlKem768 as KemCore>::DecapsulationKey;
+type EncapsulationKey = <MlKem768 as KemCore>::EncapsulationKey;
+
+/// Which side of an enrollment this device is on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Role {
+  /// The enrolled device inviting another.
+  Host,
+  /// The device joining.
+  Guest,
+}
+
+/// One side's ephemeral keys, until the other side's message arrives. Only
+/// the host has an ML-KEM key pair; the guest encapsulates to it.
+pub struct Handshake {
+  secret: EphemeralSecret,
+  public: PublicKey,
+  kem: Option<(DecapsulationKey, EncapsulationKey)>,
+}
+
+impl Handshake {
+  pub fn new(role: Role) -> Self {
+    let secret = EphemeralSecret::random_from_rng(rand::rngs::OsRng);
+    let public = PublicKey::from(&secret);
+    let kem = match role {
+      Role::Host => Some(MlKem768::generate(&mut rand::rngs::OsRng)),
+      Role::Guest => None,
+    };
+    Self { secret, public, kem }
+  }
+
+  pub fn public(&self) -> [u8; 32] {
+    self.public.to_bytes()
+  }
+
+  /// The host's ML-KEM-768 encapsulation key, sent with its public key.
+  pub fn encapsulation_key(&self) -> Option<Vec<u8>> {
+    self.kem.as_ref().map(|(_, ek)| ek.as_bytes().to_vec())
+  }
+
+  /// Completes the exchange on the guest with the host's public key and
+  /// encapsulation key. Returns the pairing keys and the ML-KEM ciphertext
+  /// to send back with this side's public key.
+  pub fn accept(self, host_public: [u8; 32], encapsulation_key: &[u8]) -> Result<(PairingKeys, Vec<u8>), String> {
+    if self.kem.is_some() {
+      return Err("pairing: the host cannot accept an enrollment".to_string());
+    }
+    let encoded = encapsulation_key
+      .try_into()
+      .map_err(|_| "invalid encapsulation key from the other device".to_string())?;
+    let (ciphertext, kem_shared) = EncapsulationKey::from_bytes(encoded)
+      .encapsulate(&mut rand::rngs::OsRng)
+      .map_err(|_| "pairing: encapsulation failed".to_string())?;
+    let kem_shared = Zeroizing::new(<[u8; 32]>::from(kem_shared));
+    let transcript = Transcript {
+      host: host_public,
+      guest: self.public.to_bytes(),
+      encapsulation_key,
+      ciphertext: &ciphertext,
+    };
+    let keys = derive(self.secret, host_public, &kem_shared, &transcript)?;
+    Ok((keys, ciphertext.to_vec()))
+  }
+
+  /// Completes the exchange on the host with the guest's public key and
+  /// ML-KEM ciphertext.
+  pub fn finish(self, guest_public: [u8; 32], ciphertext: &[u8]) -> Result<PairingKeys, String> {
+    let (decapsulation_key, encapsulation_key) = self
+      .kem
+      .ok_or_else(|| "pairing: only the host can finish an enrollment".to_string())?;
+    let ciphertext = Ciphertext::<MlKem768>::try_from(ciphertext)
+      .map_err(|_| "invalid ciphertext from the other device".to_string())?;
+    let kem_shared = decapsulation_key
+      .decapsulate(&ciphertext)
+      .map_err(|_| "pairing: decapsulation failed".to_string())?;
+    let kem_shared = Zeroizing::new(<[u8; 32]>::from(kem_shared));
+    let transcript = Transcript {
+      host: self.public.to_bytes(),
+      guest: guest_public,
+      encapsulation_key: &encapsulation_key.as_bytes(),
+      ciphertext: &ciphertext,
+    };
+    derive(self.secret, guest_public, &kem_shared, &transcript)
+  }
+}
+
+/// Everything both sides sent, bound into the derived keys.
+struct Transcript<'a> {
+  host: [u8; 32],
+  guest: [u8; 32],
+  encapsulation_key: &'a [u8],
+  ciphertext: &'a [u8],
+}
+
+/// Derives the pairing key and code from both shared secrets, keyed with
+/// their concatenation, over the transcript.
+fn derive(
+  secret: EphemeralSecret,
+  their_public: [u8; 32],
+  kem_shared: &[u8; 32],
+  transcript: &Transcript<'_>,
+) -> Result<PairingKeys, String> {
+  let shared = secret.diffie_hellman(&PublicKey::from(their_public));
+  if !shared.was_contributory() {
+    return Err("invalid public key from the other device".to_string());
+  }
+  let mut ikm = Zeroizing::new([0u8; 64]);
+  ikm[..32].copy_from_slice(shared.as_bytes());
+  ikm[32..].copy_from_slice(kem_shared);
+  let derive = |label: &[u8]| -> Result<Zeroizing<[u8; 32]>, String> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(ikm.as_slice()).map_err(|e| format!("pairing: {e}"))?;
+    mac.update(label);
+    mac.update(&transcript.host);
+    mac.update(&transcript.guest);
+    mac.update(transcript.encapsulation_key);
+    mac.update(transcript.ciphertext);
+    Ok(Zeroizing::new(mac.finalize().into_bytes().into()))
+  };
+  let key = derive(b"TORG-PAIR/key")?;
+  let sas = derive(b"TORG-PAIR/code")?;
+  let n = u32::from_be_bytes([sas[0], sas[1], sas[2], sas[3]]) % 1_000_000;
+  Ok(PairingKeys {
+    key,
+    code: format!("{:03} {:03}", n / 1000, n % 1000),
+  })
+}
+
+/// Result of a handshake: the code to compare and the key for the messages
+/// that follow a confirmed match.
+pub struct PairingKeys {
+  key: Zeroizing<[u8; 32]>,
+  /// Six digits shown on both devices, e.g. "042 917".
+  pub code: String,
+}
+
+impl PairingKeys {
+  fn cipher(&self) -> XChaCha20Poly1305 {
+    use chacha20poly1305::KeyInit;
+    XChaCha20Poly1305::new(self.key.as_slice().into())
+  }
+
+  /// Encrypts `message` as `[24B nonce][ciphertext+tag]`.
+  pub fn seal(&self, message: &[u8]) -> Result<Vec<u8>, String> {
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let ciphertext = self
+      .cipher()
+      .encrypt(XNonce::from_slice(&nonce), message)
+      .map_err(|_| "pairing: encryption failed".to_string())?;
+    let mut sealed = nonce.to_vec();
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+  }
+
+  /// Decrypts a message from [`PairingKeys::seal`] on the other device.
+  pub fn open(&self, sealed: &[u8]) -> Result<Zeroizing<Vec<u8>>, String> {
+    if sealed.len() < NONCE_LEN {
+      return Err("pairing: message too short".to_string());
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    self
+      .cipher()
+      .decrypt(XNonce::from_slice(nonce), ciphertext)
+      .map(Zeroizing::new)
+      .map_err(|_| "pairing: message could not be authenticated".to_string())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn both_sides_derive_the_same_code_and_key() {
+    let host = Handshake::new(Role::Host);
+    let guest = Handshake::new(Role::Guest);
+    let (host_public, guest_public) = (host.public(), guest.public());
+    let encapsulation_key = host.encapsulation_key().unwrap();
+    let (guest_keys, ciphertext) = guest.accept(host_public, &encapsulation_key).unwrap();
+    let host_keys = host.finish(guest_public, &ciphertext).unwrap();
+    assert_eq!(host_keys.code, guest_keys.code);
+    assert_eq!(host_keys.code.len(), 7);
+
+    let sealed = host_keys.seal(b"offer").unwrap();
+    assert_eq!(guest_keys.open(&sealed).unwrap().as_slice(), b"offer");
+    let mut tampered = sealed;
+    *tampered.last_mut().unwrap() ^= 1;
+    assert!(guest_keys.open(&tampered).is_err());
+    assert!(Handshake::new(Role::Host).finish([0u8; 32], &ciphertext).is_err());
+    assert!(Handshake::new(Role::Guest).finish(guest_public, &ciphertext).is_err());
+    assert!(Handshake::new(Role::Guest).accept(host_public, &encapsulation_key[1..]).is_err());
+  }
+
+  #[test]
+  fn a_swapped_ciphertext_changes_the_keys() {
+    let host = Handshake::new(Role::Host);
+    let encapsulation_key = host.encapsulation_key().unwrap();
+    let guest = Handshake::new(Role::Guest);
+    let guest_public = guest.public();
+    let (guest_keys, _) = guest.accept(host.public(), &encapsulation_key).unwrap();
+    // A relay that answers with its own encapsulation under the guest's
+    // public key cannot make the host derive the same secrets.
+    let (_, forged) = Handshake::new(Role::Guest).accept(host.public(), &encapsulation_key).unwrap();
+    let host_keys = host.finish(guest_public, &forged).unwrap();
+    assert_ne!(host_keys.code, guest_keys.code);
+    assert!(guest_keys.open(&host_keys.seal(b"offer").unwrap()).is_err());
+  }
+}
@@ -0,0 +1,235 @@
+//! Client for a self-hosted sync relay.
+//!
+//! The relay is a dumb blob store addressed by 64-character hex ids:
+//!
+//! - `GET /v1/blobs/<id>` returns the blob with an `ETag`, or 404;
+//! - `PUT /v1/blobs/<id>` stores it, answering 412 if an `If-Match` or
+//!   `If-None-Match: *` precondition fails;
+//! - `DELETE /v1/blobs/<id>` removes it.
+//!
+//! Only two kinds of blob are stored. The vault blob is the sealed vault file
+//! (the authenticated header and ciphertext, exactly as written to disk).
+//! Enrollment blobs hold the two devices' public keys and, once the user has
+//! confirmed the verification code, the sync secret sealed under the pairing
+//! key (see [`super::pairing`]). Blob ids are HMACs of secrets the relay
+//! never sees: the vault's sync secret, or the pairing code typed on the
+//! joining device.
+
+use super::{from_hex, to_hex};
+use crate::device::DeviceIdentity;
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::io::Read;
+use std::time::Duration;
+use url::Url;
+
+/// Largest blob accepted from the relay (the vault file size limit).
+const MAX_BLOB_BYTES: u64 = crate::limits::DEFAULT_MAX_FILE_BYTES;
+
+/// Pairing code alphabet (Crockford base32: no I, L, O, or U).
+const CODE_ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// Characters in a pairing code, shown in groups of four.
+const CODE_CHARS: usize = 16;
+
+/// A blob read from the relay.
+pub struct Blob {
+  pub bytes: Vec<u8>,
+  pub etag: Option<String>,
+}
+
+/// Condition for [`Relay::put`].
+pub enum Precondition<'a> {
+  /// Only if the blob does not exist yet.
+  Absent,
+  /// Only if the blob still has this `ETag`.
+  Unchanged(&'a str),
+  /// Always (enrollment blobs).
+  None,
+}
+
+/// Outcome of [`Relay::put`].
+pub enum Put {
+  Stored,
+  /// The precondition failed: another device wrote first.
+  Conflict,
+}
+
+/// Public keys (and, from the joining device, its identity) posted during
+/// enrollment.
+#[derive(Serialize, Deserialize)]
+pub struct Hello {
+  pub public: String,
+  /// The host's ML-KEM encapsulation key, or the guest's ciphertext.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub kem: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub device: Option<DeviceIdentity>,
+}
+
+impl Hello {
+  pub fn public_key(&self) -> Result<[u8; 32], String> {
+    from_hex(&self.public)
+  }
+
+  /// The `kem` field as `N` bytes; see [`super::pairing`] for the lengths.
+  pub fn kem<const N: usize>(&self) -> Result<[u8; N], String> {
+    let kem = self
+      .kem
+      .as_deref()
+      .ok_or_else(|| "the other device must be updated before it can pair with this one".to_string())?;
+    from_hex(kem)
+  }
+}
+
+/// Sent to the joining device once the codes were confirmed, sealed under
+/// the pairing key.
+#[derive(Serialize, Deserialize)]
+pub struct Offer {
+  pub vault_id: String,
+  pub secret: String,
+}
+
+/// Connection to one relay.
+pub struct Relay {
+  base: Url,
+  agent: ureq::Agent,
+}
+
+impl Relay {
+  /// Relays must use HTTPS, except on this machine (for testing a relay).
+  pub fn new(url: &str) -> Result<Self, String> {
+    let mut base = Url::parse(url.trim()).map_err(|e| format!("invalid relay URL: {e}"))?;
+    let loopback = matches!(base.host_str(), Some("localhost" | "127.0.0.1" | "[::1]"));
+    match base.scheme() {
+      "https" => {}
+      "http" if loopback => {}
+      _ => return Err("the relay URL must use https".to_string()),
+    }
+    if !base.path().ends_with('/') {
+      let path = format!("{}/", base.path());
+      base.set_path(&path);
+    }
+    let agent = ureq::AgentBuilder::new()
+      .timeout_connect(Duration::from_secs(10))
+      .timeout(Duration::from_secs(120))
+      .build();
+    Ok(Self { base, agent })
+  }
+
+  pub fn url(&self) -> &str {
+    self.base.as_str()
+  }
+
+  fn blob_url(&self, id: &str) -> Result<Url, String> {
+    self
+      .base
+      .join(&format!("v1/blobs/{id}"))
+      .map_err(|e| format!("invalid relay URL: {e}"))
+  }
+
+  pub fn get(&self, id: &str) -> Result<Option<Blob>, String> {
+    let response = match self.agent.request_url("GET", &self.blob_url(id)?).call() {
+      Ok(response) => response,
+      Err(ureq::Error::Status(404, _)) => return Ok(None),
+      Err(e) => return Err(format!("relay: {e}")),
+    };
+    let etag = response.header("ETag").map(String::from);
+    let mut bytes = Vec::new();
+    response
+      .into_reader()
+      .take(MAX_BLOB_BYTES + 1)
+      .read_to_end(&mut bytes)
+      .map_err(|e| format!("relay: {e}"))?;
+    if bytes.len() as u64 > MAX_BLOB_BYTES {
+      return Err("relay: blob too large".to_string());
+    }
+    Ok(Some(Blob { bytes, etag }))
+  }
+
+  pub fn put(&self, id: &str, bytes: &[u8], precondition: Precondition<'_>) -> Result<Put, String> {
+    let mut request = self
+      .agent
+      .request_url("PUT", &self.blob_url(id)?)
+      .set("Content-Type", "application/octet-stream");
+    request = match precondition {
+      Precondition::Absent => request.set("If-None-Match", "*"),
+      Precondition::Unchanged(etag) => request.set("If-Match", etag),
+      Precondition::None => request,
+    };
+    match request.send_bytes(bytes) {
+      Ok(_) => Ok(Put::Stored),
+      Err(ureq::Error::Status(412, _)) => Ok(Put::Conflict),
+      Err(e) => Err(format!("relay: {e}")),
+    }
+  }
+
+  pub fn delete(&self, id: &str) -> Result<(), String> {
+    match self.agent.request_url("DELETE", &self.blob_url(id)?).call() {
+      Ok(_) | Err(ureq::Error::Status(404, _)) => Ok(()),
+      Err(e) => Err(format!("relay: {e}")),
+    }
+  }
+}
+
+/// Relay id of the blob `purpose` derived from `secret`.
+pub fn blob_id(secret: &[u8], purpose: &str) -> Result<String, String> {
+  let mut mac = Hmac::<Sha256>::new_from_slice(secret).map_err(|e| format!("relay: {e}"))?;
+  mac.update(b"TORG-RELAY/");
+  mac.update(purpose.as_bytes());
+  Ok(to_hex(&mac.finalize().into_bytes()))
+}
+
+/// Random sync secret for a vault, hex-encoded.
+pub fn new_sync_secret() -> String {
+  to_hex(&rand::thread_rng().gen::<[u8; 32]>())
+}
+
+/// Random pairing code such as `7KQ4-M2XD-9RTA-HB3F`.
+pub fn new_pairing_code() -> String {
+  let mut rng = rand::thread_rng();
+  let chars: Vec<char> = (0..CODE_CHARS)
+    .map(|_| CODE_ALPHABET[rng.gen_range(0..CODE_ALPHABET.len())] as char)
+    .collect();
+  chars.chunks(4).map(|c| c.iter().collect::<String>()).collect::<Vec<_>>().join("-")
+}
+
+/// Canonical form of a typed pairing code: separators dropped, letters
+/// upper-cased, and look-alikes (I, L, O) read as digits.
+pub fn normalize_pairing_code(code: &str) -> Result<String, String> {
+  let normalized: String = code
+    .chars()
+    .filter(|c| !c.is_whitespace() && *c != '-')
+    .map(|c| match c.to_ascii_uppercase() {
+      'I' | 'L' => '1',
+      'O' => '0',
+      other => other,
+    })
+    .collect();
+  if normalized.len() != CODE_CHARS || !normalized.bytes().all(|b| CODE_ALPHABET.contains(&b)) {
+    return Err("invalid pairing code".to_string());
+  }
+  Ok(normalized)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn typed_pairing_codes_normalize_to_the_same_blobs() {
+    let code = new_pairing_code();
+    assert_eq!(code.len(), CODE_CHARS + 3);
+    let typed = code.to_lowercase().replace('-', " ").replace('0', "o").replace('1', "l");
+    let a = normalize_pairing_code(&code).unwrap();
+    let b = normalize_pairing_code(&typed).unwrap();
+    assert_eq!(a, b);
+    assert_eq!(blob_id(a.as_bytes(), "host").unwrap(), blob_id(b.as_bytes(), "host").unwrap());
+    assert_ne!(blob_id(a.as_bytes(), "host").unwrap(), blob_id(a.as_bytes(), "guest").unwrap());
+    assert!(normalize_pairing_code("ABCD-EFGH").is_err());
+    assert!(Relay::new("http://relay.example.com").is_err());
+    assert!(Relay::new("http://localhost:8080").is_ok());
+  }
+}
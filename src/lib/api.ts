@@ -1,4 +1,5 @@
 import { invoke } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
 import { AppError, friendlyError } from "./errors";
 
 export interface EntryPublic {
@@ -10,14 +11,108 @@ export interface EntryPublic {
   // RFC 3339 timestamps serialized by the Rust backend (chrono DateTime<Utc>).
   created_at: string;
   updated_at: string;
+  // Incremented on every change to the entry.
+  revision: number;
+  last_used_at: string | null;
+  has_totp: boolean;
+  linked_to: string | null;
+  kind: EntryKindPublic;
+  tags: string[];
+  // null at the top level.
+  folder: string | null;
+  // Privacy mode masked title, username, and notes; expandEntry returns them.
+  masked: boolean;
+  // Left out of lists; only searchEntries with showHidden returns it.
+  hidden: boolean;
 }
 
+export interface IdentityFields {
+  full_name: string;
+  given_name: string;
+  family_name: string;
+  email: string;
+  phone: string;
+  address_line1: string;
+  address_line2: string;
+  city: string;
+  region: string;
+  postal_code: string;
+  country: string;
+}
+
+export interface CardFields {
+  cardholder_name: string;
+  number: string;
+  // MM/YY
+  expiry: string;
+  // Omit to keep (on update), empty string to not store.
+  cvv?: string;
+}
+
+export interface ApiKeyPublic {
+  service: string;
+  scopes: string[];
+  expires_at: string | null;
+  environment: string;
+  // Variable name for .env export; empty derives it from the title.
+  env_var: string;
+}
+
+export interface SshKeyPublic {
+  algorithm: string;
+  public_key: string;
+  fingerprint: string;
+  comment: string;
+}
+
+export type EntryKind =
+  | { type: "login" }
+  | ({ type: "identity" } & IdentityFields)
+  | ({ type: "card" } & CardFields)
+  // Import an existing unencrypted OpenSSH key; public fields are derived from it.
+  | { type: "ssh_key"; private_key: string; comment?: string }
+  | {
+      type: "pgp_key";
+      user_id: string;
+      fingerprint: string;
+      public_key: string;
+      // ASCII-armored secret key block; empty keeps the stored one on update.
+      private_key: string;
+      // Omit to keep (on update), empty string to remove.
+      passphrase?: string;
+    }
+  | ({ type: "api_key" } & ApiKeyPublic & { token: string });
+
+// Card numbers, security codes, and private keys are never sent to the frontend.
+export type EntryKindPublic =
+  | { type: "login" }
+  | ({ type: "identity" } & IdentityFields)
+  | { type: "card"; brand: string; last4: string }
+  | ({ type: "ssh_key" } & SshKeyPublic)
+  | {
+      type: "pgp_key";
+      user_id: string;
+      fingerprint: string;
+      public_key: string;
+      has_passphrase: boolean;
+    }
+  | ({ type: "api_key" } & ApiKeyPublic);
+
 export interface EntryInput {
   title: string;
   username: string;
   password: string;
   url: string;
   notes: string;
+  totp_secret?: string;
+  // Share the password of another entry instead of storing one.
+  linked_to?: string;
+  kind?: EntryKind;
+  tags?: string[];
+  // Omit or leave blank for the top level.
+  folder?: string;
+  // Keep out of lists; see searchEntries.
+  hidden?: boolean;
 }
 
 function isErrorWithMessage(value: unknown): value is { message: unknown } {
@@ -53,7 +148,7 @@ async function invokeCommand<T = void>(
     return await invoke<T>(command, args);
   } catch (e) {
     const friendly = asFriendlyError(e);
-    throw new AppError(friendly.message, friendly.lockoutSeconds);
+    throw new AppError(friendly.message, friendly.lockoutSeconds, friendly.conflictRevision, friendly.policyViolations);
   }
 }
 
@@ -64,29 +159,192 @@ function aliasPasswordArgs(value: string, camelKey: string, snakeKey: string) {
   };
 }
 
-export async function heartbeat(): Promise<void> {
-  await invokeCommand("heartbeat");
+export type ActivitySource = "ui" | "extension" | "cli";
+
+export interface SourceActivity {
+  source: ActivitySource;
+  count: number;
+  idle_secs: number;
+}
+
+// `interaction` marks a key press or click other than copying a secret; it
+// restarts the count for the copy lock (see setCopyLock).
+export async function heartbeat(source?: ActivitySource, interaction?: boolean): Promise<void> {
+  await invokeCommand("heartbeat", { source: source ?? null, interaction: interaction ?? null });
+}
+
+export async function getActivity(): Promise<SourceActivity[]> {
+  return await invokeCommand<SourceActivity[]>("get_activity");
+}
+
+// Fails, leaving the vault unlocked, if unsaved changes cannot be written;
+// `discardChanges` locks without them.
+export async function lockVault(discardChanges?: boolean): Promise<void> {
+  await invokeCommand("lock_vault", { discardChanges });
+}
+
+export interface VaultStats {
+  entry_count: number;
+  location: string;
+  dirty: boolean;
+  pending_writes: number;
+  last_save_error: string | null;
+  // Who last wrote the stored vault, e.g. "modified by LAPTOP-WORK 2 hours
+  // ago"; compare device_id with this_device.id to spot other devices.
+  last_written_by: WriterStamp | null;
+  this_device: DeviceIdentity | null;
+}
+
+export interface DeviceIdentity {
+  id: string;
+  label: string;
+}
+
+export interface WriterStamp {
+  device_id: string;
+  label: string;
+  at: string;
+}
+
+export async function getVaultStats(): Promise<VaultStats> {
+  return await invokeCommand<VaultStats>("get_vault_stats");
+}
+
+export async function retrySave(): Promise<VaultStats> {
+  return await invokeCommand<VaultStats>("retry_save");
+}
+
+export interface SaveFailure {
+  error: string;
+  pending: number;
+  // Set when an automatic lock (idle, OS session, copy count, exit) went
+  // ahead anyway: the changes were journaled for the next unlock, or lost.
+  locked?: "journaled" | "lost";
 }
 
-export async function lockVault(): Promise<void> {
-  await invokeCommand("lock_vault");
+export const SAVE_FAILED_EVENT = "vault://save-failed";
+
+// Changes stay in memory after a failed write; the backend retries them with
+// the next save or on `retrySave`.
+export async function onSaveFailed(cb: (failure: SaveFailure) => void): Promise<UnlistenFn> {
+  return await listen<SaveFailure>(SAVE_FAILED_EVENT, (event) => cb(event.payload));
+}
+
+// Writes all unsaved changes (explicit save mode or failed writes).
+export async function saveVault(): Promise<VaultStats> {
+  return await invokeCommand<VaultStats>("save_vault");
+}
+
+export interface DirtyState {
+  dirty: boolean;
+  pending: number;
+}
+
+export const DIRTY_EVENT = "vault://dirty";
+
+// Fires when explicit save mode queues a change and after queued changes
+// are written.
+export async function onDirtyChanged(cb: (state: DirtyState) => void): Promise<UnlistenFn> {
+  return await listen<DirtyState>(DIRTY_EVENT, (event) => cb(event.payload));
+}
+
+export interface UnlockFailure {
+  attempts: number;
+  locked_out_secs: number | null;
+}
+
+export const UNLOCK_FAILED_EVENT = "vault://unlock-failed";
+
+export async function onUnlockFailed(cb: (failure: UnlockFailure) => void): Promise<UnlistenFn> {
+  return await listen<UnlockFailure>(UNLOCK_FAILED_EVENT, (event) => cb(event.payload));
+}
+
+export interface PasswordReminder {
+  changed_at: string;
+  due_at: string;
+}
+
+export const PASSWORD_REMINDER_EVENT = "vault://password-reminder";
+
+// Fired on unlock when the master password is overdue for a change.
+export async function onPasswordReminder(cb: (reminder: PasswordReminder) => void): Promise<UnlistenFn> {
+  return await listen<PasswordReminder>(PASSWORD_REMINDER_EVENT, (event) => cb(event.payload));
+}
+
+export interface UnlockHistory {
+  failed_attempts: number;
+  last_failed_at: string | null;
+  previous_unlock_at: string | null;
 }
 
+// Call right after unlocking to show e.g. "3 failed attempts since you last
+// unlocked, last at 14:32".
+export async function getUnlockHistory(): Promise<UnlockHistory> {
+  return await invokeCommand<UnlockHistory>("get_unlock_history");
+}
+
+export type CipherSuite = "xchacha20-poly1305" | "aes-256-gcm-siv";
+
+export type KdfAlgorithm = "argon2id" | "scrypt";
+
 // Tauri arg key mapping varies across templates, so we send both aliases.
-export async function createVault(masterPassword: string): Promise<void> {
-  await invokeCommand(
-    "create_vault",
-    aliasPasswordArgs(masterPassword, "masterPassword", "master_password")
-  );
+export async function createVault(
+  masterPassword: string,
+  cipher?: CipherSuite,
+  kdf?: KdfAlgorithm
+): Promise<void> {
+  await invokeCommand("create_vault", {
+    ...aliasPasswordArgs(masterPassword, "masterPassword", "master_password"),
+    cipher,
+    kdf
+  });
 }
 
-export async function unlockVault(masterPassword: string): Promise<void> {
-  await invokeCommand(
+export interface UnlockOutcome {
+  entries: number;
+  // Entries are still being decrypted and arrive through ENTRIES_LOADED_EVENT;
+  // until the last batch, vault commands fail with "vault is still loading".
+  streaming: boolean;
+}
+
+export async function unlockVault(masterPassword: string): Promise<UnlockOutcome> {
+  return await invokeCommand<UnlockOutcome>(
     "unlock_vault",
     aliasPasswordArgs(masterPassword, "masterPassword", "master_password")
   );
 }
 
+export const ENTRIES_LOADED_EVENT = "vault://entries-loaded";
+
+export interface EntriesLoaded {
+  entries: EntryPublic[];
+  loaded: number;
+  total: number;
+  done: boolean;
+  // Set when loading failed; the vault is locked again.
+  error: string | null;
+}
+
+export async function onEntriesLoaded(cb: (batch: EntriesLoaded) => void): Promise<UnlistenFn> {
+  return await listen<EntriesLoaded>(ENTRIES_LOADED_EVENT, (event) => cb(event.payload));
+}
+
+export const COPY_LOCK_EVENT = "vault://copy-lock";
+
+// Fires with the copy count when the copy lock has just locked the vault.
+export async function onCopyLock(cb: (copies: number) => void): Promise<UnlistenFn> {
+  return await listen<number>(COPY_LOCK_EVENT, (event) => cb(event.payload));
+}
+
+export const SESSION_LOCK_EVENT = "vault://session-lock";
+
+export type SessionChange = "switched" | "locked" | "logged_off";
+
+// Fires when switching OS users, locking, or logging off locked the vault.
+export async function onSessionLock(cb: (change: SessionChange) => void): Promise<UnlistenFn> {
+  return await listen<SessionChange>(SESSION_LOCK_EVENT, (event) => cb(event.payload));
+}
+
 export async function changeMasterPassword(
   currentPassword: string,
   newPassword: string
@@ -97,8 +355,87 @@ export async function changeMasterPassword(
   });
 }
 
-export async function exportVault(path: string): Promise<void> {
-  await invokeCommand("export_vault", { path });
+export interface CompactReport {
+  trash_purged: number;
+  tombstones_purged: number;
+  history_purged: number;
+  audit_events_purged: number;
+  // Paths of superseded vault copies that were overwritten and removed.
+  shredded: string[];
+}
+
+// Purges expired trash, re-encrypts the vault under a fresh key (same
+// master password), and shreds superseded vault copies.
+export async function compactVault(masterPassword: string): Promise<CompactReport> {
+  return await invokeCommand<CompactReport>(
+    "compact_vault",
+    aliasPasswordArgs(masterPassword, "masterPassword", "master_password")
+  );
+}
+
+// Re-encrypts the vault under a fresh data key without changing the master
+// password; recommended after restoring a vault that may have been exposed.
+export async function rotateVaultKey(masterPassword: string): Promise<void> {
+  await invokeCommand(
+    "rotate_vault_key",
+    aliasPasswordArgs(masterPassword, "masterPassword", "master_password")
+  );
+}
+
+// Shown on the unlock screen; works while the vault is locked.
+export async function getVaultHint(): Promise<string | null> {
+  return await invokeCommand<string | null>("get_vault_hint");
+}
+
+// The hint is stored unencrypted in the vault file; warn before saving it.
+// Pass null or "" to remove it.
+export async function setVaultHint(hint: string | null): Promise<void> {
+  await invokeCommand("set_vault_hint", { hint });
+}
+
+// Read back from the written file after export.
+export interface ExportReport {
+  size: number;
+  entry_count: number;
+}
+
+export async function exportVault(path: string): Promise<ExportReport> {
+  return await invokeCommand<ExportReport>("export_vault", { path });
+}
+
+export type FolderExportStatus = {
+  id: string;
+  folder: string;
+  path: string;
+  every_days: number | null;
+  last_run: string | null;
+  last_error: string | null;
+  next_run: string | null;
+};
+
+export async function exportFolder(
+  folder: string,
+  path: string,
+  password: string,
+  everyDays: number | null
+): Promise<FolderExportStatus> {
+  return await invokeCommand<FolderExportStatus>("export_folder", { folder, path, password, everyDays });
+}
+
+export async function listFolderExports(): Promise<FolderExportStatus[]> {
+  return await invokeCommand<FolderExportStatus[]>("list_folder_exports");
+}
+
+export async function runFolderExport(id: string): Promise<FolderExportStatus> {
+  return await invokeCommand<FolderExportStatus>("run_folder_export", { id });
+}
+
+export async function setFolderExportSchedule(id: string, everyDays: number | null): Promise<FolderExportStatus> {
+  return await invokeCommand<FolderExportStatus>("set_folder_export_schedule", { id, everyDays });
+}
+
+export async function removeFolderExport(id: string): Promise<void> {
+  await invokeCommand<void>("remove_folder_export", { id });
 }
 
 export async function importVault(path: string, masterPassword: string): Promise<void> {
@@ -108,8 +445,241 @@ export async function importVault(path: string, masterPassword: string): Promise
   });
 }
 
-export async function getEntries(): Promise<EntryPublic[]> {
-  return await invokeCommand<EntryPublic[]>("get_entries");
+export interface MergeConflict {
+  entry_id: string;
+  copy_id: string;
+  copy_title: string;
+}
+
+export interface MergeReport {
+  added: number;
+  updated: number;
+  // Entries deleted in the other copy, now in the trash.
+  removed: number;
+  conflicts: MergeConflict[];
+}
+
+// Merges another copy of the same vault into the unlocked one. Entries
+// edited on both sides are kept twice; the older version becomes
+// "<title> (conflict from <device> <date>)" and is listed in `conflicts`.
+export async function mergeVault(path: string, masterPassword: string): Promise<MergeReport> {
+  return invokeCommand<MergeReport>("merge_vault", {
+    path,
+    ...aliasPasswordArgs(masterPassword, "masterPassword", "master_password")
+  });
+}
+
+export interface SharedVaultStatus {
+  id: string;
+  folder: string;
+  path: string;
+  last_synced: string | null;
+  last_attempt: string | null;
+  last_error: string | null;
+}
+
+export interface SharedSyncReport extends MergeReport {
+  written: boolean;
+}
+
+// Shares a folder as a vault file at `path`, locked with its own password.
+export async function createSharedVault(folder: string, path: string, password: string): Promise<SharedVaultStatus> {
+  return invokeCommand<SharedVaultStatus>("create_shared_vault", { folder, path, password });
+}
+
+export async function joinSharedVault(path: string, password: string, folder: string): Promise<SharedSyncReport> {
+  return invokeCommand<SharedSyncReport>("join_shared_vault", { path, password, folder });
+}
+
+export async function listSharedVaults(): Promise<SharedVaultStatus[]> {
+  return invokeCommand<SharedVaultStatus[]>("list_shared_vaults");
+}
+
+export async function syncSharedVault(id: string): Promise<SharedSyncReport> {
+  return invokeCommand<SharedSyncReport>("sync_shared_vault", { id });
+}
+
+export async function leaveSharedVault(id: string): Promise<void> {
+  await invokeCommand<void>("leave_shared_vault", { id });
+}
+
+export interface EnrolledDevice {
+  id: string;
+  label: string;
+  enrolled_at: string;
+}
+
+export interface SyncStatus {
+  relay_url: string | null;
+  devices: EnrolledDevice[];
+}
+
+export interface SyncReport {
+  merged: MergeReport | null;
+  pushed: boolean;
+}
+
+// Sync goes through a self-hosted relay that only ever stores the sealed
+// vault file and enrollment messages.
+export async function enableSync(relayUrl: string): Promise<SyncStatus> {
+  return invokeCommand<SyncStatus>("enable_sync", { relayUrl });
+}
+
+export async function disableSync(): Promise<SyncStatus> {
+  return invokeCommand<SyncStatus>("disable_sync");
+}
+
+export async function getSyncStatus(): Promise<SyncStatus> {
+  return invokeCommand<SyncStatus>("get_sync_status");
+}
+
+// The master password is only needed when another device re-keyed the vault.
+export async function syncNow(masterPassword?: string): Promise<SyncReport> {
+  return invokeCommand<SyncReport>(
+    "sync_now",
+    masterPassword === undefined ? {} : aliasPasswordArgs(masterPassword, "masterPassword", "master_password")
+  );
+}
+
+export interface EnrollmentRequest {
+  device_id: string;
+  device_label: string;
+  verification_code: string;
+}
+
+// Enrolled device: show the pairing code, poll until a device answers, then
+// let the user confirm that both screens show the same verification code.
+export async function startDeviceEnrollment(): Promise<string> {
+  return invokeCommand<string>("start_device_enrollment");
+}
+
+export async function pollDeviceEnrollment(): Promise<EnrollmentRequest | null> {
+  return invokeCommand<EnrollmentRequest | null>("poll_device_enrollment");
+}
+
+export async function confirmDeviceEnrollment(codesMatch: boolean): Promise<SyncStatus> {
+  return invokeCommand<SyncStatus>("confirm_device_enrollment", { codesMatch });
+}
+
+// New device: returns the verification code to compare, then poll
+// finishJoinSync until the enrolled device has confirmed.
+export async function joinSync(relayUrl: string, pairingCode: string): Promise<string> {
+  return invokeCommand<string>("join_sync", { relayUrl, pairingCode });
+}
+
+export async function finishJoinSync(): Promise<boolean> {
+  return invokeCommand<boolean>("finish_join_sync");
+}
+
+export interface LanPeer {
+  device_id: string;
+  label: string;
+}
+
+export interface LanRequest {
+  device_id: string;
+  label: string;
+  verification_code: string;
+}
+
+export interface LanSyncReport {
+  merged: MergeReport | null;
+  downloaded: boolean;
+}
+
+// LAN sync: one install calls startLanSync and waits for onLanSyncRequest;
+// the other discovers it and connects. Both show the verification code and
+// call confirmLanPeer, which resolves once both users have confirmed.
+export async function startLanSync(): Promise<void> {
+  await invokeCommand("start_lan_sync");
+}
+
+export async function stopLanSync(): Promise<void> {
+  await invokeCommand("stop_lan_sync");
+}
+
+export async function discoverLanPeers(timeoutSecs?: number): Promise<LanPeer[]> {
+  return invokeCommand<LanPeer[]>("discover_lan_peers", { timeoutSecs });
+}
+
+export async function connectLanPeer(deviceId: string): Promise<LanRequest> {
+  return invokeCommand<LanRequest>("connect_lan_peer", { deviceId });
+}
+
+export const LAN_REQUEST_EVENT = "lan-sync://request";
+
+export async function onLanSyncRequest(cb: (request: LanRequest) => void): Promise<UnlistenFn> {
+  return await listen<LanRequest>(LAN_REQUEST_EVENT, (event) => cb(event.payload));
+}
+
+export async function confirmLanPeer(codesMatch: boolean, masterPassword?: string): Promise<LanSyncReport> {
+  return invokeCommand<LanSyncReport>("confirm_lan_peer", {
+    codesMatch,
+    ...(masterPassword === undefined ? {} : aliasPasswordArgs(masterPassword, "masterPassword", "master_password"))
+  });
+}
+
+export interface DeletedEntry extends EntryPublic {
+  deleted_at: string;
+}
+
+// "stored" is insertion order; "manual" groups by folder (top level first)
+// in the order saved with reorderEntries.
+export type EntryOrder = "stored" | "manual";
+
+export async function getEntries(order?: EntryOrder): Promise<EntryPublic[]> {
+  return await invokeCommand<EntryPublic[]>("get_entries", { order });
+}
+
+// Saves the order of entries in a folder (null for the top level). Entries
+// left out sort after the listed ones.
+export async function reorderEntries(folder: string | null, orderedIds: string[]): Promise<void> {
+  await invokeCommand<void>("reorder_entries", { folder, orderedIds });
+}
+
+// Most recently edited entries, newest first (default 10).
+export async function getRecentlyModified(limit?: number): Promise<EntryPublic[]> {
+  return await invokeCommand<EntryPublic[]>("get_recently_modified", { limit });
+}
+
+// Entries in the trash, most recently deleted first (default 10).
+export async function getRecentlyDeleted(limit?: number): Promise<DeletedEntry[]> {
+  return await invokeCommand<DeletedEntry[]>("get_recently_deleted", { limit });
+}
+
+export async function restoreEntry(id: string): Promise<EntryPublic> {
+  return await invokeCommand<EntryPublic>("restore_entry", { id });
+}
+
+// Empty fields match everything; all given criteria must match.
+export interface SearchFilter {
+  query?: string;
+  tags?: string[];
+  type?: EntryKindPublic["type"];
+  folder?: string | null;
+}
+
+export interface SavedSearch {
+  id: string;
+  name: string;
+  filter: SearchFilter;
+}
+
+export async function getSavedSearches(): Promise<SavedSearch[]> {
+  return await invokeCommand<SavedSearch[]>("get_saved_searches");
+}
+
+// Creates a saved search, or replaces the one with `id`.
+export async function saveSearch(name: string, filter: SearchFilter, id?: string): Promise<SavedSearch> {
+  return await invokeCommand<SavedSearch>("save_search", { id, name, filter });
+}
+
+export async function deleteSavedSearch(id: string): Promise<void> {
+  await invokeCommand<void>("delete_saved_search", { id });
+}
+
+export async function runSavedSearch(id: string): Promise<EntryPublic[]> {
+  return await invokeCommand<EntryPublic[]>("run_saved_search", { id });
 }
 
 export interface EntryUpdateInput {
@@ -119,12 +689,80 @@ export interface EntryUpdateInput {
   password?: string;
   url: string;
   notes: string;
+  // Omit to keep, empty string to remove.
+  totp_secret?: string;
+  // Omit to keep, empty string to remove; a removed link keeps the shared
+  // password unless a new one is given.
+  linked_to?: string;
+  // Omit to keep the current type and fields.
+  kind?: EntryKind;
+  // Omit to keep the current tags.
+  tags?: string[];
+  // Omit to keep, empty string to move to the top level.
+  folder?: string;
+  // Omit to keep.
+  hidden?: boolean;
+  // The `revision` the form was loaded with; the update fails with a
+  // conflict (AppError.conflictRevision) if the entry changed since.
+  expected_revision?: number;
 }
 
+// "socket" is a Unix domain socket only the current user can open; it is
+// rejected on Windows. Changes take effect the next time the app starts.
+export type BridgeTransport = "tcp" | "socket";
+
 export interface ExtensionConfig {
   enabled: boolean;
   token: string;
   port: number;
+  transport: BridgeTransport;
+  approved_origins: string[];
+  // Executables besides known browsers allowed to read secrets.
+  allowed_processes: string[];
+  // Auto-disable policy; null turns a rule off.
+  disable_after_idle_days: number | null;
+  disable_after_locked_minutes: number | null;
+  last_used_at: string | null;
+  // Set when the bridge turned itself off, until it is re-enabled.
+  auto_disabled: AutoDisableReason | null;
+  // Extra tokens besides `token`, which has the full scope.
+  scoped_tokens: ScopedToken[];
+  // Hours a desktop-approved browser session lasts; null when not required.
+  session_hours: number | null;
+}
+
+export type TokenScope = "status" | "read_entries" | "full";
+
+export interface ScopedToken {
+  token: string;
+  scope: TokenScope;
+  label: string;
+  created_at: string;
+}
+
+export type AutoDisableReason = "unused" | "locked_too_long";
+
+// A password or identity handed to the browser extension.
+export interface AutofillEvent {
+  entry_id: string;
+  title: string;
+  field: "password" | "identity";
+  // Browser display name, when the client could be identified.
+  client: string | null;
+  origin: string;
+  at: string;
+}
+
+export const EXTENSION_AUTOFILL_EVENT = "extension://autofilled";
+
+export async function onExtensionAutofill(cb: (event: AutofillEvent) => void): Promise<UnlistenFn> {
+  return await listen<AutofillEvent>(EXTENSION_AUTOFILL_EVENT, (event) => cb(event.payload));
+}
+
+export const EXTENSION_AUTO_DISABLED_EVENT = "extension://auto-disabled";
+
+export async function onExtensionAutoDisabled(cb: (reason: AutoDisableReason) => void): Promise<UnlistenFn> {
+  return await listen<AutoDisableReason>(EXTENSION_AUTO_DISABLED_EVENT, (event) => cb(event.payload));
 }
 
 export async function addEntry(input: EntryInput): Promise<EntryPublic> {
@@ -143,14 +781,959 @@ export async function copySecret(id: string): Promise<void> {
   await invokeCommand("copy_secret", { id });
 }
 
-export async function getExtensionConfig(): Promise<ExtensionConfig> {
-  return await invokeCommand<ExtensionConfig>("get_extension_config");
+export const CLIPBOARD_HISTORY_EVENT = "clipboard://history-detected";
+
+// Fired at most once per run, on the first copy, with the names of running
+// clipboard history managers (their histories outlive the auto-clear).
+export async function onClipboardHistoryDetected(cb: (managers: string[]) => void): Promise<UnlistenFn> {
+  return await listen<string[]>(CLIPBOARD_HISTORY_EVENT, (event) => cb(event.payload));
 }
 
-export async function setExtensionEnabled(enabled: boolean): Promise<ExtensionConfig> {
-  return await invokeCommand<ExtensionConfig>("set_extension_enabled", { enabled });
+export interface DiagnosticCheck {
+  id: string;
+  severity: "ok" | "warning";
+  message: string;
 }
 
-export async function rotateExtensionToken(): Promise<ExtensionConfig> {
-  return await invokeCommand<ExtensionConfig>("rotate_extension_token");
+export async function runDiagnostics(): Promise<DiagnosticCheck[]> {
+  return await invokeCommand<DiagnosticCheck[]>("run_diagnostics");
+}
+
+// Found at startup when the previous run did not shut down cleanly.
+export interface RecoveryReport {
+  detected_at: string;
+  previous_start: string | null;
+  checks: DiagnosticCheck[];
+  // Unlocking is refused until the report is dismissed.
+  vault_damaged: boolean;
+  last_backup: string | null;
+}
+
+export async function getRecoveryReport(): Promise<RecoveryReport | null> {
+  return await invokeCommand<RecoveryReport | null>("get_recovery_report");
+}
+
+export async function dismissRecoveryReport(): Promise<void> {
+  return await invokeCommand<void>("dismiss_recovery_report");
+}
+
+export type CopyableField =
+  | "username"
+  | "password"
+  | "totp"
+  | "number"
+  | "expiry"
+  | "cvv"
+  | "private_key"
+  | "public_key"
+  | "passphrase"
+  | "token";
+
+export async function copyField(id: string, field: CopyableField): Promise<void> {
+  await invokeCommand("copy_field", { id, field });
+}
+
+// Returns a secret for display ("show password"); the reveal is audited.
+// The backend emits CONCEAL_EVENT when the value must be hidden again.
+export async function revealField(id: string, field: CopyableField): Promise<string> {
+  return await invokeCommand<string>("reveal_field", { id, field });
+}
+
+export interface Revealed {
+  token: number;
+  entry_id: string;
+  field: CopyableField;
+}
+
+export const CONCEAL_EVENT = "vault://conceal";
+
+export async function onConceal(cb: (revealed: Revealed) => void): Promise<UnlistenFn> {
+  return await listen<Revealed>(CONCEAL_EVENT, (event) => cb(event.payload));
+}
+
+// Hides every revealed secret now (each one gets a CONCEAL_EVENT).
+export async function concealAll(): Promise<void> {
+  await invokeCommand("conceal_all");
+}
+
+export type EntryAccessKind = "secret_copied" | "secret_revealed" | "extension_secret_read" | "cli_secret_read";
+
+export interface EntryAccess {
+  at: string;
+  kind: EntryAccessKind;
+  detail: string | null;
+  // Executable name of the extension bridge client, when identified.
+  client: string | null;
+}
+
+export interface EntryActivity {
+  copies: number;
+  reveals: number;
+  extension_reads: number;
+  cli_reads: number;
+  last_access_at: string | null;
+  recent: EntryAccess[];
+}
+
+export async function getEntryActivity(id: string, limit?: number): Promise<EntryActivity> {
+  return await invokeCommand<EntryActivity>("get_entry_activity", { id, limit });
+}
+
+export interface LoadTiming {
+  at: string;
+  unlock_ms: number;
+  entries_ms: number;
+  entries: number;
+  streamed: boolean;
+}
+
+export interface SaveTiming {
+  at: string;
+  ms: number;
+  full: boolean;
+}
+
+export interface StorageTimings {
+  last_load: LoadTiming | null;
+  last_save: SaveTiming | null;
+}
+
+export async function getStorageTimings(): Promise<StorageTimings> {
+  return await invokeCommand<StorageTimings>("get_storage_timings");
+}
+
+// Secret fields (password, totp_secret, and the type-specific "details")
+// only record whether they held a value.
+export type FieldChange =
+  | { type: "text"; field: string; old: string; new: string }
+  | { type: "secret"; field: string; was_set: boolean; is_set: boolean };
+
+export interface EntryChange {
+  revision: number;
+  at: string;
+  device: string | null;
+  fields: FieldChange[];
+}
+
+// Newest first.
+export async function getEntryHistory(id: string): Promise<EntryChange[]> {
+  return await invokeCommand<EntryChange[]>("get_entry_history", { id });
+}
+
+export type AuditKind =
+  | EntryAccessKind
+  | "extension_client_rejected"
+  | "env_exported"
+  | "secrets_injected"
+  | "unlocked"
+  | "unlock_failed"
+  | "unlock_locked_out"
+  | "entry_added"
+  | "entry_updated"
+  | "entry_deleted"
+  | "entry_restored"
+  | "vault_exported"
+  | "vault_imported"
+  | "vault_merged"
+  | "device_enrolled"
+  | "backup_restored"
+  | "entries_imported"
+  | "hidden_entry_shown"
+  | "canary_tripped";
+
+export interface AuditEvent {
+  at: string;
+  kind: AuditKind;
+  entry_id?: string;
+  detail?: string;
+  client?: string;
+}
+
+export type FeedCategory = "unlocks" | "exports" | "edits" | "extension" | "secrets";
+
+export interface FeedFilter {
+  categories?: FeedCategory[];
+  entry_id?: string;
+  // RFC 3339 timestamp; only events at or after it.
+  since?: string;
+}
+
+// Recent audit events, newest first, for the activity timeline.
+export async function getActivityFeed(limit?: number, filter?: FeedFilter): Promise<AuditEvent[]> {
+  return await invokeCommand<AuditEvent[]>("get_activity_feed", { limit, filter });
+}
+
+export interface SequenceStep {
+  entry_id: string;
+  copied: "username" | "password" | "totp";
+  remaining: SequenceStep["copied"][];
+}
+
+// Copies the username now; the password and TOTP code follow on each
+// `nextInSequence` call, or every `delaySecs` seconds (1-120) when given.
+export async function copySequence(id: string, delaySecs?: number): Promise<SequenceStep> {
+  return await invokeCommand<SequenceStep>("copy_sequence", { id, delaySecs, delay_secs: delaySecs });
+}
+
+export async function nextInSequence(): Promise<SequenceStep | null> {
+  return await invokeCommand<SequenceStep | null>("next_in_sequence");
+}
+
+export async function cancelCopySequence(): Promise<void> {
+  await invokeCommand("cancel_copy_sequence");
+}
+
+export interface LockoutPolicy {
+  max_attempts: number;
+  lockout_secs: number;
+  exponential_backoff: boolean;
+  audit: boolean;
+}
+
+export interface AutoLockPolicy {
+  ui_secs: number;
+  extension_secs: number;
+  cli_secs: number;
+}
+
+export interface RevealPolicy {
+  // 1-300 seconds.
+  hide_after_secs: number;
+  // Block screen capture of the main window while a secret is shown.
+  protect_capture: boolean;
+}
+
+export type NoticeKind =
+  | "expiry_reminder"
+  | "sync_result"
+  | "audit_finding"
+  | "backup_failure"
+  | "backup_result"
+  | "export_failure"
+  | "autofill";
+
+export interface NotificationPolicy {
+  enabled: boolean;
+  // Include entry titles; off by default because titles name accounts.
+  show_titles: boolean;
+  muted: NoticeKind[];
+  // Also notify on every extension fill; off by default.
+  autofill: boolean;
+}
+
+export interface Settings {
+  lockout: LockoutPolicy;
+  auto_lock: AutoLockPolicy;
+  reveal: RevealPolicy;
+  // Months between master password change reminders; null turns them off.
+  password_reminder_months: number | null;
+  breach_dataset: string | null;
+  scheduled_audit: boolean;
+  notifications: NotificationPolicy;
+  save_mode: SaveMode;
+  backup: BackupSchedule;
+  retention: RetentionPolicy;
+  // Hosts (and their subdomains) the browser extension never fills.
+  autofill_exclusions: string[];
+  hooks: VaultHook[];
+  password_policies: PasswordPolicy[];
+  // Name of the policy new and changed passwords must meet; null for none.
+  active_password_policy: string | null;
+  generator_history: GeneratorHistoryPolicy;
+  // Lock after this many secret copies without other interaction; null for off.
+  copy_lock_after: number | null;
+  // Mask titles, usernames, and notes in entry lists until a row is expanded.
+  privacy_mode: boolean;
+}
+
+export interface GeneratorHistoryPolicy {
+  // Passwords kept, 0 to 50; 0 turns the history off.
+  size: number;
+  // Keep them in the vault across locks.
+  persist: boolean;
+}
+
+export interface GeneratedInfo {
+  // Pass to copyGenerated; 0 is the newest.
+  index: number;
+  label: string | null;
+  generated_at: string;
+  length: number;
+}
+
+// Call after generating a password, so it can be copied again if the entry is never saved.
+export async function recordGeneratedPassword(password: string, label?: string): Promise<void> {
+  await invokeCommand("record_generated_password", { password, label: label ?? null });
+}
+
+export async function getGeneratorHistory(): Promise<GeneratedInfo[]> {
+  return await invokeCommand<GeneratedInfo[]>("get_generator_history");
+}
+
+export async function copyGenerated(index: number): Promise<void> {
+  await invokeCommand("copy_generated", { index });
+}
+
+export async function clearGeneratorHistory(): Promise<void> {
+  await invokeCommand("clear_generator_history");
+}
+
+export async function setPrivacyMode(enabled: boolean): Promise<Settings> {
+  return await invokeCommand<Settings>("set_privacy_mode", { enabled });
+}
+
+export interface SearchOptions {
+  // Also return hidden entries whose title equals the query (ignoring case).
+  showHidden?: boolean;
+  // Required with showHidden.
+  masterPassword?: string;
+}
+
+// Matching entries, best first; hidden matches come before the rest.
+export async function searchEntries(query: string, options: SearchOptions = {}): Promise<EntryPublic[]> {
+  return await invokeCommand<EntryPublic[]>("search_entries", {
+    query,
+    showHidden: options.showHidden,
+    masterPassword: options.masterPassword
+  });
+}
+
+// The entry with its title, username, and notes unmasked.
+export async function expandEntry(id: string): Promise<EntryPublic> {
+  return await invokeCommand<EntryPublic>("expand_entry", { id });
+}
+
+export const CANARY_EVENT = "vault://canary-tripped";
+
+export interface CanaryTripped {
+  entry_id: string;
+  title: string;
+  // "copy", "reveal", or "extension".
+  via: string;
+  at: string;
+}
+
+// Fires whenever the canary entry's secret is read.
+export async function onCanaryTripped(cb: (trip: CanaryTripped) => void): Promise<UnlistenFn> {
+  return await listen<CanaryTripped>(CANARY_EVENT, (event) => cb(event.payload));
+}
+
+// The canary entry's id, or null when none is set.
+export async function getCanaryEntry(masterPassword: string): Promise<string | null> {
+  return await invokeCommand<string | null>("get_canary_entry", { masterPassword });
+}
+
+// Pass null to disarm the canary.
+export async function setCanaryEntry(masterPassword: string, id: string | null): Promise<void> {
+  await invokeCommand("set_canary_entry", { masterPassword, id });
+}
+
+export const PRESENTATION_MODE_EVENT = "vault://presentation-mode";
+
+// Blocks copies and reveals and masks usernames until turned off again.
+export async function setPresentationMode(enabled: boolean): Promise<boolean> {
+  return await invokeCommand<boolean>("set_presentation_mode", { enabled });
+}
+
+export async function getPresentationMode(): Promise<boolean> {
+  return await invokeCommand<boolean>("get_presentation_mode");
+}
+
+export async function onPresentationMode(cb: (enabled: boolean) => void): Promise<UnlistenFn> {
+  return await listen<boolean>(PRESENTATION_MODE_EVENT, (event) => cb(event.payload));
+}
+
+// 1 to 100 copies, or null to turn the copy lock off.
+export async function setCopyLock(copies: number | null): Promise<Settings> {
+  return await invokeCommand<Settings>("set_copy_lock", { copies });
+}
+
+export async function setGeneratorHistory(size: number, persist: boolean): Promise<Settings> {
+  return await invokeCommand<Settings>("set_generator_history", { size, persist });
+}
+
+export type CharClass = "lowercase" | "uppercase" | "digit" | "symbol";
+
+export interface PasswordPolicy {
+  name: string;
+  // 1 to 256 characters.
+  min_length: number;
+  required_classes: CharClass[];
+  // Matched anywhere in the password, ignoring case; at most 200.
+  banned_words: string[];
+  // Reject passwords another entry already uses.
+  no_reuse: boolean;
+}
+
+export type PolicyViolation =
+  | { code: "too_short"; min: number; actual: number }
+  | { code: "missing_class"; class: CharClass }
+  | { code: "banned_word"; word: string }
+  | { code: "reused"; entry_ids: string[] };
+
+export type VaultEvent = "lock" | "unlock" | "save";
+
+export type HookAction =
+  // Absolute path, started without a shell; gets ORGANIZER_EVENT and ORGANIZER_EVENT_AT.
+  | { type: "exec"; program: string; args: string[] }
+  // localhost, 127.0.0.1, or [::1] only; receives a POST with {event, at}.
+  | { type: "webhook"; url: string };
+
+export interface VaultHook {
+  event: VaultEvent;
+  action: HookAction;
+}
+
+export interface RetentionPolicy {
+  // Days deletion tombstones are kept for merges (30 to 3650).
+  tombstone_days: number;
+  // Field-level changes kept per entry (0 to 1000).
+  max_history_versions: number;
+  // Days deleted entries stay in the trash (1 to 365).
+  trash_days: number;
+  // Audit log size limit in KiB (64 to 102400).
+  max_audit_log_kib: number;
+}
+
+export async function setRetentionPolicy(policy: RetentionPolicy): Promise<Settings> {
+  return await invokeCommand<Settings>("set_retention_policy", { policy });
+}
+
+// Hooks run programs with the user's rights: the UI must show a warning and pass
+// acknowledgeRisk only after the user confirms it. An empty list clears them.
+export async function setVaultHooks(hooks: VaultHook[], acknowledgeRisk: boolean): Promise<Settings> {
+  return await invokeCommand<Settings>("set_vault_hooks", { hooks, acknowledgeRisk });
+}
+
+// At most 20 policies with unique names; `active` must name one of them.
+// Passwords already saved are not rechecked.
+export async function setPasswordPolicies(policies: PasswordPolicy[], active: string | null): Promise<Settings> {
+  return await invokeCommand<Settings>("set_password_policies", { policies, active });
+}
+
+// Empty when no policy is active or the password complies. Pass the entry's id
+// when editing so its current password does not count as reuse.
+export async function checkPasswordPolicy(password: string, entryId?: string): Promise<PolicyViolation[]> {
+  return await invokeCommand<PolicyViolation[]>("check_password_policy", { password, entryId: entryId ?? null });
+}
+
+// `site` may be a URL or a host; it is stored lowercase without `www.`.
+export async function addAutofillExclusion(site: string): Promise<Settings> {
+  return await invokeCommand<Settings>("add_autofill_exclusion", { site });
+}
+
+export async function removeAutofillExclusion(site: string): Promise<Settings> {
+  return await invokeCommand<Settings>("remove_autofill_exclusion", { site });
+}
+
+export type BackupFrequency = "daily" | "weekly";
+
+export interface BackupSchedule {
+  enabled: boolean;
+  frequency: BackupFrequency;
+  directory: string | null;
+}
+
+export interface BackupStatus {
+  last_run: string | null;
+  last_success: string | null;
+  // Set when the latest run failed.
+  last_error: string | null;
+  last_file: string | null;
+  schedule: BackupSchedule;
+  // null while backups are off.
+  next_run: string | null;
+  running: boolean;
+}
+
+// "auto" writes after every change; "explicit" waits for saveVault, lock,
+// or exit.
+export type SaveMode = "auto" | "explicit";
+
+export async function setSaveMode(mode: SaveMode): Promise<Settings> {
+  return await invokeCommand<Settings>("set_save_mode", { mode });
+}
+
+export type LockoutPreset = "standard" | "strict";
+
+export async function getSettings(): Promise<Settings> {
+  return await invokeCommand<Settings>("get_settings");
+}
+
+export async function setLockoutPolicy(policy: LockoutPolicy): Promise<Settings> {
+  return await invokeCommand<Settings>("set_lockout_policy", { policy });
+}
+
+export async function setLockoutPreset(preset: LockoutPreset): Promise<Settings> {
+  return await invokeCommand<Settings>("set_lockout_preset", { preset });
+}
+
+export async function setPasswordReminder(months: number | null): Promise<Settings> {
+  return await invokeCommand<Settings>("set_password_reminder", { months });
+}
+
+export async function setRevealPolicy(policy: RevealPolicy): Promise<Settings> {
+  return await invokeCommand<Settings>("set_reveal_policy", { policy });
+}
+
+// Quick-access window (label "quick"), shown by the CmdOrCtrl+Shift+Space
+// global hotkey. Results carry no secrets; copying happens in the backend.
+export interface QuickMatch {
+  id: string;
+  title: string;
+  username: string;
+  url: string;
+  kind: EntryKindPublic["type"];
+}
+
+export async function quickSearch(query: string, limit?: number): Promise<QuickMatch[]> {
+  return await invokeCommand<QuickMatch[]>("quick_search", { query, limit });
+}
+
+export async function quickCopy(id: string, field: string): Promise<void> {
+  await invokeCommand("quick_copy", { id, field });
+}
+
+export async function hideQuickWindow(): Promise<void> {
+  await invokeCommand("hide_quick_window");
+}
+
+export async function getExtensionConfig(): Promise<ExtensionConfig> {
+  return await invokeCommand<ExtensionConfig>("get_extension_config");
+}
+
+export async function setExtensionEnabled(enabled: boolean): Promise<ExtensionConfig> {
+  return await invokeCommand<ExtensionConfig>("set_extension_enabled", { enabled });
+}
+
+export async function rotateExtensionToken(): Promise<ExtensionConfig> {
+  return await invokeCommand<ExtensionConfig>("rotate_extension_token");
+}
+
+export async function createExtensionToken(label: string, scope: TokenScope): Promise<ExtensionConfig> {
+  return await invokeCommand<ExtensionConfig>("create_extension_token", { label, scope });
+}
+
+export async function revokeExtensionToken(token: string): Promise<ExtensionConfig> {
+  return await invokeCommand<ExtensionConfig>("revoke_extension_token", { token });
+}
+
+export async function setExtensionTransport(transport: BridgeTransport): Promise<ExtensionConfig> {
+  return await invokeCommand<ExtensionConfig>("set_extension_transport", { transport });
+}
+
+// Turns the bridge off after `idleDays` (1-365) without use and once the
+// vault has been locked for `lockedMinutes` (1-10080); null disables a rule.
+export async function setExtensionAutoDisable(
+  idleDays: number | null,
+  lockedMinutes: number | null
+): Promise<ExtensionConfig> {
+  return await invokeCommand<ExtensionConfig>("set_extension_auto_disable", { idleDays, lockedMinutes });
+}
+
+// `http://127.0.0.1:<port>`, `unix:<socket path>` or `pipe:<pipe name>`.
+export async function getExtensionEndpoint(): Promise<string> {
+  return await invokeCommand<string>("get_extension_endpoint");
+}
+
+export interface KdfParams {
+  algorithm: string;
+  memory_kib: number;
+  iterations: number;
+  parallelism: number;
+}
+
+export interface EmergencyKit {
+  vault_id: string;
+  vault_path: string;
+  vault_created_at: string;
+  format_version: number;
+  cipher: string;
+  kdf: KdfParams;
+  entry_count: number;
+  recovery_code_placeholders: string[];
+  generated_at: string;
+}
+
+export async function generateEmergencyKit(): Promise<EmergencyKit> {
+  return await invokeCommand<EmergencyKit>("generate_emergency_kit");
+}
+
+export interface StaleEntry {
+  id: string;
+  title: string;
+  username: string;
+  url: string;
+  created_at: string;
+  last_used_at: string | null;
+  idle_days: number;
+}
+
+export interface StaleEntriesReport {
+  months: number;
+  entries: StaleEntry[];
+  total_sites: number;
+}
+
+export async function getStaleEntries(months: number): Promise<StaleEntriesReport> {
+  return await invokeCommand<StaleEntriesReport>("get_stale_entries", { months });
+}
+
+export interface TwoFactorSuggestion {
+  id: string;
+  title: string;
+  url: string;
+  domain: string;
+}
+
+export async function get2faSuggestions(): Promise<TwoFactorSuggestion[]> {
+  return await invokeCommand<TwoFactorSuggestion[]>("get_2fa_suggestions");
+}
+
+export interface SecuritySnapshot {
+  taken_at: string;
+  entries: number;
+  weak: number;
+  reused: number;
+}
+
+export interface SecurityReport {
+  current: SecuritySnapshot;
+  weak_entry_ids: string[];
+  reused_entry_ids: string[];
+  // Daily snapshots, oldest first.
+  trend: SecuritySnapshot[];
+}
+
+export async function getSecurityReport(): Promise<SecurityReport> {
+  return await invokeCommand<SecurityReport>("get_security_report");
+}
+
+export type CommonMatch = "exact" | "leet";
+
+export interface PasswordStrength {
+  // 0 to 7, like calculateStrength; common passwords score 0.
+  score: number;
+  // Set when the password is on the bundled common password list.
+  common: CommonMatch | null;
+}
+
+// Works while locked, so setup can check a new master password.
+export async function estimatePasswordStrength(password: string): Promise<PasswordStrength> {
+  return await invokeCommand<PasswordStrength>("estimate_password_strength", { password });
+}
+
+export interface SecurityAudit {
+  ran_at: string;
+  weak_entry_ids: string[];
+  reused_entry_ids: string[];
+  missing_2fa: TwoFactorSuggestion[];
+  expiring_api_keys: ExpiringApiKey[];
+  breached: BreachedEntry[];
+  // Keys like "weak/<entry id>" not reported by the previous audit.
+  new_issues: string[];
+}
+
+// Opt-in weekly background audit; runs while unlocked and idle.
+// Backups are copies of the encrypted vault file, written by the app in the
+// background (locked or not) to the chosen directory.
+export async function setBackupSchedule(schedule: BackupSchedule): Promise<Settings> {
+  return await invokeCommand<Settings>("set_backup_schedule", { schedule });
+}
+
+export async function getBackupStatus(): Promise<BackupStatus> {
+  return await invokeCommand<BackupStatus>("get_backup_status");
+}
+
+// What a backup holds. The counts and dates are null unless the master
+// password the backup was written under is given.
+export interface BackupPreview {
+  size: number;
+  cipher: string;
+  kdf: string;
+  written_by: WriterStamp | null;
+  entry_count: number | null;
+  trash_count: number | null;
+  last_modified: string | null;
+  vault_id: string | null;
+}
+
+export async function previewBackup(path: string, masterPassword?: string): Promise<BackupPreview> {
+  return await invokeCommand<BackupPreview>("preview_backup", {
+    path,
+    ...(masterPassword === undefined ? {} : aliasPasswordArgs(masterPassword, "masterPassword", "master_password"))
+  });
+}
+
+// Replaces the vault with a verified backup and unlocks into it. The current
+// vault is kept until the next compaction.
+export async function restoreBackup(path: string, masterPassword: string): Promise<BackupPreview> {
+  return await invokeCommand<BackupPreview>("restore_backup", {
+    path,
+    ...aliasPasswordArgs(masterPassword, "masterPassword", "master_password")
+  });
+}
+
+export type ImportSource =
+  | "chrome"
+  | "edge"
+  | "firefox"
+  | "keychain"
+  | "credential_manager"
+  | "dashlane"
+  | "enpass";
+
+export interface BrowserProfile {
+  source: ImportSource;
+  name: string;
+  path: string;
+}
+
+export interface ImportItem {
+  index: number;
+  title: string;
+  url: string;
+  username: string;
+  // Entry with the same site and username already in the vault.
+  duplicate_of: string | null;
+  same_password: boolean;
+}
+
+export interface ImportSkipped {
+  item: string;
+  reason: string;
+}
+
+export interface ImportPreview {
+  source: ImportSource;
+  items: ImportItem[];
+  skipped: ImportSkipped[];
+}
+
+// Returned by previewBrowserImport when a Firefox profile needs its primary password.
+export const FIREFOX_PRIMARY_PASSWORD_ERROR = "the Firefox primary password is incorrect";
+
+export async function listBrowserProfiles(): Promise<BrowserProfile[]> {
+  return await invokeCommand<BrowserProfile[]>("list_browser_profiles");
+}
+
+// Reads a profile's saved logins into memory for review; nothing is added
+// until commitImport.
+export async function previewBrowserImport(
+  source: ImportSource,
+  profile: string,
+  primaryPassword?: string
+): Promise<ImportPreview> {
+  return await invokeCommand<ImportPreview>("preview_browser_import", {
+    source,
+    profile,
+    ...(primaryPassword === undefined ? {} : aliasPasswordArgs(primaryPassword, "primaryPassword", "primary_password"))
+  });
+}
+
+// "keychain" on macOS, "credential_manager" on Windows, null elsewhere.
+export async function getSystemImportSource(): Promise<ImportSource | null> {
+  return await invokeCommand<ImportSource | null>("get_system_import_source");
+}
+
+// Reads web logins from the OS credential store; the OS may prompt per item.
+export async function previewSystemImport(): Promise<ImportPreview> {
+  return await invokeCommand<ImportPreview>("preview_system_import");
+}
+
+export async function previewFileImport(
+  source: "dashlane" | "enpass",
+  path: string
+): Promise<ImportPreview> {
+  return await invokeCommand<ImportPreview>("preview_file_import", { source, path });
+}
+
+export async function commitImport(indices: number[]): Promise<{ added: number }> {
+  return await invokeCommand<{ added: number }>("commit_import", { indices });
+}
+
+export async function cancelImport(): Promise<void> {
+  await invokeCommand("cancel_import");
+}
+
+export type ConvertFormat = "vault" | "csv" | "kdbx";
+
+export interface ConvertInput {
+  input: string;
+  output: string;
+  /** Defaults to the format the file extension suggests. */
+  input_format?: ConvertFormat;
+  output_format?: ConvertFormat;
+  input_password?: string;
+  output_password?: string;
+  /** Keeps only entries with this tag. */
+  tag?: string;
+}
+
+export interface ConvertReport {
+  converted: number;
+  /** Entries of types CSV and KeePass files cannot hold. */
+  left_out: number;
+}
+
+export async function convertFile(input: ConvertInput): Promise<ConvertReport> {
+  return await invokeCommand<ConvertReport>("convert_file", { input });
+}
+
+export async function setScheduledAudit(enabled: boolean): Promise<Settings> {
+  return await invokeCommand<Settings>("set_scheduled_audit", { enabled });
+}
+
+export async function setNotificationPolicy(policy: NotificationPolicy): Promise<Settings> {
+  return await invokeCommand<Settings>("set_notification_policy", { policy });
+}
+
+// Cached result of the last scheduled audit since unlock, or null.
+export async function getSecurityAudit(): Promise<SecurityAudit | null> {
+  return await invokeCommand<SecurityAudit | null>("get_security_audit");
+}
+
+export interface NewSecurityIssues {
+  count: number;
+  ran_at: string;
+}
+
+export const SECURITY_ISSUES_EVENT = "security://new-issues";
+
+export async function onNewSecurityIssues(cb: (issues: NewSecurityIssues) => void): Promise<UnlistenFn> {
+  return await listen<NewSecurityIssues>(SECURITY_ISSUES_EVENT, (event) => cb(event.payload));
+}
+
+export interface BreachedEntry {
+  id: string;
+  title: string;
+  url: string;
+  occurrences: number;
+}
+
+// Path to a downloaded Pwned Passwords "SHA-1, ordered by hash" file, or
+// null to clear it.
+export async function setBreachDataset(path: string | null): Promise<Settings> {
+  return await invokeCommand<Settings>("set_breach_dataset", { path });
+}
+
+// Checks passwords against the offline dataset; nothing leaves the machine.
+export async function checkBreachedPasswords(): Promise<BreachedEntry[]> {
+  return await invokeCommand<BreachedEntry[]>("check_breached_passwords");
+}
+
+export interface PeerProcess {
+  pid: number;
+  name: string;
+  // Full path of the executable.
+  path: string;
+}
+
+// `process` is the client that asked, when it could be identified. Requests
+// are dropped after an hour, and the oldest once 20 are waiting.
+export interface PendingOrigin {
+  origin: string;
+  process: PeerProcess | null;
+  requested_at: string;
+}
+
+export async function getPendingExtensionOrigins(): Promise<PendingOrigin[]> {
+  return await invokeCommand<PendingOrigin[]>("get_pending_extension_origins");
+}
+
+export async function approveExtensionOrigin(origin: string): Promise<ExtensionConfig> {
+  return await invokeCommand<ExtensionConfig>("approve_extension_origin", { origin });
+}
+
+export async function revokeExtensionOrigin(origin: string): Promise<ExtensionConfig> {
+  return await invokeCommand<ExtensionConfig>("revoke_extension_origin", { origin });
+}
+
+// A browser session waiting to be approved (see `session_hours`).
+export interface SessionRequest {
+  id: string;
+  origin: string;
+  process: PeerProcess | null;
+  requested_at: string;
+}
+
+export const EXTENSION_SESSION_REQUESTED_EVENT = "extension://session-requested";
+
+export async function onExtensionSessionRequested(cb: (request: SessionRequest) => void): Promise<UnlistenFn> {
+  return await listen<SessionRequest>(EXTENSION_SESSION_REQUESTED_EVENT, (event) => cb(event.payload));
+}
+
+// Re-approve browser sessions every `hours` (1-168); null turns it off.
+export async function setExtensionSessionHours(hours: number | null): Promise<ExtensionConfig> {
+  return await invokeCommand<ExtensionConfig>("set_extension_session_hours", { hours });
+}
+
+export async function getPendingExtensionSessions(): Promise<SessionRequest[]> {
+  return await invokeCommand<SessionRequest[]>("get_pending_extension_sessions");
+}
+
+export async function approveExtensionSession(id: string): Promise<void> {
+  await invokeCommand<void>("approve_extension_session", { id });
+}
+
+export async function denyExtensionSession(id: string): Promise<void> {
+  await invokeCommand<void>("deny_extension_session", { id });
+}
+
+export async function endExtensionSessions(): Promise<void> {
+  await invokeCommand<void>("end_extension_sessions");
+}
+
+export async function generateSshKey(
+  title: string,
+  algo: "ed25519" | "rsa",
+  comment?: string
+): Promise<EntryPublic> {
+  return await invokeCommand<EntryPublic>("generate_ssh_key", { title, algo, comment });
+}
+
+export async function getSshPublicKey(id: string): Promise<string> {
+  return await invokeCommand<string>("get_ssh_public_key", { id });
+}
+
+export interface ExpiringApiKey {
+  id: string;
+  title: string;
+  service: string;
+  environment: string;
+  expires_at: string;
+  days_left: number;
+  expired: boolean;
+}
+
+export async function getExpiringApiKeys(withinDays: number): Promise<ExpiringApiKey[]> {
+  return await invokeCommand<ExpiringApiKey[]>("get_expiring_api_keys", { withinDays });
+}
+
+// Writes plaintext secrets to disk: the UI must show a warning and pass
+// acknowledgePlaintext only after the user confirms it. Returns the number of variables.
+export async function exportEnv(
+  tag: string,
+  path: string,
+  masterPassword: string,
+  acknowledgePlaintext: boolean
+): Promise<number> {
+  return await invokeCommand<number>("export_env", { tag, path, masterPassword, acknowledgePlaintext });
+}
+
+// Starts `command` (program first) with the template's `NAME=value` lines added to its
+// environment, `{{entry:field}}` placeholders resolved from the vault. Requires the master
+// password; fails in presentation mode. Returns the process id.
+export async function runWithSecrets(
+  template: string,
+  command: string[],
+  masterPassword: string,
+  cwd?: string
+): Promise<number> {
+  return await invokeCommand<number>("run_with_secrets", { template, command, masterPassword, cwd: cwd ?? null });
+}
+
+// Only available in builds with the `sqlite` feature; the old vault file is kept as a backup.
+export async function migrateVaultToSqlite(): Promise<void> {
+  await invokeCommand("migrate_vault_to_sqlite");
 }
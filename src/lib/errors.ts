@@ -3,6 +3,8 @@
  * Maps technical backend errors to human-readable messages.
  */
 
+import type { PolicyViolation } from "./api";
+
 interface ErrorMapping {
   pattern: RegExp;
   message: string;
@@ -11,8 +13,15 @@ interface ErrorMapping {
 export interface FriendlyErrorDetail {
   message: string;
   lockoutSeconds?: number;
+  // Current revision of an entry that changed since it was loaded.
+  conflictRevision?: number;
+  // Why a password was rejected by the active password policy.
+  policyViolations?: PolicyViolation[];
 }
 
+// Entries of a large vault are still being decrypted after unlocking.
+export const STILL_LOADING_MESSAGE = "Your vault is still loading. Please wait a moment.";
+
 const ERROR_MAP: ReadonlyArray<ErrorMapping> = [
   // Password/authentication errors
   { pattern: /load:.*Crypto/i, message: "Incorrect password. Please try again." },
@@ -20,6 +29,7 @@ const ERROR_MAP: ReadonlyArray<ErrorMapping> = [
 
   // Vault state errors
   { pattern: /vault is locked/i, message: "Your vault is locked. Please unlock it first." },
+  { pattern: /vault is still loading/i, message: STILL_LOADING_MESSAGE },
   { pattern: /vault already exists/i, message: "A vault already exists. Please unlock it or delete the existing vault." },
   { pattern: /vault does not exist/i, message: "No vault found. Please create a new vault first." },
 
@@ -40,6 +50,15 @@ const ERROR_MAP: ReadonlyArray<ErrorMapping> = [
   // Entry errors
   { pattern: /entry not found/i, message: "Entry not found. It may have been deleted." },
 
+  // Presentation mode
+  { pattern: /presentation mode is on/i, message: "Presentation mode is on. Turn it off to copy or reveal secrets." },
+
+  // Crash recovery
+  {
+    pattern: /could not be read after the last run ended unexpectedly/i,
+    message: "The vault may be damaged after the app last closed unexpectedly. Review the recovery report above."
+  },
+
   // Clipboard errors
   { pattern: /clipboard/i, message: "Unable to access clipboard. Please try copying manually." },
 
@@ -52,14 +71,27 @@ const ERROR_MAP: ReadonlyArray<ErrorMapping> = [
 
 const FALLBACK_MESSAGE = "An unexpected error occurred. Please try again.";
 const LOCKOUT_PATTERN = /Too many failed attempts.*?(\d+)\s*seconds/i;
+const CONFLICT_PATTERN = /^conflict: .*\(revision (\d+), expected \d+\)/;
+const POLICY_PATTERN = /^policy: (\[.*\])$/;
+const POLICY_MESSAGE = "This password does not meet the active password policy.";
+const CONFLICT_MESSAGE = "This entry was changed elsewhere (for example by the browser extension). Reopen it to see the latest version.";
 
 export class AppError extends Error {
   lockoutSeconds?: number;
-
-  constructor(message: string, lockoutSeconds?: number) {
+  conflictRevision?: number;
+  policyViolations?: PolicyViolation[];
+
+  constructor(
+    message: string,
+    lockoutSeconds?: number,
+    conflictRevision?: number,
+    policyViolations?: PolicyViolation[]
+  ) {
     super(message);
     this.name = "AppError";
     this.lockoutSeconds = lockoutSeconds;
+    this.conflictRevision = conflictRevision;
+    this.policyViolations = policyViolations;
   }
 }
 
@@ -84,6 +116,20 @@ export function friendlyError(rawError: string): FriendlyErrorDetail {
     };
   }
 
+  const conflict = rawError.match(CONFLICT_PATTERN);
+  if (conflict) {
+    return { message: CONFLICT_MESSAGE, conflictRevision: Number.parseInt(conflict[1], 10) };
+  }
+
+  const policy = rawError.match(POLICY_PATTERN);
+  if (policy) {
+    try {
+      return { message: POLICY_MESSAGE, policyViolations: JSON.parse(policy[1]) as PolicyViolation[] };
+    } catch {
+      return { message: POLICY_MESSAGE };
+    }
+  }
+
   const match = ERROR_MAP.find(({ pattern }) => pattern.test(rawError));
   if (match) return { message: match.message };
 
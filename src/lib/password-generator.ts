@@ -2,6 +2,8 @@
  * Secure password generator using Web Crypto API.
  */
 
+import type { PasswordPolicy } from "./api";
+
 export interface PasswordOptions {
   length: number;
   uppercase: boolean;
@@ -27,6 +29,22 @@ export const DEFAULT_OPTIONS: PasswordOptions = {
 
 type CharsetKey = keyof typeof CHAR_SETS;
 
+/**
+ * Generator options that meet a password policy: at least its minimum length
+ * and every character class it requires. Banned words and reuse are left to
+ * the backend check.
+ */
+export function optionsForPolicy(policy: PasswordPolicy, base: PasswordOptions = DEFAULT_OPTIONS): PasswordOptions {
+  const required = new Set(policy.required_classes);
+  return {
+    length: Math.max(base.length, policy.min_length),
+    uppercase: base.uppercase || required.has("uppercase"),
+    lowercase: base.lowercase || required.has("lowercase"),
+    numbers: base.numbers || required.has("digit"),
+    symbols: base.symbols || required.has("symbol")
+  };
+}
+
 /**
  * Generates a cryptographically secure random password.
  * @param options Password generation options
@@ -66,6 +84,122 @@ function getEnabledCharsets(options: PasswordOptions): CharsetKey[] {
   return enabled;
 }
 
+const CONSONANTS = "bcdfghjklmnpqrstvwxz";
+const VOWELS = "aeiou";
+// Consonant pairs that read naturally at the start of a syllable.
+const ONSETS = ["b", "c", "d", "f", "g", "h", "j", "k", "l", "m", "n", "p", "r", "s", "t", "v", "w", "z", "br", "ch", "cl", "dr", "fl", "gr", "pl", "sh", "st", "th", "tr"];
+const CODAS = ["", "", "", "n", "r", "s", "t", "m", "l", "x"];
+
+export interface PronounceableOptions {
+  // Number of syllables, 2 to 20.
+  syllables: number;
+  // Placed between syllables, e.g. "-"; may be empty.
+  separator: string;
+  // Uppercases the first letter of each syllable.
+  capitalize: boolean;
+  // Random digits appended at the end, 0 to 8.
+  digits: number;
+}
+
+export const DEFAULT_PRONOUNCEABLE: PronounceableOptions = {
+  syllables: 5,
+  separator: "-",
+  capitalize: true,
+  digits: 2
+};
+
+/**
+ * Generates an easier to type password from random syllables, such as
+ * `Tro-Gan-Pleb-Sud-Vit-47`. Each syllable is an onset, a vowel, and often a
+ * final consonant, so it carries less entropy per character than
+ * `generatePassword`; use more syllables for the same strength.
+ */
+export function generatePronounceable(options: PronounceableOptions = DEFAULT_PRONOUNCEABLE): string {
+  if (!Number.isInteger(options.syllables) || options.syllables < 2 || options.syllables > 20) {
+    throw new Error("Use between 2 and 20 syllables");
+  }
+  if (!Number.isInteger(options.digits) || options.digits < 0 || options.digits > 8) {
+    throw new Error("Use between 0 and 8 digits");
+  }
+
+  const syllables: string[] = [];
+  for (let i = 0; i < options.syllables; i++) {
+    const syllable = pick(ONSETS) + getRandomChar(VOWELS) + pick(CODAS);
+    syllables.push(options.capitalize ? syllable[0].toUpperCase() + syllable.slice(1) : syllable);
+  }
+  let digits = "";
+  for (let i = 0; i < options.digits; i++) {
+    digits += getRandomChar(CHAR_SETS.numbers);
+  }
+  if (digits) syllables.push(digits);
+  return syllables.join(options.separator);
+}
+
+// Characters each pattern placeholder draws from.
+const PATTERN_SETS: Record<string, string> = {
+  C: CONSONANTS.toUpperCase(),
+  c: CONSONANTS,
+  V: VOWELS.toUpperCase(),
+  v: VOWELS,
+  L: CHAR_SETS.uppercase,
+  l: CHAR_SETS.lowercase,
+  "9": CHAR_SETS.numbers,
+  "#": CHAR_SETS.symbols,
+  "*": CHAR_SETS.uppercase + CHAR_SETS.lowercase + CHAR_SETS.numbers + CHAR_SETS.symbols
+};
+
+// Longest pattern accepted, in output characters.
+export const MAX_PATTERN_LENGTH = 256;
+
+/**
+ * Help text for the pattern language, for the generator's UI.
+ */
+export const PATTERN_HELP =
+  "C/c consonant, V/v vowel, L/l letter (upper/lower), 9 digit, # symbol, * any; \\x is a literal x, anything else is copied.";
+
+type PatternToken = { literal: string } | { set: string };
+
+/**
+ * Parses a generator pattern such as `Cvccvv-99-#` (see `PATTERN_HELP`).
+ * Throws on an empty or too long pattern, or one ending in a lone `\`.
+ */
+export function parsePattern(pattern: string): PatternToken[] {
+  const tokens: PatternToken[] = [];
+  const chars = Array.from(pattern);
+  for (let i = 0; i < chars.length; i++) {
+    const char = chars[i];
+    if (char === "\\") {
+      if (i + 1 >= chars.length) throw new Error("Pattern ends with an unfinished \\");
+      tokens.push({ literal: chars[++i] });
+    } else if (char in PATTERN_SETS) {
+      tokens.push({ set: PATTERN_SETS[char] });
+    } else {
+      tokens.push({ literal: char });
+    }
+  }
+  if (!tokens.some((token) => "set" in token)) {
+    throw new Error("Pattern needs at least one placeholder");
+  }
+  if (tokens.length > MAX_PATTERN_LENGTH) {
+    throw new Error(`Patterns can produce at most ${MAX_PATTERN_LENGTH} characters`);
+  }
+  return tokens;
+}
+
+/**
+ * Generates a password following a pattern, for sites with composition rules
+ * the other modes can't meet, e.g. `Cvccvv-99-#` gives `Bolkue-07-%`.
+ */
+export function generateFromPattern(pattern: string): string {
+  return parsePattern(pattern)
+    .map((token) => ("set" in token ? getRandomChar(token.set) : token.literal))
+    .join("");
+}
+
+function pick<T>(items: readonly T[]): T {
+  return items[getRandomValues(1)[0] % items.length];
+}
+
 /**
  * Gets a random character from the given charset.
  */
@@ -95,7 +229,8 @@ function shuffleArray<T>(array: T[]): T[] {
 }
 
 /**
- * Calculates password strength based on length and character variety.
+ * Calculates password strength based on length and character variety. Common
+ * passwords are caught by the backend's `estimatePasswordStrength`.
  */
 export function calculateStrength(password: string): "weak" | "fair" | "good" | "strong" {
   let score = 0;
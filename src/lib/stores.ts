@@ -10,6 +10,8 @@ export const view = writable<View>(DEFAULT_VIEW);
 export const isLocked = writable(true);
 export const entries = writable<EntryPublic[]>([]);
 export const lastError = writable<string | null>(null);
+// Progress of entries still streaming in after unlocking a large vault.
+export const entriesLoading = writable<{ loaded: number; total: number } | null>(null);
 
 // Convenience helpers (optional)
 export function setError(msg: string | null) {